@@ -0,0 +1,228 @@
+//! External annotation import
+//!
+//! Loads findings from linters and static analyzers (SARIF, or a simple
+//! JSON list of path/line/message objects) so they can be reviewed in
+//! the context of the diff.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Severity of an annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    /// Parse a SARIF level or free-form severity string
+    fn parse(level: &str) -> Self {
+        match level.to_lowercase().as_str() {
+            "error" | "fatal" | "critical" | "high" => Severity::Error,
+            "warning" | "warn" | "medium" => Severity::Warning,
+            _ => Severity::Note,
+        }
+    }
+}
+
+/// A single finding attached to a file line
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// Repository-relative path
+    pub path: String,
+    /// 1-based line number in the new file
+    pub line: u32,
+    /// Finding message
+    pub message: String,
+    /// Severity level
+    pub severity: Severity,
+    /// Rule or tool identifier, if provided
+    pub rule: Option<String>,
+}
+
+/// Annotations indexed by path and line for fast lookup while rendering
+#[derive(Debug, Default)]
+pub struct AnnotationIndex {
+    by_path: HashMap<String, Vec<Annotation>>,
+}
+
+impl AnnotationIndex {
+    /// Build an index from a list of annotations
+    pub fn new(annotations: Vec<Annotation>) -> Self {
+        let mut by_path: HashMap<String, Vec<Annotation>> = HashMap::new();
+        for annotation in annotations {
+            by_path.entry(annotation.path.clone()).or_default().push(annotation);
+        }
+        for list in by_path.values_mut() {
+            list.sort_by_key(|a| (a.line, a.severity));
+        }
+        Self { by_path }
+    }
+
+    /// Whether there are no annotations at all
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    /// Most severe annotation on a given line
+    pub fn severity_at(&self, path: &str, line: u32) -> Option<Severity> {
+        self.by_path
+            .get(path)?
+            .iter()
+            .filter(|a| a.line == line)
+            .map(|a| a.severity)
+            .min()
+    }
+
+    /// Every annotation, file by file
+    pub fn iter(&self) -> impl Iterator<Item = &Annotation> {
+        self.by_path.values().flatten()
+    }
+
+    /// All annotations for a file, sorted by line
+    pub fn for_path(&self, path: &str) -> &[Annotation] {
+        self.by_path.get(path).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// Simple annotation format: `[{"path": ..., "line": ..., "message": ...}]`
+#[derive(Deserialize)]
+struct SimpleAnnotation {
+    #[serde(alias = "file")]
+    path: String,
+    line: u32,
+    message: String,
+    #[serde(default, alias = "level")]
+    severity: Option<String>,
+    #[serde(default)]
+    rule: Option<String>,
+}
+
+/// Load annotations from a SARIF or simple JSON file
+///
+/// Paths are normalized to be relative to `repo_root`.
+pub fn load_annotations(file: &Path, repo_root: &Path) -> Result<Vec<Annotation>> {
+    let contents = fs::read_to_string(file)
+        .with_context(|| format!("Failed to read annotations from {}", file.display()))?;
+    let value: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not valid JSON", file.display()))?;
+
+    let annotations = if value.get("runs").is_some() {
+        parse_sarif(&value)
+    } else if value.is_array() {
+        let simple: Vec<SimpleAnnotation> = serde_json::from_value(value)
+            .context("Expected a list of {path, line, message} objects")?;
+        simple
+            .into_iter()
+            .map(|a| Annotation {
+                path: a.path,
+                line: a.line,
+                message: a.message,
+                severity: Severity::parse(a.severity.as_deref().unwrap_or("warning")),
+                rule: a.rule,
+            })
+            .collect()
+    } else {
+        bail!("Unrecognized annotation format in {}", file.display());
+    };
+
+    Ok(annotations
+        .into_iter()
+        .map(|mut a| {
+            a.path = normalize_path(&a.path, repo_root);
+            a
+        })
+        .collect())
+}
+
+/// Extract results from a SARIF 2.x log
+fn parse_sarif(value: &Value) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    let runs = value["runs"].as_array().map(|r| r.as_slice()).unwrap_or(&[]);
+
+    for run in runs {
+        let results = run["results"].as_array().map(|r| r.as_slice()).unwrap_or(&[]);
+        for result in results {
+            let message = result["message"]["text"]
+                .as_str()
+                .or_else(|| result["message"]["markdown"].as_str())
+                .unwrap_or("")
+                .to_string();
+            let severity = Severity::parse(result["level"].as_str().unwrap_or("warning"));
+            let rule = result["ruleId"].as_str().map(|s| s.to_string());
+
+            let locations = result["locations"].as_array().map(|l| l.as_slice()).unwrap_or(&[]);
+            for location in locations {
+                let physical = &location["physicalLocation"];
+                let Some(uri) = physical["artifactLocation"]["uri"].as_str() else {
+                    continue;
+                };
+                let Some(line) = physical["region"]["startLine"].as_u64() else {
+                    continue;
+                };
+                annotations.push(Annotation {
+                    path: uri.to_string(),
+                    line: line as u32,
+                    message: message.clone(),
+                    severity,
+                    rule: rule.clone(),
+                });
+            }
+        }
+    }
+
+    annotations
+}
+
+/// Turn a file URI or absolute path into a repository-relative path
+fn normalize_path(path: &str, repo_root: &Path) -> String {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    let path = path.strip_prefix("./").unwrap_or(path);
+    Path::new(path)
+        .strip_prefix(repo_root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sarif() {
+        let sarif = json!({
+            "runs": [{
+                "results": [{
+                    "ruleId": "clippy::unwrap_used",
+                    "level": "error",
+                    "message": { "text": "used unwrap" },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": "src/main.rs" },
+                            "region": { "startLine": 12 }
+                        }
+                    }]
+                }]
+            }]
+        });
+
+        let annotations = parse_sarif(&sarif);
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].path, "src/main.rs");
+        assert_eq!(annotations[0].line, 12);
+        assert_eq!(annotations[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        let root = Path::new("/repo");
+        assert_eq!(normalize_path("file:///repo/src/lib.rs", root), "src/lib.rs");
+        assert_eq!(normalize_path("./src/lib.rs", root), "src/lib.rs");
+    }
+}
@@ -3,30 +3,54 @@
 //! Contains the App struct with all application state,
 //! and the main event loop for handling input and rendering.
 
-use std::collections::HashMap;
-use std::io;
-use std::path::PathBuf;
-
-use anyhow::Result;
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
 };
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
+    widgets::{Clear, Widget},
     Terminal,
 };
-
-use crate::git::{self, Commit, FileDiff, Worktree};
-use crate::syntax::Highlighter;
+use regex::Regex;
+
+use crate::annotations::{Annotation, AnnotationIndex, Severity};
+use crate::clipboard;
+use crate::difftool;
+use crate::editor;
+use crate::git::{self, BlameLine, ChangeKind, Commit, DiffAlgorithm, FileDiff, Hunk, LineType, Stage, Worktree};
+use crate::jumplist::JumpList;
+use crate::pulls::{self, PullRequest};
+use crate::notes::{self, LineNotes};
+use crate::review;
+use crate::session::{self, Session};
+use crate::syntax::{FileSymbol, Highlighter};
 use crate::ui::{
-    DiffMode, FocusArea, Styles, TreeNode,
-    build_file_tree, flatten_tree, is_hidden_file,
-    render_diff_content, render_footer, render_header, render_sidebar,
-    render_commit_popup, render_worktree_popup, render_help_popup,
-    diff_view::{calculate_total_lines, file_line_count},
+    DiffMode, DEFAULT_HIDDEN, FlatTree, FocusArea, buffer_to_ansi, LineIndex, SidebarPosition, Styles, Theme, TreeNode, BLAME_WIDTH,
+    build_file_tree, flatten_tree, fuzzy_matches, glob_matches, is_generated, is_hidden_file, is_marked, language_matches, path_filter_matches,
+    render_blame_gutter, render_conflict_banner, render_diff_content, render_footer, render_header, render_path_tooltip, render_sidebar, render_tab_bar,
+    render_commit_popup, commit_popup_hit, CommitSort, render_worktree_popup, render_worktree_form, render_confirm_popup, render_stash_popup, render_help_popup, help_section_starts, render_conflict_popup,
+    render_issues_popup, render_recent_popup, render_pull_popup, render_symbol_popup, signature_label, render_timeline, TimelineStep,
+    render_worktree_dashboard, WorktreeSort, render_diffstat, DiffstatSort,
+    render_message_panel, message_lines, message_panel_width, CommitMessage,
+    outline_entries, render_outline_popup, OutlineEntry,
+    diff_view::{file_mode, line_at_offset, new_line_offset}, text,
     DEFAULT_SIDEBAR_WIDTH, MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH, SIDEBAR_RESIZE_STEP,
 };
 
@@ -39,16 +63,293 @@ pub enum ViewMode {
     CommitFilter,
     /// Worktree switcher popup
     WorktreeSwitcher,
+    /// Form for a new worktree's branch and path
+    WorktreeCreate,
     /// Worktree list view
     WorktreeList,
     /// Help overlay
     Help,
     /// Search mode (vim-like /)
     Search,
+    /// Predicted merge conflicts popup
+    Conflicts,
+    /// Recently viewed files popup
+    Recent,
+    /// Sidebar type-to-filter bar
+    TreeFilter,
+    /// Bar narrowing the files to an extension or language as it's typed
+    LanguageFilter,
+    /// External annotations panel
+    Issues,
+    /// Open pull requests picker
+    PullRequests,
+    /// Prompt for a file or revision to compare the current file with
+    ComparePrompt,
+    /// Prompt for a note on the line under the cursor
+    NotePrompt,
+    /// Prompt for globs limiting the diff to matching paths
+    PathPrompt,
+    /// Functions and types of the changed files, to jump to by name
+    Symbols,
+    /// Symbols the current file's changes touch
+    Outline,
+    /// Table of changed files and folders with their line counts
+    Diffstat,
+    /// Stash entries, to show one's changes
+    Stashes,
+}
+
+/// Context lines around changes unless configured otherwise
+pub const DEFAULT_CONTEXT: u32 = 3;
+
+/// Commits listed before the commit picker offers to load more
+pub const DEFAULT_MAX_COMMITS: usize = 1000;
+
+/// Largest binary file shown as a hexdump unless configured otherwise
+pub const DEFAULT_HEXDUMP_MAX_BYTES: u64 = 4096;
+
+/// Initial view settings, e.g. from command-line flags
+#[derive(Debug, Clone)]
+pub struct ViewOptions {
+    /// Diff layout
+    pub diff_mode: DiffMode,
+    /// Context lines around changes
+    pub context_lines: u32,
+    /// Commits listed before the commit picker offers to load more
+    pub max_commits: usize,
+    /// Expand hidden files (dotfiles, lock files)
+    pub show_hidden: bool,
+    /// Capture the mouse; off leaves the terminal's own selection and scrollback
+    pub mouse: bool,
+    /// UI palette and syntax theme
+    pub theme: Theme,
+    /// Largest binary file shown as a hexdump
+    pub hexdump_max_bytes: u64,
+    /// Files with fewer lines than this show whole in the split view
+    pub full_file_lines: Option<usize>,
+    /// Make `n`/`N` skip files marked reviewed
+    pub skip_reviewed: bool,
+    /// Leave submodule pointer changes out of diffs
+    pub ignore_submodules: bool,
+    /// Diff a branch from where it forked off the base, as `base...HEAD`
+    pub merge_base: bool,
+    /// How lines are matched up between the two sides
+    pub algorithm: DiffAlgorithm,
+    /// Globs of files that start collapsed, with their folders closed
+    pub collapse: Vec<String>,
+    /// Globs of files hidden along with dotfiles, e.g. lock files
+    pub hidden: Vec<String>,
+    /// Globs limiting the diff to matching paths (all files when empty)
+    pub paths: Vec<String>,
+    /// Patterns marking a file generated when found near its top
+    pub generated: Vec<Regex>,
+    /// Where the file list sits
+    pub sidebar: SidebarPosition,
+    /// Shell command for `D`, overriding git's `diff.tool`
+    pub difftool: Option<String>,
+}
+
+impl Default for ViewOptions {
+    /// The view gv opens with when nothing is configured
+    fn default() -> Self {
+        Self {
+            diff_mode: DiffMode::SideBySide,
+            context_lines: DEFAULT_CONTEXT,
+            max_commits: DEFAULT_MAX_COMMITS,
+            show_hidden: false,
+            mouse: true,
+            theme: Theme::default(),
+            hexdump_max_bytes: DEFAULT_HEXDUMP_MAX_BYTES,
+            full_file_lines: None,
+            skip_reviewed: false,
+            ignore_submodules: false,
+            merge_base: true,
+            algorithm: DiffAlgorithm::default(),
+            collapse: Vec::new(),
+            hidden: DEFAULT_HIDDEN.iter().map(|pattern| pattern.to_string()).collect(),
+            paths: Vec::new(),
+            generated: Vec::new(),
+            sidebar: SidebarPosition::default(),
+            difftool: None,
+        }
+    }
 }
 
 const MOUSE_SCROLL_LINES: i32 = 5;
 
+/// How deeply macros may replay other macros, so `Qa@aQ` can't loop forever
+const MAX_MACRO_DEPTH: usize = 10;
+
+/// How long the sidebar cursor must rest before follow mode scrolls the content
+const FOLLOW_DELAY: Duration = Duration::from_millis(80);
+
+/// How long `<`/`>` presses are batched before the context change is applied
+const CONTEXT_DELAY: Duration = Duration::from_millis(150);
+
+/// Most context lines `>` widens to
+const MAX_CONTEXT_LINES: u32 = 20;
+
+/// Diffs are computed with at least this much context so that narrower
+/// settings can be derived without going back to git
+const SOURCE_CONTEXT_LINES: u32 = 3;
+
+/// Event poll interval when nothing is pending
+/// How long a file must stay on screen to count as viewed
+const SEEN_DELAY: Duration = Duration::from_millis(1500);
+/// Most recently viewed files remembered
+const MAX_RECENT_FILES: usize = 30;
+
+/// Below this width side-by-side views fall back to unified
+const NARROW_WIDTH: u16 = 100;
+/// Below this width the sidebar hides until summoned
+const COMPACT_WIDTH: u16 = 80;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A content position that survives collapsing and mode changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ViewPosition {
+    /// File at the top of the viewport
+    path: Option<String>,
+    /// Line offset from the start of that file
+    offset: usize,
+}
+
+/// Per-worktree review state, parked while another tab is active
+///
+/// Mirrors the matching `App` fields; `App::exchange_tab` swaps them in and out.
+#[derive(Default)]
+struct Tab {
+    repo_path: git::RepoPath,
+    main_branch: String,
+    base_sha: Option<String>,
+    head_rev: String,
+    current_worktree: usize,
+    commits: Vec<Commit>,
+    commit_walk: Option<Receiver<CommitEvent>>,
+    more_commits: bool,
+    diff_load: Option<DiffLoad>,
+    resume_position: Option<ViewPosition>,
+    diffs: Vec<FileDiff>,
+    diff_groups: Vec<String>,
+    source_hunks: Vec<Vec<Hunk>>,
+    source_context: u32,
+    visible_diffs: Vec<usize>,
+    file_tree: Vec<TreeNode>,
+    sidebar_rows: Vec<usize>,
+    expanded_folders: HashMap<String, bool>,
+    expanded_files: HashSet<String>,
+    collapsed_files: HashMap<String, bool>,
+    content_scroll: usize,
+    content_cursor: Option<usize>,
+    sidebar_scroll: usize,
+    file_cursor: usize,
+    group_by_commit: bool,
+    timeline: Option<usize>,
+    only_commit: Option<Commit>,
+    stash: Option<git::Stash>,
+    blame: Option<HashMap<String, Vec<BlameLine>>>,
+    commit_messages: Option<Vec<CommitMessage>>,
+    message_scroll: usize,
+    change_filter: Option<ChangeKind>,
+    path_filter: Vec<String>,
+    language_filter: Option<String>,
+    stage: Stage,
+    reviewed: HashMap<String, String>,
+    notes: LineNotes,
+    seen: HashSet<String>,
+    recent: Vec<String>,
+    viewing: Option<(String, Instant)>,
+    marked: HashSet<String>,
+    selecting: bool,
+    jump_list: JumpList<ViewPosition>,
+    conflict_files: Vec<String>,
+    marker_files: Vec<String>,
+    generated_files: HashSet<String>,
+    compare: Option<(git::CompareSide, git::CompareSide)>,
+    since_review: Option<String>,
+    base_steps: usize,
+    fork_point: Option<String>,
+    piped: Option<Vec<FileDiff>>,
+    pull: Option<PullRequest>,
+}
+
+/// Messages sent from the background fetch thread
+enum FetchEvent {
+    Progress(git::FetchProgress),
+    Finished(Result<(), String>),
+}
+
+/// Messages sent from the background commit walk
+enum CommitEvent {
+    Batch(Vec<Commit>),
+    Finished(Result<bool, String>), // Whether commits remain past the limit
+}
+
+/// State of an in-flight fetch
+struct FetchState {
+    remote: String,
+    progress: git::FetchProgress,
+    rx: Receiver<FetchEvent>,
+    pull: Option<PullRequest>, // Pull request to open once its head arrives
+}
+
+/// A program to hand the terminal to from the event loop
+enum External {
+    /// The diff tool, on a diff
+    Difftool(usize),
+    /// The editor, on a file at a line
+    Editor(PathBuf, u32),
+}
+
+/// A worktree change waiting on the confirm dialog
+enum WorktreeAction {
+    /// Remove the worktree at this index
+    Delete(usize),
+    /// Clear out what git keeps of these worktrees, whose directories are gone
+    Prune(Vec<String>),
+}
+
+/// Branch and path typed into the new worktree form
+#[derive(Default)]
+struct WorktreeForm {
+    branch: String,
+    path: String,
+    on_path: bool,         // Typing the path rather than the branch
+    path_edited: bool,     // The path was typed, so it no longer follows the branch
+    error: Option<String>, // Why the last attempt failed
+}
+
+/// A branch diff being computed in the background
+struct DiffLoad {
+    key: git::DiffKey,
+    context: u32, // Context lines it's computed with
+    rx: Receiver<Result<Vec<FileDiff>, String>>,
+}
+
+/// Highlighting done off the UI thread, into a detached highlighter
+enum HighlightJob {
+    /// A diff's hunks, stateful within each hunk
+    Hunks { path: String, hunks: Vec<Vec<String>> },
+    /// One side of a file shown in full
+    Full { cache_key: String, filename: String, lines: Vec<String> },
+}
+
+impl HighlightJob {
+    fn run(&self, highlighter: &mut Highlighter) {
+        match self {
+            Self::Hunks { path, hunks } => {
+                let hunks: Vec<Vec<&str>> = hunks.iter().map(|hunk| hunk.iter().map(String::as_str).collect()).collect();
+                let _ = highlighter.highlight_hunks(path, path, &hunks);
+            }
+            Self::Full { cache_key, filename, lines } => {
+                let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+                let _ = highlighter.highlight_lines(cache_key, filename, &lines);
+            }
+        }
+    }
+}
+
 /// Main application state
 pub struct App {
     // Window dimensions
@@ -56,23 +357,61 @@ pub struct App {
     height: u16,
 
     // Repository
-    repo_path: PathBuf,
+    repo_path: git::RepoPath,
     main_branch: String,
+    /// Short SHA the base currently resolves to
+    base_sha: Option<String>,
+    /// Revision compared against the base: HEAD, or a fixed one (`show`)
+    head_rev: String,
+    /// Two files compared directly (`diff`, `=`) instead of a branch against its base
+    compare: Option<(git::CompareSide, git::CompareSide)>,
+    compare_input: String,
+    /// Line a note is being written for, by path and new-file line number
+    note_target: Option<(String, u32)>,
+    note_input: String,
+    /// Commit the branch was last reviewed at, while showing only what changed since
+    since_review: Option<String>,
+    /// Commits back from the head the diff starts at, `HEAD~N` style (0 = the base)
+    base_steps: usize,
+    /// Merge base of the base and the head, which the diff starts from when `merge_base` is on
+    fork_point: Option<String>,
+    /// Diff git piped in when running as its pager, shown instead of computing one
+    piped: Option<Vec<FileDiff>>,
+    /// Pull request being reviewed, which notes can be posted to
+    pull: Option<PullRequest>,
+
+    /// Whether mouse capture is enabled
+    mouse: bool,
 
     // Worktrees
     worktrees: Vec<Worktree>,
     current_worktree: usize,
+    tabs: Vec<Tab>, // Parked state of each tab; the active one's slot is empty
+    active_tab: usize,
+    tab_origin: Option<(ViewPosition, usize)>, // View and sidebar cursor before `g`, restored for `gt`/`gT`/`gq`
 
     // Commits
     commits: Vec<Commit>,
+    commit_walk: Option<Receiver<CommitEvent>>, // Commits still streaming in from the background walk
+    more_commits: bool, // The walk stopped at `commit_limit` with commits left
+    commit_limit: usize,
 
     // Diffs
     diffs: Vec<FileDiff>,
+    /// Commit label of each diff when grouped by commit, else empty
+    diff_groups: Vec<String>,
+    line_index: OnceCell<LineIndex>, // Line offsets of visible diffs; see invalidate_layout
+    diff_cache: git::DiffCache,
+    source_hunks: Vec<Vec<Hunk>>, // Hunks of each diff at `source_context` lines
+    source_context: u32,
     visible_diffs: Vec<usize>, // Indices into diffs
 
     // File tree
     file_tree: Vec<TreeNode>,
+    sidebar_rows: Vec<usize>, // Cached flatten_tree result; refreshed by rebuild_file_tree
     expanded_folders: HashMap<String, bool>,
+    expanded_files: HashSet<String>, // Files listing their hunks in the sidebar
+    collapsed_files: HashMap<String, bool>, // Files collapsed or expanded by hand, kept across reloads
 
     // View state
     view_mode: ViewMode,
@@ -81,18 +420,91 @@ pub struct App {
 
     // Scroll positions
     content_scroll: usize,
+    content_cursor: Option<usize>, // Absolute content line, set by line jumps and clicks
     sidebar_scroll: usize,
     file_cursor: usize,
     popup_cursor: usize,
+    commit_anchor: Option<usize>, // Commit a range selection in the commit filter started at
+    commit_query: String,         // `/` filter on the commit filter's subjects and authors
+    commit_typing: bool,          // The commit query is being typed
+    commit_sort: CommitSort,
+    worktree_form: WorktreeForm,
 
     // Options
     show_hidden: bool,
+    group_by_commit: bool,
+    /// Timeline step being replayed, indexing commits oldest first
+    timeline: Option<usize>,
+    /// Commit shown on its own, against its first parent
+    only_commit: Option<Commit>,
+    /// Stash entry shown instead of the branch, against the commit it was made on
+    stash: Option<git::Stash>,
+    /// Stash entries, while the stash browser is open
+    stashes: Vec<git::Stash>,
+    /// Blame of each file's new lines, computed as files scroll into view; None hides the gutter
+    blame: Option<HashMap<String, Vec<BlameLine>>>,
+    /// Messages of the selected commits, while the message panel is open
+    commit_messages: Option<Vec<CommitMessage>>,
+    message_scroll: usize,
+    /// Wrap long lines onto continuation rows in the unified views
+    wrap: bool,
+    /// Columns of the diff view as last drawn, which wrapped lines fit
+    wrap_width: u16,
+    /// Show small binary files as hexdumps
+    hexdump: bool,
+    hexdump_max_bytes: u64,
+    full_file_lines: Option<usize>, // Files shorter than this show whole in the split view
+    change_filter: Option<ChangeKind>,
+    path_filter: Vec<String>, // Globs limiting the diff to matching paths
+    path_input: String,
+    language_filter: Option<String>, // Extensions or languages the files are narrowed to
+    language_filter_origin: Option<String>, // Language filter to restore when the bar is cancelled
+    stage: Stage, // Which uncommitted changes the diff covers
+    reviewed: HashMap<String, String>, // Files marked reviewed, by path, with the change id they had then
+    notes: LineNotes, // Notes left on lines of the branch (or revision) shown, by path then line
+    seen: HashSet<String>, // Paths of files viewed for at least SEEN_DELAY
+    recent: Vec<String>, // Viewed files, most recent first
+    viewing: Option<(String, Instant)>, // File at the top of the viewport, and since when
+    skip_reviewed: bool,
+    ignore_submodules: bool,
+    merge_base: bool, // Diff from where the branch forked off the base rather than the base's tip
+    algorithm: DiffAlgorithm,
+    collapse_globs: Vec<String>,
+    hidden_patterns: Vec<String>,
+    generated_patterns: Vec<Regex>,
+    generated_files: HashSet<String>, // Files whose content matches a generated pattern
+    include_generated: bool, // Expand generated files and count them in the totals
+    marked: HashSet<String>, // Marked file/folder paths; restricts content when not selecting
+    selecting: bool, // Sidebar selection mode: Space marks instead of collapsing
     context_lines: u32,
+    context_at: Option<Instant>, // When the pending context change is due
+    context_restore: u32, // Context `x` brings back after hiding it
     sidebar_width: u16,
+    sidebar_position: SidebarPosition,
     sidebar_dragging: bool, // True when dragging sidebar border to resize
+    sidebar_follow: bool, // Content previews the sidebar selection as it moves
+    follow_at: Option<Instant>, // When the pending follow scroll is due
 
-    // Filter input (for worktree switcher)
+    // Filter input (for worktree switcher and symbol picker)
     filter_input: String,
+    symbols: Vec<FileSymbol>, // Symbols of the shown files while the symbol picker is open
+    outline: Vec<OutlineEntry>, // Changed symbols of `outline_file` while the outline is open
+    outline_file: usize,
+    worktree_statuses: Vec<Option<git::WorktreeStatus>>, // Status per worktree, for the switcher and dashboard
+    worktree_status_rx: Option<Receiver<(PathBuf, Option<git::WorktreeStatus>)>>, // Statuses still being read
+    worktree_order: Vec<usize>, // Dashboard row order, as worktree indices
+    worktree_sort: WorktreeSort,
+    diffstat_nodes: Vec<TreeNode>, // Files and folders listed by the diffstat
+    diffstat_order: Vec<usize>, // Diffstat row order, as node indices
+    diffstat_sort: DiffstatSort,
+    worktree_confirm: Option<WorktreeAction>, // Worktree change awaiting confirmation
+    review_confirm: bool,           // Draft review awaiting confirmation before posting
+    path_tooltip: bool,             // Full path of the current file shown under the header until the next key
+    help_scroll: usize,
+    auto_unified: Option<DiffMode>, // Mode to restore once the terminal is wide enough again
+    sidebar_overlay: bool, // Sidebar floating over the content on a narrow terminal
+    tree_filter: Option<String>, // Sidebar filter query while the filter bar is open
+    tree_filter_origin: usize, // Sidebar cursor to restore when the filter is cancelled
 
     // Search state
     search_input: String,
@@ -102,69 +514,278 @@ pub struct App {
 
     // Number prefix for vim-style jumps
     number_prefix: Option<usize>,
+    // First key of a two-key command (e.g. `z` in `zz`)
+    pending_key: Option<char>,
+
+    // Keyboard macros: `Q{reg}` records, `@{reg}` replays
+    macros: HashMap<char, Vec<KeyEvent>>,
+    recording: Option<(char, Vec<KeyEvent>)>,
+    last_macro: Option<char>, // Register replayed by `@@`
+    macro_depth: usize, // Nesting of macros currently replaying
+
+    // Positions before significant jumps (Ctrl+o / Ctrl+i)
+    jump_list: JumpList<ViewPosition>,
 
     // Styling and highlighting
+    theme: Theme,
     styles: Styles,
     highlighter: Highlighter,
 
-    // Loading state
-    loading: bool,
+    // Loading state: the branch diff and highlights computed in the background
+    diff_load: Option<DiffLoad>,
+    loaded_diffs: Option<Vec<FileDiff>>, // Finished load, for reload_diffs to pick up
+    resume_position: Option<ViewPosition>, // Where the saved session was, for the load to scroll to
+    highlights: Option<Receiver<Highlighter>>,
     error: Option<String>,
+
+    // Files predicted to conflict with the base
+    conflict_files: Vec<String>,
+
+    // Files whose added lines still hold conflict markers
+    marker_files: Vec<String>,
+
+    // External annotations (--annotations)
+    annotations: AnnotationIndex,
+
+    // Background fetch and transient status line
+    fetch: Option<FetchState>,
+    status_message: Option<String>,
+
+    // Pull request picker; `pulls` is None while `gh` is still listing them
+    pulls: Option<Vec<PullRequest>>,
+    pulls_rx: Option<Receiver<Result<Vec<PullRequest>, String>>>,
+
+    // External diff tool and editor, run from the event loop with the terminal suspended
+    difftool: Option<String>,
+    external: Option<External>, // Program to run once the terminal is released
 }
 
 impl App {
     /// Create a new App instance
-    pub fn new(repo_path: PathBuf, base_branch: Option<String>) -> Result<Self> {
+    ///
+    /// `head` replaces HEAD as the revision being reviewed, e.g. to show a
+    /// single commit against its parent.
+    pub fn new(
+        repo_path: git::RepoPath,
+        base_branch: Option<String>,
+        head: Option<String>,
+        view: ViewOptions,
+    ) -> Result<Self> {
+        let head_rev = head.unwrap_or_else(|| "HEAD".to_string());
+
         // Discover the main branch
-        let main_branch = base_branch
-            .unwrap_or_else(|| git::get_main_branch(&repo_path).unwrap_or_else(|_| "main".to_string()));
+        let main_branch = match base_branch {
+            Some(base) => {
+                // Fail early on typos rather than showing an empty diff
+                git::resolve_base(&repo_path, &base)?;
+                base
+            }
+            None => git::get_main_branch(&repo_path).unwrap_or_else(|_| "main".to_string()),
+        };
 
+        let mut app = Self::with_view(repo_path, main_branch, head_rev, view);
+
+        // Load initial data
+        app.load_data()?;
+
+        // Notes are easy to miss on a single commit, so open them alongside its diff
+        if app.head_rev != "HEAD" {
+            app.wait_for_commits();
+            if app.commits.iter().any(|c| c.note.is_some()) {
+                app.toggle_message_panel();
+            }
+        }
+
+        Ok(app)
+    }
+
+    /// Create the application comparing two files rather than a branch
+    ///
+    /// `repo_path` needn't be a repository unless a side is a revision.
+    pub fn compare(
+        repo_path: git::RepoPath,
+        old: git::CompareSide,
+        new: git::CompareSide,
+        view: ViewOptions,
+    ) -> Result<Self> {
+        let mut app = Self::with_view(repo_path, String::new(), "HEAD".to_string(), view);
+        app.compare = Some((old, new));
+        app.load_data()?;
+        Ok(app)
+    }
+
+    /// Show a diff read from git's output, as its pager
+    pub fn piped(repo_path: git::RepoPath, diffs: Vec<FileDiff>, view: ViewOptions) -> Result<Self> {
+        let mut app = Self::with_view(repo_path, String::new(), "HEAD".to_string(), view);
+        app.piped = Some(diffs);
+        app.load_data()?;
+        Ok(app)
+    }
+
+    /// Application state before anything is loaded
+    fn with_view(
+        repo_path: git::RepoPath,
+        main_branch: String,
+        head_rev: String,
+        view: ViewOptions,
+    ) -> Self {
         let mut app = Self {
             width: 0,
             height: 0,
             repo_path,
             main_branch,
+            base_sha: None,
+            head_rev,
+            compare: None,
+            compare_input: String::new(),
+            note_target: None,
+            note_input: String::new(),
+            since_review: None,
+            base_steps: 0,
+            fork_point: None,
+            piped: None,
+            pull: None,
+            mouse: view.mouse,
             worktrees: Vec::new(),
             current_worktree: 0,
+            tabs: vec![Tab::default()],
+            active_tab: 0,
+            tab_origin: None,
             commits: Vec::new(),
+            commit_walk: None,
+            more_commits: false,
             diffs: Vec::new(),
+            diff_groups: Vec::new(),
+            line_index: OnceCell::new(),
+            diff_cache: git::DiffCache::default(),
+            source_hunks: Vec::new(),
+            source_context: SOURCE_CONTEXT_LINES,
             visible_diffs: Vec::new(),
             file_tree: Vec::new(),
+            sidebar_rows: Vec::new(),
             expanded_folders: HashMap::new(),
+            expanded_files: HashSet::new(),
+            collapsed_files: HashMap::new(),
             view_mode: ViewMode::Diff,
-            diff_mode: DiffMode::SideBySide,
+            diff_mode: view.diff_mode,
             focus: FocusArea::Content,
             content_scroll: 0,
+            content_cursor: None,
             sidebar_scroll: 0,
             file_cursor: 0,
             popup_cursor: 0,
-            show_hidden: false,
-            context_lines: 3,
+            commit_anchor: None,
+            commit_query: String::new(),
+            commit_typing: false,
+            commit_sort: CommitSort::default(),
+            worktree_form: WorktreeForm::default(),
+            show_hidden: view.show_hidden,
+            group_by_commit: false,
+            timeline: None,
+            only_commit: None,
+            stash: None,
+            stashes: Vec::new(),
+            blame: None,
+            commit_messages: None,
+            message_scroll: 0,
+            wrap: false,
+            wrap_width: 0,
+            hexdump: false,
+            hexdump_max_bytes: view.hexdump_max_bytes.min(git::HEXDUMP_LIMIT),
+            full_file_lines: view.full_file_lines,
+            change_filter: None,
+            path_filter: view.paths,
+            path_input: String::new(),
+            language_filter: None,
+            language_filter_origin: None,
+            stage: Stage::Both,
+            reviewed: HashMap::new(),
+            notes: LineNotes::new(),
+            seen: HashSet::new(),
+            recent: Vec::new(),
+            viewing: None,
+            skip_reviewed: view.skip_reviewed,
+            ignore_submodules: view.ignore_submodules,
+            merge_base: view.merge_base,
+            algorithm: view.algorithm,
+            collapse_globs: view.collapse,
+            hidden_patterns: view.hidden,
+            generated_patterns: view.generated,
+            generated_files: HashSet::new(),
+            include_generated: false,
+            marked: HashSet::new(),
+            selecting: false,
+            context_lines: view.context_lines,
+            commit_limit: view.max_commits,
+            context_at: None,
+            context_restore: view.context_lines.max(1),
             sidebar_width: DEFAULT_SIDEBAR_WIDTH,
+            sidebar_position: view.sidebar,
             sidebar_dragging: false,
+            sidebar_follow: false,
+            follow_at: None,
             filter_input: String::new(),
+            symbols: Vec::new(),
+            outline: Vec::new(),
+            outline_file: 0,
+            worktree_statuses: Vec::new(),
+            worktree_status_rx: None,
+            worktree_order: Vec::new(),
+            worktree_sort: WorktreeSort::default(),
+            diffstat_nodes: Vec::new(),
+            diffstat_order: Vec::new(),
+            diffstat_sort: DiffstatSort::default(),
+            worktree_confirm: None,
+            review_confirm: false,
+            path_tooltip: false,
+            help_scroll: 0,
+            auto_unified: None,
+            sidebar_overlay: false,
+            tree_filter: None,
+            tree_filter_origin: 0,
             search_input: String::new(),
             search_matches: Vec::new(),
             search_match_index: 0,
             search_active: false,
             number_prefix: None,
-            styles: Styles::new(),
+            pending_key: None,
+            macros: HashMap::new(),
+            recording: None,
+            last_macro: None,
+            macro_depth: 0,
+            jump_list: JumpList::default(),
+            theme: view.theme,
+            styles: Styles::for_theme(view.theme),
             highlighter: Highlighter::new(),
-            loading: true,
+            diff_load: None,
+            loaded_diffs: None,
+            resume_position: None,
+            highlights: None,
             error: None,
+            conflict_files: Vec::new(),
+            marker_files: Vec::new(),
+            annotations: AnnotationIndex::default(),
+            fetch: None,
+            status_message: None,
+            pulls: None,
+            pulls_rx: None,
+            difftool: view.difftool,
+            external: None,
         };
 
-        // Load initial data
-        app.load_data()?;
+        app.highlighter.set_theme(view.theme.syntax_theme());
+        app
+    }
 
-        Ok(app)
+    /// Attach external annotations (linter findings) to the diff
+    pub fn set_annotations(&mut self, annotations: Vec<Annotation>) {
+        self.annotations = AnnotationIndex::new(annotations);
     }
 
     /// Load/reload data from the repository
     fn load_data(&mut self) -> Result<()> {
-        self.loading = true;
         self.error = None;
-        self.highlighter.set_base_path(self.repo_path.clone());
+        self.highlighter.set_base_path(self.repo_path.to_path_buf());
 
         // Load worktrees
         self.worktrees = git::list_worktrees(&self.repo_path).unwrap_or_default();
@@ -176,173 +797,1183 @@ impl App {
             .position(|w| w.is_current)
             .unwrap_or(0);
 
-        // Load commits
-        self.commits = git::list_commits(&self.repo_path, &self.main_branch).unwrap_or_default();
+        self.notes = self.notes_key().map(|key| notes::load(&self.repo_path, key)).unwrap_or_default();
 
-        // Load diffs
-        self.reload_diffs()?;
+        // Positions from another worktree are meaningless here
+        self.jump_list.clear();
+        self.content_cursor = None;
 
-        self.loading = false;
-        Ok(())
+        // Relative bases like HEAD~5 move with the worktree, and fetches move branches
+        // A comparison of two files or a piped diff has neither a base nor commits
+        self.commits = Vec::new();
+        if self.compare.is_some() || self.piped.is_some() {
+            self.base_sha = None;
+            self.fork_point = None;
+            self.commit_walk = None;
+            self.more_commits = false;
+        } else {
+            // Without a common ancestor there's nothing to fork from, so the base's tip is used
+            self.fork_point = self
+                .merge_base
+                .then(|| git::merge_base(&self.repo_path, &self.main_branch, &self.head_rev).ok())
+                .flatten()
+                .and_then(|full| git::resolve_base(&self.repo_path, &full).ok());
+            self.base_sha = git::resolve_base(&self.repo_path, &self.diff_base()).ok();
+            if self.head_rev == "HEAD" && git::has_uncommitted_changes(&self.repo_path).unwrap_or(false) {
+                self.commits.push(Commit::uncommitted());
+            }
+            self.spawn_commit_walk(self.commit_limit);
+        }
+
+        // Load diffs; a branch's are computed in the background, the old ones staying up meanwhile
+        if self.shows_branch_diff() {
+            self.spawn_diff_load();
+            Ok(())
+        } else {
+            self.reload_diffs()
+        }
     }
 
-    /// Reload diffs based on current commit selection
-    fn reload_diffs(&mut self) -> Result<()> {
+    /// Whether `reload_diffs` shows the branch against its base, rather than
+    /// compared files, a piped diff, a timeline step or commit groups
+    fn shows_branch_diff(&self) -> bool {
+        self.compare.is_none()
+            && self.piped.is_none()
+            && self.only_commit.is_none()
+            && self.stash.is_none()
+            && self.timeline.is_none()
+            && !self.group_by_commit
+    }
+
+    /// Base, head, whether uncommitted changes count and the selected commits of the branch diff
+    fn branch_diff_args(&self) -> (String, String, bool, Vec<String>) {
         let include_uncommitted = self.commits
             .iter()
             .any(|c| c.is_uncommitted && c.selected);
 
-        let selected_hashes: Vec<String> = self.commits
+        let mut selected_hashes: Vec<String> = self.commits
             .iter()
             .filter(|c| c.selected && !c.is_uncommitted)
             .map(|c| c.full_hash.clone())
             .collect();
+        // Commits still on their way start selected like the rest
+        if selected_hashes.is_empty() && self.commit_walk.is_some() && self.commits.iter().all(|c| c.is_uncommitted) {
+            selected_hashes.push(self.head_rev.clone());
+        }
+        (self.diff_base().to_string(), self.head_rev.clone(), include_uncommitted, selected_hashes)
+    }
+
+    /// Lines of context diffs are computed with, so they can be narrowed without recomputing
+    fn wanted_source_context(&self) -> u32 {
+        // A piped diff has whatever context git gave it, and can only be narrowed
+        match self.piped {
+            Some(_) => u32::MAX,
+            None => self.context_lines.max(SOURCE_CONTEXT_LINES),
+        }
+    }
 
-        self.diffs = git::compute_diff(
+    /// Compute the branch diff on a background thread, or show it at once if cached
+    fn spawn_diff_load(&mut self) {
+        let (base, head, include_uncommitted, selected_hashes) = self.branch_diff_args();
+        let context = self.wanted_source_context();
+        let key = git::DiffKey::new(
             &self.repo_path,
-            &self.main_branch,
+            &base,
+            &head,
             include_uncommitted,
             &selected_hashes,
-            self.context_lines,
+            context,
+            self.ignore_submodules,
+            self.algorithm,
+        );
+        if self.diff_cache.contains(&key) {
+            if let Err(e) = self.reload_diffs() {
+                self.status_message = Some(format!("Reload failed: {}", e));
+            }
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let repo_path = self.repo_path.clone();
+        let ignore_submodules = self.ignore_submodules;
+        let stage = self.stage;
+        let algorithm = self.algorithm;
+        thread::spawn(move || {
+            let result = git::compute_diff(
+                &repo_path,
+                &base,
+                &head,
+                include_uncommitted,
+                &selected_hashes,
+                context,
+                ignore_submodules,
+                stage,
+                algorithm,
+            );
+            let _ = tx.send(result.map_err(|e| format!("{:#}", e)));
+        });
+        self.diff_load = Some(DiffLoad { key, context, rx });
+    }
+
+    /// Show the background diff once computed, blocking until it is with `wait`
+    fn poll_diffs(&mut self, wait: bool) {
+        let Some(load) = &self.diff_load else {
+            return;
+        };
+        let result = match if wait { load.rx.recv().map_err(|_| TryRecvError::Disconnected) } else { load.rx.try_recv() } {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("diff thread exited".to_string()),
+        };
+        let Some(DiffLoad { key, context, .. }) = self.diff_load.take() else {
+            return;
+        };
+        // Context widened past what it's computing needs computing again
+        if context != self.wanted_source_context() {
+            self.spawn_diff_load();
+            return;
+        }
+        let diffs = result.unwrap_or_else(|e| {
+            self.status_message = Some(format!("Loading the diff failed: {}", e));
+            Vec::new()
+        });
+        self.diff_cache.insert(key, &diffs);
+
+        // The old diffs were browsable meanwhile, so keep the reader where they
+        // are, unless the load was to resume a saved session
+        let position = self.resume_position.take().unwrap_or_else(|| self.view_position());
+        self.loaded_diffs = Some(diffs);
+        if let Err(e) = self.reload_diffs() {
+            self.status_message = Some(format!("Reload failed: {}", e));
+        }
+        self.restore_view_position(&position);
+    }
+
+    /// Block until the branch diff has been computed and highlighted
+    pub fn wait_for_diffs(&mut self) {
+        self.poll_diffs(true);
+        self.poll_highlights(true);
+    }
+
+    /// Compute (or fetch from the cache) the diff of `head` against `base`
+    fn load_diff(
+        &mut self,
+        base: &str,
+        head: &str,
+        include_uncommitted: bool,
+        selected_hashes: &[String],
+    ) -> Vec<FileDiff> {
+        let key = git::DiffKey::new(
+            &self.repo_path,
+            base,
+            head,
+            include_uncommitted,
+            selected_hashes,
+            self.source_context,
+            self.ignore_submodules,
+            self.algorithm,
+        );
+        if let Some(diffs) = self.diff_cache.get(&key) {
+            return diffs;
+        }
+
+        let diffs = git::compute_diff(
+            &self.repo_path,
+            base,
+            head,
+            include_uncommitted,
+            selected_hashes,
+            self.source_context,
+            self.ignore_submodules,
+            self.stage,
+            self.algorithm,
         ).unwrap_or_default();
+        self.diff_cache.insert(key, &diffs);
+        diffs
+    }
+
+    /// Compute (or fetch from the cache) the changes of one commit against its first parent
+    fn load_commit_diff(&mut self, hash: &str) -> Vec<FileDiff> {
+        let key = git::DiffKey::new(
+            &self.repo_path,
+            &format!("{}^", hash),
+            hash,
+            false,
+            &[hash.to_string()],
+            self.source_context,
+            self.ignore_submodules,
+            self.algorithm,
+        );
+        if let Some(diffs) = self.diff_cache.get(&key) {
+            return diffs;
+        }
+
+        let diffs = git::compute_commit_diff(&self.repo_path, hash, self.source_context, self.ignore_submodules, self.algorithm)
+            .unwrap_or_default();
+        self.diff_cache.insert(key, &diffs);
+        diffs
+    }
+
+    /// Diffs of each selected commit against its parent, oldest first
+    ///
+    /// Uncommitted changes come last. Fills `diff_groups` with each file's commit.
+    fn load_commit_groups(&mut self) -> Vec<FileDiff> {
+        let groups: Vec<(String, Option<String>)> = self.commits
+            .iter()
+            .rev()
+            .filter(|c| c.selected)
+            .map(|c| {
+                let hash = (!c.is_uncommitted).then(|| c.full_hash.clone());
+                let label = match &hash {
+                    Some(_) => format!("{} {}", c.hash, c.subject),
+                    None => match self.stage.label() {
+                        Some(stage) => format!("Uncommitted changes ({})", stage),
+                        None => "Uncommitted changes".to_string(),
+                    },
+                };
+                (label, hash)
+            })
+            .collect();
+
+        let mut diffs = Vec::new();
+        for (label, hash) in groups {
+            let group = match hash {
+                Some(hash) => self.load_commit_diff(&hash),
+                None => self.load_diff("HEAD", "HEAD", true, &[]),
+            };
+            self.diff_groups.extend(std::iter::repeat_n(label, group.len()));
+            diffs.extend(group);
+        }
+        diffs
+    }
+
+    /// Cumulative diff of the base against a timeline step
+    ///
+    /// Steps are the branch commits oldest first, then uncommitted changes.
+    fn load_timeline_step(&mut self, step: usize) -> Vec<FileDiff> {
+        let base = self.diff_base().to_string();
+        let Some(commit) = self.commits.iter().rev().nth(step) else {
+            return Vec::new();
+        };
+        if commit.is_uncommitted {
+            self.load_diff(&base, "HEAD", true, &["HEAD".to_string()])
+        } else {
+            let hash = commit.full_hash.clone();
+            self.load_diff(&base, &hash, false, std::slice::from_ref(&hash))
+        }
+    }
+
+    /// Start, move or stop the timeline scrubber, keeping the view in place
+    fn set_timeline(&mut self, timeline: Option<usize>) {
+        if timeline == self.timeline {
+            return;
+        }
+        self.timeline = timeline;
+        self.only_commit = None;
+        self.stash = None;
+        let position = self.view_position();
+        if let Err(e) = self.reload_diffs() {
+            self.status_message = Some(format!("Reload failed: {}", e));
+        }
+        self.restore_view_position(&position);
+    }
+
+    /// Move the timeline scrubber by `delta` steps
+    fn move_timeline(&mut self, delta: i32) {
+        let Some(step) = self.timeline else {
+            return;
+        };
+        let last = self.commits.len().saturating_sub(1);
+        let step = (step as i64 + delta as i64).clamp(0, last as i64) as usize;
+        self.set_timeline(Some(step));
+    }
+
+    /// Refresh the message panel's commits from the current selection
+    fn load_commit_messages(&mut self) {
+        let Some(messages) = &mut self.commit_messages else {
+            return;
+        };
+        *messages = self.commits
+            .iter()
+            .filter(|c| c.selected && !c.is_uncommitted)
+            .map(|c| CommitMessage {
+                hash: c.hash.clone(),
+                message: git::commit_message(&self.repo_path, &c.full_hash)
+                    .unwrap_or_else(|_| c.subject.clone()),
+                note: c.note.clone(),
+                signature: signature_label(c.signature, c.signer.as_deref()),
+            })
+            .collect();
+    }
+
+    /// Open or close the commit message panel
+    fn toggle_message_panel(&mut self) {
+        if self.commit_messages.take().is_none() {
+            self.commit_messages = Some(Vec::new());
+            self.message_scroll = 0;
+            self.load_commit_messages();
+        }
+    }
+
+    /// Width of the commit message panel, or 0 while it's closed
+    fn message_panel_width(&self) -> u16 {
+        match self.commit_messages {
+            Some(_) => message_panel_width(self.width.saturating_sub(self.sidebar_layout_width())),
+            None => 0,
+        }
+    }
+
+    /// Reload diffs based on current commit selection
+    fn reload_diffs(&mut self) -> Result<()> {
+        // Whatever a background load was computing is superseded by what's loaded now
+        let loaded = self.loaded_diffs.take();
+        self.diff_load = None;
+
+        self.source_context = self.wanted_source_context();
+        self.diff_groups.clear();
+        if let Some(blame) = &mut self.blame {
+            blame.clear();
+        }
+        self.diffs = if let Some((old, new)) = &self.compare {
+            git::compare_files(&self.repo_path, old, new, self.source_context)?
+        } else if let Some(diffs) = &self.piped {
+            diffs.clone()
+        } else if let Some(stash) = &self.stash {
+            git::compute_stash_diff(&self.repo_path, &stash.full_hash, self.source_context, self.ignore_submodules, self.algorithm)?
+        } else if let Some(commit) = &self.only_commit {
+            if commit.is_uncommitted {
+                self.load_diff("HEAD", "HEAD", true, &[])
+            } else {
+                let hash = commit.full_hash.clone();
+                self.load_commit_diff(&hash)
+            }
+        } else if let Some(step) = self.timeline {
+            self.load_timeline_step(step)
+        } else if self.group_by_commit {
+            self.load_commit_groups()
+        } else if let Some(diffs) = loaded {
+            diffs
+        } else {
+            let (base, head, include_uncommitted, selected_hashes) = self.branch_diff_args();
+            self.load_diff(&base, &head, include_uncommitted, &selected_hashes)
+        };
 
-        // Collapse hidden files by default
+        // Code moved rather than rewritten is told apart from real removals and additions
+        git::mark_moved(&mut self.diffs);
+        self.drop_stale_reviews();
+        self.source_hunks = self.diffs.iter().map(|d| d.hunks.clone()).collect();
+        self.marker_files = self
+            .diffs
+            .iter()
+            .filter(|diff| {
+                diff.hunks.iter().flat_map(|hunk| &hunk.lines).any(|line| {
+                    line.line_type == LineType::Added && git::is_conflict_marker(&line.content)
+                })
+            })
+            .map(|diff| diff.path.clone())
+            .collect();
+        if self.context_lines < self.source_context {
+            for diff in &mut self.diffs {
+                diff.hunks = git::reduce_context(&diff.hunks, self.context_lines);
+            }
+        }
+        // Collapse hidden files unless they're shown; a file compared on purpose never is
         for diff in &mut self.diffs {
-            if is_hidden_file(&diff.path) {
-                diff.collapsed = true;
+            if is_hidden_file(&diff.path, &self.hidden_patterns) && self.compare.is_none() {
+                diff.collapsed = !self.show_hidden;
             }
         }
+        self.apply_auto_collapse();
+        self.generated_files = self
+            .diffs
+            .iter()
+            .filter(|diff| is_generated(diff, &self.generated_patterns))
+            .map(|diff| diff.path.clone())
+            .collect();
+        for diff in &mut self.diffs {
+            if self.generated_files.contains(&diff.path) {
+                diff.collapsed = !self.include_generated;
+            }
+        }
+        // Files collapsed or expanded by hand come back that way
+        for diff in &mut self.diffs {
+            if let Some(&collapsed) = self.collapsed_files.get(&diff.path) {
+                diff.collapsed = collapsed;
+            }
+        }
+        self.apply_hexdump();
+        self.apply_full_file_lines();
+        self.load_commit_messages();
 
-        // Rebuild file tree
-        self.file_tree = build_file_tree(&self.diffs, &self.expanded_folders);
-        self.set_sidebar_cursor(self.file_cursor);
-
-        // Update visible diffs
+        // Update visible diffs and rebuild file tree
         self.update_visible_diffs();
+        self.rebuild_file_tree();
+        self.set_sidebar_cursor(self.file_cursor);
 
         // Clear highlight cache when diffs change
         self.highlighter.clear_cache();
-        self.prime_highlight_cache();
-        if self.diff_mode == DiffMode::SideBySideFull {
-            self.prime_full_highlight_cache();
-        }
+        self.prime_highlights();
         self.set_content_scroll(self.content_scroll);
 
         Ok(())
     }
 
-    fn prime_highlight_cache(&mut self) {
-        for diff in &self.diffs {
-            if diff.is_binary {
-                continue;
-            }
-
-            // Collect lines grouped by hunk for proper multi-line construct handling
-            let hunks: Vec<Vec<&str>> = diff.hunks
+    /// Highlight every file's hunks, and the whole of each file shown in full, in the background
+    ///
+    /// Until the results land, lines are highlighted one at a time as they're drawn.
+    fn prime_highlights(&mut self) {
+        let mut jobs = Vec::new();
+        for diff in self.diffs.iter().filter(|diff| !diff.is_binary) {
+            // Per-hunk stateful highlighting preserves multi-line constructs
+            // (like block comments) within hunks while resetting between hunks
+            let hunks: Vec<Vec<String>> = diff.hunks
                 .iter()
-                .map(|hunk| hunk.lines.iter().map(|l| l.content.as_str()).collect())
+                .map(|hunk| hunk.lines.iter().map(|l| l.content.clone()).collect())
                 .collect();
-
             if !hunks.is_empty() {
-                // Use per-hunk stateful highlighting - preserves multi-line constructs
-                // (like block comments) within hunks while resetting between hunks
-                let _ = self.highlighter.highlight_hunks(&diff.path, &diff.path, &hunks);
+                jobs.push(HighlightJob::Hunks { path: diff.path.clone(), hunks });
             }
-        }
-    }
 
-    fn prime_full_highlight_cache(&mut self) {
-        for diff in &self.diffs {
-            if diff.is_binary {
+            if file_mode(diff, self.diff_mode) != DiffMode::SideBySideFull {
                 continue;
             }
-
             let old_filename = diff.old_path.as_deref().unwrap_or(&diff.path);
-            let new_filename = diff.path.as_str();
-            let old_cache_key = format!("{}::full::old", old_filename);
-            let new_cache_key = format!("{}::full::new", new_filename);
-
-            if let Some(old_lines) = diff.old_content.as_ref() {
-                let line_refs: Vec<&str> = old_lines.iter().map(|line| line.as_str()).collect();
-                if !line_refs.is_empty() {
-                    let _ = self.highlighter.highlight_lines(&old_cache_key, old_filename, &line_refs);
-                }
-            }
-
-            if let Some(new_lines) = diff.new_content.as_ref() {
-                let line_refs: Vec<&str> = new_lines.iter().map(|line| line.as_str()).collect();
-                if !line_refs.is_empty() {
-                    let _ = self.highlighter.highlight_lines(&new_cache_key, new_filename, &line_refs);
+            let sides = [(old_filename, "old", &diff.old_content), (diff.path.as_str(), "new", &diff.new_content)];
+            for (filename, side, content) in sides {
+                if let Some(lines) = content.as_ref().filter(|lines| !lines.is_empty()) {
+                    jobs.push(HighlightJob::Full {
+                        cache_key: format!("{}::full::{}", filename, side),
+                        filename: filename.to_string(),
+                        lines: lines.clone(),
+                    });
                 }
             }
         }
-    }
 
-    /// Update the list of visible diff indices
-    fn update_visible_diffs(&mut self) {
-        // All diffs are visible (hidden files are collapsed, not filtered)
-        self.visible_diffs = (0..self.diffs.len()).collect();
+        // Replacing the receiver drops results primed for diffs since changed
+        let (tx, rx) = mpsc::channel();
+        let mut highlighter = self.highlighter.detached();
+        thread::spawn(move || {
+            for job in &jobs {
+                job.run(&mut highlighter);
+            }
+            let _ = tx.send(highlighter);
+        });
+        self.highlights = Some(rx);
     }
 
-    /// Get the current branch name
-    fn current_branch(&self) -> &str {
-        self.worktrees
-            .get(self.current_worktree)
-            .and_then(|w| w.branch.as_deref())
-            .unwrap_or("HEAD")
+    /// Take in the background highlights once done, blocking until they are with `wait`
+    fn poll_highlights(&mut self, wait: bool) {
+        let Some(rx) = &self.highlights else {
+            return;
+        };
+        let primed = match if wait { rx.recv().map_err(|_| TryRecvError::Disconnected) } else { rx.try_recv() } {
+            Ok(primed) => primed,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                self.highlights = None;
+                return;
+            }
+        };
+        self.highlights = None;
+        self.highlighter.merge(primed);
     }
 
-    /// Run the application
-    pub fn run(&mut self) -> Result<()> {
-        // Setup terminal
-        enable_raw_mode()?;
-        let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, crossterm::event::EnableMouseCapture)?;
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
+    /// Update the list of visible diff indices
+    fn update_visible_diffs(&mut self) {
+        // Hidden files are collapsed, not filtered; change-type, path and language filters and marks remove files.
+        // Marks only restrict once selection mode is left, so more files can be marked.
+        let restrict = !self.selecting && !self.marked.is_empty();
+        self.visible_diffs = self.diffs
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| self.change_filter.is_none_or(|kind| d.change == kind))
+            .filter(|(_, d)| {
+                self.path_filter.is_empty()
+                    || path_filter_matches(&self.path_filter, &d.path)
+                    || d.old_path.as_deref().is_some_and(|old| path_filter_matches(&self.path_filter, old))
+            })
+            .filter(|(_, d)| self.language_filter.as_deref().is_none_or(|query| language_matches(query, &d.path)))
+            .filter(|(_, d)| !restrict || is_marked(&d.path, &self.marked))
+            .map(|(i, _)| i)
+            .collect();
 
-        // Main loop
-        loop {
-            // Draw
-            terminal.draw(|frame| {
-                self.width = frame.area().width;
-                self.height = frame.area().height;
-                self.render(frame);
-            })?;
-
-            // Handle events
-            if event::poll(std::time::Duration::from_millis(100))? {
-                match event::read()? {
-                    Event::Key(key) => {
-                        if self.handle_key(key) {
-                            break;
-                        }
-                    }
-                    Event::Mouse(mouse) => {
-                        self.handle_mouse(mouse);
-                    }
-                    Event::Resize(w, h) => {
-                        self.width = w;
-                        self.height = h;
-                    }
-                    _ => {}
-                }
+        // Head each commit's first visible file with the commit
+        for diff in &mut self.diffs {
+            diff.section = None;
+        }
+        let mut previous = None;
+        for &i in &self.visible_diffs {
+            let group = self.diff_groups.get(i);
+            if group.is_some() && group != previous {
+                self.diffs[i].section = group.cloned();
             }
+            previous = group;
         }
-
-        // Restore terminal
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            crossterm::event::DisableMouseCapture
-        )?;
-
-        Ok(())
+        self.invalidate_layout();
     }
 
-    /// Render the application
-    fn render(&mut self, frame: &mut ratatui::Frame) {
+    /// Rebuild the sidebar tree from the visible diffs
+    ///
+    /// While the filter bar is open only matching files are listed, with
+    /// every folder expanded so none of them are hidden.
+    fn rebuild_file_tree(&mut self) {
+        self.file_tree = match &self.tree_filter {
+            Some(query) => {
+                let matching: Vec<usize> = self.visible_diffs
+                    .iter()
+                    .copied()
+                    .filter(|&i| fuzzy_matches(query, &self.diffs[i].path))
+                    .collect();
+                build_file_tree(&self.diffs, &matching, &HashMap::new(), &HashSet::new(), &self.hidden_patterns)
+            }
+            None => build_file_tree(
+                &self.diffs,
+                &self.visible_diffs,
+                &self.expanded_folders,
+                &self.expanded_files,
+                &self.hidden_patterns,
+            ),
+        };
+        self.sidebar_rows = flatten_tree(&self.file_tree);
+    }
+
+    /// Re-derive visible diffs, sidebar, and scroll after a filter change
+    fn apply_filters(&mut self) {
+        self.update_visible_diffs();
+        self.rebuild_file_tree();
+        self.set_sidebar_cursor(self.file_cursor);
+        self.set_content_scroll(self.content_scroll);
+    }
+
+    /// Cycle the change-type filter: all → added → deleted → modified → renamed
+    fn cycle_change_filter(&mut self) {
+        self.change_filter = match self.change_filter {
+            None => Some(ChangeKind::Added),
+            Some(ChangeKind::Added) => Some(ChangeKind::Deleted),
+            Some(ChangeKind::Deleted) => Some(ChangeKind::Modified),
+            Some(ChangeKind::Modified) => Some(ChangeKind::Renamed),
+            Some(ChangeKind::Renamed) => None,
+        };
+        self.apply_filters();
+        self.status_message = Some(match self.change_filter {
+            Some(kind) => format!("Showing {} files only", kind.label()),
+            None => "Showing all files".to_string(),
+        });
+    }
+
+    /// Prompt for the path filter's globs, starting from the current ones
+    fn start_path_filter(&mut self) {
+        self.path_input = self.path_filter.join(" ");
+        self.view_mode = ViewMode::PathPrompt;
+    }
+
+    /// Limit the diff to paths matching any of the space-separated globs in `input`, or show all when empty
+    fn set_path_filter(&mut self, input: &str) {
+        self.path_filter = input.split_whitespace().map(str::to_string).collect();
+        self.apply_filters();
+        self.status_message = Some(match self.path_filter.is_empty() {
+            true => "Showing all paths".to_string(),
+            false => format!("Showing {} of {} files", self.visible_diffs.len(), self.diffs.len()),
+        });
+    }
+
+    /// Open the language filter bar, or clear the filter when one is set
+    fn toggle_language_filter(&mut self) {
+        if self.language_filter.take().is_some() {
+            self.apply_filters();
+            self.status_message = Some("Showing all languages".to_string());
+            return;
+        }
+        self.language_filter_origin = None;
+        self.language_filter = Some(String::new());
+        self.view_mode = ViewMode::LanguageFilter;
+    }
+
+    /// Edit the language filter, narrowing the files as it's typed
+    fn update_language_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(query) = self.language_filter.as_mut() {
+            edit(query);
+        }
+        self.apply_filters();
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+        let top = nodes.iter().position(|n| !n.is_folder).unwrap_or(0);
+        self.sidebar_scroll = 0;
+        self.set_sidebar_cursor(top);
+    }
+
+    /// Cycle which uncommitted changes are shown: all → staged → unstaged
+    fn cycle_stage(&mut self) {
+        if !self.commits.iter().any(|c| c.is_uncommitted && c.selected) {
+            self.status_message = Some("No uncommitted changes selected".to_string());
+            return;
+        }
+        self.stage = self.stage.next();
+        self.status_message = Some(match self.reload_diffs() {
+            Err(e) => format!("Reload failed: {}", e),
+            Ok(()) => match self.stage.label() {
+                Some(stage) => format!("Showing {} changes only", stage),
+                None => "Showing staged and unstaged changes".to_string(),
+            },
+        });
+    }
+
+    /// Match up lines with the next diff algorithm and recompute the diff
+    fn cycle_algorithm(&mut self) {
+        if self.compare.is_some() || self.piped.is_some() {
+            self.status_message = Some("Compared and piped diffs keep their algorithm".to_string());
+            return;
+        }
+        self.algorithm = self.algorithm.next();
+        let position = self.view_position();
+        self.status_message = Some(match self.reload_diffs() {
+            Err(e) => format!("Reload failed: {}", e),
+            Ok(()) => format!("Diff algorithm: {}", self.algorithm.name()),
+        });
+        self.restore_view_position(&position);
+    }
+
+    /// Diffs currently shown in the content view
+    fn visible_files(&self) -> impl Iterator<Item = &FileDiff> {
+        self.visible_diffs.iter().filter_map(|&i| self.diffs.get(i))
+    }
+
+    /// Get the current branch name, or the revision being shown
+    fn current_branch(&self) -> &str {
+        if self.head_rev != "HEAD" {
+            return &self.head_rev;
+        }
+        self.worktrees
+            .get(self.current_worktree)
+            .and_then(|w| w.branch.as_deref())
+            .unwrap_or("HEAD")
+    }
+
+    /// Names of the new and old side: branch and base, or the two compared files
+    fn compared_labels(&self) -> (String, String) {
+        match &self.compare {
+            Some((old, new)) => (new.label(), old.label()),
+            None if self.piped.is_some() => ("stdin".to_string(), "git".to_string()),
+            None if self.since_review.is_some() => (self.current_branch().to_string(), "last review".to_string()),
+            None if self.base_steps > 0 => (self.current_branch().to_string(), self.diff_base().into_owned()),
+            None => (self.current_branch().to_string(), self.main_branch.clone()),
+        }
+    }
+
+    /// Revision the diff starts from: where the branch forked off the base
+    /// (or the base itself), the last reviewed commit, or `HEAD~N`
+    fn diff_base(&self) -> Cow<'_, str> {
+        if let Some(commit) = &self.since_review {
+            Cow::Borrowed(commit)
+        } else if self.base_steps > 0 {
+            Cow::Owned(format!("{}~{}", self.head_rev, self.base_steps))
+        } else {
+            Cow::Borrowed(self.fork_point.as_deref().unwrap_or(&self.main_branch))
+        }
+    }
+
+    /// Switch between diffing from where the branch forked off the base
+    /// (`base...HEAD`) and from the base's tip (`base..HEAD`)
+    fn toggle_merge_base(&mut self) {
+        if self.compare.is_some() || self.piped.is_some() {
+            self.status_message = Some("Only a branch's diff has a merge base".to_string());
+            return;
+        }
+        let position = self.view_position();
+        self.merge_base = !self.merge_base;
+        self.status_message = Some(match self.load_data() {
+            Err(e) => e.to_string(),
+            Ok(()) if self.merge_base => format!("Diffing from where the branch forked ({}...{})", self.main_branch, self.head_rev),
+            Ok(()) => format!("Diffing against the base's tip ({}..{})", self.main_branch, self.head_rev),
+        });
+        self.restore_view_position(&position);
+    }
+
+    /// Move the base one commit further back from the head (`-`) or nearer (`+`)
+    ///
+    /// Stepping nearer than `HEAD~1` returns to the branch's own base, so
+    /// a couple of presses answer "what did the last commits change?".
+    fn step_base(&mut self, back: bool) {
+        if self.compare.is_some() || self.piped.is_some() {
+            self.status_message = Some("Only a branch's base can be stepped".to_string());
+            return;
+        }
+        let steps = match (back, self.base_steps) {
+            (true, steps) => steps + 1,
+            (false, 0) => {
+                self.status_message = Some(format!("Base: {}", self.diff_base()));
+                return;
+            }
+            (false, steps) => steps - 1,
+        };
+        let base = format!("{}~{}", self.head_rev, steps);
+        if steps > 0 && git::resolve_base(&self.repo_path, &base).is_err() {
+            self.status_message = Some(format!("{} doesn't exist", base));
+            return;
+        }
+
+        let position = self.view_position();
+        self.base_steps = steps;
+        self.since_review = None;
+        match self.load_data() {
+            Ok(()) => {
+                self.restore_view_position(&position);
+                self.status_message = Some(format!("Base: {}", self.diff_base()));
+            }
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Branch whose review is tracked: the worktree's, when showing its HEAD
+    fn review_branch(&self) -> Option<&str> {
+        if self.compare.is_some() || self.piped.is_some() || self.head_rev != "HEAD" {
+            return None;
+        }
+        self.worktrees.get(self.current_worktree).and_then(|w| w.branch.as_deref())
+    }
+
+    /// Show only what changed since the branch was last reviewed
+    pub fn since_last_review(&mut self) -> Result<()> {
+        let Some(branch) = self.review_branch().map(str::to_string) else {
+            bail!("Reviews are only tracked for a worktree's branch");
+        };
+        let Some(commit) = review::last_reviewed(&self.repo_path, &branch) else {
+            bail!("{} hasn't been reviewed yet", branch);
+        };
+        if git::resolve_base(&self.repo_path, &commit).is_err() {
+            bail!("The last reviewed commit of {} no longer exists", branch);
+        }
+        self.since_review = Some(commit);
+        self.base_steps = 0;
+        self.load_data()
+    }
+
+    /// Toggle between the whole branch and what changed since the last review
+    fn toggle_since_review(&mut self) {
+        let position = self.view_position();
+        let result = if self.since_review.take().is_some() {
+            self.load_data()
+        } else {
+            self.since_last_review()
+        };
+        match result {
+            Ok(()) => self.restore_view_position(&position),
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
+    /// Remember where each tab's branch was reviewed up to, for `U` next time
+    ///
+    /// Tabs where no file was looked at or (un)marked reviewed this session are
+    /// skipped, so a quick peek doesn't hide changes nobody read. Marks
+    /// restored from the last session don't count.
+    pub fn record_review(&self) -> Result<()> {
+        let active = (
+            &self.repo_path,
+            self.current_worktree,
+            &self.head_rev,
+            self.compare.is_some() || self.piped.is_some(),
+            &self.seen,
+        );
+        let parked = self.tabs
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != self.active_tab)
+            .map(|(_, t)| {
+                (&t.repo_path, t.current_worktree, &t.head_rev, t.compare.is_some() || t.piped.is_some(), &t.seen)
+            });
+
+        for (repo_path, worktree, head_rev, not_branch, seen) in std::iter::once(active).chain(parked) {
+            if not_branch || head_rev != "HEAD" || seen.is_empty() {
+                continue;
+            }
+            let Some(branch) = self.worktrees.get(worktree).and_then(|w| w.branch.as_deref()) else {
+                continue;
+            };
+            let commit = git::resolve_commit(repo_path, head_rev)?;
+            review::record_reviewed(repo_path, branch, &commit)?;
+        }
+        Ok(())
+    }
+
+    /// Pick the review up where the last session on this branch left it
+    ///
+    /// Files marked reviewed or collapsed come back, as do the commits left
+    /// out and the scroll position. Reviewed files that changed since reopen.
+    pub fn resume_session(&mut self) {
+        let Some(saved) = self.review_branch().and_then(|branch| session::load(&self.repo_path, branch)) else {
+            return;
+        };
+        self.reviewed.extend(saved.reviewed);
+        self.collapsed_files.extend(saved.collapsed);
+        for diff in &mut self.diffs {
+            if let Some(&collapsed) = self.collapsed_files.get(&diff.path) {
+                diff.collapsed = collapsed;
+            }
+        }
+
+        // The commit list must be complete for the ones left out to be found
+        if !saved.unselected.is_empty() {
+            self.wait_for_commits();
+            for commit in &mut self.commits {
+                if saved.unselected.contains(&commit.full_hash) {
+                    commit.selected = false;
+                }
+            }
+        }
+        if !saved.unselected.is_empty() || self.diff_load.is_none() {
+            self.spawn_diff_load();
+        }
+
+        let position = ViewPosition { path: saved.file, offset: saved.offset };
+        match self.diff_load {
+            Some(_) => self.resume_position = Some(position),
+            None => self.restore_view_position(&position),
+        }
+    }
+
+    /// Save each tab's review of its branch, for `resume_session` next time
+    pub fn save_session(&mut self) -> Result<()> {
+        self.save_tab_session()?;
+        for index in 0..self.tabs.len() {
+            if index == self.active_tab {
+                continue;
+            }
+            // Parked tabs are swapped in, since where they're scrolled depends on their layout
+            let mut tab = std::mem::take(&mut self.tabs[index]);
+            self.exchange_tab(&mut tab);
+            self.invalidate_layout();
+            let result = self.save_tab_session();
+            self.exchange_tab(&mut tab);
+            self.invalidate_layout();
+            self.tabs[index] = tab;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Save the active tab's review of its branch, if it shows one
+    fn save_tab_session(&self) -> Result<()> {
+        let Some(branch) = self.review_branch() else {
+            return Ok(());
+        };
+        let collapsed: BTreeMap<String, bool> =
+            self.collapsed_files.iter().map(|(path, &collapsed)| (path.clone(), collapsed)).collect();
+        // A diff still loading hasn't moved the reader from where the session was
+        let position = self.resume_position.clone().unwrap_or_else(|| self.view_position());
+        let saved = Session {
+            reviewed: self.reviewed.iter().map(|(path, id)| (path.clone(), id.clone())).collect(),
+            collapsed,
+            file: position.path,
+            offset: position.offset,
+            unselected: self.commits.iter().filter(|c| !c.selected).map(|c| c.full_hash.clone()).collect(),
+        };
+        session::save(&self.repo_path, branch, saved)
+    }
+
+    /// Print the whole diff to stdout if it fits in a `width` x `height` terminal
+    ///
+    /// Returns whether it did. Like `less -F`, a short diff then stays in the
+    /// scrollback instead of opening the viewer; one row is left for the prompt.
+    pub fn print_if_fits(&mut self, width: u16, height: u16) -> Result<bool> {
+        self.width = width;
+        self.height = height;
+        self.apply_responsive_layout();
+
+        if self.total_content_lines() >= height as usize {
+            return Ok(false);
+        }
+        io::stdout().write_all(self.ansi_text().as_bytes())?;
+        Ok(true)
+    }
+
+    /// Print the whole diff to stdout as ANSI-colored text laid out `width` columns wide
+    pub fn print_ansi(&mut self, width: u16) -> Result<()> {
+        self.width = width;
+        self.apply_responsive_layout();
+        io::stdout().write_all(self.ansi_text().as_bytes())?;
+        Ok(())
+    }
+
+    /// The whole diff, first file to last, as the current view lays it out
+    ///
+    /// Rendered a screenful at a time, since a buffer can't be taller than
+    /// `u16::MAX` rows.
+    fn ansi_text(&mut self) -> String {
+        const CHUNK_ROWS: usize = 1024;
+        // Exported lines are cut off at the width, not wrapped
+        let total = LineIndex::build(&self.diffs, &self.visible_diffs, &self.notes, self.diff_mode, None).total_lines();
+        let mut text = String::new();
+        for start in (0..total).step_by(CHUNK_ROWS) {
+            let area = Rect::new(0, 0, self.width, (total - start).min(CHUNK_ROWS) as u16);
+            let mut buf = ratatui::buffer::Buffer::empty(area);
+            render_diff_content(
+                &mut buf,
+                area,
+                &self.diffs,
+                &self.visible_diffs,
+                start,
+                self.diff_mode,
+                &mut self.highlighter,
+                &self.annotations,
+                &self.notes,
+                None,
+                &self.styles,
+            );
+            text.push_str(&buffer_to_ansi(&buf));
+        }
+        text
+    }
+
+    /// Save the whole diff as ANSI text under `.git/gv/exports`, named after the branch
+    fn export_ansi(&mut self) -> Result<PathBuf> {
+        let text = self.ansi_text();
+        self.export("ansi", &text)
+    }
+
+    /// The diff as shown, as a unified patch
+    ///
+    /// Follows the commit selection, the change-type, path and language
+    /// filters and marked files, and leaves out hidden files unless they're shown.
+    pub fn patch_text(&self) -> String {
+        let hide = !self.show_hidden && self.compare.is_none();
+        git::diff_patch(
+            self.visible_diffs
+                .iter()
+                .map(|&i| &self.diffs[i])
+                .filter(|diff| !(hide && is_hidden_file(&diff.path, &self.hidden_patterns))),
+        )
+    }
+
+    /// The diff and commits as JSON, for `--json`
+    ///
+    /// Lists the files the filters show, flagging hidden and generated ones
+    /// rather than leaving them out, so scripts can decide for themselves.
+    pub fn json(&self) -> serde_json::Value {
+        let (head, base) = self.compared_labels();
+        let files: Vec<&FileDiff> = self.visible_diffs.iter().map(|&i| &self.diffs[i]).collect();
+        let (added, removed) = git::compute_stats(files.iter().copied());
+        let files: Vec<serde_json::Value> = files
+            .iter()
+            .map(|diff| {
+                let mut file = git::file_json(diff);
+                file["hidden"] = is_hidden_file(&diff.path, &self.hidden_patterns).into();
+                file["generated"] = self.generated_files.contains(&diff.path).into();
+                file
+            })
+            .collect();
+        serde_json::json!({
+            "head": head,
+            "base": base,
+            "base_sha": self.base_sha,
+            "stats": { "files": files.len(), "added": added, "removed": removed },
+            "commits": self.commits.iter().map(git::commit_json).collect::<Vec<_>>(),
+            "files": files,
+        })
+    }
+
+    /// Save the Markdown review report in the state directory
+    fn export_markdown(&mut self) -> Result<PathBuf> {
+        let text = self.markdown();
+        self.export("md", &text)
+    }
+
+    /// Save the diff as shown as a `.patch` file in the state directory
+    fn export_patch(&mut self) -> Result<PathBuf> {
+        let text = self.patch_text();
+        self.export("patch", &text)
+    }
+
+    /// Write an export named after the reviewed revision, returning its path
+    fn export(&self, extension: &str, text: &str) -> Result<PathBuf> {
+        let dir = git::state_dir(&self.repo_path)?.join("exports");
+        std::fs::create_dir_all(&dir)?;
+        let (head, _) = self.compared_labels();
+        let name: String = head
+            .chars()
+            .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+            .collect();
+        let path = dir.join(format!("{}.{}", name, extension));
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    /// Run the application
+    pub fn run(&mut self) -> Result<()> {
+        // Setup terminal
+        // Lets Ctrl+i be told apart from Tab where the terminal supports it
+        let enhanced_keys = matches!(supports_keyboard_enhancement(), Ok(true));
+        enter_terminal(self.mouse, enhanced_keys)?;
+        let backend = CrosstermBackend::new(io::stdout());
+        let mut terminal = Terminal::new(backend)?;
+
+        // Main loop
+        loop {
+            // Draw
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Handle events, waking early for pending debounced work
+            let timeout = [self.follow_at, self.context_at]
+                .into_iter()
+                .flatten()
+                .min()
+                .map(|at| at.saturating_duration_since(Instant::now()).min(POLL_INTERVAL))
+                .unwrap_or(POLL_INTERVAL);
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) if self.handle_key(key) => {
+                        break;
+                    }
+                    Event::Mouse(mouse) => {
+                        self.handle_mouse(mouse);
+                    }
+                    Event::Resize(w, h) => {
+                        self.width = w;
+                        self.height = h;
+                    }
+                    _ => {}
+                }
+            }
+
+            self.poll_fetch();
+            self.poll_diffs(false);
+            self.poll_highlights(false);
+            self.poll_commits(false);
+            self.poll_worktree_statuses(false);
+            self.poll_pulls();
+            self.poll_follow();
+            self.poll_context();
+            self.poll_viewing();
+
+            // Hand the terminal over to the diff tool or editor, then redraw from scratch
+            if let Some(external) = self.external.take() {
+                leave_terminal(self.mouse, enhanced_keys)?;
+                match external {
+                    External::Difftool(index) => self.run_difftool(index),
+                    External::Editor(path, line) => self.run_editor(&path, line),
+                }
+                enter_terminal(self.mouse, enhanced_keys)?;
+                terminal.clear()?;
+            }
+        }
+
+        // Restore terminal
+        leave_terminal(self.mouse, enhanced_keys)?;
+
+        Ok(())
+    }
+
+    /// Lay the view out for the frame's size and render it
+    pub fn draw(&mut self, frame: &mut ratatui::Frame) {
+        self.width = frame.area().width;
+        self.height = frame.area().height;
+        self.apply_responsive_layout();
+        self.render(frame);
+    }
+
+    /// Handle a key press, then finish the work the event loop would defer
+    ///
+    /// Debounced changes like sidebar follow and context lines apply at
+    /// once and background commit loading is waited for, so the next draw
+    /// shows the press's full effect. Returns true if the app should quit.
+    pub fn press(&mut self, key: KeyEvent) -> bool {
+        let quit = self.handle_key(key);
+        let now = Instant::now();
+        self.follow_at = self.follow_at.map(|_| now);
+        self.context_at = self.context_at.map(|_| now);
+        self.poll_diffs(true);
+        self.poll_commits(true);
+        self.poll_worktree_statuses(true);
+        self.poll_follow();
+        self.poll_context();
+        self.poll_highlights(true);
+        quit
+    }
+
+    /// Handle a mouse event, as the event loop would
+    pub fn mouse(&mut self, mouse: MouseEvent) {
+        self.handle_mouse(mouse);
+    }
+
+    /// Whether anything differs between the base and the reviewed revision, in the paths filtered to
+    pub fn has_changes(&self) -> bool {
+        self.visible_files().next().is_some()
+    }
+
+    /// Short plain-text account of the review, for printing after quit
+    pub fn summary(&self) -> String {
+        let (head, base) = self.compared_labels();
+        let mut summary = format!("{} vs {}", head, base);
+        if let Some(sha) = self.base_sha.as_deref().filter(|sha| !self.main_branch.starts_with(sha)) {
+            summary.push_str(&format!(" ({})", sha));
+        }
+        summary.push('\n');
+
+        if self.compare.is_none() && self.piped.is_none() {
+            let selected = self.commits.iter().filter(|c| c.selected).count();
+            summary.push_str(&format!("  commits:  {} of {} selected\n", selected, self.commits.len()));
+        }
+
+        let files = self.visible_files().count();
+        let (added, removed) = git::compute_stats(self.visible_files());
+        summary.push_str(&format!(
+            "  files:    {} changed, +{} -{}\n",
+            files, added, removed
+        ));
+
+        let reviewed = self.visible_files().filter(|d| self.reviewed.contains_key(&d.path)).count();
+        summary.push_str(&format!("  reviewed: {} of {} files\n", reviewed, files));
+        summary
+    }
+
+    /// The review as Markdown, for pasting into a pull request description
+    ///
+    /// A table of the shown files with their stats and whether each was
+    /// marked reviewed, then every note left on the branch.
+    pub fn markdown(&self) -> String {
+        let (head, base) = self.compared_labels();
+        let mut text = format!("## Review of `{}` against `{}`\n\n", head, base);
+
+        let files = self.visible_files().count();
+        let (added, removed) = git::compute_stats(self.visible_files());
+        let reviewed = self.visible_files().filter(|d| self.reviewed.contains_key(&d.path)).count();
+        let mut totals = Vec::new();
+        if self.compare.is_none() && self.piped.is_none() {
+            let selected = self.commits.iter().filter(|c| c.selected).count();
+            totals.push(format!("{} of {} commits", selected, self.commits.len()));
+        }
+        totals.push(format!("{} files changed, +{} -{}", files, added, removed));
+        totals.push(format!("{} of {} reviewed", reviewed, files));
+        text.push_str(&format!("{}\n\n", totals.join(" · ")));
+
+        if files > 0 {
+            text.push_str("| File | Change | + | - | Reviewed |\n|---|---|--:|--:|:-:|\n");
+            for diff in self.visible_files() {
+                let mark = if self.reviewed.contains_key(&diff.path) { "✓" } else { "" };
+                text.push_str(&format!(
+                    "| `{}` | {} | {} | {} | {} |\n",
+                    diff.path.replace('|', "\\|"),
+                    diff.change.label(),
+                    diff.added,
+                    diff.removed,
+                    mark,
+                ));
+            }
+        }
+
+        if !self.notes.is_empty() {
+            text.push_str("\n### Notes\n\n");
+            for (path, lines) in &self.notes {
+                for (line, note) in lines {
+                    text.push_str(&format!("- `{}:{}`: {}\n", path, line, note));
+                }
+            }
+        }
+        text
+    }
+
+    /// Render the application
+    fn render(&mut self, frame: &mut ratatui::Frame) {
         let area = frame.area();
 
         match self.view_mode {
@@ -353,125 +1984,517 @@ impl App {
             }
             ViewMode::CommitFilter => {
                 self.render_diff_view(frame, area);
-                render_commit_popup(frame.buffer_mut(), area, &self.commits, self.popup_cursor, &self.styles);
+                let status = self.commit_popup_status();
+                let shown: Vec<&Commit> = self.shown_commits().into_iter().map(|i| &self.commits[i]).collect();
+                render_commit_popup(
+                    frame.buffer_mut(),
+                    area,
+                    &shown,
+                    self.popup_cursor,
+                    self.commit_anchor,
+                    &self.commit_query,
+                    self.commit_typing,
+                    self.commit_sort,
+                    status.as_deref(),
+                    &self.styles,
+                );
             }
             ViewMode::WorktreeSwitcher => {
                 self.render_diff_view(frame, area);
-                render_worktree_popup(frame.buffer_mut(), area, &self.worktrees, self.popup_cursor, &self.filter_input, &self.styles);
+                render_worktree_popup(
+                    frame.buffer_mut(),
+                    area,
+                    &self.worktrees,
+                    &self.worktree_statuses,
+                    self.popup_cursor,
+                    &self.filter_input,
+                    &self.styles,
+                );
+            }
+            ViewMode::Stashes => {
+                self.render_diff_view(frame, area);
+                let shown = self.stash.as_ref().map(|s| s.full_hash.as_str());
+                render_stash_popup(frame.buffer_mut(), area, &self.stashes, self.popup_cursor, shown, &self.styles);
+            }
+            ViewMode::WorktreeCreate => {
+                self.render_diff_view(frame, area);
+                let form = &self.worktree_form;
+                render_worktree_form(
+                    frame.buffer_mut(),
+                    area,
+                    &form.branch,
+                    &form.path,
+                    form.on_path,
+                    form.error.as_deref(),
+                    &self.styles,
+                );
             }
             ViewMode::WorktreeList => {
                 self.render_worktree_list(frame, area);
             }
+            ViewMode::Diffstat => {
+                render_diffstat(
+                    frame.buffer_mut(),
+                    area,
+                    &self.diffstat_nodes,
+                    &self.diffstat_order,
+                    self.popup_cursor,
+                    self.diffstat_sort,
+                    &self.styles,
+                );
+            }
             ViewMode::Help => {
                 self.render_diff_view(frame, area);
-                render_help_popup(frame.buffer_mut(), area, &self.styles);
+                self.help_scroll = render_help_popup(frame.buffer_mut(), area, self.help_scroll, &self.styles);
             }
             ViewMode::Search => {
                 self.render_diff_view(frame, area);
                 self.render_search_bar(frame.buffer_mut(), area);
             }
+            ViewMode::TreeFilter => {
+                self.render_diff_view(frame, area);
+                self.render_tree_filter_bar(frame.buffer_mut(), area);
+            }
+            ViewMode::LanguageFilter => {
+                self.render_diff_view(frame, area);
+                self.render_language_filter_bar(frame.buffer_mut(), area);
+            }
+            ViewMode::ComparePrompt => {
+                self.render_diff_view(frame, area);
+                self.render_compare_bar(frame.buffer_mut(), area);
+            }
+            ViewMode::NotePrompt => {
+                self.render_diff_view(frame, area);
+                self.render_note_bar(frame.buffer_mut(), area);
+            }
+            ViewMode::PathPrompt => {
+                self.render_diff_view(frame, area);
+                self.render_path_bar(frame.buffer_mut(), area);
+            }
+            ViewMode::Symbols => {
+                self.render_diff_view(frame, area);
+                let symbols: Vec<&FileSymbol> = self.filtered_symbols().into_iter().map(|i| &self.symbols[i]).collect();
+                render_symbol_popup(frame.buffer_mut(), area, &symbols, self.popup_cursor, &self.filter_input, &self.styles);
+            }
+            ViewMode::Outline => {
+                self.render_diff_view(frame, area);
+                if let Some(diff) = self.diffs.get(self.outline_file) {
+                    render_outline_popup(frame.buffer_mut(), area, &diff.path, &self.outline, self.popup_cursor, &self.styles);
+                }
+            }
+            ViewMode::Recent => {
+                self.render_diff_view(frame, area);
+                render_recent_popup(frame.buffer_mut(), area, &self.recent, self.popup_cursor, &self.styles);
+            }
+            ViewMode::PullRequests => {
+                self.render_diff_view(frame, area);
+                render_pull_popup(frame.buffer_mut(), area, self.pulls.as_deref(), self.popup_cursor, &self.styles);
+            }
+            ViewMode::Issues => {
+                self.render_diff_view(frame, area);
+                let issues = self.scoped_annotations();
+                render_issues_popup(frame.buffer_mut(), area, &issues, self.popup_cursor, &self.styles);
+            }
+            ViewMode::Conflicts => {
+                self.render_diff_view(frame, area);
+                render_conflict_popup(
+                    frame.buffer_mut(),
+                    area,
+                    &self.main_branch,
+                    &self.conflict_files,
+                    self.popup_cursor,
+                    &self.styles,
+                );
+            }
         }
     }
 
     /// Render the main diff view
     fn render_diff_view(&mut self, frame: &mut ratatui::Frame, area: Rect) {
-        // Layout: header (1) + content + footer (1)
+        // Layout: header (1, plus the tab strip and conflict banner) + content + footer (1)
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(1),
+                Constraint::Length(self.header_height()),
                 Constraint::Min(0),
                 Constraint::Length(1),
             ])
             .split(area);
 
-        let header_area = chunks[0];
+        let mut header_area = chunks[0];
         let content_area = chunks[1];
         let footer_area = chunks[2];
 
-        // Split content into sidebar + diff
-        let content_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(self.sidebar_width),
-                Constraint::Min(0),
-            ])
-            .split(content_area);
+        if self.tabs.len() > 1 {
+            let tab_area = Rect { height: 1, ..header_area };
+            render_tab_bar(frame.buffer_mut(), tab_area, &self.tab_labels(), self.active_tab, &self.styles);
+            header_area.y += 1;
+            header_area.height -= 1;
+        }
+        if !self.marker_files.is_empty() {
+            let banner_area = Rect { height: 1, ..header_area };
+            render_conflict_banner(frame.buffer_mut(), banner_area, &self.marker_files, &self.styles);
+            header_area.y += 1;
+            header_area.height -= 1;
+        }
+
+        // Split content into sidebar + diff, or diff over a bottom file strip
+        let (sidebar_area, mut diff_area) = if self.sidebar_position == SidebarPosition::Bottom {
+            let content_chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(0),
+                    Constraint::Length(self.bottom_panel_height()),
+                ])
+                .split(content_area);
+            (content_chunks[1], content_chunks[0])
+        } else {
+            let content_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Length(self.sidebar_layout_width()),
+                    Constraint::Min(0),
+                ])
+                .split(content_area);
+            (content_chunks[0], content_chunks[1])
+        };
 
-        let sidebar_area = content_chunks[0];
-        let diff_area = content_chunks[1];
+        // Carve the commit message panel off the right of the diff
+        let panel_width = self.message_panel_width();
+        if let Some(messages) = &self.commit_messages
+            && panel_width > 0
+        {
+            diff_area.width -= panel_width;
+            let panel_area = Rect::new(diff_area.right(), diff_area.y, panel_width, diff_area.height);
+            // Inner width excludes the border and padding
+            let total = message_lines(messages, panel_width.saturating_sub(3), &self.styles).len();
+            self.message_scroll = self.message_scroll.min(total.saturating_sub(panel_area.height as usize));
+            render_message_panel(frame.buffer_mut(), panel_area, messages, self.message_scroll, &self.styles);
+        }
 
-        // Calculate stats
-        let (added, removed) = git::compute_stats(&self.diffs);
+        // Calculate stats over the files currently shown, generated ones only when included
+        let counted = self.visible_files().filter(|d| self.include_generated || !self.generated_files.contains(&d.path));
+        let (added, removed) = git::compute_stats(counted);
+        let mut filters = Vec::new();
+        if let Some(stash) = &self.stash {
+            filters.push(stash.name());
+        } else if let Some(commit) = &self.only_commit {
+            filters.push(format!("only {}", commit.hash));
+        } else if self.timeline.is_some() {
+            filters.push("timeline".to_string());
+        } else if self.group_by_commit {
+            filters.push("by commit".to_string());
+        }
+        if let Some(kind) = self.change_filter {
+            filters.push(format!("only {}", kind.label()));
+        }
+        if !self.path_filter.is_empty() {
+            filters.push(format!("paths {}", self.path_filter.join(" ")));
+        }
+        if let Some(query) = self.language_filter.as_deref().filter(|q| !q.trim().is_empty()) {
+            filters.push(format!("lang {}", query.trim()));
+        }
+        if !self.include_generated && !self.generated_files.is_empty() {
+            filters.push(format!("{} generated uncounted", self.generated_files.len()));
+        }
+        if self.selecting {
+            filters.push("selecting".to_string());
+        } else if !self.marked.is_empty() {
+            filters.push(format!("{} marked", self.marked.len()));
+        }
+        let filter_label = (!filters.is_empty()).then(|| filters.join(" · "));
+        let loading = if self.diff_load.is_some() {
+            Some("loading diff")
+        } else if self.commit_walk.is_some() {
+            Some("loading commits")
+        } else {
+            None
+        };
         let selected_count = self.commits.iter().filter(|c| c.selected).count();
         let total_count = self.commits.len();
+        let stage = self.stage.label().filter(|_| self.commits.iter().any(|c| c.is_uncommitted && c.selected));
+        let reviewed = Some(self.reviewed_count()).filter(|&(done, _)| done > 0);
 
         // Get current file at scroll position
         let current_file = self.get_current_file();
 
         // Render header
+        let (head_label, base_label) = self.compared_labels();
         render_header(
             frame.buffer_mut(),
             header_area,
-            self.current_branch(),
-            &self.main_branch,
+            &head_label,
+            &base_label,
+            self.base_sha.as_deref(),
             selected_count,
             total_count,
+            stage,
+            reviewed,
             added,
             removed,
             current_file.as_deref(),
+            filter_label.as_deref(),
+            loading,
             &self.styles,
         );
 
         // Render sidebar
-        let tree_nodes = flatten_tree(&self.file_tree);
-        let tree_refs: Vec<&TreeNode> = tree_nodes.iter().cloned().collect();
-        let hidden_count = self.diffs.iter().filter(|d| is_hidden_file(&d.path)).count();
-
-        render_sidebar(
-            frame.buffer_mut(),
-            sidebar_area,
-            &tree_refs,
-            self.file_cursor,
-            self.sidebar_scroll,
-            hidden_count,
-            self.focus == FocusArea::Sidebar,
-            &self.styles,
-        );
+        let hidden_count = self.diffs.iter().filter(|d| is_hidden_file(&d.path, &self.hidden_patterns)).count();
+
+        // The blame gutter takes the left of the diff in the unified and full-file views
+        let gutter = (self.blame.is_some() && self.diff_mode != DiffMode::SideBySide && diff_area.width > BLAME_WIDTH * 3)
+            .then(|| {
+                let gutter = Rect { width: BLAME_WIDTH, ..diff_area };
+                diff_area.x += BLAME_WIDTH;
+                diff_area.width -= BLAME_WIDTH;
+                gutter
+            });
+
+        // Wrapped lines fit the view as drawn, so its width is part of the layout
+        if diff_area.width != self.wrap_width {
+            self.wrap_width = diff_area.width;
+            if self.wrap {
+                self.invalidate_layout();
+                self.set_content_scroll(self.content_scroll);
+            }
+        }
 
-        // Get visible diffs
-        let visible: Vec<&FileDiff> = self.visible_diffs
-            .iter()
-            .filter_map(|&i| self.diffs.get(i))
-            .collect();
+        if let Some(gutter) = gutter {
+            self.blame_files_in_view(diff_area.height as usize);
+            if let Some(blame) = &self.blame {
+                render_blame_gutter(
+                    frame.buffer_mut(),
+                    gutter,
+                    &self.diffs,
+                    &self.visible_diffs,
+                    self.content_scroll,
+                    &self.notes,
+                    self.diff_mode,
+                    self.wrap_columns(),
+                    blame,
+                    &self.styles,
+                );
+            }
+        }
 
         // Render diff content
+        let wrap = self.wrap_columns();
         render_diff_content(
             frame.buffer_mut(),
             diff_area,
-            &visible,
+            &self.diffs,
+            &self.visible_diffs,
             self.content_scroll,
             self.diff_mode,
             &mut self.highlighter,
+            &self.annotations,
+            &self.notes,
+            wrap,
             &self.styles,
         );
 
-        // Render footer
-        render_footer(
-            frame.buffer_mut(),
-            footer_area,
-            self.focus,
-            self.diff_mode,
-            self.show_hidden,
-            self.context_lines,
-            &self.styles,
-        );
+        // Underline the cursor line, vim cursorline style
+        if let Some(row) = self.content_cursor.and_then(|c| c.checked_sub(self.content_scroll))
+            && row < diff_area.height as usize
+        {
+            let line = Rect::new(diff_area.x, diff_area.y + row as u16, diff_area.width, 1);
+            frame.buffer_mut().set_style(line, self.styles.content_cursor);
+        }
+
+        // Render the sidebar, floating over the content when summoned on a narrow terminal
+        let sidebar_area = if self.sidebar_overlay {
+            let overlay = Rect::new(content_area.x, content_area.y, self.sidebar_shown_width(), content_area.height);
+            Clear.render(overlay, frame.buffer_mut());
+            overlay
+        } else {
+            sidebar_area
+        };
+        if sidebar_area.width > 0 {
+            render_sidebar(
+                frame.buffer_mut(),
+                sidebar_area,
+                self.sidebar_nodes(),
+                self.file_cursor,
+                self.sidebar_scroll,
+                hidden_count,
+                self.focus == FocusArea::Sidebar,
+                &self.marked,
+                self.selecting,
+                &self.seen,
+                &self.reviewed,
+                &self.styles,
+            );
+        }
+
+        // Render footer, or the timeline scrubber in its place
+        if let Some(step) = self.timeline {
+            let steps: Vec<TimelineStep> = self.commits
+                .iter()
+                .rev()
+                .map(|c| TimelineStep { hash: &c.hash, subject: &c.subject })
+                .collect();
+            render_timeline(frame.buffer_mut(), footer_area, &steps, step, &self.styles);
+        } else {
+            let status = self.status_line();
+            render_footer(
+                frame.buffer_mut(),
+                footer_area,
+                self.focus,
+                self.sidebar_follow,
+                self.diff_mode,
+                self.show_hidden,
+                self.context_lines,
+                status.as_deref(),
+                &self.styles,
+            );
+        }
+
+        if self.path_tooltip
+            && let Some(path) = &current_file
+        {
+            render_path_tooltip(frame.buffer_mut(), content_area, path, &self.styles);
+        }
     }
 
-    /// Render worktree list view
+    /// Render the worktree dashboard
     fn render_worktree_list(&mut self, frame: &mut ratatui::Frame, area: Rect) {
-        // Similar to diff view but shows worktree list instead
-        render_worktree_popup(frame.buffer_mut(), area, &self.worktrees, self.popup_cursor, &self.filter_input, &self.styles);
+        let message = self.status_line();
+        render_worktree_dashboard(
+            frame.buffer_mut(),
+            area,
+            &self.worktrees,
+            &self.worktree_statuses,
+            self.worktree_status_rx.is_some(),
+            &self.worktree_order,
+            self.popup_cursor,
+            self.worktree_sort,
+            &self.main_branch,
+            message.as_deref(),
+            &self.styles,
+        );
+
+        let question = match &self.worktree_confirm {
+            Some(WorktreeAction::Delete(index)) => self.worktrees.get(*index).map(|wt| {
+                (
+                    "Delete Worktree",
+                    format!("Delete the worktree at {}? Its branch is kept.", wt.path.display()),
+                )
+            }),
+            Some(WorktreeAction::Prune(stale)) => Some((
+                "Prune Worktrees",
+                format!("Forget {} worktree(s) whose directories are gone: {}?", stale.len(), stale.join(", ")),
+            )),
+            None => None,
+        };
+        if let Some((title, message)) = question {
+            render_confirm_popup(frame.buffer_mut(), area, title, &message, &self.styles);
+        }
+    }
+
+    /// Render the sidebar filter bar at the bottom of the screen
+    fn render_tree_filter_bar(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        use ratatui::text::{Line, Span};
+
+        let y = area.height.saturating_sub(1);
+        for x in 0..area.width {
+            buf[(x, y)].set_char(' ').set_style(self.styles.popup);
+        }
+
+        let query = self.tree_filter.as_deref().unwrap_or("");
+        let matches = FlatTree::new(&self.file_tree, &self.sidebar_rows)
+            .iter()
+            .filter(|n| !n.is_folder)
+            .count();
+        let info = if matches == 0 {
+            " (no matches)".to_string()
+        } else {
+            format!(" ({} files) [Enter to open, Esc to cancel]", matches)
+        };
+        let line = Line::from(vec![
+            Span::styled("filter: ", self.styles.popup_title),
+            Span::styled(query, self.styles.popup),
+            Span::styled("_", self.styles.popup_title),
+            Span::styled(info, self.styles.line_number),
+        ]);
+        buf.set_line(0, y, &line, area.width);
+    }
+
+    /// Render the language filter bar at the bottom of the screen
+    fn render_language_filter_bar(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        use ratatui::text::{Line, Span};
+
+        let y = area.height.saturating_sub(1);
+        for x in 0..area.width {
+            buf[(x, y)].set_char(' ').set_style(self.styles.popup);
+        }
+
+        let query = self.language_filter.as_deref().unwrap_or("");
+        let info = match self.visible_diffs.len() {
+            0 => " (no matches)".to_string(),
+            files => format!(" ({} files) [Enter to keep, Ctrl-u to clear, Esc to cancel]", files),
+        };
+        let line = Line::from(vec![
+            Span::styled("extension or language: ", self.styles.popup_title),
+            Span::styled(query, self.styles.popup),
+            Span::styled("_", self.styles.popup_title),
+            Span::styled(info, self.styles.line_number),
+        ]);
+        buf.set_line(0, y, &line, area.width);
+    }
+
+    /// Render the compare prompt at the bottom of the screen
+    fn render_compare_bar(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        use ratatui::text::{Line, Span};
+
+        let y = area.height.saturating_sub(1);
+        for x in 0..area.width {
+            buf[(x, y)].set_char(' ').set_style(self.styles.popup);
+        }
+
+        let line = Line::from(vec![
+            Span::styled("compare with: ", self.styles.popup_title),
+            Span::styled(&self.compare_input, self.styles.popup),
+            Span::styled("_", self.styles.popup_title),
+            Span::styled(" (path, REV or REV:PATH) [Enter to open in a tab, Esc to cancel]", self.styles.line_number),
+        ]);
+        buf.set_line(0, y, &line, area.width);
+    }
+
+    /// Render the note prompt at the bottom of the screen
+    fn render_note_bar(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        use ratatui::text::{Line, Span};
+
+        let y = area.height.saturating_sub(1);
+        for x in 0..area.width {
+            buf[(x, y)].set_char(' ').set_style(self.styles.popup);
+        }
+
+        let target = self.note_target.as_ref().map(|(path, line)| format!("{}:{}", path, line)).unwrap_or_default();
+        let line = Line::from(vec![
+            Span::styled(format!("note on {}: ", target), self.styles.popup_title),
+            Span::styled(&self.note_input, self.styles.popup),
+            Span::styled("_", self.styles.popup_title),
+            Span::styled(" [Enter to save, empty to delete, Esc to cancel]", self.styles.line_number),
+        ]);
+        buf.set_line(0, y, &line, area.width);
+    }
+
+    /// Render the path filter prompt at the bottom of the screen
+    fn render_path_bar(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        use ratatui::text::{Line, Span};
+
+        let y = area.height.saturating_sub(1);
+        for x in 0..area.width {
+            buf[(x, y)].set_char(' ').set_style(self.styles.popup);
+        }
+
+        let line = Line::from(vec![
+            Span::styled("paths: ", self.styles.popup_title),
+            Span::styled(&self.path_input, self.styles.popup),
+            Span::styled("_", self.styles.popup_title),
+            Span::styled(" (globs or folders, space-separated) [Enter to filter, empty for all, Esc to cancel]", self.styles.line_number),
+        ]);
+        buf.set_line(0, y, &line, area.width);
     }
 
     /// Render search bar at the bottom of the screen
@@ -525,68 +2548,440 @@ impl App {
                                self.search_input,
                                self.search_match_index + 1,
                                self.search_matches.len());
-        let x = area.width.saturating_sub(indicator.len() as u16);
+        let x = area.width.saturating_sub(text::width(&indicator) as u16);
 
         let line = Line::from(vec![Span::styled(indicator, self.styles.popup_title)]);
         buf.set_line(x, y, &line, area.width - x);
     }
 
-    /// Get the file at the current scroll position
-    fn get_current_file(&self) -> Option<String> {
-        self.get_file_at_position(self.content_scroll)
+    /// Text for the footer status slot, if any
+    fn status_line(&self) -> Option<String> {
+        if let Some(fetch) = &self.fetch {
+            let p = fetch.progress;
+            return Some(if p.total_objects > 0 {
+                format!(
+                    "Fetching {}… {}/{} objects ({} KiB)",
+                    fetch.remote,
+                    p.received_objects,
+                    p.total_objects,
+                    p.received_bytes / 1024
+                )
+            } else {
+                format!("Fetching {}…", fetch.remote)
+            });
+        }
+        if let Some((register, _)) = &self.recording {
+            return Some(match &self.status_message {
+                Some(message) => format!("recording @{} · {}", register, message),
+                None => format!("recording @{}", register),
+            });
+        }
+        self.status_message.clone()
     }
 
-    /// Get the file at a specific scroll position
-    fn get_file_at_position(&self, position: usize) -> Option<String> {
-        let visible: Vec<&FileDiff> = self.visible_diffs
-            .iter()
-            .filter_map(|&i| self.diffs.get(i))
-            .collect();
+    /// Start fetching the base branch's remote in the background
+    fn start_fetch(&mut self) {
+        self.spawn_fetch(None);
+    }
+
+    /// Fetch a pull request's head in the background, then show it
+    fn fetch_pull_request(&mut self, pull: PullRequest) {
+        self.spawn_fetch(Some(pull));
+    }
+
+    /// List the branch's commits in the background, after those already listed
+    fn spawn_commit_walk(&mut self, limit: usize) {
+        let (tx, rx) = mpsc::channel();
+        let repo_path = self.repo_path.clone();
+        let (base, head) = (self.diff_base().to_string(), self.head_rev.clone());
+        let skip = self.commits.iter().filter(|c| !c.is_uncommitted).count();
+        thread::spawn(move || {
+            // Stops early once the receiver is dropped by a reload
+            let result = git::walk_commits(&repo_path, &base, &head, skip, limit, |batch| {
+                tx.send(CommitEvent::Batch(batch)).is_ok()
+            });
+            let _ = tx.send(CommitEvent::Finished(result.map_err(|e| format!("{:#}", e))));
+        });
+        self.commit_walk = Some(rx);
+        self.more_commits = false;
+    }
+
+    /// Add the commits the background walk has found, blocking until it's done with `wait`
+    fn poll_commits(&mut self, wait: bool) {
+        let Some(rx) = &self.commit_walk else {
+            return;
+        };
+
+        let finished = loop {
+            let event = if wait { rx.recv().map_err(|_| TryRecvError::Disconnected) } else { rx.try_recv() };
+            match event {
+                Ok(CommitEvent::Batch(batch)) => self.commits.extend(batch),
+                Ok(CommitEvent::Finished(result)) => break Some(result),
+                Err(TryRecvError::Empty) => break None,
+                Err(TryRecvError::Disconnected) => break Some(Err("commit walk exited".to_string())),
+            }
+        };
+
+        let Some(result) = finished else {
+            return;
+        };
+        self.commit_walk = None;
+        match result {
+            Ok(more) => self.more_commits = more,
+            Err(e) => self.status_message = Some(format!("Listing commits failed: {}", e)),
+        }
+
+        // Views built from the commit list need all of it, and a diff that
+        // counted on commits arriving is wrong if none did
+        if self.group_by_commit
+            || self.timeline.is_some()
+            || self.commit_messages.is_some()
+            || self.commits.iter().all(|c| c.is_uncommitted)
+        {
+            let position = self.view_position();
+            let _ = self.reload_diffs();
+            self.restore_view_position(&position);
+        }
+    }
+
+    /// Block until the background walk has listed every commit up to the limit
+    pub fn wait_for_commits(&mut self) {
+        self.poll_commits(true);
+    }
+
+    /// Fetch the base branch's remote, or just a pull request's head from it
+    fn spawn_fetch(&mut self, pull: Option<PullRequest>) {
+        if self.fetch.is_some() {
+            return;
+        }
+
+        let remote = match git::remote_for_base(&self.repo_path, &self.main_branch) {
+            Ok(remote) => remote,
+            Err(e) => {
+                self.status_message = Some(format!("Fetch failed: {}", e));
+                return;
+            }
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let repo_path = self.repo_path.clone();
+        let remote_name = remote.clone();
+        let refspec = pull.as_ref().map(PullRequest::refspec);
+        thread::spawn(move || {
+            let progress_tx = tx.clone();
+            let refspecs: Vec<&str> = refspec.as_deref().into_iter().collect();
+            let result = git::fetch_remote(&repo_path, &remote_name, &refspecs, |progress| {
+                let _ = progress_tx.send(FetchEvent::Progress(progress));
+            });
+            let _ = tx.send(FetchEvent::Finished(result.map_err(|e| format!("{:#}", e))));
+        });
+
+        self.status_message = None;
+        self.fetch = Some(FetchState {
+            remote,
+            progress: git::FetchProgress::default(),
+            rx,
+            pull,
+        });
+    }
+
+    /// Drain fetch progress and reload once the fetch completes
+    fn poll_fetch(&mut self) {
+        let Some(fetch) = self.fetch.as_mut() else {
+            return;
+        };
+
+        let finished = loop {
+            match fetch.rx.try_recv() {
+                Ok(FetchEvent::Progress(progress)) => fetch.progress = progress,
+                Ok(FetchEvent::Finished(result)) => break Some(result),
+                Err(TryRecvError::Empty) => break None,
+                Err(TryRecvError::Disconnected) => break Some(Err("fetch thread exited".to_string())),
+            }
+        };
+
+        if let Some(result) = finished {
+            let Some(FetchState { remote, pull, .. }) = self.fetch.take() else {
+                return;
+            };
+            self.status_message = Some(match (result, pull) {
+                (Ok(()), Some(pull)) => self.show_pull_request(&pull, &remote),
+                (Ok(()), None) => match self.load_data() {
+                    Ok(()) => {
+                        if self.view_mode == ViewMode::WorktreeList {
+                            self.refresh_worktree_statuses();
+                        }
+                        format!("Fetched {}", remote)
+                    }
+                    Err(e) => format!("Fetched {}, reload failed: {}", remote, e),
+                },
+                (Err(e), _) => format!("Fetch failed: {}", e),
+            });
+        }
+    }
+
+    /// List open pull requests in the background and show the picker
+    fn open_pull_picker(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let repo_path = self.repo_path.clone();
+        thread::spawn(move || {
+            let _ = tx.send(pulls::list_pull_requests(&repo_path).map_err(|e| format!("{:#}", e)));
+        });
+        self.pulls = None;
+        self.pulls_rx = Some(rx);
+        self.popup_cursor = 0;
+        self.view_mode = ViewMode::PullRequests;
+    }
 
-        let mut line = 0;
-        for diff in visible {
-            let file_lines = file_line_count(diff, self.diff_mode);
+    /// Pick up the pull request list once `gh` returns
+    fn poll_pulls(&mut self) {
+        let Some(rx) = &self.pulls_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err("listing thread exited".to_string()),
+        };
+        self.pulls_rx = None;
+        match result {
+            Ok(pulls) => self.pulls = Some(pulls),
+            Err(e) => {
+                self.status_message = Some(e);
+                if self.view_mode == ViewMode::PullRequests {
+                    self.view_mode = ViewMode::Diff;
+                }
+            }
+        }
+    }
 
-            if line + file_lines > position {
-                return Some(diff.path.clone());
+    /// Review a fetched pull request against its target branch
+    fn show_pull_request(&mut self, pull: &PullRequest, remote: &str) -> String {
+        let base = format!("{}/{}", remote, pull.base_ref);
+        if git::resolve_base(&self.repo_path, &base).is_ok() {
+            self.main_branch = base;
+        }
+        let previous = std::mem::replace(&mut self.head_rev, pull.head_oid.chars().take(12).collect());
+        self.since_review = None;
+        self.base_steps = 0;
+        match self.load_data() {
+            Ok(()) => {
+                self.pull = Some(pull.clone());
+                format!("Reviewing #{}: {}", pull.number, pull.title)
+            }
+            Err(e) => {
+                self.head_rev = previous;
+                format!("Couldn't load #{}: {}", pull.number, e)
             }
-            line += file_lines;
         }
+    }
+
+    /// Notes to post on the pull request: the reviewer's own, then the loaded annotations
+    fn review_comments(&self) -> Vec<pulls::ReviewComment> {
+        let notes = self.notes.iter().flat_map(|(path, lines)| {
+            lines.iter().map(|(&line, text)| pulls::ReviewComment {
+                path: path.clone(),
+                line,
+                body: text.clone(),
+            })
+        });
+        let findings = self
+            .annotations
+            .iter()
+            .map(|a| {
+                let severity = match a.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Note => "note",
+                };
+                let rule = a.rule.as_ref().map(|rule| format!(" (`{}`)", rule)).unwrap_or_default();
+                pulls::ReviewComment {
+                    path: a.path.clone(),
+                    line: a.line,
+                    body: format!("**{}**{}: {}", severity, rule, a.message),
+                }
+            });
+        notes.chain(findings).collect()
+    }
+
+    /// Ask before posting the notes as a draft review of the pull request shown
+    fn confirm_review(&mut self) {
+        let Some(pull) = &self.pull else {
+            self.status_message = Some("Open a pull request with P first".to_string());
+            return;
+        };
+        let count = self.review_comments().len();
+        if count == 0 {
+            self.status_message = Some("No notes to post (leave one with # or load findings with --annotations)".to_string());
+            return;
+        }
+        self.status_message = Some(format!("Post {} notes as a draft review of #{}? (y/n)", count, pull.number));
+        self.review_confirm = true;
+    }
+
+    /// Post the notes as a draft review, placing each on its line of the pull request's diff
+    fn post_review(&mut self) -> String {
+        let Some(pull) = &self.pull else {
+            return "No pull request open".to_string();
+        };
+        let files: Vec<(&str, &[Hunk])> = self
+            .diffs
+            .iter()
+            .zip(&self.source_hunks)
+            .map(|(diff, hunks)| (diff.path.as_str(), hunks.as_slice()))
+            .collect();
+        let (placed, unplaced) = pulls::place_comments(self.review_comments(), &files);
+        match pulls::post_draft_review(&self.repo_path, pull.number, &pull.head_oid, &placed, &unplaced) {
+            Ok(url) => format!(
+                "Draft review of #{} created with {} line comments ({} in the summary): {}",
+                pull.number,
+                placed.len(),
+                unplaced.len(),
+                url
+            ),
+            Err(e) => format!("Posting review failed: {}", e),
+        }
+    }
+
+    /// Get the file at the current scroll position
+    fn get_current_file(&self) -> Option<String> {
+        self.get_file_at_position(self.content_scroll)
+    }
 
-        None
+    /// Get the file at a specific scroll position
+    fn get_file_at_position(&self, position: usize) -> Option<String> {
+        let index = self.layout().file_at(position)?;
+        Some(self.diffs[index].path.clone())
     }
 
     /// Handle keyboard input. Returns true if app should quit.
     fn handle_key(&mut self, key: KeyEvent) -> bool {
+        // A lone `Q` in the diff view ends the recording rather than being part of it
+        let stops_recording = self.view_mode == ViewMode::Diff
+            && self.pending_key.is_none()
+            && key.code == KeyCode::Char('Q')
+            && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if let Some((register, keys)) = &mut self.recording
+            && self.macro_depth == 0
+        {
+            if stops_recording {
+                let register = *register;
+                let keys = std::mem::take(keys);
+                self.recording = None;
+                self.status_message = Some(format!("Recorded {} keys into @{}", keys.len(), register));
+                self.macros.insert(register, keys);
+                return false;
+            }
+            keys.push(key);
+        }
+
         match self.view_mode {
-            ViewMode::Diff => self.handle_diff_key(key),
+            ViewMode::Diff => {
+                let cursor = self.file_cursor;
+                let quit = self.handle_diff_key(key);
+                if self.file_cursor != cursor {
+                    self.schedule_follow();
+                }
+                quit
+            }
             ViewMode::CommitFilter => self.handle_commit_filter_key(key),
             ViewMode::WorktreeSwitcher => self.handle_worktree_switcher_key(key),
+            ViewMode::WorktreeCreate => self.handle_worktree_form_key(key),
+            ViewMode::Stashes => self.handle_stashes_key(key),
             ViewMode::WorktreeList => self.handle_worktree_list_key(key),
+            ViewMode::Diffstat => self.handle_diffstat_key(key),
             ViewMode::Help => self.handle_help_key(key),
             ViewMode::Search => self.handle_search_key(key),
+            ViewMode::Conflicts => self.handle_conflicts_key(key),
+            ViewMode::Issues => self.handle_issues_key(key),
+            ViewMode::Recent => self.handle_recent_key(key),
+            ViewMode::PullRequests => self.handle_pull_key(key),
+            ViewMode::TreeFilter => self.handle_tree_filter_key(key),
+            ViewMode::LanguageFilter => self.handle_language_filter_key(key),
+            ViewMode::ComparePrompt => self.handle_compare_key(key),
+            ViewMode::NotePrompt => self.handle_note_key(key),
+            ViewMode::PathPrompt => self.handle_path_key(key),
+            ViewMode::Symbols => self.handle_symbols_key(key),
+            ViewMode::Outline => self.handle_outline_key(key),
         }
     }
 
     /// Handle keys in diff view
     fn handle_diff_key(&mut self, key: KeyEvent) -> bool {
         // Check for number prefix
-        if let KeyCode::Char(c) = key.code {
-            if c.is_ascii_digit() {
-                let digit = c.to_digit(10).unwrap() as usize;
-                self.number_prefix = Some(self.number_prefix.unwrap_or(0) * 10 + digit);
-                return false;
-            }
+        if let KeyCode::Char(c) = key.code
+            && c.is_ascii_digit()
+        {
+            let digit = c.to_digit(10).unwrap() as usize;
+            self.number_prefix = Some(self.number_prefix.unwrap_or(0) * 10 + digit);
+            return false;
+        }
+
+        self.status_message = None;
+
+        // A pending draft review takes the next key as its answer
+        if std::mem::take(&mut self.review_confirm) {
+            self.status_message = Some(match key.code {
+                KeyCode::Char('y') => self.post_review(),
+                _ => "Review not posted".to_string(),
+            });
+            return false;
         }
 
+        let showing_path = std::mem::take(&mut self.path_tooltip);
+
         let (count, had_prefix) = match self.number_prefix.take() {
             Some(value) => (value, true),
             None => (1, false),
         };
 
+        if let Some(prefix) = self.pending_key.take() {
+            let tab_origin = self.tab_origin.take();
+            match (prefix, key.code) {
+                ('z', KeyCode::Char(c)) => self.reposition_view(c),
+                ('g', KeyCode::Char(c @ ('t' | 'T' | 'q'))) => {
+                    // Undo the `g` jump so the tab keeps its place
+                    if let Some((position, file_cursor)) = tab_origin {
+                        self.restore_view_position(&position);
+                        self.set_sidebar_cursor(file_cursor);
+                    }
+                    self.handle_tab_key(c, count, had_prefix);
+                }
+                // `g` has already jumped; anything else is a new command
+                ('g', _) => {
+                    self.number_prefix = had_prefix.then_some(count);
+                    return self.handle_diff_key(key);
+                }
+                ('Q', KeyCode::Char(c)) if c.is_ascii_lowercase() => {
+                    self.recording = Some((c, Vec::new()));
+                }
+                ('@', KeyCode::Char(c)) => return self.replay_macro(c, count),
+                _ => {}
+            }
+            return false;
+        }
+
         match (key.code, key.modifiers) {
-            // Quit
             (KeyCode::Char('q'), _) => return true,
+            // Record a macro (`Q{a-z}`)
+            (KeyCode::Char('Q'), _) => {
+                self.pending_key = Some('Q');
+                self.status_message = Some("Q: record macro into a-z".to_string());
+            }
+            (KeyCode::Char('@'), _) => {
+                self.pending_key = Some('@');
+                // Keep the count for the register key
+                self.number_prefix = had_prefix.then_some(count);
+            }
+            (KeyCode::Esc, _) if self.selecting => {
+                self.toggle_selection_mode();
+            }
+            (KeyCode::Esc, _) if self.sidebar_overlay => {
+                self.set_sidebar_overlay(false);
+            }
+            (KeyCode::Esc, _) if self.timeline.is_some() => {
+                self.set_timeline(None);
+            }
             (KeyCode::Esc, _) => return true,
 
             // Navigation
@@ -620,7 +3015,38 @@ impl App {
                     self.scroll_content(-page * count as i32);
                 }
             }
+            (KeyCode::Char('f'), KeyModifiers::CONTROL) => {
+                self.scroll_pages(count as i32);
+            }
+            (KeyCode::Char('b'), KeyModifiers::CONTROL) => {
+                self.scroll_pages(-(count as i32));
+            }
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+                self.scroll_view(count as i32);
+            }
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => {
+                self.scroll_view(-(count as i32));
+            }
+            (KeyCode::Char(c @ ('H' | 'M' | 'L')), _) => {
+                self.jump_in_view(c, count);
+            }
+            (KeyCode::Char('o'), KeyModifiers::CONTROL) => {
+                self.jump_back();
+            }
+            (KeyCode::Char('i'), KeyModifiers::CONTROL) => {
+                self.jump_forward();
+            }
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => {
+                self.path_tooltip = !showing_path;
+            }
             (KeyCode::Char('g'), _) => {
+                // `g` may turn out to start `gt`/`gT`/`gq`
+                self.tab_origin = Some((self.view_position(), self.file_cursor));
+                self.pending_key = Some('g');
+                self.number_prefix = had_prefix.then_some(count);
+                if self.focus == FocusArea::Content {
+                    self.record_jump();
+                }
                 if self.focus == FocusArea::Sidebar {
                     self.set_sidebar_cursor(0);
                 } else {
@@ -628,6 +3054,9 @@ impl App {
                 }
             }
             (KeyCode::Char('G'), _) => {
+                if self.focus == FocusArea::Content {
+                    self.record_jump();
+                }
                 if self.focus == FocusArea::Sidebar {
                     let total = self.sidebar_len();
                     if total > 0 {
@@ -652,6 +3081,7 @@ impl App {
                         self.next_search_match();
                     }
                 } else {
+                    self.record_jump();
                     for _ in 0..count {
                         self.next_file();
                     }
@@ -664,6 +3094,7 @@ impl App {
                         self.prev_search_match();
                     }
                 } else {
+                    self.record_jump();
                     for _ in 0..count {
                         self.prev_file();
                     }
@@ -671,6 +3102,12 @@ impl App {
             }
 
             // Focus
+            (KeyCode::Tab | KeyCode::Char('S'), _) if self.sidebar_hidden() => {
+                self.set_sidebar_overlay(!self.sidebar_overlay);
+            }
+            (KeyCode::Char('S'), _) => {
+                self.status_message = Some("The sidebar only hides below 80 columns".to_string());
+            }
             (KeyCode::Tab, _) => {
                 self.focus = match self.focus {
                     FocusArea::Content => FocusArea::Sidebar,
@@ -680,29 +3117,153 @@ impl App {
 
             // View toggles
             (KeyCode::Char('u'), KeyModifiers::NONE) => {
-                self.diff_mode = match self.diff_mode {
+                // Choosing a mode by hand overrides the narrow-terminal switch
+                self.auto_unified = None;
+                self.set_diff_mode(match self.diff_mode {
                     DiffMode::SideBySide => DiffMode::Unified,
-                    DiffMode::Unified => DiffMode::SideBySideFull,
+                    DiffMode::Unified => DiffMode::WordDiff,
+                    DiffMode::WordDiff => DiffMode::SideBySideFull,
                     DiffMode::SideBySideFull => DiffMode::SideBySide,
-                };
-                if self.diff_mode == DiffMode::SideBySideFull {
-                    self.prime_full_highlight_cache();
-                }
-                self.set_content_scroll(self.content_scroll);
+                });
             }
-            (KeyCode::Char('x'), _) => {
-                self.context_lines = match self.context_lines {
-                    3 => 1,
-                    1 => 0,
-                    _ => 3,
+            (KeyCode::Char('<' | '>' | 'x'), _) => {
+                let context = match key.code {
+                    KeyCode::Char('<') => self.context_lines.saturating_sub(1),
+                    KeyCode::Char('>') => (self.context_lines + 1).min(MAX_CONTEXT_LINES.max(self.context_lines)),
+                    _ if self.context_lines > 0 => 0,
+                    _ => self.context_restore,
                 };
-                let _ = self.reload_diffs();
+                if self.context_lines > 0 {
+                    self.context_restore = self.context_lines;
+                }
+                self.context_lines = context;
+                // Applied once presses settle; the footer shows the new value right away
+                self.context_at = Some(Instant::now() + CONTEXT_DELAY);
             }
             (KeyCode::Char('h'), KeyModifiers::NONE) => {
                 // Toggle collapse/expand of hidden files
                 self.show_hidden = !self.show_hidden;
                 self.toggle_hidden_files();
             }
+            (KeyCode::Char('i'), KeyModifiers::NONE) => {
+                if self.generated_files.is_empty() {
+                    self.status_message = Some("No generated files (configure gv.generated)".to_string());
+                } else {
+                    self.include_generated = !self.include_generated;
+                    self.toggle_generated_files();
+                    self.status_message = Some(if self.include_generated {
+                        "Generated files included".to_string()
+                    } else {
+                        "Generated files collapsed and left out of the totals".to_string()
+                    });
+                }
+            }
+            (KeyCode::Char('B'), _) => {
+                self.group_by_commit = !self.group_by_commit;
+                self.only_commit = None;
+                self.stash = None;
+                let position = self.view_position();
+                if let Err(e) = self.reload_diffs() {
+                    self.status_message = Some(format!("Reload failed: {}", e));
+                }
+                self.restore_view_position(&position);
+            }
+            (KeyCode::Char('t'), KeyModifiers::NONE) => {
+                // Steps count from the oldest commit, so the list must be complete
+                self.wait_for_commits();
+                if self.timeline.is_some() {
+                    self.set_timeline(None);
+                } else if self.commits.is_empty() {
+                    self.status_message = Some("No commits to replay".to_string());
+                } else {
+                    self.set_timeline(Some(self.commits.len() - 1));
+                }
+            }
+            (KeyCode::Right, _) if self.timeline.is_some() => {
+                self.move_timeline(count as i32);
+            }
+            (KeyCode::Left, _) if self.timeline.is_some() => {
+                self.move_timeline(-(count as i32));
+            }
+            (KeyCode::Char('m'), KeyModifiers::NONE) => {
+                self.toggle_message_panel();
+            }
+            (KeyCode::Char('J'), _) if self.commit_messages.is_some() => {
+                self.message_scroll = self.message_scroll.saturating_add(count);
+            }
+            (KeyCode::Char('K'), _) if self.commit_messages.is_some() => {
+                self.message_scroll = self.message_scroll.saturating_sub(count);
+            }
+            (KeyCode::Char('D'), _) => {
+                self.request_difftool();
+            }
+            (KeyCode::Char('e'), KeyModifiers::NONE) => {
+                self.request_editor();
+            }
+            (KeyCode::Char('y'), KeyModifiers::NONE) => {
+                self.copy_patch(false);
+            }
+            (KeyCode::Char('Y'), _) => {
+                self.copy_patch(true);
+            }
+            (KeyCode::Char('='), _) => {
+                self.compare_input.clear();
+                self.view_mode = ViewMode::ComparePrompt;
+            }
+            (KeyCode::Char('#'), _) => {
+                self.start_note();
+            }
+            (KeyCode::Char('r'), KeyModifiers::NONE) => {
+                self.toggle_current_reviewed();
+            }
+            (KeyCode::Char('R'), _) => {
+                self.record_jump();
+                self.next_unreviewed_file();
+            }
+            (KeyCode::Char('U'), _) => {
+                self.toggle_since_review();
+            }
+            (KeyCode::Char('-'), _) => {
+                self.step_base(true);
+            }
+            (KeyCode::Char('+'), _) => {
+                self.step_base(false);
+            }
+            (KeyCode::Char('A'), _) => {
+                self.confirm_review();
+            }
+            (KeyCode::Char('E'), _) => {
+                self.status_message = Some(match self.export_ansi() {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            (KeyCode::Char('t'), KeyModifiers::CONTROL) => {
+                self.cycle_theme();
+            }
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => {
+                self.status_message = Some(match self.export_patch() {
+                    Ok(path) => format!("Saved patch to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                self.status_message = Some(match self.export_markdown() {
+                    Ok(path) => format!("Saved review report to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            (KeyCode::Char('X'), _) => {
+                self.hexdump = !self.hexdump;
+                self.apply_hexdump();
+                self.invalidate_layout();
+                self.set_content_scroll(self.content_scroll);
+                self.status_message = Some(if self.hexdump {
+                    format!("Hexdump on for binary files up to {} bytes", self.hexdump_max_bytes)
+                } else {
+                    "Hexdump off".to_string()
+                });
+            }
             (KeyCode::Char('['), _) => {
                 // Shrink sidebar
                 self.resize_sidebar(-1);
@@ -711,6 +3272,20 @@ impl App {
                 // Expand sidebar
                 self.resize_sidebar(1);
             }
+            (KeyCode::Char(' '), _) if self.selecting => {
+                self.toggle_mark();
+                self.move_sidebar_cursor(1);
+            }
+            (KeyCode::Char('v'), _) => {
+                self.toggle_selection_mode();
+            }
+            (KeyCode::Char('V'), _) => {
+                self.selecting = false;
+                if !self.marked.is_empty() {
+                    self.marked.clear();
+                    self.apply_filters();
+                }
+            }
             (KeyCode::Char(' '), _) => {
                 if self.focus == FocusArea::Sidebar {
                     self.toggle_sidebar_node();
@@ -718,32 +3293,140 @@ impl App {
                     self.toggle_current_file();
                 }
             }
-            (KeyCode::Enter, _) => {
-                if self.focus == FocusArea::Sidebar {
-                    self.jump_to_sidebar_selection();
+            (KeyCode::Enter, _) if self.focus == FocusArea::Sidebar => {
+                self.jump_to_sidebar_selection();
+                if self.sidebar_overlay && self.focus == FocusArea::Content {
+                    self.sidebar_overlay = false;
                 }
             }
+            (KeyCode::Char('o'), KeyModifiers::NONE) if self.focus == FocusArea::Sidebar => {
+                let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+                let expanded = nodes.get(self.file_cursor)
+                    .is_some_and(|n| self.expanded_files.contains(&n.path));
+                self.set_sidebar_hunks_expanded(!expanded);
+            }
+            (KeyCode::Right, _) if self.focus == FocusArea::Sidebar => {
+                self.set_sidebar_hunks_expanded(true);
+            }
+            (KeyCode::Left, _) if self.focus == FocusArea::Sidebar => {
+                self.set_sidebar_hunks_expanded(false);
+            }
             (KeyCode::Char('z'), _) => {
+                self.pending_key = Some('z');
+            }
+            (KeyCode::Char('Z'), _) => {
                 self.toggle_all_files();
             }
+            (KeyCode::Char('f'), KeyModifiers::NONE) => {
+                self.cycle_change_filter();
+            }
+            (KeyCode::Char('l'), KeyModifiers::NONE) => {
+                self.start_path_filter();
+            }
+            (KeyCode::Char('d'), KeyModifiers::NONE) => {
+                self.open_diffstat();
+            }
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => {
+                self.toggle_language_filter();
+            }
+            (KeyCode::Char('s'), KeyModifiers::NONE) => {
+                self.cycle_stage();
+            }
+            (KeyCode::Char('b'), KeyModifiers::NONE) => {
+                self.toggle_blame();
+            }
+            (KeyCode::Char('a'), KeyModifiers::NONE) => {
+                self.cycle_algorithm();
+            }
+            (KeyCode::Char('.'), KeyModifiers::NONE) => {
+                self.toggle_merge_base();
+            }
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
+                self.toggle_wrap();
+            }
+            (KeyCode::Char('p'), _) => {
+                self.sidebar_follow = !self.sidebar_follow;
+                self.follow_at = None;
+                self.status_message = Some(format!(
+                    "Sidebar follow {}",
+                    if self.sidebar_follow { "on" } else { "off" }
+                ));
+            }
 
             // Popups
             (KeyCode::Char('c'), _) => {
                 self.view_mode = ViewMode::CommitFilter;
                 self.popup_cursor = 0;
+                self.commit_anchor = None;
+                self.commit_query.clear();
+                self.commit_typing = false;
             }
             (KeyCode::Char('w'), KeyModifiers::NONE) => {
                 self.view_mode = ViewMode::WorktreeSwitcher;
                 self.popup_cursor = 0;
                 self.filter_input.clear();
+                self.spawn_worktree_statuses();
             }
             (KeyCode::Char('W'), _) => {
-                self.view_mode = ViewMode::WorktreeList;
-                self.popup_cursor = self.current_worktree;
+                self.open_worktree_dashboard();
+            }
+            (KeyCode::Char('$'), _) => {
+                self.open_stashes();
             }
             (KeyCode::Char('?'), _) => {
+                self.help_scroll = 0;
                 self.view_mode = ViewMode::Help;
             }
+
+            // Remote
+            (KeyCode::Char('F'), _) => {
+                self.start_fetch();
+            }
+            (KeyCode::Char('P'), _) => {
+                self.open_pull_picker();
+            }
+            (KeyCode::Char('\''), _) => {
+                self.popup_cursor = 0;
+                self.view_mode = ViewMode::Recent;
+            }
+            (KeyCode::Char('T'), _) => {
+                self.symbols = self.collect_symbols();
+                if self.symbols.is_empty() {
+                    self.status_message = Some("No functions or types found in the changed files".to_string());
+                } else {
+                    self.popup_cursor = 0;
+                    self.filter_input.clear();
+                    self.view_mode = ViewMode::Symbols;
+                }
+            }
+            (KeyCode::Char('O'), _) => self.open_outline(),
+            (KeyCode::Char('I'), _) => {
+                if self.annotations.is_empty() {
+                    self.status_message = Some("No annotations loaded (use --annotations)".to_string());
+                } else {
+                    self.popup_cursor = 0;
+                    self.view_mode = ViewMode::Issues;
+                }
+            }
+            (KeyCode::Char('C'), _) => {
+                match git::predict_conflicts(&self.repo_path, &self.main_branch, &self.head_rev) {
+                    Ok(files) => {
+                        self.conflict_files = files;
+                        self.popup_cursor = 0;
+                        self.view_mode = ViewMode::Conflicts;
+                    }
+                    Err(e) => {
+                        self.status_message = Some(format!("Conflict check failed: {}", e));
+                    }
+                }
+            }
+            // `/` filters the tree when the sidebar has focus
+            (KeyCode::Char('/'), _) if self.focus == FocusArea::Sidebar => {
+                self.tree_filter_origin = self.file_cursor;
+                self.tree_filter = Some(String::new());
+                self.view_mode = ViewMode::TreeFilter;
+                self.update_tree_filter(|_| {});
+            }
             (KeyCode::Char('/'), _) => {
                 self.view_mode = ViewMode::Search;
                 self.search_input.clear();
@@ -751,136 +3434,1121 @@ impl App {
                 self.search_match_index = 0;
                 self.search_active = false;
             }
-
             _ => {}
         }
-
+
+        false
+    }
+
+    /// Indices of the commits matching the commit filter's `/` query, in its sort order
+    fn shown_commits(&self) -> Vec<usize> {
+        let query = self.commit_query.to_lowercase();
+        let mut shown: Vec<usize> = self.commits
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                query.is_empty()
+                    || c.subject.to_lowercase().contains(&query)
+                    || c.author.to_lowercase().contains(&query)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.commit_sort.sort(&self.commits, &mut shown);
+        shown
+    }
+
+    /// Handle keys in commit filter popup
+    ///
+    /// The cursor, range anchor and selection keys work on the commits the
+    /// `/` query leaves shown.
+    fn handle_commit_filter_key(&mut self, key: KeyEvent) -> bool {
+        if self.commit_typing {
+            match key.code {
+                KeyCode::Esc => {
+                    self.commit_query.clear();
+                    self.commit_typing = false;
+                    self.popup_cursor = 0;
+                }
+                KeyCode::Enter => self.commit_typing = false,
+                KeyCode::Backspace => {
+                    self.commit_query.pop();
+                    self.popup_cursor = 0;
+                }
+                KeyCode::Char(c) => {
+                    self.commit_query.push(c);
+                    self.popup_cursor = 0;
+                }
+                _ => {}
+            }
+            return false;
+        }
+
+        let shown = self.shown_commits();
+        match key.code {
+            KeyCode::Esc if self.commit_anchor.take().is_some() => {}
+            KeyCode::Esc if !self.commit_query.is_empty() => {
+                // Clear the query, staying on the same commit
+                let current = shown.get(self.popup_cursor).copied();
+                self.commit_query.clear();
+                self.popup_cursor = self.shown_commits().iter().position(|&i| Some(i) == current).unwrap_or(0);
+            }
+            KeyCode::Char('s') => {
+                // Re-sort, staying on the same commit
+                let current = shown.get(self.popup_cursor).copied();
+                self.commit_sort = self.commit_sort.next();
+                self.commit_anchor = None;
+                self.popup_cursor = self.shown_commits().iter().position(|&i| Some(i) == current).unwrap_or(0);
+            }
+            KeyCode::Esc => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('/') => {
+                self.commit_typing = true;
+                self.commit_anchor = None;
+            }
+            KeyCode::Enter => {
+                self.commit_anchor = None;
+                self.view_mode = ViewMode::Diff;
+                self.only_commit = None;
+                self.stash = None;
+                let _ = self.reload_diffs();
+            }
+            KeyCode::Char('o') => {
+                // Again on the commit already shown goes back to the selection
+                let commit = shown.get(self.popup_cursor).map(|&i| self.commits[i].clone());
+                self.only_commit = match (&self.only_commit, commit) {
+                    (Some(shown), Some(commit)) if shown.full_hash == commit.full_hash => None,
+                    (_, commit) => commit,
+                };
+                self.stash = None;
+                self.timeline = None;
+                self.group_by_commit = false;
+                self.view_mode = ViewMode::Diff;
+                self.status_message = Some(match &self.only_commit {
+                    Some(commit) => format!("Showing only {} {}", commit.hash, commit.subject),
+                    None => "Showing the selected commits".to_string(),
+                });
+                if let Err(e) = self.reload_diffs() {
+                    self.status_message = Some(format!("Reload failed: {}", e));
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.popup_cursor < shown.len().saturating_sub(1) =>
+            {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            KeyCode::Char(' ') if let Some(anchor) = self.commit_anchor.take() => {
+                self.toggle_commit_range(anchor, self.popup_cursor);
+            }
+            KeyCode::Char(' ') => {
+                if let Some(&i) = shown.get(self.popup_cursor) {
+                    self.commits[i].selected = !self.commits[i].selected;
+                }
+            }
+            KeyCode::Char('v') => {
+                self.commit_anchor = match self.commit_anchor {
+                    Some(_) => None,
+                    None => Some(self.popup_cursor).filter(|&i| i < shown.len()),
+                };
+            }
+            KeyCode::Char('a') => {
+                for &i in &shown {
+                    self.commits[i].selected = true;
+                }
+            }
+            KeyCode::Char('n') => {
+                for &i in &shown {
+                    self.commits[i].selected = false;
+                }
+            }
+            KeyCode::Char('L') if self.more_commits => {
+                self.spawn_commit_walk(self.commit_limit);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Select every shown commit from `from` to `to`, or deselect them when all already are
+    fn toggle_commit_range(&mut self, from: usize, to: usize) {
+        let shown = self.shown_commits();
+        let Some(range) = shown.get(from.min(to)..=from.max(to).min(shown.len().saturating_sub(1))) else {
+            return;
+        };
+        let select = !range.iter().all(|&i| self.commits[i].selected);
+        for &i in range {
+            self.commits[i].selected = select;
+        }
+        self.status_message = Some(format!(
+            "{} {} commits",
+            if select { "Selected" } else { "Deselected" },
+            range.len()
+        ));
+    }
+
+    /// Note under the commit list on whether the walk is still going or stopped at its limit
+    fn commit_popup_status(&self) -> Option<String> {
+        if self.commit_walk.is_some() {
+            Some("Loading commits…".to_string())
+        } else if self.more_commits {
+            let listed = self.commits.iter().filter(|c| !c.is_uncommitted).count();
+            Some(format!("Stopped at {} commits · L: load {} more", listed, self.commit_limit))
+        } else {
+            None
+        }
+    }
+
+    /// Click a commit in the commit filter to toggle it, or Shift-click to
+    /// toggle every commit from the cursor to it
+    fn handle_commit_popup_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::ScrollDown => {
+                self.popup_cursor = (self.popup_cursor + 1).min(self.shown_commits().len().saturating_sub(1));
+            }
+            MouseEventKind::ScrollUp => self.popup_cursor = self.popup_cursor.saturating_sub(1),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let area = Rect::new(0, 0, self.width, self.height);
+                let status = self.commit_popup_status().is_some();
+                let shown = self.shown_commits();
+                let commits: Vec<&Commit> = shown.iter().map(|&i| &self.commits[i]).collect();
+                let Some(index) = commit_popup_hit(area, &commits, self.popup_cursor, status, mouse.column, mouse.row) else {
+                    return;
+                };
+                if mouse.modifiers.contains(KeyModifiers::SHIFT) {
+                    let anchor = self.commit_anchor.take().unwrap_or(self.popup_cursor);
+                    self.toggle_commit_range(anchor, index);
+                } else {
+                    let commit = &mut self.commits[shown[index]];
+                    commit.selected = !commit.selected;
+                }
+                self.popup_cursor = index;
+            }
+            _ => {}
+        }
+    }
+
+    /// Indices of the worktrees matching the switcher's filter
+    fn filtered_worktrees(&self) -> Vec<usize> {
+        let filter = self.filter_input.to_lowercase();
+        self.worktrees
+            .iter()
+            .enumerate()
+            .filter(|(_, wt)| {
+                filter.is_empty()
+                    || wt.path.to_string_lossy().to_lowercase().contains(&filter)
+                    || wt.branch.as_ref().is_some_and(|b| b.to_lowercase().contains(&filter))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Handle keys in worktree switcher popup
+    fn handle_worktree_switcher_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.view_mode = ViewMode::Diff;
+                self.filter_input.clear();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Open the selected worktree in a new tab
+                if let Some(index) = self.filtered_worktrees().get(self.popup_cursor).copied() {
+                    self.open_tab(index);
+                }
+                self.filter_input.clear();
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // What was typed to find a worktree names the branch of the new one
+                let branch = std::mem::take(&mut self.filter_input);
+                self.worktree_form = WorktreeForm::default();
+                self.set_worktree_branch(branch);
+                self.view_mode = ViewMode::WorktreeCreate;
+            }
+            KeyCode::Enter => {
+                // Switch to selected worktree
+                if let Some(index) = self.filtered_worktrees().get(self.popup_cursor).copied() {
+                    self.repo_path = self.repo_path.with_path(&self.worktrees[index].path);
+                    self.current_worktree = index;
+                    self.since_review = None;
+                    self.base_steps = 0;
+                    self.piped = None;
+                    self.only_commit = None;
+                    self.stash = None;
+                    let _ = self.load_data();
+                }
+
+                self.view_mode = ViewMode::Diff;
+                self.filter_input.clear();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            KeyCode::Char(c) => {
+                self.filter_input.push(c);
+                self.popup_cursor = 0;
+            }
+            KeyCode::Backspace => {
+                self.filter_input.pop();
+                self.popup_cursor = 0;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Set the new worktree's branch, and its path too while it hasn't been typed
+    ///
+    /// The path defaults to a sibling of the main worktree named after the
+    /// branch, e.g. `../gv-fix-tests` for `fix/tests`.
+    fn set_worktree_branch(&mut self, branch: String) {
+        let form = &mut self.worktree_form;
+        if !form.path_edited {
+            let repo = self.worktrees
+                .first()
+                .and_then(|wt| wt.path.file_name())
+                .map_or("worktree".into(), |name| name.to_string_lossy());
+            form.path = if branch.is_empty() {
+                String::new()
+            } else {
+                format!("../{}-{}", repo, branch.replace('/', "-"))
+            };
+        }
+        form.branch = branch;
+    }
+
+    /// Handle keys in the new worktree form
+    fn handle_worktree_form_key(&mut self, key: KeyEvent) -> bool {
+        let form = &mut self.worktree_form;
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => self.view_mode = ViewMode::WorktreeSwitcher,
+            (KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down, _) => form.on_path = !form.on_path,
+            (KeyCode::Enter, _) if !form.on_path => form.on_path = true,
+            (KeyCode::Enter, _) => self.create_worktree(),
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) if form.on_path => {
+                form.path.clear();
+                form.path_edited = true;
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => self.set_worktree_branch(String::new()),
+            (KeyCode::Backspace, _) if form.on_path => {
+                form.path.pop();
+                form.path_edited = true;
+            }
+            (KeyCode::Backspace, _) => {
+                let mut branch = form.branch.clone();
+                branch.pop();
+                self.set_worktree_branch(branch);
+            }
+            (KeyCode::Char(c), _) if form.on_path => {
+                form.path.push(c);
+                form.path_edited = true;
+            }
+            (KeyCode::Char(c), _) => {
+                let branch = format!("{}{}", form.branch, c);
+                self.set_worktree_branch(branch);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Create the worktree the form describes and switch to it, or show why it couldn't be
+    fn create_worktree(&mut self) {
+        let form = &self.worktree_form;
+        match git::create_worktree(&self.repo_path, &form.branch, Path::new(&form.path)) {
+            Ok(path) => {
+                self.status_message = Some(format!("Created worktree {} for {}", path.display(), form.branch.trim()));
+                self.repo_path = self.repo_path.with_path(path);
+                self.since_review = None;
+                self.base_steps = 0;
+                self.piped = None;
+                self.only_commit = None;
+                self.stash = None;
+                if let Err(e) = self.load_data() {
+                    self.status_message = Some(format!("Created the worktree, but loading it failed: {:#}", e));
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            Err(e) => self.worktree_form.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Handle keys in worktree list view
+    fn handle_worktree_list_key(&mut self, key: KeyEvent) -> bool {
+        // A pending change takes the next key as its answer
+        if let Some(action) = self.worktree_confirm.take() {
+            self.status_message = Some(match (key.code, action) {
+                (KeyCode::Char('y'), WorktreeAction::Delete(index)) => self.delete_worktree(index),
+                (KeyCode::Char('y'), WorktreeAction::Prune(_)) => self.prune_worktrees(),
+                (_, WorktreeAction::Delete(_)) => "Delete cancelled".to_string(),
+                (_, WorktreeAction::Prune(_)) => "Prune cancelled".to_string(),
+            });
+            return false;
+        }
+        self.status_message = None;
+
+        let selected = self.worktree_order.get(self.popup_cursor).copied();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                if let Some(wt) = selected.and_then(|i| self.worktrees.get(i)) {
+                    self.repo_path = self.repo_path.with_path(&wt.path);
+                    self.current_worktree = selected.unwrap_or(0);
+                    self.since_review = None;
+                    self.base_steps = 0;
+                    self.piped = None;
+                    self.only_commit = None;
+                    self.stash = None;
+                    let _ = self.load_data();
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('t') => {
+                if let Some(index) = selected {
+                    self.open_tab(index);
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.popup_cursor < self.worktree_order.len().saturating_sub(1) =>
+            {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            KeyCode::Char('s') => {
+                self.worktree_sort = self.worktree_sort.next();
+                self.sort_worktrees(selected);
+            }
+            KeyCode::Char('r') => {
+                self.refresh_worktree_statuses();
+            }
+            KeyCode::Char('f') => {
+                self.start_fetch();
+            }
+            KeyCode::Char('d') => match selected.and_then(|i| self.worktrees.get(i).map(|wt| (i, wt))) {
+                Some((_, wt)) if wt.is_current => {
+                    self.status_message = Some("Can't delete the worktree being viewed".to_string());
+                }
+                Some((index, _)) => self.worktree_confirm = Some(WorktreeAction::Delete(index)),
+                None => {}
+            },
+            KeyCode::Char('p') => match git::stale_worktrees(&self.repo_path) {
+                Ok(stale) if stale.is_empty() => self.status_message = Some("No stale worktrees to prune".to_string()),
+                Ok(stale) => self.worktree_confirm = Some(WorktreeAction::Prune(stale)),
+                Err(e) => self.status_message = Some(format!("Prune failed: {:#}", e)),
+            },
+            _ => {}
+        }
+        false
+    }
+
+    /// Open the stash browser, with the cursor on the stash shown if any
+    fn open_stashes(&mut self) {
+        match git::list_stashes(&self.repo_path) {
+            Ok(stashes) => {
+                self.popup_cursor = self.stash
+                    .as_ref()
+                    .and_then(|shown| stashes.iter().position(|s| s.full_hash == shown.full_hash))
+                    .unwrap_or(0);
+                self.stashes = stashes;
+                self.view_mode = ViewMode::Stashes;
+            }
+            Err(e) => self.status_message = Some(format!("Listing stashes failed: {:#}", e)),
+        }
+    }
+
+    /// Handle keys in the stash browser
+    fn handle_stashes_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('$') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor + 1 < self.stashes.len() => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                // Again on the stash already shown goes back to the branch
+                let Some(selected) = self.stashes.get(self.popup_cursor).cloned() else {
+                    return false;
+                };
+                self.stash = match &self.stash {
+                    Some(shown) if shown.full_hash == selected.full_hash => None,
+                    _ => Some(selected),
+                };
+                self.only_commit = None;
+                self.timeline = None;
+                self.group_by_commit = false;
+                self.view_mode = ViewMode::Diff;
+                self.status_message = Some(match &self.stash {
+                    Some(stash) => format!("Showing {}: {}", stash.name(), stash.message),
+                    None => "Showing the branch".to_string(),
+                });
+                if let Err(e) = self.reload_diffs() {
+                    self.status_message = Some(format!("Reload failed: {:#}", e));
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Open the diffstat of the files shown, with the cursor on the current file
+    fn open_diffstat(&mut self) {
+        self.diffstat_nodes =
+            build_file_tree(&self.diffs, &self.visible_diffs, &HashMap::new(), &HashSet::new(), &self.hidden_patterns);
+        let current = self.get_current_file();
+        self.sort_diffstat(current.as_deref());
+        self.view_mode = ViewMode::Diffstat;
+    }
+
+    /// Re-sort the diffstat, keeping the cursor on the row for `selected`
+    fn sort_diffstat(&mut self, selected: Option<&str>) {
+        self.diffstat_order = self.diffstat_sort.order(&self.diffstat_nodes);
+        self.popup_cursor = selected
+            .and_then(|path| self.diffstat_order.iter().position(|&i| self.diffstat_nodes[i].path == path))
+            .unwrap_or(0);
+    }
+
+    /// Handle keys in the diffstat
+    fn handle_diffstat_key(&mut self, key: KeyEvent) -> bool {
+        let selected = self.diffstat_order.get(self.popup_cursor).map(|&i| &self.diffstat_nodes[i]);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('d') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                // A folder jumps to its first file as listed
+                let folder = selected.filter(|n| n.is_folder).map(|n| format!("{}/", n.path));
+                let target = self.diffstat_order[self.popup_cursor..]
+                    .iter()
+                    .map(|&i| &self.diffstat_nodes[i])
+                    .find(|n| !n.is_folder && folder.as_ref().is_none_or(|f| n.path.starts_with(f.as_str())))
+                    .and_then(|n| self.diffs.iter().position(|d| d.path == n.path));
+                if let Some(index) = target {
+                    if let Some(diff) = self.diffs.get_mut(index) {
+                        diff.collapsed = false;
+                    }
+                    self.invalidate_layout();
+                    self.scroll_to_diff_index(index);
+                    self.focus = FocusArea::Content;
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor + 1 < self.diffstat_order.len() => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.popup_cursor = 0,
+            KeyCode::Char('G') | KeyCode::End => self.popup_cursor = self.diffstat_order.len().saturating_sub(1),
+            KeyCode::Char('s') => {
+                let selected = selected.map(|n| n.path.clone());
+                self.diffstat_sort = self.diffstat_sort.next();
+                self.sort_diffstat(selected.as_deref());
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Open the worktree dashboard with fresh statuses
+    fn open_worktree_dashboard(&mut self) {
+        self.view_mode = ViewMode::WorktreeList;
+        self.refresh_worktree_statuses();
+        let current = self.current_worktree;
+        self.sort_worktrees(Some(current));
+    }
+
+    /// Re-list worktrees and start reading each one's status
+    fn refresh_worktree_statuses(&mut self) {
+        let selected = self.worktree_order.get(self.popup_cursor).copied();
+        let selected_path = selected.and_then(|i| self.worktrees.get(i)).map(|w| w.path.clone());
+
+        if let Ok(mut worktrees) = git::list_worktrees(&self.repo_path) {
+            git::find_current_worktree(&mut worktrees, &self.repo_path);
+            self.current_worktree = worktrees.iter().position(|w| w.is_current).unwrap_or(0);
+            self.worktrees = worktrees;
+        }
+        self.spawn_worktree_statuses();
+
+        let selected = selected_path.and_then(|p| self.worktrees.iter().position(|w| w.path == p));
+        self.sort_worktrees(selected);
+    }
+
+    /// Read every worktree's status in the background, so a repo with many
+    /// large worktrees still opens the switcher and dashboard at once
+    fn spawn_worktree_statuses(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let paths: Vec<git::RepoPath> = self.worktrees.iter()
+            .map(|wt| self.repo_path.with_path(&wt.path))
+            .collect();
+        let base = self.main_branch.clone();
+        thread::spawn(move || {
+            for path in paths {
+                // Stops early once the receiver is dropped by another refresh
+                let status = git::worktree_status(&path, &base).ok();
+                if tx.send((path.to_path_buf(), status)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.worktree_statuses = vec![None; self.worktrees.len()];
+        self.worktree_status_rx = Some(rx);
+    }
+
+    /// Take in the worktree statuses read so far, blocking until all are with `wait`
+    fn poll_worktree_statuses(&mut self, wait: bool) {
+        let Some(rx) = &self.worktree_status_rx else {
+            return;
+        };
+
+        let mut received = false;
+        let finished = loop {
+            let event = if wait { rx.recv().map_err(|_| TryRecvError::Disconnected) } else { rx.try_recv() };
+            match event {
+                Ok((path, status)) => {
+                    if let Some(i) = self.worktrees.iter().position(|wt| wt.path == path)
+                        && let Some(slot) = self.worktree_statuses.get_mut(i)
+                    {
+                        *slot = status;
+                        received = true;
+                    }
+                }
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        if finished {
+            self.worktree_status_rx = None;
+        }
+
+        // Sorting by recency or changes needs the statuses
+        if received && self.view_mode == ViewMode::WorktreeList {
+            let selected = self.worktree_order.get(self.popup_cursor).copied();
+            self.sort_worktrees(selected);
+        }
+    }
+
+    /// Re-sort the dashboard, keeping the cursor on `selected`
+    fn sort_worktrees(&mut self, selected: Option<usize>) {
+        self.worktree_order = self.worktree_sort.order(&self.worktrees, &self.worktree_statuses);
+        self.popup_cursor = selected
+            .and_then(|s| self.worktree_order.iter().position(|&i| i == s))
+            .unwrap_or(0);
+    }
+
+    /// Remove a worktree after confirmation, returning the outcome message
+    fn delete_worktree(&mut self, index: usize) -> String {
+        let Some(wt) = self.worktrees.get(index) else {
+            return "No worktree selected".to_string();
+        };
+        if wt.is_current {
+            return "Can't delete the worktree being viewed".to_string();
+        }
+        let path = wt.path.clone();
+        match git::remove_worktree(&self.repo_path, &path) {
+            Ok(()) => {
+                self.refresh_worktree_statuses();
+                format!("Deleted worktree {}", path.display())
+            }
+            Err(e) => format!("Delete failed: {:#}", e),
+        }
+    }
+
+    /// Forget the worktrees whose directories are gone
+    fn prune_worktrees(&mut self) -> String {
+        match git::prune_worktrees(&self.repo_path) {
+            Ok(pruned) => {
+                self.refresh_worktree_statuses();
+                format!("Pruned {} stale worktree(s)", pruned.len())
+            }
+            Err(e) => format!("Prune failed: {:#}", e),
+        }
+    }
+
+    /// Handle keys in help overlay
+    fn handle_help_key(&mut self, key: KeyEvent) -> bool {
+        let page = self.viewport_height() / 2;
+        let sections = help_section_starts();
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?'), _) => {
+                self.view_mode = ViewMode::Diff;
+            }
+            (KeyCode::Char('j') | KeyCode::Down, _) => self.help_scroll += 1,
+            (KeyCode::Char('k') | KeyCode::Up, _) => self.help_scroll = self.help_scroll.saturating_sub(1),
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) | (KeyCode::PageDown, _) => self.help_scroll += page,
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) | (KeyCode::PageUp, _) => {
+                self.help_scroll = self.help_scroll.saturating_sub(page);
+            }
+            (KeyCode::Char('g'), _) => self.help_scroll = 0,
+            (KeyCode::Char('G'), _) => self.help_scroll = usize::MAX,
+            // Rendering clamps the scroll, so it's always a real position here
+            (KeyCode::Tab | KeyCode::Right | KeyCode::Char('n'), _) => {
+                if let Some(&next) = sections.iter().find(|&&start| start > self.help_scroll) {
+                    self.help_scroll = next;
+                }
+            }
+            (KeyCode::BackTab | KeyCode::Left | KeyCode::Char('N'), _) => {
+                self.help_scroll = sections
+                    .iter()
+                    .rev()
+                    .find(|&&start| start < self.help_scroll)
+                    .copied()
+                    .unwrap_or(0);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle keys in conflict prediction popup
+    fn handle_conflicts_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                if let Some(path) = self.conflict_files.get(self.popup_cursor) {
+                    match self.diffs.iter().position(|d| &d.path == path) {
+                        Some(index) => {
+                            self.scroll_to_diff_index(index);
+                            self.focus = FocusArea::Content;
+                        }
+                        None => {
+                            self.status_message = Some(format!("{} is not in the current diff", path));
+                        }
+                    }
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('j') | KeyCode::Down
+                if self.popup_cursor < self.conflict_files.len().saturating_sub(1) =>
+            {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle keys in issues panel
+    fn handle_issues_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.scoped_annotations().len();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                let target = self.scoped_annotations()
+                    .get(self.popup_cursor)
+                    .map(|a| (a.path.clone(), a.line));
+                if let Some((path, line)) = target {
+                    self.jump_to_line(&path, line);
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor < count.saturating_sub(1) => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_pull_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.pulls.as_ref().map_or(0, Vec::len);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('P') => {
+                self.pulls_rx = None;
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                if let Some(pull) = self.pulls.as_ref().and_then(|p| p.get(self.popup_cursor)).cloned() {
+                    self.fetch_pull_request(pull);
+                    self.view_mode = ViewMode::Diff;
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor + 1 < count => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_recent_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('\'') => {
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Enter => {
+                let target = self.recent.get(self.popup_cursor)
+                    .and_then(|path| self.diffs.iter().position(|d| d.path == *path));
+                if let Some(index) = target {
+                    if let Some(diff) = self.diffs.get_mut(index) {
+                        diff.collapsed = false;
+                    }
+                    self.invalidate_layout();
+                    self.scroll_to_diff_index(index);
+                    self.focus = FocusArea::Content;
+                }
+                self.view_mode = ViewMode::Diff;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor + 1 < self.recent.len() => {
+                self.popup_cursor += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            }
+            _ => {}
+        }
         false
     }
 
-    /// Handle keys in commit filter popup
-    fn handle_commit_filter_key(&mut self, key: KeyEvent) -> bool {
-        match key.code {
-            KeyCode::Esc => {
-                self.view_mode = ViewMode::Diff;
+    /// Annotations that fall on lines the visible diffs add or change
+    fn scoped_annotations(&self) -> Vec<&Annotation> {
+        let mut scoped = Vec::new();
+        for diff in self.visible_files() {
+            let annotations = self.annotations.for_path(&diff.path);
+            if annotations.is_empty() {
+                continue;
             }
-            KeyCode::Enter => {
+            let changed: std::collections::HashSet<u32> = diff.hunks
+                .iter()
+                .flat_map(|h| h.lines.iter())
+                .filter(|l| l.line_type == LineType::Added)
+                .filter_map(|l| l.new_lineno)
+                .collect();
+            scoped.extend(annotations.iter().filter(|a| changed.contains(&a.line)));
+        }
+        scoped
+    }
+
+    /// Scroll the content view to a new-file line, falling back to the file header
+    fn jump_to_line(&mut self, path: &str, line: u32) {
+        let Some(index) = self.diffs.iter().position(|d| d.path == path) else {
+            return;
+        };
+        if let Some(diff) = self.diffs.get_mut(index) {
+            diff.collapsed = false;
+        }
+        self.invalidate_layout();
+        self.scroll_to_diff_index(index);
+        if let Some(offset) = self.diffs.get(index).and_then(|d| new_line_offset(d, &self.notes, self.diff_mode, self.wrap_columns(), line)) {
+            let target = self.content_scroll + offset;
+            self.content_cursor = Some(target);
+            self.set_content_scroll(target);
+        }
+        self.focus = FocusArea::Content;
+    }
+
+    /// Functions and types defined in the shown files, in file order
+    fn collect_symbols(&self) -> Vec<FileSymbol> {
+        let mut symbols = Vec::new();
+        for diff in self.visible_files().filter(|d| !d.is_binary) {
+            let Some(lines) = &diff.new_content else {
+                continue;
+            };
+            let added: Vec<u32> = diff.hunks
+                .iter()
+                .flat_map(|hunk| &hunk.lines)
+                .filter(|line| line.line_type == LineType::Added)
+                .filter_map(|line| line.new_lineno)
+                .collect();
+            let found = self.highlighter.symbols(&diff.path, lines);
+            for (i, symbol) in found.iter().enumerate() {
+                let end = found.get(i + 1).map_or(lines.len() as u32, |next| next.line - 1).max(symbol.line);
+                symbols.push(FileSymbol {
+                    path: diff.path.clone(),
+                    symbol: symbol.clone(),
+                    end,
+                    changed: added.iter().any(|&line| (symbol.line..=end).contains(&line)),
+                });
+            }
+        }
+        symbols
+    }
+
+    /// Indices of the symbols matching the picker's query
+    fn filtered_symbols(&self) -> Vec<usize> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| fuzzy_matches(&self.filter_input, &s.symbol.name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Handle keys in the symbol picker; letters type into its query
+    fn handle_symbols_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.filtered_symbols().len();
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => {
                 self.view_mode = ViewMode::Diff;
-                let _ = self.reload_diffs();
+                self.filter_input.clear();
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.popup_cursor < self.commits.len().saturating_sub(1) {
-                    self.popup_cursor += 1;
+            (KeyCode::Enter, _) => {
+                if let Some(index) = self.filtered_symbols().get(self.popup_cursor).copied() {
+                    let symbol = self.symbols[index].clone();
+                    self.jump_to_symbol(&symbol);
                 }
+                self.view_mode = ViewMode::Diff;
+                self.filter_input.clear();
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) if self.popup_cursor + 1 < count => {
+                self.popup_cursor += 1;
             }
-            KeyCode::Char(' ') => {
-                if let Some(commit) = self.commits.get_mut(self.popup_cursor) {
-                    commit.selected = !commit.selected;
-                }
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                self.popup_cursor = self.popup_cursor.saturating_sub(1);
             }
-            KeyCode::Char('a') => {
-                for commit in &mut self.commits {
-                    commit.selected = true;
-                }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.filter_input.push(c);
+                self.popup_cursor = 0;
             }
-            KeyCode::Char('n') => {
-                for commit in &mut self.commits {
-                    commit.selected = false;
-                }
+            (KeyCode::Backspace, _) => {
+                self.filter_input.pop();
+                self.popup_cursor = 0;
             }
             _ => {}
         }
         false
     }
 
-    /// Handle keys in worktree switcher popup
-    fn handle_worktree_switcher_key(&mut self, key: KeyEvent) -> bool {
+    /// Jump to a symbol's definition, or to the first line of it the diff shows
+    fn jump_to_symbol(&mut self, entry: &FileSymbol) {
+        let Some(index) = self.diffs.iter().position(|d| d.path == entry.path) else {
+            return;
+        };
+        self.diffs[index].collapsed = false;
+        let shown = (entry.symbol.line..=entry.end)
+            .find(|&line| new_line_offset(&self.diffs[index], &self.notes, self.diff_mode, self.wrap_columns(), line).is_some());
+        self.jump_to_line(&entry.path, shown.unwrap_or(entry.symbol.line));
+        if shown.is_none() {
+            self.status_message = Some(format!("{} is outside the shown hunks (u: full file)", entry.symbol.name));
+        }
+    }
+
+    /// Open the outline of the file at the top of the view
+    fn open_outline(&mut self) {
+        let Some(index) = self.layout().file_at(self.content_scroll) else {
+            return;
+        };
+        let diff = &self.diffs[index];
+        let symbols = diff.new_content.as_ref().map(|lines| self.highlighter.symbols(&diff.path, lines)).unwrap_or_default();
+        if symbols.is_empty() {
+            self.status_message = Some(format!("No functions or types found in {}", diff.path));
+            return;
+        }
+        self.outline = outline_entries(&diff.hunks, &symbols);
+        self.outline_file = index;
+        self.popup_cursor = 0;
+        self.view_mode = ViewMode::Outline;
+    }
+
+    /// Handle keys in the outline panel
+    fn handle_outline_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
-            KeyCode::Esc => {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('O') => {
                 self.view_mode = ViewMode::Diff;
-                self.filter_input.clear();
             }
             KeyCode::Enter => {
-                // Switch to selected worktree
-                let filtered: Vec<_> = self.worktrees
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, wt)| {
-                        self.filter_input.is_empty()
-                            || wt.path.to_string_lossy().to_lowercase().contains(&self.filter_input.to_lowercase())
-                            || wt.branch.as_ref().map_or(false, |b| b.to_lowercase().contains(&self.filter_input.to_lowercase()))
-                    })
-                    .collect();
-
-                if let Some((idx, wt)) = filtered.get(self.popup_cursor) {
-                    self.repo_path = wt.path.clone();
-                    self.current_worktree = *idx;
-                    let _ = self.load_data();
+                if let Some(&hunk) = self.outline.get(self.popup_cursor).and_then(|e| e.hunks.first()) {
+                    self.scroll_to_hunk(self.outline_file, hunk);
+                    self.focus = FocusArea::Content;
                 }
-
                 self.view_mode = ViewMode::Diff;
-                self.filter_input.clear();
             }
-            KeyCode::Char('j') | KeyCode::Down => {
+            KeyCode::Char('j') | KeyCode::Down if self.popup_cursor + 1 < self.outline.len() => {
                 self.popup_cursor += 1;
             }
             KeyCode::Char('k') | KeyCode::Up => {
                 self.popup_cursor = self.popup_cursor.saturating_sub(1);
             }
-            KeyCode::Char(c) => {
-                self.filter_input.push(c);
-                self.popup_cursor = 0;
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle keys in the compare prompt
+    fn handle_compare_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => self.view_mode = ViewMode::Diff,
+            KeyCode::Enter => {
+                self.view_mode = ViewMode::Diff;
+                let spec = self.compare_input.trim().to_string();
+                if !spec.is_empty() {
+                    self.start_compare(&spec);
+                }
             }
+            KeyCode::Char(c) => self.compare_input.push(c),
             KeyCode::Backspace => {
-                self.filter_input.pop();
-                self.popup_cursor = 0;
+                self.compare_input.pop();
             }
             _ => {}
         }
         false
     }
 
-    /// Handle keys in worktree list view
-    fn handle_worktree_list_key(&mut self, key: KeyEvent) -> bool {
+    /// Handle keys in the note prompt
+    fn handle_note_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
-            KeyCode::Esc | KeyCode::Char('q') => {
+            KeyCode::Esc => {
+                self.note_target = None;
                 self.view_mode = ViewMode::Diff;
             }
             KeyCode::Enter => {
-                if let Some(wt) = self.worktrees.get(self.popup_cursor) {
-                    self.repo_path = wt.path.clone();
-                    self.current_worktree = self.popup_cursor;
-                    let _ = self.load_data();
-                }
                 self.view_mode = ViewMode::Diff;
-            }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.popup_cursor < self.worktrees.len().saturating_sub(1) {
-                    self.popup_cursor += 1;
+                if let Some((path, line)) = self.note_target.take() {
+                    let text = self.note_input.trim().to_string();
+                    self.set_note(path, line, text);
                 }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                self.popup_cursor = self.popup_cursor.saturating_sub(1);
+            KeyCode::Char(c) => self.note_input.push(c),
+            KeyCode::Backspace => {
+                self.note_input.pop();
             }
             _ => {}
         }
         false
     }
 
-    /// Handle keys in help overlay
-    fn handle_help_key(&mut self, key: KeyEvent) -> bool {
+    /// Handle keys in the path filter prompt
+    fn handle_path_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
-            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('?') => {
+            KeyCode::Esc => self.view_mode = ViewMode::Diff,
+            KeyCode::Enter => {
                 self.view_mode = ViewMode::Diff;
+                let input = std::mem::take(&mut self.path_input);
+                self.set_path_filter(&input);
+            }
+            KeyCode::Char(c) => self.path_input.push(c),
+            KeyCode::Backspace => {
+                self.path_input.pop();
             }
             _ => {}
         }
         false
     }
 
+    /// Name the notes are kept under: the worktree's branch, or the revision shown
+    fn notes_key(&self) -> Option<&str> {
+        (self.compare.is_none() && self.piped.is_none()).then(|| self.current_branch())
+    }
+
+    /// Prompt for a note on the new-file line under the cursor, starting from the one it has
+    fn start_note(&mut self) {
+        if self.notes_key().is_none() {
+            self.status_message = Some("Notes are kept for a branch or revision, not compared files".to_string());
+            return;
+        }
+        let position = self.content_cursor.unwrap_or(self.content_scroll);
+        let layout = self.layout();
+        let Some(index) = layout.file_at(position) else {
+            return;
+        };
+        let offset = position - layout.file_start(index).unwrap_or(position);
+        let diff = &self.diffs[index];
+        let Some(line) = line_at_offset(diff, &self.notes, self.diff_mode, self.wrap_columns(), offset) else {
+            self.status_message = Some(format!("{} has no new lines to note", diff.path));
+            return;
+        };
+        self.note_input = self.notes.get(&diff.path).and_then(|lines| lines.get(&line)).cloned().unwrap_or_default();
+        self.note_target = Some((diff.path.clone(), line));
+        self.view_mode = ViewMode::NotePrompt;
+    }
+
+    /// Leave `text` as the note on a line, or delete the line's note when empty, and save the notes
+    fn set_note(&mut self, path: String, line: u32, text: String) {
+        let lines = self.notes.entry(path.clone()).or_default();
+        let message = match (lines.contains_key(&line), text.is_empty()) {
+            (false, true) => String::new(),
+            (true, true) => {
+                lines.remove(&line);
+                format!("Deleted the note on {}:{}", path, line)
+            }
+            (true, false) => {
+                lines.insert(line, text);
+                format!("Updated the note on {}:{}", path, line)
+            }
+            (false, false) => {
+                lines.insert(line, text);
+                format!("Noted {}:{}", path, line)
+            }
+        };
+        if lines.is_empty() {
+            self.notes.remove(&path);
+        }
+        if message.is_empty() {
+            return;
+        }
+
+        let position = self.view_position();
+        self.invalidate_layout();
+        self.restore_view_position(&position);
+
+        let saved = match self.notes_key() {
+            Some(key) => notes::save(&self.repo_path, key, &self.notes),
+            None => Ok(()),
+        };
+        self.status_message = Some(match saved {
+            Ok(()) => message,
+            Err(e) => format!("Saving notes failed: {:#}", e),
+        });
+    }
+
+    /// Compare the file under the cursor with `spec` in a new tab
+    ///
+    /// `spec` is a path from the worktree root, `REV:PATH`, or a revision
+    /// of the same file.
+    fn start_compare(&mut self, spec: &str) {
+        let position = self.content_cursor.unwrap_or(self.content_scroll);
+        let Some(index) = self.layout().file_at(position) else {
+            self.status_message = Some("No file to compare".to_string());
+            return;
+        };
+        let path = self.diffs[index].path.clone();
+        let root = git::repo_root(&self.repo_path).unwrap_or_else(|_| self.repo_path.to_path_buf());
+
+        // The file as reviewed: on disk in a worktree, else as of the revision shown
+        let (current, path) = match &self.compare {
+            Some((_, new)) => (new.clone(), None),
+            None if self.head_rev == "HEAD" => {
+                (git::CompareSide::File { path: root.join(&path), name: path.clone() }, Some(path))
+            }
+            None => (git::CompareSide::Blob { rev: self.head_rev.clone(), path: path.clone() }, Some(path)),
+        };
+        match git::resolve_side(&self.repo_path, &root, spec, path.as_deref()) {
+            Ok(other) => self.insert_tab(Tab {
+                repo_path: self.repo_path.clone(),
+                head_rev: "HEAD".to_string(),
+                current_worktree: self.current_worktree,
+                compare: Some((other, current)),
+                ..Tab::default()
+            }),
+            Err(e) => self.status_message = Some(e.to_string()),
+        }
+    }
+
     /// Handle keys in search mode
     fn handle_search_key(&mut self, key: KeyEvent) -> bool {
         match key.code {
@@ -922,6 +4590,85 @@ impl App {
         false
     }
 
+    fn handle_tree_filter_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => {
+                self.close_tree_filter();
+                self.set_sidebar_cursor(self.tree_filter_origin);
+            }
+            KeyCode::Enter => {
+                let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+                let target = nodes.get(self.file_cursor).map(|n| n.path.clone());
+                self.close_tree_filter();
+                if let Some(path) = target {
+                    self.restore_sidebar_cursor(&path);
+                    self.jump_to_sidebar_selection();
+                }
+            }
+            KeyCode::Down => self.move_sidebar_cursor(1),
+            KeyCode::Up => self.move_sidebar_cursor(-1),
+            KeyCode::Backspace => {
+                if self.tree_filter.as_ref().is_some_and(|q| q.chars().count() <= 1) {
+                    self.close_tree_filter();
+                    self.set_sidebar_cursor(self.tree_filter_origin);
+                } else {
+                    self.update_tree_filter(|query| {
+                        query.pop();
+                    });
+                }
+            }
+            KeyCode::Char(c) => self.update_tree_filter(|query| query.push(c)),
+            _ => {}
+        }
+        false
+    }
+
+    /// Handle keys in the language filter bar
+    fn handle_language_filter_key(&mut self, key: KeyEvent) -> bool {
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => {
+                self.language_filter = self.language_filter_origin.take();
+                self.view_mode = ViewMode::Diff;
+                self.apply_filters();
+            }
+            (KeyCode::Enter, _) => {
+                self.view_mode = ViewMode::Diff;
+                if self.language_filter.as_ref().is_some_and(|q| q.trim().is_empty()) {
+                    self.language_filter = None;
+                    self.apply_filters();
+                }
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => self.update_language_filter(String::clear),
+            (KeyCode::Backspace, _) => self.update_language_filter(|query| {
+                query.pop();
+            }),
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.update_language_filter(|query| query.push(c));
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Edit the sidebar filter query and move the cursor to the top match
+    fn update_tree_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(query) = self.tree_filter.as_mut() {
+            edit(query);
+        }
+        self.rebuild_file_tree();
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+        let top = nodes.iter().position(|n| !n.is_folder).unwrap_or(0);
+        self.sidebar_scroll = 0;
+        self.set_sidebar_cursor(top);
+    }
+
+    /// Close the filter bar and bring back the full tree
+    fn close_tree_filter(&mut self) {
+        self.tree_filter = None;
+        self.view_mode = ViewMode::Diff;
+        self.rebuild_file_tree();
+    }
+
     /// Update search matches based on current input
     fn update_search_matches(&mut self) {
         self.search_matches.clear();
@@ -934,10 +4681,11 @@ impl App {
         let query = self.search_input.to_lowercase();
 
         // Search in file tree (file names and paths)
-        let tree = flatten_tree(&self.file_tree);
+        let tree = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         for (i, node) in tree.iter().enumerate() {
+            // Hunks share their file's path, so only match their label
             if node.name.to_lowercase().contains(&query)
-                || node.path.to_lowercase().contains(&query)
+                || (node.hunk_index.is_none() && node.path.to_lowercase().contains(&query))
             {
                 self.search_matches.push(i);
             }
@@ -988,19 +4736,38 @@ impl App {
 
     /// Handle mouse input
     fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if self.view_mode == ViewMode::CommitFilter {
+            self.handle_commit_popup_mouse(mouse);
+            return;
+        }
         // Check if click is near the sidebar border (within 2 columns)
-        let near_border = (mouse.column as i32 - self.sidebar_width as i32).abs() <= 1;
+        let sidebar_width = self.sidebar_shown_width();
+        let near_border = sidebar_width > 0 && (mouse.column as i32 - sidebar_width as i32).abs() <= 1;
+        let in_sidebar = match self.sidebar_position {
+            SidebarPosition::Bottom => {
+                mouse.row >= self.header_height() + self.viewport_height() as u16 && mouse.row < self.height - 1
+            }
+            SidebarPosition::Left => mouse.column < sidebar_width,
+        };
+        let in_panel = !in_sidebar && mouse.column >= self.width - self.message_panel_width();
 
         match mouse.kind {
+            MouseEventKind::ScrollDown if in_panel => {
+                self.message_scroll = self.message_scroll.saturating_add(MOUSE_SCROLL_LINES as usize);
+            }
+            MouseEventKind::ScrollUp if in_panel => {
+                self.message_scroll = self.message_scroll.saturating_sub(MOUSE_SCROLL_LINES as usize);
+            }
+            MouseEventKind::Down(MouseButton::Left) if in_panel => {}
             MouseEventKind::ScrollDown => {
-                if mouse.column < self.sidebar_width {
+                if in_sidebar {
                     self.scroll_sidebar(MOUSE_SCROLL_LINES);
                 } else {
                     self.scroll_content(MOUSE_SCROLL_LINES);
                 }
             }
             MouseEventKind::ScrollUp => {
-                if mouse.column < self.sidebar_width {
+                if in_sidebar {
                     self.scroll_sidebar(-MOUSE_SCROLL_LINES);
                 } else {
                     self.scroll_content(-MOUSE_SCROLL_LINES);
@@ -1010,16 +4777,18 @@ impl App {
                 if near_border {
                     // Start dragging the sidebar border
                     self.sidebar_dragging = true;
-                } else if mouse.column < self.sidebar_width {
+                } else if in_sidebar {
                     self.focus = FocusArea::Sidebar;
                     self.handle_sidebar_click(mouse.row);
                 } else {
                     self.focus = FocusArea::Content;
                     // Handle click in content area (diff view)
-                    // Layout: header (row 0), content (rows 1 to height-2), footer (row height-1)
-                    if mouse.row >= 1 && mouse.row < self.height.saturating_sub(1) {
-                        let row_in_content = (mouse.row - 1) as usize;
+                    // Layout: header, content (viewport height rows), footer (row height-1)
+                    let top = self.header_height();
+                    if mouse.row >= top && mouse.row < top + self.viewport_height() as u16 {
+                        let row_in_content = (mouse.row - top) as usize;
                         let position = self.content_scroll + row_in_content;
+                        self.content_cursor = Some(position);
                         self.toggle_file_at_position(position);
                     }
                 }
@@ -1028,14 +4797,12 @@ impl App {
                 // Stop dragging
                 self.sidebar_dragging = false;
             }
-            MouseEventKind::Drag(MouseButton::Left) => {
-                if self.sidebar_dragging {
-                    // Resize sidebar to mouse position
-                    let new_width = mouse.column.max(MIN_SIDEBAR_WIDTH).min(MAX_SIDEBAR_WIDTH);
-                    // Don't let sidebar take more than 80% of screen width
-                    let max_width = (self.width * 4 / 5).min(MAX_SIDEBAR_WIDTH);
-                    self.sidebar_width = new_width.min(max_width);
-                }
+            MouseEventKind::Drag(MouseButton::Left) if self.sidebar_dragging => {
+                // Resize sidebar to mouse position
+                let new_width = mouse.column.clamp(MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH);
+                // Don't let sidebar take more than 80% of screen width
+                let max_width = (self.width * 4 / 5).min(MAX_SIDEBAR_WIDTH);
+                self.sidebar_width = new_width.min(max_width);
             }
             _ => {}
         }
@@ -1054,19 +4821,78 @@ impl App {
 
     fn set_content_scroll(&mut self, new_scroll: usize) {
         self.content_scroll = new_scroll.min(self.max_scroll());
+        // Keep the cursor on screen, like vim does when scrolling
+        if let Some(cursor) = self.content_cursor {
+            let last = self.content_scroll + self.viewport_height().saturating_sub(1);
+            let last = last.min(self.total_content_lines().saturating_sub(1));
+            self.content_cursor = Some(cursor.clamp(self.content_scroll, last.max(self.content_scroll)));
+        }
         self.sync_sidebar_selection();
     }
 
+    /// Number of content rows between the header and footer (or bottom sidebar)
+    fn viewport_height(&self) -> usize {
+        self.height
+            .saturating_sub(self.header_height() + 1)
+            .saturating_sub(self.bottom_panel_height()) as usize
+    }
+
+    /// Rows taken by the bottom sidebar, 0 when it's on the left
+    fn bottom_panel_height(&self) -> u16 {
+        match self.sidebar_position {
+            SidebarPosition::Bottom => (self.height / 3).clamp(5, 15).min(self.height.saturating_sub(3)),
+            SidebarPosition::Left => 0,
+        }
+    }
+
+    /// Line offsets of the visible diffs, built on first use after a layout change
+    fn layout(&self) -> &LineIndex {
+        self.line_index.get_or_init(|| {
+            LineIndex::build(&self.diffs, &self.visible_diffs, &self.notes, self.diff_mode, self.wrap_columns())
+        })
+    }
+
+    /// Width long lines wrap at, when wrapping
+    fn wrap_columns(&self) -> Option<u16> {
+        self.wrap.then_some(self.wrap_width)
+    }
+
+    /// Total rendered lines across visible diffs
+    fn total_content_lines(&self) -> usize {
+        self.layout().total_lines()
+    }
+
+    /// Forget cached line offsets after diffs, visibility, collapse or mode change
+    fn invalidate_layout(&mut self) {
+        self.line_index = OnceCell::new();
+    }
+
     /// Get maximum scroll position
     fn max_scroll(&self) -> usize {
-        let visible: Vec<&FileDiff> = self.visible_diffs
-            .iter()
-            .filter_map(|&i| self.diffs.get(i))
-            .collect();
+        self.total_content_lines().saturating_sub(self.viewport_height())
+    }
+
+    /// Reposition the viewport around the cursor (`zz`, `zt`, `zb`)
+    ///
+    /// Without a cursor, the header of the current file is used instead.
+    fn reposition_view(&mut self, command: char) {
+        let anchor = self.content_cursor.or_else(|| {
+            let path = self.get_current_file()?;
+            let index = self.diffs.iter().position(|d| d.path == path)?;
+            self.diff_start_line(index)
+        });
+        let Some(anchor) = anchor else {
+            return;
+        };
 
-        let total_lines = calculate_total_lines(&visible, self.diff_mode);
-        let viewport_height = self.height.saturating_sub(2) as usize;
-        total_lines.saturating_sub(viewport_height)
+        let height = self.viewport_height();
+        let target = match command {
+            'z' => anchor.saturating_sub(height / 2),
+            't' => anchor,
+            'b' => anchor.saturating_sub(height.saturating_sub(1)),
+            _ => return,
+        };
+        self.set_content_scroll(target);
     }
 
     fn sync_sidebar_selection(&mut self) {
@@ -1074,62 +4900,121 @@ impl App {
             return;
         };
 
-        let nodes = flatten_tree(&self.file_tree);
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         if nodes.is_empty() {
             return;
         }
 
-        if let Some(index) = nodes.iter().position(|node| node.path == current_file) {
+        let found = nodes.iter().position(|node| node.path == current_file);
+        if let Some(index) = found {
             self.file_cursor = index;
             self.ensure_sidebar_cursor_visible(nodes.len());
         }
     }
 
-    /// Navigate to next file
+    /// Navigate to next file, skipping reviewed ones if configured
     fn next_file(&mut self) {
-        // Find the next file boundary in scroll position
-        let visible: Vec<&FileDiff> = self.visible_diffs
-            .iter()
-            .filter_map(|&i| self.diffs.get(i))
-            .collect();
-
-        let mut line = 0;
-        for diff in visible {
-            let file_lines = file_line_count(diff, self.diff_mode);
-
-            if line > self.content_scroll {
-                self.set_content_scroll(line);
+        let mut line = self.content_scroll;
+        while let Some(target) = self.layout().next_file_start(line) {
+            if !self.skips_file_at(target) {
+                self.set_content_scroll(target);
                 return;
             }
-            line += file_lines;
+            line = target;
         }
     }
 
-    /// Navigate to previous file
+    /// Navigate to previous file, skipping reviewed ones if configured
     fn prev_file(&mut self) {
-        let visible: Vec<&FileDiff> = self.visible_diffs
-            .iter()
-            .filter_map(|&i| self.diffs.get(i))
-            .collect();
+        let mut line = self.content_scroll;
+        while let Some(target) = self.layout().prev_file_start(line) {
+            if !self.skips_file_at(target) {
+                self.set_content_scroll(target);
+                return;
+            }
+            line = target;
+        }
+    }
 
-        let mut positions: Vec<usize> = Vec::new();
-        let mut line = 0;
+    /// Whether `n`/`N` pass over the file starting at `line`
+    fn skips_file_at(&self, line: usize) -> bool {
+        self.skip_reviewed
+            && self.get_file_at_position(line).is_some_and(|path| self.reviewed.contains_key(&path))
+    }
 
-        for diff in visible {
-            positions.push(line);
-            let file_lines = file_line_count(diff, self.diff_mode);
-            line += file_lines;
+    /// Mark or unmark the file under the sidebar cursor, or the current file, as reviewed
+    ///
+    /// Reviewed files collapse out of the way; unmarking one opens it again.
+    fn toggle_current_reviewed(&mut self) {
+        let index = if self.focus == FocusArea::Sidebar {
+            let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+            nodes.get(self.file_cursor).filter(|node| !node.is_folder).and_then(|node| node.diff_index)
+        } else {
+            self.layout().file_at(self.content_scroll)
+        };
+        let Some(diff) = index.and_then(|i| self.diffs.get(i)) else {
+            return;
+        };
+        let path = diff.path.clone();
+        let reviewed = self.reviewed.remove(&path).is_none();
+        if reviewed {
+            self.reviewed.insert(path.clone(), diff.change_id());
         }
+        self.seen.insert(path.clone());
+        let position = self.view_position();
+        if let Some(index) = index {
+            self.collapse_by_hand(index, reviewed);
+        }
+        self.invalidate_layout();
+        self.restore_view_position(&position);
 
-        // Find the position before current scroll
-        for &pos in positions.iter().rev() {
-            if pos < self.content_scroll {
-                self.set_content_scroll(pos);
-                return;
-            }
+        let (done, total) = self.reviewed_count();
+        let message = if reviewed { "Reviewed" } else { "Unmarked" };
+        self.status_message = Some(format!("{} {} ({}/{} reviewed)", message, path, done, total));
+    }
+
+    /// Files shown that are marked reviewed, and files shown
+    fn reviewed_count(&self) -> (usize, usize) {
+        let done = self.visible_files().filter(|d| self.reviewed.contains_key(&d.path)).count();
+        (done, self.visible_diffs.len())
+    }
+
+    /// Forget reviews of files whose changes differ from what was reviewed, opening them again
+    ///
+    /// Files not in the diff at the moment, e.g. with another commit
+    /// selection, keep their mark until they're seen again.
+    fn drop_stale_reviews(&mut self) {
+        let current: HashMap<&str, &FileDiff> = self.diffs.iter().map(|d| (d.path.as_str(), d)).collect();
+        let stale: Vec<String> = self
+            .reviewed
+            .iter()
+            .filter(|(path, id)| current.get(path.as_str()).is_some_and(|diff| diff.change_id() != **id))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in stale {
+            self.reviewed.remove(&path);
+            self.collapsed_files.remove(&path);
         }
+    }
 
-        self.set_content_scroll(0);
+    /// Jump to the next expanded, unreviewed file, wrapping around
+    fn next_unreviewed_file(&mut self) {
+        let current = self.layout().file_at(self.content_scroll);
+        let position = current
+            .and_then(|c| self.visible_diffs.iter().position(|&i| i == c))
+            .map_or(0, |p| p + 1);
+        let count = self.visible_diffs.len();
+
+        let target = (0..count)
+            .map(|offset| self.visible_diffs[(position + offset) % count])
+            .find(|&i| {
+                let diff = &self.diffs[i];
+                !diff.collapsed && !is_hidden_file(&diff.path, &self.hidden_patterns) && !self.reviewed.contains_key(&diff.path)
+            });
+        match target.and_then(|i| self.layout().file_start(i)) {
+            Some(line) => self.set_content_scroll(line),
+            None => self.status_message = Some("No unreviewed files left".to_string()),
+        }
     }
 
     /// Toggle collapse on current file
@@ -1141,25 +5026,89 @@ impl App {
 
     /// Toggle collapse on a specific file
     fn toggle_file(&mut self, path: &str) {
-        if let Some(diff) = self.diffs.iter_mut().find(|d| d.path == path) {
-            diff.collapsed = !diff.collapsed;
+        if let Some(index) = self.diffs.iter().position(|d| d.path == path) {
+            self.collapse_by_hand(index, !self.diffs[index].collapsed);
+        }
+        self.invalidate_layout();
+        self.set_content_scroll(self.content_scroll);
+    }
+
+    /// Toggle collapse on file at a specific scroll position
+    fn toggle_file_at_position(&mut self, position: usize) {
+        if let Some(file) = self.get_file_at_position(position) {
+            self.toggle_file(&file);
+        }
+    }
+
+    /// Collapse or expand a file, keeping it that way across reloads
+    fn collapse_by_hand(&mut self, index: usize, collapsed: bool) {
+        if let Some(diff) = self.diffs.get_mut(index) {
+            diff.collapsed = collapsed;
+            self.collapsed_files.insert(diff.path.clone(), collapsed);
+        }
+    }
+
+    /// Toggle collapse on all files
+    fn toggle_all_files(&mut self) {
+        let all_collapsed = self.diffs.iter().all(|d| d.collapsed);
+        for index in 0..self.diffs.len() {
+            self.collapse_by_hand(index, !all_collapsed);
+        }
+        self.invalidate_layout();
+        self.set_content_scroll(self.content_scroll);
+    }
+
+    /// Collapse files matching the configured globs and close their folders
+    ///
+    /// Only the topmost matching folder is closed, and folders the user has
+    /// opened or closed keep that state.
+    fn apply_auto_collapse(&mut self) {
+        if self.collapse_globs.is_empty() {
+            return;
+        }
+        let matches = |path: &str| self.collapse_globs.iter().any(|g| glob_matches(g, path));
+
+        for diff in &mut self.diffs {
+            if matches(&diff.path) {
+                diff.collapsed = true;
+            }
+            let folders = diff.path.match_indices('/').map(|(i, _)| &diff.path[..i]);
+            if let Some(folder) = folders.into_iter().find(|f| matches(f)) {
+                self.expanded_folders.entry(folder.to_string()).or_insert(false);
+            }
+        }
+    }
+
+    /// Show files shorter than `full_file_lines` whole in the split view
+    fn apply_full_file_lines(&mut self) {
+        let Some(limit) = self.full_file_lines else {
+            return;
+        };
+        for diff in &mut self.diffs {
+            let lines = diff.old_content.as_ref().map_or(0, Vec::len).max(diff.new_content.as_ref().map_or(0, Vec::len));
+            let has_content = diff.old_content.is_some() || diff.new_content.is_some();
+            diff.whole = has_content && !diff.is_binary && lines < limit;
         }
-        self.set_content_scroll(self.content_scroll);
     }
 
-    /// Toggle collapse on file at a specific scroll position
-    fn toggle_file_at_position(&mut self, position: usize) {
-        if let Some(file) = self.get_file_at_position(position) {
-            self.toggle_file(&file);
+    /// Show binary files small enough as hexdumps, per the `X` toggle
+    fn apply_hexdump(&mut self) {
+        for diff in &mut self.diffs {
+            if let Some(info) = diff.binary.as_mut() {
+                info.hexdump = self.hexdump && info.max_size() <= self.hexdump_max_bytes;
+            }
         }
     }
 
-    /// Toggle collapse on all files
-    fn toggle_all_files(&mut self) {
-        let all_collapsed = self.diffs.iter().all(|d| d.collapsed);
+    /// Expand generated files when included, collapse them otherwise
+    fn toggle_generated_files(&mut self) {
         for diff in &mut self.diffs {
-            diff.collapsed = !all_collapsed;
+            if self.generated_files.contains(&diff.path) {
+                diff.collapsed = !self.include_generated;
+                self.collapsed_files.remove(&diff.path);
+            }
         }
+        self.invalidate_layout();
         self.set_content_scroll(self.content_scroll);
     }
 
@@ -1169,13 +5118,86 @@ impl App {
         // show_hidden = true means hidden files are expanded (not collapsed)
         // show_hidden = false means hidden files are collapsed
         for diff in &mut self.diffs {
-            if is_hidden_file(&diff.path) {
+            if is_hidden_file(&diff.path, &self.hidden_patterns) {
                 diff.collapsed = !self.show_hidden;
+                self.collapsed_files.remove(&diff.path);
             }
         }
+        self.invalidate_layout();
+        self.set_content_scroll(self.content_scroll);
+    }
+
+    /// Switch the diff layout
+    fn set_diff_mode(&mut self, mode: DiffMode) {
+        self.diff_mode = mode;
+        self.invalidate_layout();
+        self.prime_highlights();
         self.set_content_scroll(self.content_scroll);
     }
 
+    /// Whether the terminal is too narrow for the docked sidebar
+    fn sidebar_hidden(&self) -> bool {
+        self.sidebar_position == SidebarPosition::Left && self.width < COMPACT_WIDTH
+    }
+
+    /// Columns the docked sidebar takes from the content
+    fn sidebar_layout_width(&self) -> u16 {
+        if self.sidebar_hidden() || self.sidebar_position == SidebarPosition::Bottom {
+            0
+        } else {
+            self.sidebar_width
+        }
+    }
+
+    /// Columns of the left sidebar on screen, docked or floating
+    fn sidebar_shown_width(&self) -> u16 {
+        if self.sidebar_position == SidebarPosition::Bottom {
+            0
+        } else if !self.sidebar_hidden() {
+            self.sidebar_width
+        } else if self.sidebar_overlay {
+            self.sidebar_width.min(self.width.saturating_sub(4))
+        } else {
+            0
+        }
+    }
+
+    /// Summon or dismiss the floating sidebar, moving focus with it
+    fn set_sidebar_overlay(&mut self, shown: bool) {
+        self.sidebar_overlay = shown;
+        self.focus = if shown { FocusArea::Sidebar } else { FocusArea::Content };
+    }
+
+    /// Fit the layout to the terminal width
+    ///
+    /// Side-by-side modes fall back to unified below `NARROW_WIDTH` and come
+    /// back once there's room again; below `COMPACT_WIDTH` the sidebar hides
+    /// and can be summoned as an overlay.
+    fn apply_responsive_layout(&mut self) {
+        let narrow = self.width < NARROW_WIDTH;
+        match self.auto_unified {
+            None if narrow && !matches!(self.diff_mode, DiffMode::Unified | DiffMode::WordDiff) => {
+                self.auto_unified = Some(self.diff_mode);
+                let position = self.view_position();
+                self.set_diff_mode(DiffMode::Unified);
+                self.restore_view_position(&position);
+            }
+            Some(mode) if !narrow => {
+                self.auto_unified = None;
+                let position = self.view_position();
+                self.set_diff_mode(mode);
+                self.restore_view_position(&position);
+            }
+            _ => {}
+        }
+
+        if !self.sidebar_hidden() {
+            self.sidebar_overlay = false;
+        } else if !self.sidebar_overlay && self.focus == FocusArea::Sidebar {
+            self.focus = FocusArea::Content;
+        }
+    }
+
     /// Resize sidebar by delta steps
     fn resize_sidebar(&mut self, delta: i32) {
         let step = SIDEBAR_RESIZE_STEP as i32;
@@ -1187,13 +5209,21 @@ impl App {
         self.sidebar_width = new_width.clamp(MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH);
     }
 
+    /// Rows currently displayed in the sidebar
+    fn sidebar_nodes(&self) -> FlatTree<'_> {
+        FlatTree::new(&self.file_tree, &self.sidebar_rows)
+    }
+
     fn sidebar_len(&self) -> usize {
-        flatten_tree(&self.file_tree).len()
+        self.sidebar_rows.len()
     }
 
     fn sidebar_visible_height(&self) -> usize {
-        let content_height = self.height.saturating_sub(2);
-        content_height.saturating_sub(2) as usize
+        let sidebar_height = match self.sidebar_position {
+            SidebarPosition::Bottom => self.bottom_panel_height(),
+            SidebarPosition::Left => self.height.saturating_sub(self.header_height() + 1),
+        };
+        sidebar_height.saturating_sub(2) as usize
     }
 
     fn set_sidebar_cursor(&mut self, index: usize) {
@@ -1244,6 +5274,60 @@ impl App {
         self.sidebar_scroll = new_scroll.min(max_scroll);
     }
 
+    /// Scroll the focused pane's viewport, keeping its cursor on screen (Ctrl+e/Ctrl+y)
+    fn scroll_view(&mut self, lines: i32) {
+        if self.focus == FocusArea::Sidebar {
+            self.scroll_sidebar(lines);
+            let total = self.sidebar_len();
+            let visible = self.sidebar_visible_height();
+            if total > 0 && visible > 0 {
+                let last = (self.sidebar_scroll + visible - 1).min(total - 1);
+                self.file_cursor = self.file_cursor.clamp(self.sidebar_scroll, last);
+            }
+        } else {
+            self.scroll_content(lines);
+        }
+    }
+
+    /// Scroll the focused pane by whole pages (Ctrl+f/Ctrl+b)
+    fn scroll_pages(&mut self, pages: i32) {
+        let page = if self.focus == FocusArea::Sidebar {
+            self.sidebar_visible_height()
+        } else {
+            self.viewport_height()
+        };
+        self.scroll_view(page as i32 * pages);
+    }
+
+    /// Move the cursor to the top, middle or bottom of the viewport (H/M/L)
+    ///
+    /// A count moves that many lines in from the top (H) or bottom (L).
+    fn jump_in_view(&mut self, key: char, count: usize) {
+        let (top, height, total) = if self.focus == FocusArea::Sidebar {
+            (self.sidebar_scroll, self.sidebar_visible_height(), self.sidebar_len())
+        } else {
+            (self.content_scroll, self.viewport_height(), self.total_content_lines())
+        };
+        if total == 0 || height == 0 {
+            return;
+        }
+
+        let last = (top + height).min(total) - 1;
+        let offset = count.saturating_sub(1);
+        let target = match key {
+            'H' => top + offset,
+            'L' => last.saturating_sub(offset),
+            _ => top + (last - top) / 2,
+        }
+        .clamp(top, last);
+
+        if self.focus == FocusArea::Sidebar {
+            self.set_sidebar_cursor(target);
+        } else {
+            self.content_cursor = Some(target);
+        }
+    }
+
     fn ensure_sidebar_cursor_visible(&mut self, total: usize) {
         let visible = self.sidebar_visible_height();
         if visible == 0 {
@@ -1261,7 +5345,7 @@ impl App {
     }
 
     fn toggle_sidebar_node(&mut self) {
-        let nodes = flatten_tree(&self.file_tree);
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         let Some(node) = nodes.get(self.file_cursor) else {
             return;
         };
@@ -1271,18 +5355,19 @@ impl App {
             *expanded = !*expanded;
 
             let path = node.path.clone();
-            self.file_tree = build_file_tree(&self.diffs, &self.expanded_folders);
+            self.rebuild_file_tree();
             self.restore_sidebar_cursor(&path);
         } else if let Some(index) = node.diff_index {
-            if let Some(diff) = self.diffs.get_mut(index) {
-                diff.collapsed = !diff.collapsed;
+            if let Some(diff) = self.diffs.get(index) {
+                self.collapse_by_hand(index, !diff.collapsed);
             }
+            self.invalidate_layout();
             self.content_scroll = self.content_scroll.min(self.max_scroll());
         }
     }
 
     fn restore_sidebar_cursor(&mut self, path: &str) {
-        let nodes = flatten_tree(&self.file_tree);
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         if nodes.is_empty() {
             self.file_cursor = 0;
             self.sidebar_scroll = 0;
@@ -1299,7 +5384,7 @@ impl App {
     }
 
     fn jump_to_sidebar_selection(&mut self) {
-        let nodes = flatten_tree(&self.file_tree);
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         let Some(node) = nodes.get(self.file_cursor) else {
             return;
         };
@@ -1309,62 +5394,699 @@ impl App {
             if !*expanded {
                 *expanded = true;
                 let path = node.path.clone();
-                self.file_tree = build_file_tree(&self.diffs, &self.expanded_folders);
+                self.rebuild_file_tree();
                 self.restore_sidebar_cursor(&path);
             }
             return;
         }
 
         if let Some(index) = node.diff_index {
-            self.scroll_to_diff_index(index);
+            match node.hunk_index {
+                Some(hunk) => self.scroll_to_hunk(index, hunk),
+                None => self.scroll_to_diff_index(index),
+            }
             self.focus = FocusArea::Content;
         }
     }
 
+    /// Enter or leave sidebar selection mode
+    ///
+    /// Leaving applies the marks as a restriction on the files shown.
+    fn toggle_selection_mode(&mut self) {
+        self.selecting = !self.selecting;
+        if self.selecting {
+            self.focus = FocusArea::Sidebar;
+        }
+        let path = self.sidebar_nodes()
+            .get(self.file_cursor)
+            .map(|n| n.path.clone());
+        self.apply_filters();
+        if let Some(path) = path {
+            self.restore_sidebar_cursor(&path);
+        }
+    }
+
+    /// Mark or unmark the sidebar node under the cursor
+    fn toggle_mark(&mut self) {
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+        let Some(node) = nodes.get(self.file_cursor) else {
+            return;
+        };
+        let path = node.path.clone();
+        if !self.marked.remove(&path) {
+            // Marking a folder subsumes marks inside it
+            let prefix = format!("{}/", path);
+            self.marked.retain(|m| !m.starts_with(&prefix));
+            self.marked.insert(path);
+        }
+    }
+
+    /// Apply a pending context change once `<`/`>`/`x` presses have settled
+    fn poll_context(&mut self) {
+        if self.context_at.is_some_and(|at| Instant::now() >= at) {
+            self.context_at = None;
+            self.apply_context_lines();
+        }
+    }
+
+    /// Re-derive hunks for the current context setting
+    ///
+    /// Narrower context is cut down from the stored source hunks and wider
+    /// context filled in from the files' stored content, keeping collapse
+    /// state and full-file highlights; only files without content need git.
+    fn apply_context_lines(&mut self) {
+        let position = self.view_position();
+
+        let widen = self.context_lines > self.source_context;
+        let missing_content = |diff: &FileDiff| {
+            !diff.is_binary && !diff.hunks.is_empty() && diff.new_content.is_none() && diff.old_content.is_none()
+        };
+        if self.source_hunks.len() != self.diffs.len() || (widen && self.diffs.iter().any(missing_content)) {
+            let _ = self.reload_diffs();
+        } else {
+            for (diff, hunks) in self.diffs.iter_mut().zip(&self.source_hunks) {
+                let content = diff.new_content.as_ref().or(diff.old_content.as_ref());
+                diff.hunks = match content {
+                    Some(lines) if widen => git::widen_context(hunks, lines, self.context_lines),
+                    _ if self.context_lines >= self.source_context => hunks.clone(),
+                    _ => git::reduce_context(hunks, self.context_lines),
+                };
+                self.highlighter.invalidate(&diff.path);
+            }
+            self.invalidate_layout();
+            self.prime_highlights();
+            self.rebuild_file_tree();
+        }
+
+        self.content_cursor = None;
+        self.restore_view_position(&position);
+    }
+
+    /// Record the current file as viewed once it has stayed on screen a while
+    fn poll_viewing(&mut self) {
+        let current = match self.view_mode {
+            ViewMode::Diff => self.get_current_file(),
+            _ => None,
+        };
+        let Some(path) = current else {
+            self.viewing = None;
+            return;
+        };
+        match &self.viewing {
+            Some((viewing, since)) if *viewing == path => {
+                if since.elapsed() >= SEEN_DELAY && self.recent.first() != Some(&path) {
+                    self.recent.retain(|p| *p != path);
+                    self.recent.insert(0, path.clone());
+                    self.recent.truncate(MAX_RECENT_FILES);
+                    self.seen.insert(path);
+                }
+            }
+            _ => self.viewing = Some((path, Instant::now())),
+        }
+    }
+
+    /// Queue a follow scroll after the sidebar cursor moves
+    fn schedule_follow(&mut self) {
+        if self.sidebar_follow && self.focus == FocusArea::Sidebar {
+            self.follow_at = Some(Instant::now() + FOLLOW_DELAY);
+        }
+    }
+
+    /// Run the pending follow scroll once the cursor has settled
+    fn poll_follow(&mut self) {
+        if self.follow_at.is_some_and(|at| Instant::now() >= at) {
+            self.follow_at = None;
+            self.preview_sidebar_selection();
+        }
+    }
+
+    /// Scroll the content to the sidebar selection without taking focus
+    ///
+    /// Unlike Enter this leaves collapsed files alone, skips the jump list,
+    /// and doesn't sync the sidebar back to the content.
+    fn preview_sidebar_selection(&mut self) {
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+        let Some(node) = nodes.get(self.file_cursor) else {
+            return;
+        };
+        let (Some(diff_index), hunk_index) = (node.diff_index, node.hunk_index) else {
+            return;
+        };
+
+        let layout = self.layout();
+        let target = hunk_index
+            .and_then(|h| layout.hunk_start(diff_index, h))
+            .or_else(|| layout.file_start(diff_index));
+        if let Some(target) = target {
+            self.content_scroll = target.min(self.max_scroll());
+        }
+    }
+
+    /// Show or hide the hunks of the file under the sidebar cursor
+    fn set_sidebar_hunks_expanded(&mut self, expand: bool) {
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
+        let Some(node) = nodes.get(self.file_cursor) else {
+            return;
+        };
+        if node.is_folder {
+            return;
+        }
+
+        let path = node.path.clone();
+        let changed = if expand {
+            self.expanded_files.insert(path.clone())
+        } else {
+            self.expanded_files.remove(&path)
+        };
+        if changed {
+            self.rebuild_file_tree();
+            self.restore_sidebar_cursor(&path);
+        }
+    }
+
+    /// Scroll so a specific hunk of a file is at the top
+    fn scroll_to_hunk(&mut self, diff_index: usize, hunk_index: usize) {
+        if let Some(diff) = self.diffs.get_mut(diff_index) {
+            diff.collapsed = false;
+        }
+        self.invalidate_layout();
+        let layout = self.layout();
+        let Some(target) = layout.hunk_start(diff_index, hunk_index)
+            .or_else(|| layout.file_start(diff_index))
+        else {
+            return;
+        };
+        self.record_jump();
+        self.content_cursor = Some(target);
+        self.set_content_scroll(target);
+    }
+
     fn handle_sidebar_click(&mut self, row: u16) {
-        let content_top = 1u16;
-        let sidebar_top = content_top;
+        let content_top = self.header_height();
+        let sidebar_top = match self.sidebar_position {
+            SidebarPosition::Bottom => content_top + self.viewport_height() as u16,
+            SidebarPosition::Left => content_top,
+        };
         let inner_top = sidebar_top.saturating_add(1);
-        let inner_height = self.height.saturating_sub(2).saturating_sub(2);
+        let inner_height = self.sidebar_visible_height() as u16;
 
         if row < inner_top || row >= inner_top.saturating_add(inner_height) {
             return;
         }
 
         let index = self.sidebar_scroll + (row - inner_top) as usize;
-        let nodes = flatten_tree(&self.file_tree);
+        let nodes = FlatTree::new(&self.file_tree, &self.sidebar_rows);
         if index >= nodes.len() {
             return;
         }
 
-        let node = nodes[index];
+        let Some(node) = nodes.get(index) else {
+            return;
+        };
         let node_path = node.path.clone();
         let node_is_folder = node.is_folder;
         let node_diff_index = node.diff_index;
+        let node_hunk_index = node.hunk_index;
 
         self.set_sidebar_cursor(index);
         if node_is_folder {
             let expanded = self.expanded_folders.entry(node_path.clone()).or_insert(true);
             *expanded = !*expanded;
-            self.file_tree = build_file_tree(&self.diffs, &self.expanded_folders);
+            self.rebuild_file_tree();
             self.restore_sidebar_cursor(&node_path);
         } else if let Some(diff_index) = node_diff_index {
-            self.scroll_to_diff_index(diff_index);
+            match node_hunk_index {
+                Some(hunk) => self.scroll_to_hunk(diff_index, hunk),
+                None => self.scroll_to_diff_index(diff_index),
+            }
             self.focus = FocusArea::Content;
         }
     }
 
     fn scroll_to_diff_index(&mut self, diff_index: usize) {
-        let mut line = 0;
-        for &idx in &self.visible_diffs {
-            if let Some(diff) = self.diffs.get(idx) {
-                if idx == diff_index {
-                    self.set_content_scroll(line);
-                    return;
+        if let Some(line) = self.diff_start_line(diff_index) {
+            self.record_jump();
+            self.set_content_scroll(line);
+        }
+    }
+
+    /// First content line of a visible diff
+    fn diff_start_line(&self, diff_index: usize) -> Option<usize> {
+        self.layout().file_start(diff_index)
+    }
+
+    /// Current content position, relative to the file at the top
+    fn view_position(&self) -> ViewPosition {
+        let layout = self.layout();
+        let index = layout.file_at(self.content_scroll);
+        let start = index.and_then(|i| layout.file_start(i)).unwrap_or(0);
+        ViewPosition {
+            path: index.map(|i| self.diffs[i].path.clone()),
+            offset: self.content_scroll.saturating_sub(start),
+        }
+    }
+
+    /// Scroll back to a previously recorded position
+    fn restore_view_position(&mut self, position: &ViewPosition) {
+        let layout = self.layout();
+        let span = position.path
+            .as_ref()
+            .and_then(|p| self.diffs.iter().position(|d| &d.path == p))
+            .and_then(|index| Some((layout.file_start(index)?, layout.file_len(index)?)));
+
+        match span {
+            Some((start, len)) => {
+                self.set_content_scroll(start + position.offset.min(len.saturating_sub(1)));
+            }
+            None => self.set_content_scroll(position.offset),
+        }
+    }
+
+    /// Remember the current position before a significant jump
+    fn record_jump(&mut self) {
+        let position = self.view_position();
+        self.jump_list.record(position);
+    }
+
+    /// Go back to the previous position in the jump list (Ctrl+o)
+    fn jump_back(&mut self) {
+        let current = self.view_position();
+        if let Some(position) = self.jump_list.back(current) {
+            self.restore_view_position(&position);
+            self.focus = FocusArea::Content;
+        }
+    }
+
+    /// Go forward in the jump list (Ctrl+i)
+    fn jump_forward(&mut self) {
+        if let Some(position) = self.jump_list.forward() {
+            self.restore_view_position(&position);
+            self.focus = FocusArea::Content;
+        }
+    }
+
+    /// Replay the keys recorded in `register` (`@` for the last one) `count` times
+    ///
+    /// Returns true if the replayed keys quit the app.
+    fn replay_macro(&mut self, register: char, count: usize) -> bool {
+        let register = if register == '@' {
+            match self.last_macro {
+                Some(last) => last,
+                None => return false,
+            }
+        } else {
+            register
+        };
+        let Some(keys) = self.macros.get(&register).cloned() else {
+            self.status_message = Some(format!("Nothing recorded in @{}", register));
+            return false;
+        };
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            return false;
+        }
+
+        self.last_macro = Some(register);
+        self.macro_depth += 1;
+        let mut quit = false;
+        'replay: for _ in 0..count {
+            for &key in &keys {
+                if self.handle_key(key) {
+                    quit = true;
+                    break 'replay;
+                }
+            }
+        }
+        self.macro_depth -= 1;
+        quit
+    }
+
+    /// Swap the per-worktree state with `tab`
+    fn exchange_tab(&mut self, tab: &mut Tab) {
+        std::mem::swap(&mut self.repo_path, &mut tab.repo_path);
+        std::mem::swap(&mut self.main_branch, &mut tab.main_branch);
+        std::mem::swap(&mut self.base_sha, &mut tab.base_sha);
+        std::mem::swap(&mut self.head_rev, &mut tab.head_rev);
+        std::mem::swap(&mut self.current_worktree, &mut tab.current_worktree);
+        std::mem::swap(&mut self.commits, &mut tab.commits);
+        std::mem::swap(&mut self.commit_walk, &mut tab.commit_walk);
+        std::mem::swap(&mut self.more_commits, &mut tab.more_commits);
+        std::mem::swap(&mut self.diff_load, &mut tab.diff_load);
+        std::mem::swap(&mut self.resume_position, &mut tab.resume_position);
+        std::mem::swap(&mut self.diffs, &mut tab.diffs);
+        std::mem::swap(&mut self.diff_groups, &mut tab.diff_groups);
+        std::mem::swap(&mut self.source_hunks, &mut tab.source_hunks);
+        std::mem::swap(&mut self.source_context, &mut tab.source_context);
+        std::mem::swap(&mut self.visible_diffs, &mut tab.visible_diffs);
+        std::mem::swap(&mut self.file_tree, &mut tab.file_tree);
+        std::mem::swap(&mut self.sidebar_rows, &mut tab.sidebar_rows);
+        std::mem::swap(&mut self.expanded_folders, &mut tab.expanded_folders);
+        std::mem::swap(&mut self.expanded_files, &mut tab.expanded_files);
+        std::mem::swap(&mut self.collapsed_files, &mut tab.collapsed_files);
+        std::mem::swap(&mut self.content_scroll, &mut tab.content_scroll);
+        std::mem::swap(&mut self.content_cursor, &mut tab.content_cursor);
+        std::mem::swap(&mut self.sidebar_scroll, &mut tab.sidebar_scroll);
+        std::mem::swap(&mut self.file_cursor, &mut tab.file_cursor);
+        std::mem::swap(&mut self.group_by_commit, &mut tab.group_by_commit);
+        std::mem::swap(&mut self.timeline, &mut tab.timeline);
+        std::mem::swap(&mut self.only_commit, &mut tab.only_commit);
+        std::mem::swap(&mut self.stash, &mut tab.stash);
+        std::mem::swap(&mut self.blame, &mut tab.blame);
+        std::mem::swap(&mut self.commit_messages, &mut tab.commit_messages);
+        std::mem::swap(&mut self.message_scroll, &mut tab.message_scroll);
+        std::mem::swap(&mut self.change_filter, &mut tab.change_filter);
+        std::mem::swap(&mut self.path_filter, &mut tab.path_filter);
+        std::mem::swap(&mut self.language_filter, &mut tab.language_filter);
+        std::mem::swap(&mut self.stage, &mut tab.stage);
+        std::mem::swap(&mut self.reviewed, &mut tab.reviewed);
+        std::mem::swap(&mut self.notes, &mut tab.notes);
+        std::mem::swap(&mut self.seen, &mut tab.seen);
+        std::mem::swap(&mut self.recent, &mut tab.recent);
+        std::mem::swap(&mut self.viewing, &mut tab.viewing);
+        std::mem::swap(&mut self.marked, &mut tab.marked);
+        std::mem::swap(&mut self.selecting, &mut tab.selecting);
+        std::mem::swap(&mut self.jump_list, &mut tab.jump_list);
+        std::mem::swap(&mut self.conflict_files, &mut tab.conflict_files);
+        std::mem::swap(&mut self.marker_files, &mut tab.marker_files);
+        std::mem::swap(&mut self.generated_files, &mut tab.generated_files);
+        std::mem::swap(&mut self.compare, &mut tab.compare);
+        std::mem::swap(&mut self.since_review, &mut tab.since_review);
+        std::mem::swap(&mut self.base_steps, &mut tab.base_steps);
+        std::mem::swap(&mut self.fork_point, &mut tab.fork_point);
+        std::mem::swap(&mut self.piped, &mut tab.piped);
+        std::mem::swap(&mut self.pull, &mut tab.pull);
+        self.invalidate_layout();
+    }
+
+    /// Handle the second key of `gt` (next tab, or tab N with a count), `gT` and `gq`
+    fn handle_tab_key(&mut self, key: char, count: usize, had_prefix: bool) {
+        let len = self.tabs.len();
+        match key {
+            't' | 'T' if len < 2 => {
+                self.status_message =
+                    Some("Only one tab open (Ctrl-t in the worktree switcher or t in the dashboard opens one)".to_string());
+            }
+            't' if had_prefix => self.switch_tab(count.saturating_sub(1)),
+            't' => self.switch_tab((self.active_tab + 1) % len),
+            'T' => self.switch_tab((self.active_tab + len - 1) % len),
+            _ => self.close_tab(),
+        }
+    }
+
+    /// Open a worktree in a new tab after the current one
+    fn open_tab(&mut self, worktree: usize) {
+        let Some(path) = self.worktrees.get(worktree).map(|wt| wt.path.clone()) else {
+            return;
+        };
+        self.insert_tab(Tab {
+            repo_path: self.repo_path.with_path(path),
+            main_branch: self.main_branch.clone(),
+            head_rev: "HEAD".to_string(),
+            current_worktree: worktree,
+            ..Tab::default()
+        });
+    }
+
+    /// Open a new tab after the current one and load what it shows
+    fn insert_tab(&mut self, mut parked: Tab) {
+        self.exchange_tab(&mut parked);
+        self.tabs[self.active_tab] = parked;
+        self.active_tab += 1;
+        self.tabs.insert(self.active_tab, Tab::default());
+
+        self.highlighter.set_base_path(self.repo_path.to_path_buf());
+        if let Err(e) = self.load_data() {
+            self.status_message = Some(format!("Failed to load tab: {}", e));
+        }
+        self.view_mode = ViewMode::Diff;
+    }
+
+    /// Make tab `index` the active one, keeping each tab's state as it was
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        let mut tab = std::mem::take(&mut self.tabs[index]);
+        self.exchange_tab(&mut tab);
+        self.tabs[self.active_tab] = tab;
+        self.active_tab = index;
+        self.refresh_tab();
+    }
+
+    /// Close the active tab, moving to its right neighbour (or the left one at the end)
+    fn close_tab(&mut self) {
+        if self.tabs.len() < 2 {
+            self.status_message = Some("Only one tab open".to_string());
+            return;
+        }
+        let target = if self.active_tab + 1 < self.tabs.len() {
+            self.active_tab + 1
+        } else {
+            self.active_tab - 1
+        };
+        let mut tab = std::mem::take(&mut self.tabs[target]);
+        self.exchange_tab(&mut tab);
+        self.tabs.remove(self.active_tab);
+        self.active_tab = if target > self.active_tab { target - 1 } else { target };
+        self.refresh_tab();
+    }
+
+    /// Bring a tab's parked diffs up to date with the shared view settings
+    fn refresh_tab(&mut self) {
+        self.highlighter.set_base_path(self.repo_path.to_path_buf());
+        self.highlighter.clear_cache();
+        self.apply_hexdump();
+        self.invalidate_layout();
+        // Context may have changed while the tab was parked
+        let cursor = self.content_cursor;
+        self.apply_context_lines();
+        self.content_cursor = cursor;
+        self.view_mode = ViewMode::Diff;
+    }
+
+    /// Tab strip label: the branch (or revision, or compared files) each tab shows
+    fn tab_labels(&self) -> Vec<String> {
+        (0..self.tabs.len())
+            .map(|i| {
+                let (worktree, head_rev, compare, piped) = if i == self.active_tab {
+                    (self.current_worktree, &self.head_rev, &self.compare, self.piped.is_some())
+                } else {
+                    let tab = &self.tabs[i];
+                    (tab.current_worktree, &tab.head_rev, &tab.compare, tab.piped.is_some())
+                };
+                if piped {
+                    return "stdin".to_string();
+                }
+                if let Some((old, new)) = compare {
+                    return format!("{} ↔ {}", old.label(), new.label());
+                }
+                if head_rev != "HEAD" {
+                    return head_rev.clone();
                 }
-                line += file_line_count(diff, self.diff_mode);
+                self.worktrees
+                    .get(worktree)
+                    .map(|wt| match &wt.branch {
+                        Some(branch) => branch.clone(),
+                        None => wt.path.file_name().map_or_else(String::new, |n| n.to_string_lossy().into_owned()),
+                    })
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Rows above the content: the header, plus the tab strip and conflict banner when shown
+    fn header_height(&self) -> u16 {
+        1 + u16::from(self.tabs.len() > 1) + u16::from(!self.marker_files.is_empty())
+    }
+
+    /// Queue the file under the cursor for the external diff tool
+    fn request_difftool(&mut self) {
+        let position = self.content_cursor.unwrap_or(self.content_scroll);
+        let Some(index) = self.layout().file_at(position) else {
+            return;
+        };
+        if self.diffs[index].is_binary {
+            self.status_message = Some("Can't open binary files in a diff tool".to_string());
+            return;
+        }
+        self.external = Some(External::Difftool(index));
+    }
+
+    /// Wrap long lines or cut them off at the view's edge
+    fn toggle_wrap(&mut self) {
+        let position = self.view_position();
+        self.wrap = !self.wrap;
+        self.invalidate_layout();
+        self.restore_view_position(&position);
+        self.status_message = Some(match (self.wrap, self.diff_mode) {
+            (false, _) => "Wrap off".to_string(),
+            (true, DiffMode::Unified | DiffMode::WordDiff) => "Wrap on".to_string(),
+            (true, _) => "Wrap shows in the unified views (u)".to_string(),
+        });
+    }
+
+    /// Show or hide the blame gutter
+    fn toggle_blame(&mut self) {
+        if self.compare.is_some() || self.piped.is_some() {
+            self.status_message = Some("No history to blame compared or piped files on".to_string());
+            return;
+        }
+        self.blame = match self.blame {
+            Some(_) => None,
+            None => Some(HashMap::new()),
+        };
+        self.status_message = Some(match (&self.blame, self.diff_mode) {
+            (None, _) => "Blame hidden".to_string(),
+            (Some(_), DiffMode::SideBySide) => "Blame shows in the unified and full-file views (u)".to_string(),
+            (Some(_), _) => "Blame shown".to_string(),
+        });
+    }
+
+    /// Revision the new side of the diff is at, for blaming its lines
+    fn blame_head(&self) -> String {
+        if let Some(stash) = &self.stash {
+            return stash.full_hash.clone();
+        }
+        let step = self.timeline.and_then(|step| self.commits.iter().rev().nth(step));
+        match self.only_commit.as_ref().or(step) {
+            Some(commit) if !commit.is_uncommitted => commit.full_hash.clone(),
+            _ => self.head_rev.clone(),
+        }
+    }
+
+    /// Blame the files on screen that haven't been yet
+    fn blame_files_in_view(&mut self, height: usize) {
+        let layout = self.layout();
+        let mut files: Vec<usize> = (self.content_scroll..self.content_scroll + height)
+            .filter_map(|row| layout.file_at(row))
+            .collect();
+        files.dedup();
+        let head = self.blame_head();
+        let Some(blame) = &mut self.blame else {
+            return;
+        };
+        for index in files {
+            let diff = &self.diffs[index];
+            if diff.is_binary || diff.change == ChangeKind::Deleted || blame.contains_key(&diff.path) {
+                continue;
             }
+            // A file that can't be blamed gets an empty entry, so it isn't retried every frame
+            let lines = git::blame_lines(&self.repo_path, &head, &diff.path, diff.new_content.as_deref()).unwrap_or_default();
+            blame.insert(diff.path.clone(), lines);
+        }
+    }
+
+    /// Switch to the next built-in theme, re-highlighting the visible files in its syntax colors
+    fn cycle_theme(&mut self) {
+        self.theme = self.theme.next();
+        self.styles = Styles::for_theme(self.theme);
+        self.highlighter.set_theme(self.theme.syntax_theme());
+        self.prime_highlights();
+        self.status_message = Some(format!("Theme: {}", self.theme.name()));
+    }
+
+    /// Copy the hunk under the cursor, or its whole file, to the clipboard as a patch
+    fn copy_patch(&mut self, whole_file: bool) {
+        let position = self.content_cursor.unwrap_or(self.content_scroll);
+        let layout = self.layout();
+        let Some(index) = layout.file_at(position) else {
+            return;
+        };
+        let hunk = layout.hunk_at(position).map(|(_, hunk)| hunk);
+        let diff = &self.diffs[index];
+        let (patch, what) = match hunk {
+            _ if whole_file => (git::file_patch(diff), format!("{} as a patch", diff.path)),
+            Some(hunk) => (git::hunk_patch(diff, hunk), format!("hunk {} of {}", hunk + 1, diff.path)),
+            None => (None, String::new()),
+        };
+        self.status_message = Some(match patch {
+            None if diff.is_binary => "Can't copy a binary diff".to_string(),
+            None => format!("No hunk to copy in {}", diff.path),
+            Some(patch) => match clipboard::copy(&patch) {
+                Ok(()) => format!("Copied {}", what),
+                Err(e) => format!("Copy failed: {}", e),
+            },
+        });
+    }
+
+    /// Queue the file under the cursor for the editor, at the line under the cursor
+    fn request_editor(&mut self) {
+        let position = self.content_cursor.unwrap_or(self.content_scroll);
+        let layout = self.layout();
+        let Some(index) = layout.file_at(position) else {
+            return;
+        };
+        let offset = position - layout.file_start(index).unwrap_or(position);
+        let diff = &self.diffs[index];
+        if diff.change == ChangeKind::Deleted {
+            self.status_message = Some(format!("{} was deleted", diff.path));
+            return;
+        }
+        let root = git::repo_root(&self.repo_path).unwrap_or_else(|_| self.repo_path.to_path_buf());
+        let path = root.join(&diff.path);
+        if !path.is_file() {
+            self.status_message = Some(format!("{} isn't in the work tree", diff.path));
+            return;
+        }
+        let line = line_at_offset(diff, &self.notes, self.diff_mode, self.wrap_columns(), offset).unwrap_or(1);
+        self.external = Some(External::Editor(path, line));
+    }
+
+    /// Open a diff in the external diff tool, reporting failures in the status line
+    fn run_difftool(&mut self, index: usize) {
+        let diff = &self.diffs[index];
+        // An added file has no old side and a deleted one no new side; any other missing side wasn't loaded
+        let (old, new) = (diff.old_content.as_deref(), diff.new_content.as_deref());
+        if (old.is_none() && diff.change != ChangeKind::Added) || (new.is_none() && diff.change != ChangeKind::Deleted) {
+            self.status_message = Some(format!("Diff tool: {} isn't loaded as text", diff.path));
+            return;
+        }
+        let result = difftool::resolve_command(&self.repo_path, self.difftool.as_deref())
+            .and_then(|command| difftool::launch(&command, &diff.path, old, new));
+        if let Err(err) = result {
+            self.status_message = Some(format!("Diff tool: {:#}", err));
+        }
+    }
+
+    /// Open a file in the editor, then pick up any edits to the work tree
+    fn run_editor(&mut self, path: &Path, line: u32) {
+        let editor = editor::resolve_editor(&self.repo_path);
+        if let Err(err) = editor::launch(&editor, path, line) {
+            self.status_message = Some(format!("Editor: {:#}", err));
+            return;
         }
+        if self.head_rev == "HEAD" && self.compare.is_none() && self.piped.is_none() {
+            let _ = self.load_data();
+        }
+    }
+}
+
+/// Take over the terminal: raw mode, alternate screen and optional mouse capture
+pub(crate) fn enter_terminal(mouse: bool, enhanced_keys: bool) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    if mouse {
+        execute!(stdout, crossterm::event::EnableMouseCapture)?;
+    }
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
     }
+    Ok(())
+}
 
+/// Give the terminal back to the shell, undoing `enter_terminal`
+pub(crate) fn leave_terminal(mouse: bool, enhanced_keys: bool) -> Result<()> {
+    let mut stdout = io::stdout();
+    if enhanced_keys {
+        execute!(stdout, PopKeyboardEnhancementFlags)?;
+    }
+    disable_raw_mode()?;
+    if mouse {
+        execute!(stdout, crossterm::event::DisableMouseCapture)?;
+    }
+    execute!(stdout, LeaveAlternateScreen)?;
+    Ok(())
 }
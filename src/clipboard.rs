@@ -0,0 +1,24 @@
+//! System clipboard over OSC 52
+//!
+//! Asks the terminal to set the clipboard with an OSC 52 escape sequence,
+//! which works over SSH and needs no display server. Inside tmux the
+//! sequence is wrapped so tmux passes it on (with `set-clipboard on` it
+//! sets tmux's own buffer too).
+
+use std::io::{self, Write};
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+/// Put `text` on the system clipboard
+pub fn copy(text: &str) -> Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    let sequence = match std::env::var_os("TMUX") {
+        Some(_) => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+        None => sequence,
+    };
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
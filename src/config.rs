@@ -0,0 +1,276 @@
+//! Per-repository configuration
+//!
+//! Settings come from `git config` (`gv.*` keys) and a `.gv.toml` file at
+//! the repository root. Local git config wins, since `.gv.toml` is usually
+//! committed and shared. `GV_*` environment variables win over both, so CI
+//! containers and dotfiles can configure gv without writing files.
+
+use std::fs;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::git;
+
+/// Name of the repository config file
+const CONFIG_FILE: &str = ".gv.toml";
+
+/// Repository settings
+///
+/// Keys the file doesn't set keep their defaults, and unknown keys and
+/// tables are left for newer versions.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RepoConfig {
+    /// Default base revision (`base` / `gv.base`)
+    pub base: Option<String>,
+    /// Whether to capture the mouse (`mouse` / `gv.mouse`)
+    pub mouse: Option<bool>,
+    /// Color theme name (`theme` / `gv.theme`)
+    pub theme: Option<String>,
+    /// Largest binary file shown as a hexdump (`hexdump_max_bytes` / `gv.hexdumpMaxBytes`)
+    pub hexdump_max_bytes: Option<u64>,
+    /// Whether `n`/`N` skip reviewed files (`skip_reviewed` / `gv.skipReviewed`)
+    pub skip_reviewed: Option<bool>,
+    /// Print a review summary on quit (`summary` / `gv.summary`)
+    pub summary: Option<bool>,
+    /// Shell command for the external diff tool, using `$LOCAL` and `$REMOTE` (`difftool` / `gv.difftool`)
+    pub difftool: Option<String>,
+    /// File list placement, `left` or `bottom` (`sidebar` / `gv.sidebar`)
+    pub sidebar: Option<String>,
+    /// Leave submodule pointer changes out of diffs (`ignore_submodules` / `gv.ignoreSubmodules`)
+    pub ignore_submodules: Option<bool>,
+    /// Diff from where the branch forked off the base, as `base...HEAD` (`merge_base` / `gv.mergeBase`)
+    pub merge_base: Option<bool>,
+    /// Context lines around changes (`context` / `gv.context`)
+    pub context: Option<u32>,
+    /// Most commits listed before asking to load more (`max_commits` / `gv.maxCommits`)
+    pub max_commits: Option<usize>,
+    /// Files with fewer lines show whole in the split view (`full_file_lines` / `gv.fullFileLines`)
+    pub full_file_lines: Option<usize>,
+    /// Globs of files that start collapsed (`collapse` / `gv.collapse`, repeatable)
+    pub collapse: Vec<String>,
+    /// Globs of files hidden along with dotfiles, replacing the lock file defaults (`hidden` / `gv.hidden`, repeatable)
+    pub hidden: Vec<String>,
+    /// Regexes that mark a file generated when found near its top (`generated` / `gv.generated`, repeatable)
+    pub generated: Vec<String>,
+}
+
+impl RepoConfig {
+    /// Load the configuration for the repository containing `repo_path`
+    pub fn load(repo_path: &git::RepoPath) -> Result<Self> {
+        let mut config = match git::repo_root(repo_path) {
+            Ok(root) => Self::load_file(&root.join(CONFIG_FILE))?,
+            Err(_) => Self::default(),
+        };
+
+        if let Some(base) = git::config_string(repo_path, "gv.base") {
+            config.base = Some(base);
+        }
+        if let Some(mouse) = git::config_bool(repo_path, "gv.mouse") {
+            config.mouse = Some(mouse);
+        }
+        if let Some(theme) = git::config_string(repo_path, "gv.theme") {
+            config.theme = Some(theme);
+        }
+        if let Some(max) = git::config_i64(repo_path, "gv.hexdumpMaxBytes") {
+            config.hexdump_max_bytes = Some(max.max(0) as u64);
+        }
+        if let Some(skip) = git::config_bool(repo_path, "gv.skipReviewed") {
+            config.skip_reviewed = Some(skip);
+        }
+        if let Some(ignore) = git::config_bool(repo_path, "gv.ignoreSubmodules") {
+            config.ignore_submodules = Some(ignore);
+        }
+        if let Some(merge_base) = git::config_bool(repo_path, "gv.mergeBase") {
+            config.merge_base = Some(merge_base);
+        }
+        if let Some(summary) = git::config_bool(repo_path, "gv.summary") {
+            config.summary = Some(summary);
+        }
+        if let Some(difftool) = git::config_string(repo_path, "gv.difftool") {
+            config.difftool = Some(difftool);
+        }
+        if let Some(sidebar) = git::config_string(repo_path, "gv.sidebar") {
+            config.sidebar = Some(sidebar);
+        }
+        if let Some(context) = git::config_i64(repo_path, "gv.context") {
+            config.context = Some(context.max(0) as u32);
+        }
+        if let Some(max) = git::config_i64(repo_path, "gv.maxCommits") {
+            config.max_commits = Some(max.max(0) as usize);
+        }
+        if let Some(lines) = git::config_i64(repo_path, "gv.fullFileLines") {
+            config.full_file_lines = Some(lines.max(0) as usize);
+        }
+        let collapse = git::config_strings(repo_path, "gv.collapse");
+        if !collapse.is_empty() {
+            config.collapse = collapse;
+        }
+        let hidden = git::config_strings(repo_path, "gv.hidden");
+        if !hidden.is_empty() {
+            config.hidden = hidden;
+        }
+        let generated = git::config_strings(repo_path, "gv.generated");
+        if !generated.is_empty() {
+            config.generated = generated;
+        }
+
+        config.apply_env(|name| std::env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Override settings from `GV_*` variables, looked up with `var`
+    ///
+    /// Each is named after its `.gv.toml` key, e.g. `GV_BASE` or
+    /// `GV_SKIP_REVIEWED`; `GV_COLLAPSE`, `GV_HIDDEN_PATTERNS` and
+    /// `GV_GENERATED` separate their globs and patterns with commas.
+    /// Empty variables count as unset.
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let bool_var = |name: &str| {
+            var(name)
+                .map(|value| parse_bool(&value).with_context(|| format!("Invalid {}", name)))
+                .transpose()
+        };
+        let int_var = |name: &str| {
+            var(name)
+                .map(|value| value.trim().parse::<u64>().with_context(|| format!("Invalid {}: expected a number", name)))
+                .transpose()
+        };
+
+        if let Some(base) = var("GV_BASE") {
+            self.base = Some(base);
+        }
+        if let Some(mouse) = bool_var("GV_MOUSE")? {
+            self.mouse = Some(mouse);
+        }
+        if let Some(theme) = var("GV_THEME") {
+            self.theme = Some(theme);
+        }
+        if let Some(max) = int_var("GV_HEXDUMP_MAX_BYTES")? {
+            self.hexdump_max_bytes = Some(max);
+        }
+        if let Some(skip) = bool_var("GV_SKIP_REVIEWED")? {
+            self.skip_reviewed = Some(skip);
+        }
+        if let Some(summary) = bool_var("GV_SUMMARY")? {
+            self.summary = Some(summary);
+        }
+        if let Some(difftool) = var("GV_DIFFTOOL") {
+            self.difftool = Some(difftool);
+        }
+        if let Some(sidebar) = var("GV_SIDEBAR") {
+            self.sidebar = Some(sidebar);
+        }
+        if let Some(ignore) = bool_var("GV_IGNORE_SUBMODULES")? {
+            self.ignore_submodules = Some(ignore);
+        }
+        if let Some(merge_base) = bool_var("GV_MERGE_BASE")? {
+            self.merge_base = Some(merge_base);
+        }
+        if let Some(context) = int_var("GV_CONTEXT")? {
+            self.context = Some(context.min(u32::MAX as u64) as u32);
+        }
+        if let Some(max) = int_var("GV_MAX_COMMITS")? {
+            self.max_commits = Some(max as usize);
+        }
+        if let Some(lines) = int_var("GV_FULL_FILE_LINES")? {
+            self.full_file_lines = Some(lines as usize);
+        }
+        let list = |value: String| -> Vec<String> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        if let Some(collapse) = var("GV_COLLAPSE") {
+            self.collapse = list(collapse);
+        }
+        if let Some(hidden) = var("GV_HIDDEN_PATTERNS") {
+            self.hidden = list(hidden);
+        }
+        if let Some(generated) = var("GV_GENERATED") {
+            self.generated = list(generated);
+        }
+        Ok(())
+    }
+
+    /// Read a `.gv.toml` file, if it exists
+    fn load_file(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text)
+                .with_context(|| format!("Invalid config file {}", path.display())),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Parse a TOML document
+    fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+}
+
+/// Parse a boolean environment value like `1`, `true`, `no` or `off`
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        other => bail!("expected true or false, got '{}'", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let text = "# team defaults\nbase = \"origin/develop\"  # release work\nmouse = false\nhexdump_max_bytes = 8_192\nskip_reviewed = true\nmerge_base = false\nmax_commits = 500\nfull_file_lines = 80\ncollapse = [\n  \"vendor/**\",  # third party\n  '**/snapshots/**',\n]\ngenerated = ['@generated', 'DO NOT EDIT']\nother = 'x'\n\n[extras]\nbase = \"ignored\"\n";
+        let config = RepoConfig::parse(text).unwrap();
+        assert_eq!(config.base.as_deref(), Some("origin/develop"));
+        assert_eq!(config.mouse, Some(false));
+        assert_eq!(config.hexdump_max_bytes, Some(8192));
+        assert_eq!(config.skip_reviewed, Some(true));
+        assert_eq!(config.merge_base, Some(false));
+        assert_eq!(config.max_commits, Some(500));
+        assert_eq!(config.full_file_lines, Some(80));
+        assert_eq!(config.collapse, vec!["vendor/**", "**/snapshots/**"]);
+        assert_eq!(config.generated, vec!["@generated", "DO NOT EDIT"]);
+
+        assert!(RepoConfig::parse("base = origin/develop").is_err());
+        assert!(RepoConfig::parse("mouse = \"no\"").is_err());
+        assert!(RepoConfig::parse("[theme]\nname = \"dark\"").is_err());
+        assert!(RepoConfig::parse("context = -1").is_err());
+
+        // Basic strings take escapes; literal strings keep backslashes as written
+        let config = RepoConfig::parse("base = \"a\\\"b\"\ngenerated = [\"\\\\bgenerated\", '\\bauto']").unwrap();
+        assert_eq!(config.base.as_deref(), Some("a\"b"));
+        assert_eq!(config.generated, vec!["\\bgenerated", "\\bauto"]);
+    }
+
+    #[test]
+    fn test_apply_env() {
+        let mut config = RepoConfig::parse("base = \"main\"\nmouse = true\ncontext = 5\n").unwrap();
+        let env = |name: &str| match name {
+            "GV_BASE" => Some("origin/develop".to_string()),
+            "GV_MOUSE" => Some("off".to_string()),
+            "GV_THEME" => Some(String::new()),
+            "GV_COLLAPSE" => Some("vendor/**, *.lock".to_string()),
+            "GV_HIDDEN_PATTERNS" => Some("*.lock,, dist/**".to_string()),
+            _ => None,
+        };
+        config.apply_env(env).unwrap();
+        assert_eq!(config.base.as_deref(), Some("origin/develop"));
+        assert_eq!(config.mouse, Some(false));
+        assert_eq!(config.theme, None);
+        assert_eq!(config.context, Some(5));
+        assert_eq!(config.collapse, vec!["vendor/**", "*.lock"]);
+        assert_eq!(config.hidden, vec!["*.lock", "dist/**"]);
+
+        let bad = |name: &str| (name == "GV_CONTEXT").then(|| "lots".to_string());
+        assert!(RepoConfig::default().apply_env(bad).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! External diff tools
+//!
+//! Hands one file to a GUI or terminal diff tool: the configured `diff.tool`
+//! or `merge.tool` from git config, or an explicit shell command. Both sides
+//! are written to temporary files and passed the way `git difftool` does, as
+//! `$LOCAL` (old) and `$REMOTE` (new) in the environment.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+use tempfile::NamedTempFile;
+
+use crate::git;
+
+/// Shell command to run for a diff, with `$LOCAL` and `$REMOTE` for the two sides
+///
+/// An explicit `template` wins; otherwise `diff.tool` then `merge.tool` are
+/// looked up, using their `difftool.<tool>.cmd` / `mergetool.<tool>.cmd`
+/// when set and `<tool> "$LOCAL" "$REMOTE"` when not.
+pub fn resolve_command(repo_path: &git::RepoPath, template: Option<&str>) -> Result<String> {
+    if let Some(template) = template {
+        return Ok(template.to_string());
+    }
+    for (tool_key, section) in [("diff.tool", "difftool"), ("merge.tool", "mergetool")] {
+        if let Some(tool) = git::config_string(repo_path, tool_key) {
+            let command = git::config_string(repo_path, &format!("{}.{}.cmd", section, tool))
+                .unwrap_or_else(|| format!("{} \"$LOCAL\" \"$REMOTE\"", tool));
+            return Ok(command);
+        }
+    }
+    bail!("No diff tool configured (set diff.tool, merge.tool or gv.difftool)")
+}
+
+/// Write both sides of `path` to temporary files and run `command` on them
+///
+/// A side that doesn't exist, like the old side of an added file, is passed
+/// as `/dev/null`. Blocks until the tool exits; the temporary files are
+/// removed afterwards.
+pub fn launch(command: &str, path: &str, old: Option<&[String]>, new: Option<&[String]>) -> Result<()> {
+    let local = old.map(|lines| temp_file("old", path, lines)).transpose()?;
+    let remote = new.map(|lines| temp_file("new", path, lines)).transpose()?;
+    let local = local.as_ref().map_or(Path::new("/dev/null"), NamedTempFile::path);
+    let remote = remote.as_ref().map_or(Path::new("/dev/null"), NamedTempFile::path);
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("LOCAL", local)
+        .env("REMOTE", remote)
+        // Merge tool commands expect these too
+        .env("BASE", local)
+        .env("MERGED", remote)
+        .status()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+    if !status.success() {
+        bail!("`{}` exited with {}", command, status);
+    }
+    Ok(())
+}
+
+/// Write `lines` to a new temporary file, keeping the file name so tools can highlight it
+///
+/// The name gets a random part and the file is created exclusively, so
+/// nothing already in the temp directory is written through. It's removed
+/// when dropped.
+fn temp_file(side: &str, path: &str, lines: &[String]) -> Result<NamedTempFile> {
+    let name = Path::new(path)
+        .file_name()
+        .map_or_else(|| "file".into(), |n| n.to_string_lossy());
+    let mut temp = tempfile::Builder::new()
+        .prefix(&format!("gv-{}-", side))
+        .suffix(&format!("-{}", name))
+        .tempfile()
+        .context("Failed to create a temporary file")?;
+
+    let mut text = lines.join("\n");
+    if !lines.is_empty() {
+        text.push('\n');
+    }
+    temp.write_all(text.as_bytes())
+        .and_then(|()| temp.flush())
+        .with_context(|| format!("Failed to write {}", temp.path().display()))?;
+    Ok(temp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_sides() {
+        let out = std::env::temp_dir().join(format!("gv-difftool-{}", std::process::id()));
+        let command = format!("echo \"$LOCAL\" > {0}; echo \"$REMOTE\" >> {0}; cat \"$REMOTE\" >> {0}", out.display());
+        launch(&command, "src/lib.rs", None, Some(&["fn main() {}".to_string()])).unwrap();
+
+        // A missing side is /dev/null; the other keeps the file name and is removed afterwards
+        let text = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "/dev/null");
+        assert!(lines[1].ends_with("-lib.rs"), "{}", lines[1]);
+        assert!(!Path::new(lines[1]).exists());
+        assert_eq!(lines[2], "fn main() {}");
+        let _ = std::fs::remove_file(&out);
+    }
+}
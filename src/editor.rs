@@ -0,0 +1,38 @@
+//! Opening files in the user's editor
+//!
+//! Picks the editor the way git does for commit messages (`GIT_EDITOR`,
+//! `core.editor`, `VISUAL`, `EDITOR`, then `vi`) and opens a file at a line
+//! with the `+LINE` argument vi, vim, nano, emacs, helix and most others take.
+
+use std::path::Path;
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+
+use crate::git;
+
+/// Editor command to run, which may carry its own arguments
+pub fn resolve_editor(repo_path: &git::RepoPath) -> String {
+    let env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    env("GIT_EDITOR")
+        .or_else(|| git::config_string(repo_path, "core.editor"))
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Open `path` at `line` in `editor`, blocking until it exits
+pub fn launch(editor: &str, path: &Path, line: u32) -> Result<()> {
+    // Through the shell like git, so `code --wait` and friends work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(editor)
+        .arg(format!("+{}", line))
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run `{}`", editor))?;
+    if !status.success() {
+        bail!("`{}` exited with {}", editor, status);
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! Binary file metadata
+//!
+//! Binary files have no lines to diff, so the viewer shows their sizes,
+//! blob ids and detected type instead, and a downscaled preview of images.
+
+use git2::{ObjectType, Oid};
+
+/// Largest blob whose bytes are kept for hexdump display
+pub const HEXDUMP_LIMIT: u64 = 64 * 1024;
+
+/// Largest image decoded for a preview
+const PREVIEW_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Widest an image preview gets, in pixels (one per terminal column)
+const PREVIEW_WIDTH: u32 = 48;
+
+/// Tallest an image preview gets, in pixels (two per terminal row)
+const PREVIEW_HEIGHT: u32 = 32;
+
+/// Color transparent pixels are blended onto
+const PREVIEW_BACKGROUND: [u8; 3] = [48, 48, 48];
+
+/// An image scaled down to preview in the terminal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    /// Width of the full image, in pixels
+    pub width: u32,
+    /// Height of the full image, in pixels
+    pub height: u32,
+    /// Width of the preview, in pixels
+    pub columns: u32,
+    /// The preview's pixels, row by row
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Thumbnail {
+    /// Decode a PNG, JPEG, GIF or BMP and scale it to fit the preview size
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(bytes).ok()?;
+        let preview = image.thumbnail(PREVIEW_WIDTH, PREVIEW_HEIGHT).to_rgba8();
+        let blend = |pixel: &image::Rgba<u8>| {
+            let [r, g, b, a] = pixel.0;
+            let mix = |c: u8, bg: u8| ((c as u32 * a as u32 + bg as u32 * (255 - a as u32)) / 255) as u8;
+            [mix(r, PREVIEW_BACKGROUND[0]), mix(g, PREVIEW_BACKGROUND[1]), mix(b, PREVIEW_BACKGROUND[2])]
+        };
+        Some(Self {
+            width: image.width(),
+            height: image.height(),
+            columns: preview.width(),
+            pixels: preview.pixels().map(blend).collect(),
+        })
+    }
+
+    /// Height of the preview, in pixels
+    pub fn rows(&self) -> u32 {
+        (self.pixels.len() as u32).checked_div(self.columns).unwrap_or(0)
+    }
+
+    /// The preview's pixel at column `x` of row `y`
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        if x >= self.columns {
+            return None;
+        }
+        self.pixels.get((y * self.columns + x) as usize).copied()
+    }
+}
+
+/// One side of a binary file change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinarySide {
+    /// Size in bytes
+    pub size: u64,
+    /// Abbreviated blob id
+    pub id: String,
+    /// Contents, kept for blobs up to [`HEXDUMP_LIMIT`]
+    pub bytes: Option<Vec<u8>>,
+    /// Downscaled image, for blobs that decode as one
+    pub preview: Option<Thumbnail>,
+}
+
+impl BinarySide {
+    /// Describe a blob's contents
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let id = Oid::hash_object(ObjectType::Blob, bytes)
+            .map(|oid| oid.to_string()[..7].to_string())
+            .unwrap_or_default();
+        let size = bytes.len() as u64;
+        Self {
+            size,
+            id,
+            bytes: (size <= HEXDUMP_LIMIT).then(|| bytes.to_vec()),
+            preview: (size <= PREVIEW_LIMIT).then(|| Thumbnail::decode(bytes)).flatten(),
+        }
+    }
+}
+
+/// Metadata for a changed binary file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryInfo {
+    /// Old blob (absent for added files)
+    pub old: Option<BinarySide>,
+    /// New blob (absent for deleted files)
+    pub new: Option<BinarySide>,
+    /// Detected MIME type
+    pub mime: &'static str,
+    /// Whether the file is shown as a hexdump
+    pub hexdump: bool,
+}
+
+impl BinaryInfo {
+    /// Size of the larger side, in bytes
+    pub fn max_size(&self) -> u64 {
+        let size = |side: &Option<BinarySide>| side.as_ref().map_or(0, |s| s.size);
+        size(&self.old).max(size(&self.new))
+    }
+
+    /// Size change from old to new, in bytes
+    fn size_delta(&self) -> i64 {
+        let size = |side: &Option<BinarySide>| side.as_ref().map_or(0, |s| s.size as i64);
+        size(&self.new) - size(&self.old)
+    }
+
+    /// One-line summary, e.g. `image/png · 1.2 KiB → 3.4 KiB (+2.2 KiB) · 1a2b3c4 → 5d6e7f8`
+    pub fn summary(&self) -> String {
+        let size = |side: &Option<BinarySide>| side.as_ref().map_or("∅".to_string(), |s| format_size(s.size));
+        let id = |side: &Option<BinarySide>| side.as_ref().map_or("∅".to_string(), |s| s.id.clone());
+
+        let delta = self.size_delta();
+        let sign = if delta < 0 { "-" } else { "+" };
+        format!(
+            "{} · {} → {} ({}{}) · {} → {}",
+            self.mime,
+            size(&self.old),
+            size(&self.new),
+            sign,
+            format_size(delta.unsigned_abs()),
+            id(&self.old),
+            id(&self.new),
+        )
+    }
+}
+
+/// Format a byte count with binary units
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Detect a MIME type from a file's leading bytes, falling back to its extension
+pub fn detect_mime(path: &str, bytes: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"\x00\x00\x01\x00", "image/x-icon"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"MZ", "application/x-msdownload"),
+        (b"\x00asm", "application/wasm"),
+        (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"ID3", "audio/mpeg"),
+    ];
+
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") {
+        match &bytes[8..12] {
+            b"WEBP" => return "image/webp",
+            b"WAVE" => return "audio/wav",
+            _ => {}
+        }
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        return "video/mp4";
+    }
+
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("jar") => "application/java-archive",
+        Some("class") => "application/java-vm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_summary() {
+        let info = BinaryInfo {
+            old: Some(BinarySide { size: 2048, id: "1a2b3c4".to_string(), bytes: None, preview: None }),
+            new: Some(BinarySide { size: 1000, id: "5d6e7f8".to_string(), bytes: None, preview: None }),
+            mime: detect_mime("logo.png", b"\x89PNG\r\n\x1a\n...."),
+            hexdump: false,
+        };
+        assert_eq!(info.size_delta(), -1048);
+        assert_eq!(info.summary(), "image/png · 2.0 KiB → 1000 B (-1.0 KiB) · 1a2b3c4 → 5d6e7f8");
+
+        assert_eq!(detect_mime("font.ttf", b"\x00\x01\x00\x00"), "font/ttf");
+        assert_eq!(detect_mime("blob", b"\x00\x01"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        // A 200x100 PNG, half red and half see-through
+        let image = image::RgbaImage::from_fn(200, 100, |x, _| {
+            if x < 100 { image::Rgba([255, 0, 0, 255]) } else { image::Rgba([0, 0, 255, 0]) }
+        });
+        let mut png = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let side = BinarySide::from_bytes(&png);
+        let thumbnail = side.preview.unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (200, 100));
+        assert_eq!((thumbnail.columns, thumbnail.rows()), (48, 24));
+        assert_eq!(thumbnail.pixel(0, 0), Some([255, 0, 0]));
+        assert_eq!(thumbnail.pixel(47, 23), Some(PREVIEW_BACKGROUND));
+        assert_eq!(thumbnail.pixel(48, 0), None);
+        assert!(BinarySide::from_bytes(b"\x00\x01").preview.is_none());
+    }
+}
@@ -0,0 +1,101 @@
+//! Line blame
+//!
+//! Attributes each line of a file, as the diff shows it, to the commit that
+//! last changed it. Edits not committed yet are blamed on no commit, the
+//! way `git blame` marks them "Not Committed Yet".
+
+use std::path::Path;
+use anyhow::Result;
+use git2::{Blame, BlameOptions};
+use super::repo::{RepoPath, open_repo};
+
+/// Who last changed a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Abbreviated hash of the commit, None for an uncommitted line
+    pub hash: Option<String>,
+    /// Name of the commit's author
+    pub author: String,
+}
+
+impl BlameLine {
+    fn uncommitted() -> Self {
+        Self { hash: None, author: "Not committed".to_string() }
+    }
+}
+
+/// Blame each line of `path` as of `head`, or of `content` when given
+///
+/// `content` is the new side of the diff, e.g. the file with uncommitted
+/// edits, blamed on top of `head`'s history. A file `head` doesn't have is
+/// all uncommitted.
+pub fn blame_lines(repo_path: &RepoPath, head: &str, path: &str, content: Option<&[String]>) -> Result<Vec<BlameLine>> {
+    let repo = open_repo(repo_path)?;
+    let mut opts = BlameOptions::new();
+    if let Ok(commit) = repo.revparse_single(head).and_then(|object| object.peel_to_commit()) {
+        opts.newest_commit(commit.id());
+    }
+    let blame = match (repo.blame_file(Path::new(path), Some(&mut opts)), content) {
+        (Ok(blame), _) => blame,
+        (Err(_), Some(lines)) => return Ok(vec![BlameLine::uncommitted(); lines.len()]),
+        (Err(e), None) => return Err(e.into()),
+    };
+
+    match content {
+        Some(lines) => {
+            let mut text = lines.join("\n");
+            text.push('\n');
+            let buffer = blame.blame_buffer(text.as_bytes())?;
+            Ok(collect_lines(&buffer, lines.len()))
+        }
+        None => {
+            let count = blame.iter().map(|hunk| hunk.lines_in_hunk()).sum();
+            Ok(collect_lines(&blame, count))
+        }
+    }
+}
+
+/// One entry per line, 1 to `count`
+fn collect_lines(blame: &Blame<'_>, count: usize) -> Vec<BlameLine> {
+    (1..=count)
+        .map(|line| match blame.get_line(line) {
+            // Lines from the buffer have no commit, nor a signature to read
+            Some(hunk) if !hunk.final_commit_id().is_zero() => {
+                let id = hunk.final_commit_id().to_string();
+                BlameLine {
+                    hash: Some(id[..7].to_string()),
+                    author: hunk.final_signature().name().unwrap_or_default().to_string(),
+                }
+            }
+            _ => BlameLine::uncommitted(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Repository, Signature};
+
+    #[test]
+    fn test_blame_lines() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-blame-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let sig = Signature::now("Ada", "ada@example.com").unwrap();
+        std::fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let commit = repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[]).unwrap();
+
+        let hash = Some(commit.to_string()[..7].to_string());
+        let ada = BlameLine { hash, author: "Ada".to_string() };
+        assert_eq!(blame_lines(&dir, "HEAD", "a.txt", None).unwrap(), vec![ada.clone(), ada.clone()]);
+
+        let edited = ["one".to_string(), "TWO".to_string()];
+        assert_eq!(blame_lines(&dir, "HEAD", "a.txt", Some(&edited)).unwrap(), vec![ada, BlameLine::uncommitted()]);
+        assert_eq!(blame_lines(&dir, "HEAD", "new.txt", Some(&edited)).unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,160 @@
+//! Diff result caching
+//!
+//! Keeps the most recently computed diffs so flipping between commit
+//! selections doesn't re-run the whole git diff every time.
+
+use std::path::PathBuf;
+use super::repo::{RepoPath, open_repo};
+
+use super::{DiffAlgorithm, FileDiff};
+
+/// Number of diff results kept around
+const DEFAULT_CAPACITY: usize = 8;
+
+/// Everything a computed diff depends on
+///
+/// Base and HEAD are resolved to object ids so a fetch or a new commit
+/// never serves a stale result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffKey {
+    repo_path: PathBuf,
+    base_oid: Option<String>,
+    head_oid: Option<String>,
+    selected: Vec<String>,
+    include_uncommitted: bool,
+    context_lines: u32,
+    ignore_submodules: bool,
+    algorithm: DiffAlgorithm,
+}
+
+impl DiffKey {
+    /// Build a key for a diff of `head` in `repo_path` against `base_branch`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        repo_path: &RepoPath,
+        base_branch: &str,
+        head: &str,
+        include_uncommitted: bool,
+        selected_commits: &[String],
+        context_lines: u32,
+        ignore_submodules: bool,
+        algorithm: DiffAlgorithm,
+    ) -> Self {
+        let repo = open_repo(repo_path).ok();
+        let resolve = |spec: &str| {
+            repo.as_ref()
+                .and_then(|r| r.revparse_single(spec).ok())
+                .map(|obj| obj.id().to_string())
+        };
+
+        let mut selected = selected_commits.to_vec();
+        selected.sort();
+
+        Self {
+            repo_path: repo_path.to_path_buf(),
+            base_oid: resolve(base_branch),
+            head_oid: resolve(head),
+            selected,
+            include_uncommitted,
+            context_lines,
+            ignore_submodules,
+            algorithm,
+        }
+    }
+
+    /// Whether results for this key may be cached
+    ///
+    /// Diffs against the working directory change without any ref moving.
+    fn is_cacheable(&self) -> bool {
+        !self.include_uncommitted && self.base_oid.is_some() && self.head_oid.is_some()
+    }
+}
+
+/// Least-recently-used cache of computed diffs
+#[derive(Debug)]
+pub struct DiffCache {
+    capacity: usize,
+    /// Entries ordered from least to most recently used
+    entries: Vec<(DiffKey, Vec<FileDiff>)>,
+}
+
+impl Default for DiffCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl DiffCache {
+    /// Create a cache holding at most `capacity` results
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Look up a cached diff, marking it as recently used
+    pub fn get(&mut self, key: &DiffKey) -> Option<Vec<FileDiff>> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index);
+        let diffs = entry.1.clone();
+        self.entries.push(entry);
+        Some(diffs)
+    }
+
+    /// Whether a diff for `key` is cached
+    pub fn contains(&self, key: &DiffKey) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Store a computed diff, evicting the least recently used entry if full
+    pub fn insert(&mut self, key: DiffKey, diffs: &[FileDiff]) {
+        if !key.is_cacheable() || self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() >= self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, diffs.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(selected: &str) -> DiffKey {
+        DiffKey {
+            repo_path: PathBuf::from("/repo"),
+            base_oid: Some("base".to_string()),
+            head_oid: Some("head".to_string()),
+            selected: vec![selected.to_string()],
+            include_uncommitted: false,
+            context_lines: 3,
+            ignore_submodules: false,
+            algorithm: DiffAlgorithm::Myers,
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = DiffCache::new(2);
+        cache.insert(key("a"), &[]);
+        cache.insert(key("b"), &[]);
+        assert!(cache.get(&key("a")).is_some());
+
+        cache.insert(key("c"), &[]);
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("a")).is_some());
+        assert!(cache.get(&key("c")).is_some());
+    }
+
+    #[test]
+    fn test_skips_uncommitted() {
+        let mut cache = DiffCache::default();
+        let uncommitted = DiffKey { include_uncommitted: true, ..key("a") };
+        cache.insert(uncommitted.clone(), &[]);
+        assert!(cache.get(&uncommitted).is_none());
+    }
+}
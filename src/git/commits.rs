@@ -1,12 +1,50 @@
 //! Git commit listing and filtering
 //!
-//! Lists commits between the base branch and HEAD,
-//! and detects uncommitted changes.
+//! Lists commits between the base branch and HEAD (or another revision),
+//! with any `refs/notes/commits` notes and signature status, and detects
+//! uncommitted changes.
 
-use std::collections::HashSet;
-use std::path::Path;
-use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::Command;
+use anyhow::Result;
 use git2::{Repository, Oid, StatusOptions};
+use super::repo::{RepoPath, open_repo};
+
+/// Outcome of verifying a commit's GPG or SSH signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignatureStatus {
+    /// No signature
+    #[default]
+    Unsigned,
+    /// Valid signature
+    Good,
+    /// Bad, expired or revoked signature
+    Bad,
+    /// Signed, but the key is unknown or untrusted, or gpg/ssh isn't available
+    Unknown,
+}
+
+impl SignatureStatus {
+    /// Badge shown next to the commit
+    pub fn badge(self) -> &'static str {
+        match self {
+            Self::Unsigned => " ",
+            Self::Good => "✓",
+            Self::Bad => "✗",
+            Self::Unknown => "?",
+        }
+    }
+
+    /// Map git's `%G?` placeholder
+    fn from_code(code: &str) -> Self {
+        match code {
+            "G" => Self::Good,
+            "B" | "X" | "Y" | "R" => Self::Bad,
+            "N" => Self::Unsigned,
+            _ => Self::Unknown,
+        }
+    }
+}
 
 /// Represents a git commit
 #[derive(Debug, Clone)]
@@ -17,86 +55,172 @@ pub struct Commit {
     pub full_hash: String,
     /// Commit subject (first line of message)
     pub subject: String,
+    /// Author's name, empty for the uncommitted changes
+    pub author: String,
+    /// Author's email
+    pub email: String,
+    /// Author date, in seconds since the epoch
+    pub time: i64,
     /// Whether this commit is selected for display
     pub selected: bool,
     /// Virtual entry for uncommitted changes
     pub is_uncommitted: bool,
+    /// Note attached under `refs/notes/commits`, e.g. review or CI metadata
+    pub note: Option<String>,
+    /// Signature verification result
+    pub signature: SignatureStatus,
+    /// Signer identity, when signed
+    pub signer: Option<String>,
 }
 
-/// List commits between base branch and HEAD
-///
-/// Returns commits that are reachable from HEAD but not from the base branch.
-/// Also includes a virtual "uncommitted" entry if there are working directory changes.
-pub fn list_commits(repo_path: &Path, base_branch: &str) -> Result<Vec<Commit>> {
-    let repo = Repository::discover(repo_path)
-        .context("Failed to discover git repository")?;
-
-    let mut commits = Vec::new();
+/// Commits sent per batch while a walk streams them in
+const COMMIT_BATCH: usize = 64;
 
-    // Add uncommitted changes entry if applicable
-    if has_uncommitted_changes(repo_path)? {
-        commits.push(Commit {
+impl Commit {
+    /// Virtual entry standing for the working directory's changes
+    pub fn uncommitted() -> Self {
+        Self {
             hash: "-------".to_string(),
             full_hash: String::new(),
             subject: "(uncommitted changes)".to_string(),
+            author: String::new(),
+            email: String::new(),
+            time: 0,
             selected: true,
             is_uncommitted: true,
-        });
+            note: None,
+            signature: SignatureStatus::Unsigned,
+            signer: None,
+        }
     }
+}
 
-    // Get the base branch commit
-    let base_oid = match repo.revparse_single(base_branch) {
-        Ok(obj) => obj.id(),
-        Err(_) => {
-            // Base branch doesn't exist, return just uncommitted
-            return Ok(commits);
-        }
-    };
+/// Walk the commits reachable from `head` but not from the base branch
+///
+/// Everything reachable from the base is hidden from the walk, so it only
+/// visits the branch's own commits however long the base's history is.
+/// Skips the first `skip` commits and hands the next `limit` to `send` in
+/// batches, newest first, stopping early once `send` returns false.
+/// Returns whether commits remain past the limit.
+pub fn walk_commits(
+    repo_path: &RepoPath,
+    base_branch: &str,
+    head: &str,
+    skip: usize,
+    limit: usize,
+    mut send: impl FnMut(Vec<Commit>) -> bool,
+) -> Result<bool> {
+    let repo = open_repo(repo_path)?;
 
-    // Get HEAD commit
-    let head_oid = match repo.head() {
-        Ok(head) => match head.target() {
-            Some(oid) => oid,
-            None => return Ok(commits),
-        },
-        Err(_) => return Ok(commits),
+    // A base or head that doesn't resolve has no commits to list
+    let Ok(base) = repo.revparse_single(base_branch).and_then(|obj| obj.peel_to_commit()) else {
+        return Ok(false);
+    };
+    let Ok(head) = repo.revparse_single(head).and_then(|obj| obj.peel_to_commit()) else {
+        return Ok(false);
     };
 
-    // Build set of commits reachable from base
-    let base_commits = build_commit_set(&repo, base_oid)?;
-
-    // Walk from HEAD and collect commits not in base
     let mut revwalk = repo.revwalk()?;
-    revwalk.push(head_oid)?;
+    revwalk.push(head.id())?;
+    revwalk.hide(base.id())?;
     revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+    let mut revwalk = revwalk.skip(skip);
 
-    for oid_result in revwalk {
-        let oid = oid_result?;
-
-        // Stop if we hit a commit that's in the base
-        if base_commits.contains(&oid) {
-            break;
+    let mut remaining = limit;
+    while remaining > 0 {
+        let mut batch = Vec::new();
+        for oid_result in revwalk.by_ref().take(remaining.min(COMMIT_BATCH)) {
+            let oid = oid_result?;
+            let commit = repo.find_commit(oid)?;
+            let hash = oid.to_string();
+            batch.push(Commit {
+                hash: hash[..7].to_string(),
+                full_hash: hash,
+                subject: commit.summary().unwrap_or("").to_string(),
+                author: commit.author().name().unwrap_or("").to_string(),
+                email: commit.author().email().unwrap_or("").to_string(),
+                time: commit.author().when().seconds(),
+                selected: true,
+                is_uncommitted: false,
+                note: commit_note(&repo, oid),
+                signature: SignatureStatus::Unsigned,
+                signer: None,
+            });
         }
+        if batch.is_empty() {
+            return Ok(false);
+        }
+        remaining -= batch.len();
+        verify_batch(&repo, &mut batch);
+        if !send(batch) {
+            return Ok(false);
+        }
+    }
+    Ok(revwalk.next().is_some())
+}
 
-        let commit = repo.find_commit(oid)?;
-        let hash = oid.to_string();
-
-        commits.push(Commit {
-            hash: hash[..7].to_string(),
-            full_hash: hash,
-            subject: commit.summary().unwrap_or("").to_string(),
-            selected: true,
-            is_uncommitted: false,
-        });
+/// Fill in signature status for the signed commits of a batch
+fn verify_batch(repo: &Repository, commits: &mut [Commit]) {
+    // Only signed commits need the (slow) trip through gpg or ssh
+    let signed: Vec<usize> = (0..commits.len())
+        .filter(|&i| Oid::from_str(&commits[i].full_hash).is_ok_and(|oid| repo.extract_signature(&oid, None).is_ok()))
+        .collect();
+    if signed.is_empty() {
+        return;
+    }
+    let hashes: Vec<&str> = signed.iter().map(|&i| commits[i].full_hash.as_str()).collect();
+    let mut verified = verify_signatures(repo, &hashes);
+    for i in signed {
+        let commit = &mut commits[i];
+        let (status, signer) = verified.remove(&commit.full_hash).unwrap_or((SignatureStatus::Unknown, None));
+        commit.signature = status;
+        commit.signer = signer;
     }
+}
+
+/// Full message of a commit
+pub fn commit_message(repo_path: &RepoPath, hash: &str) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+    let commit = repo.find_commit(Oid::from_str(hash)?)?;
+    Ok(commit.message().unwrap_or("").trim_end().to_string())
+}
 
-    Ok(commits)
+/// Note attached to a commit in the default notes ref
+fn commit_note(repo: &Repository, oid: Oid) -> Option<String> {
+    let note = repo.find_note(None, oid).ok()?;
+    let message = note.message()?.trim_end();
+    (!message.is_empty()).then(|| message.to_string())
+}
+
+/// Verify commit signatures with git, which runs gpg or ssh-keygen
+///
+/// Returns status and signer by full hash; empty if git can't be run.
+fn verify_signatures(repo: &Repository, hashes: &[&str]) -> HashMap<String, (SignatureStatus, Option<String>)> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(repo.path())
+        .args(["show", "--no-patch", "--no-walk=unsorted", "--format=%H%x1f%G?%x1f%GS"])
+        .args(hashes)
+        .output();
+    let Ok(output) = output else {
+        return HashMap::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            let hash = fields.next()?.to_string();
+            let status = SignatureStatus::from_code(fields.next()?);
+            let signer = fields.next().filter(|s| !s.is_empty()).map(str::to_string);
+            Some((hash, (status, signer)))
+        })
+        .collect()
 }
 
 /// Check if there are uncommitted changes in the working directory
-pub fn has_uncommitted_changes(repo_path: &Path) -> Result<bool> {
-    let repo = Repository::discover(repo_path)
-        .context("Failed to discover git repository")?;
+pub fn has_uncommitted_changes(repo_path: &RepoPath) -> Result<bool> {
+    let repo = open_repo(repo_path)?;
 
     let mut opts = StatusOptions::new();
     opts.include_untracked(true);
@@ -108,27 +232,6 @@ pub fn has_uncommitted_changes(repo_path: &Path) -> Result<bool> {
     Ok(!statuses.is_empty())
 }
 
-/// Build a set of all commits reachable from a given OID
-fn build_commit_set(repo: &Repository, start: Oid) -> Result<HashSet<Oid>> {
-    let mut set = HashSet::new();
-    let mut revwalk = repo.revwalk()?;
-    revwalk.push(start)?;
-
-    // Limit to prevent infinite traversal on large repos
-    const MAX_COMMITS: usize = 10000;
-
-    for (i, oid_result) in revwalk.enumerate() {
-        if i >= MAX_COMMITS {
-            break;
-        }
-        if let Ok(oid) = oid_result {
-            set.insert(oid);
-        }
-    }
-
-    Ok(set)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,11 +242,61 @@ mod tests {
             hash: "abc1234".to_string(),
             full_hash: "abc1234567890".to_string(),
             subject: "Test commit".to_string(),
+            author: "Jane".to_string(),
+            email: "jane@example.com".to_string(),
+            time: 1_700_000_000,
             selected: true,
             is_uncommitted: false,
+            note: None,
+            signature: SignatureStatus::Unsigned,
+            signer: None,
         };
 
         assert_eq!(commit.hash, "abc1234");
         assert!(!commit.is_uncommitted);
     }
+
+    #[test]
+    fn test_walk_commits() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-walk-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let mut parent = repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[]).unwrap();
+        repo.reference("refs/heads/base", parent, true, "").unwrap();
+        for i in 0..5 {
+            let commit = repo.find_commit(parent).unwrap();
+            parent = repo.commit(Some("HEAD"), &sig, &sig, &format!("change {}", i), &tree, &[&commit]).unwrap();
+        }
+
+        let mut batches = Vec::new();
+        let more = walk_commits(&dir, "base", "HEAD", 0, 2, |batch| {
+            batches.push(batch);
+            true
+        })
+        .unwrap();
+        assert!(more);
+        let subjects: Vec<&str> = batches.iter().flatten().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["change 4", "change 3"]);
+
+        let mut rest = Vec::new();
+        let more = walk_commits(&dir, "base", "HEAD", 4, 10, |batch| {
+            rest.extend(batch);
+            true
+        })
+        .unwrap();
+        assert!(!more);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].subject, "change 0");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_signature_codes() {
+        assert_eq!(SignatureStatus::from_code("G"), SignatureStatus::Good);
+        assert_eq!(SignatureStatus::from_code("R"), SignatureStatus::Bad);
+        assert_eq!(SignatureStatus::from_code("E"), SignatureStatus::Unknown);
+        assert_eq!(SignatureStatus::from_code("N"), SignatureStatus::Unsigned);
+    }
 }
@@ -0,0 +1,296 @@
+//! Comparing arbitrary files
+//!
+//! Diffs two files that needn't be tracked, a file against any revision
+//! of it, or two directory trees (as `git difftool --dir-diff` hands over,
+//! or as gathered from git's per-file external diff calls), producing the
+//! same structures as a branch diff so the usual views can show them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use super::diff::{diff_buffers, ChangeKind, FileDiff};
+use super::repo::{RepoPath, open_repo};
+
+/// One side of a comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompareSide {
+    /// A file on disk, named as it was given
+    File { path: PathBuf, name: String },
+    /// A file as of a revision
+    Blob { rev: String, path: String },
+    /// A directory, compared file by file with another
+    Dir { path: PathBuf, name: String },
+}
+
+impl CompareSide {
+    /// Name shown for this side, e.g. `src/main.rs` or `v1.0:src/main.rs`
+    pub fn label(&self) -> String {
+        match self {
+            Self::File { name, .. } | Self::Dir { name, .. } => name.clone(),
+            Self::Blob { rev, path } => format!("{}:{}", rev, path),
+        }
+    }
+
+    /// File name without its directory, used as the diff's path
+    fn file_name(&self) -> String {
+        let path = match self {
+            Self::File { name, .. } | Self::Dir { name, .. } => name,
+            Self::Blob { path, .. } => path,
+        };
+        path.trim_end_matches('/').rsplit('/').next().unwrap_or(path).to_string()
+    }
+
+    /// Read this side's contents
+    fn read(&self, repo_path: &RepoPath) -> Result<Vec<u8>> {
+        match self {
+            Self::File { path, .. } => {
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+            }
+            Self::Dir { path, .. } => bail!("{} is a directory", path.display()),
+            Self::Blob { rev, path } => {
+                let repo = open_repo(repo_path)?;
+                let spec = format!("{}:{}", rev, path);
+                let blob = repo.revparse_single(&spec)
+                    .and_then(|object| object.peel_to_blob())
+                    .with_context(|| format!("'{}' doesn't exist", spec))?;
+                Ok(blob.content().to_vec())
+            }
+        }
+    }
+}
+
+/// Work out which file a typed spec refers to
+///
+/// `spec` is a path (relative to `dir` unless absolute), `REV:PATH`, or a
+/// bare revision meaning `path` as of that revision.
+pub fn resolve_side(
+    repo_path: &RepoPath,
+    dir: &Path,
+    spec: &str,
+    path: Option<&str>,
+) -> Result<CompareSide> {
+    let file = dir.join(spec);
+    if file.is_file() {
+        return Ok(CompareSide::File { path: file, name: spec.to_string() });
+    }
+    if file.is_dir() {
+        return Ok(CompareSide::Dir { path: file, name: spec.to_string() });
+    }
+
+    let Ok(repo) = open_repo(repo_path) else {
+        bail!("No file '{}'", spec);
+    };
+    if let Some((rev, blob_path)) = spec.split_once(':')
+        && repo.revparse_single(spec).and_then(|o| o.peel_to_blob()).is_ok()
+    {
+        return Ok(CompareSide::Blob { rev: rev.to_string(), path: blob_path.to_string() });
+    }
+    if let Some(path) = path
+        && repo.revparse_single(spec).is_ok()
+    {
+        return Ok(CompareSide::Blob { rev: spec.to_string(), path: path.to_string() });
+    }
+    bail!("'{}' is neither a file nor a revision", spec)
+}
+
+/// Diff `old` against `new`, with one file (or none when identical)
+///
+/// The file is named after the sides' file names, which keeps the file
+/// tree flat for absolute or `../` paths; the full labels go in the header.
+pub fn compare_files(
+    repo_path: &RepoPath,
+    old: &CompareSide,
+    new: &CompareSide,
+    context_lines: u32,
+) -> Result<Vec<FileDiff>> {
+    match (old, new) {
+        (CompareSide::Dir { path: old_dir, .. }, CompareSide::Dir { path: new_dir, .. }) => {
+            return compare_dirs(old_dir, new_dir, context_lines);
+        }
+        (CompareSide::Dir { .. }, _) | (_, CompareSide::Dir { .. }) => {
+            bail!("A directory can only be compared with another directory");
+        }
+        _ => {}
+    }
+    let old_contents = old.read(repo_path)?;
+    let new_contents = new.read(repo_path)?;
+    let (old_name, new_name) = (old.file_name(), new.file_name());
+    diff_buffers(&old_name, &old_contents, &new_name, &new_contents, context_lines)
+}
+
+/// Gather one of git's per-file `GIT_EXTERNAL_DIFF` calls into one comparison
+///
+/// Git runs the external diff once per changed file, numbering the calls
+/// with `GIT_DIFF_PATH_COUNTER` out of `GIT_DIFF_PATH_TOTAL`. Each call
+/// copies its two sides (git deletes its temporary files as the call
+/// returns) under a spool directory, and the last one gets back the two
+/// trees to compare; earlier calls get `None`.
+pub fn spool_external(
+    repo_path: &RepoPath,
+    path: &str,
+    old_file: &Path,
+    new_file: &Path,
+) -> Result<Option<(CompareSide, CompareSide)>> {
+    let count = |name: &str| std::env::var(name).ok().and_then(|value| value.parse::<usize>().ok());
+    let counts = (count("GIT_DIFF_PATH_COUNTER"), count("GIT_DIFF_PATH_TOTAL"));
+    let (counter, total) = match counts {
+        (Some(counter), Some(total)) => (counter, total),
+        _ => (1, 1),
+    };
+    let spool = external_spool_dir(repo_path);
+    if counter <= 1 {
+        let _ = fs::remove_dir_all(&spool);
+    }
+    spool_file(&spool, path, old_file, new_file)?;
+    Ok((counter >= total).then(|| spool_sides(&spool)))
+}
+
+/// Directory the external diff calls share
+///
+/// It's inside the git directory, since git runs each call through a fresh
+/// shell, or under the temporary directory for `git diff --no-index`.
+pub fn external_spool_dir(repo_path: &RepoPath) -> PathBuf {
+    match open_repo(repo_path) {
+        Ok(repo) => repo.path().join("gv-external"),
+        Err(_) => std::env::temp_dir().join("gv-external"),
+    }
+}
+
+/// Copy one file's sides to `old/PATH` and `new/PATH`
+///
+/// A `/dev/null` side, of an added or deleted file, is left out.
+fn spool_file(spool: &Path, path: &str, old_file: &Path, new_file: &Path) -> Result<()> {
+    for (side, file) in [("old", old_file), ("new", new_file)] {
+        let dir = spool.join(side);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        if file == Path::new("/dev/null") {
+            continue;
+        }
+        let target = dir.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::copy(file, &target).with_context(|| format!("Failed to copy {}", file.display()))?;
+    }
+    Ok(())
+}
+
+/// The two spooled trees, compared like `--dir-diff` directories
+fn spool_sides(spool: &Path) -> (CompareSide, CompareSide) {
+    let side = |name: &str| CompareSide::Dir { path: spool.join(name), name: name.to_string() };
+    (side("old"), side("new"))
+}
+
+/// Diff every file under two directories, by their path relative to each
+///
+/// Files on one side only show as added or deleted. Symlinks are followed,
+/// since `git difftool --dir-diff` links the working tree's files in.
+fn compare_dirs(old_dir: &Path, new_dir: &Path, context_lines: u32) -> Result<Vec<FileDiff>> {
+    let mut paths = Vec::new();
+    list_files(old_dir, "", &mut paths)?;
+    list_files(new_dir, "", &mut paths)?;
+    paths.sort();
+    paths.dedup();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let read = |dir: &Path| {
+            let file = dir.join(&path);
+            match file.is_file() {
+                true => fs::read(&file)
+                    .map(Some)
+                    .with_context(|| format!("Failed to read {}", file.display())),
+                false => Ok(None),
+            }
+        };
+        let (old, new) = (read(old_dir)?, read(new_dir)?);
+        let change = match (&old, &new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+        let old = old.unwrap_or_default();
+        let new = new.unwrap_or_default();
+        for mut diff in diff_buffers(&path, &old, &path, &new, context_lines)? {
+            diff.change = change;
+            files.push(diff);
+        }
+    }
+    Ok(files)
+}
+
+/// Collect the paths of files under `dir`, relative to the top, skipping `.git`
+fn list_files(dir: &Path, prefix: &str, paths: &mut Vec<String>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == ".git" {
+            continue;
+        }
+        let path = format!("{}{}", prefix, name);
+        let full = entry.path();
+        if full.is_dir() {
+            list_files(&full, &format!("{}/", path), paths)?;
+        } else if full.is_file() {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_files() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-compare-{}", std::process::id())));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        fs::write(dir.join("b.txt"), "one\n2\nthree\nfour\n").unwrap();
+
+        let old = resolve_side(&dir, &dir, "a.txt", None).unwrap();
+        let new = resolve_side(&dir, &dir, "b.txt", None).unwrap();
+        let diffs = compare_files(&dir, &old, &new, 3).unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!((diffs[0].added, diffs[0].removed), (2, 1));
+        assert_eq!(diffs[0].new_content.as_ref().map(Vec::len), Some(4));
+        assert!(compare_files(&dir, &old, &old, 3).unwrap().is_empty());
+        assert!(resolve_side(&dir, &dir, "missing.txt", None).is_err());
+
+        fs::create_dir_all(dir.join("left/src")).unwrap();
+        fs::create_dir_all(dir.join("right/src")).unwrap();
+        fs::write(dir.join("left/src/lib.rs"), "a\n").unwrap();
+        fs::write(dir.join("right/src/lib.rs"), "b\n").unwrap();
+        fs::write(dir.join("left/gone.txt"), "x\n").unwrap();
+        fs::write(dir.join("right/new.txt"), "y\n").unwrap();
+        let left = resolve_side(&dir, &dir, "left", None).unwrap();
+        let right = resolve_side(&dir, &dir, "right", None).unwrap();
+        let diffs = compare_files(&dir, &left, &right, 3).unwrap();
+        let changes: Vec<_> = diffs.iter().map(|d| (d.path.as_str(), d.change)).collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("gone.txt", ChangeKind::Deleted),
+                ("new.txt", ChangeKind::Added),
+                ("src/lib.rs", ChangeKind::Modified),
+            ]
+        );
+        assert!(compare_files(&dir, &left, &old, 3).is_err());
+
+        // External diff calls pile up into one tree per side
+        let spool = dir.join("spool");
+        let (left_lib, right_lib) = (dir.join("left/src/lib.rs"), dir.join("right/src/lib.rs"));
+        spool_file(&spool, "src/lib.rs", &left_lib, &right_lib).unwrap();
+        spool_file(&spool, "new.txt", Path::new("/dev/null"), &dir.join("right/new.txt")).unwrap();
+        let (old_tree, new_tree) = spool_sides(&spool);
+        let diffs = compare_files(&dir, &old_tree, &new_tree, 3).unwrap();
+        let changes: Vec<_> = diffs.iter().map(|d| (d.path.as_str(), d.change)).collect();
+        let expected = vec![("new.txt", ChangeKind::Added), ("src/lib.rs", ChangeKind::Modified)];
+        assert_eq!(changes, expected);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
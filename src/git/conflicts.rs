@@ -0,0 +1,229 @@
+//! Conflict markers
+//!
+//! Splits a file git left conflicted into clean text and conflicts, each
+//! with our side, their side and (with `merge.conflictStyle diff3` or
+//! `zdiff3`) the common base, and writes it back once conflicts are resolved.
+
+/// Length of a conflict marker such as `<<<<<<<`
+const MARKER_LEN: usize = 7;
+
+/// A stretch of a conflicted file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region {
+    /// Lines both sides agree on
+    Clean(Vec<String>),
+    /// Lines the sides changed differently
+    Conflict(Conflict),
+}
+
+/// One conflict, between `<<<<<<<` and `>>>>>>>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// Label after `<<<<<<<`, usually `HEAD`
+    pub ours_label: String,
+    /// Label after `>>>>>>>`, usually the merged branch or commit
+    pub theirs_label: String,
+    /// Label after `|||||||`, when the markers include the base
+    pub base_label: String,
+    pub ours: Vec<String>,
+    /// Common ancestor's lines, when the markers include them
+    pub base: Option<Vec<String>>,
+    pub theirs: Vec<String>,
+    /// How the conflict was settled, if it has been
+    pub resolution: Option<Resolution>,
+}
+
+/// Which side a resolved conflict keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Our lines followed by theirs
+    Both,
+}
+
+impl Resolution {
+    /// Short description for the status line
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ours => "ours",
+            Self::Theirs => "theirs",
+            Self::Both => "both",
+        }
+    }
+}
+
+impl Conflict {
+    /// Lines the conflict resolves to, or None while unresolved
+    pub fn resolved_lines(&self) -> Option<Vec<String>> {
+        Some(match self.resolution? {
+            Resolution::Ours => self.ours.clone(),
+            Resolution::Theirs => self.theirs.clone(),
+            Resolution::Both => self.ours.iter().chain(&self.theirs).cloned().collect(),
+        })
+    }
+
+    /// The conflict as git wrote it, markers included
+    fn marked_lines(&self) -> Vec<String> {
+        let marker = |c: char, label: &str| {
+            let marker = c.to_string().repeat(MARKER_LEN);
+            if label.is_empty() { marker } else { format!("{} {}", marker, label) }
+        };
+        let mut lines = vec![marker('<', &self.ours_label)];
+        lines.extend(self.ours.iter().cloned());
+        if let Some(base) = &self.base {
+            lines.push(marker('|', &self.base_label));
+            lines.extend(base.iter().cloned());
+        }
+        lines.push(marker('=', ""));
+        lines.extend(self.theirs.iter().cloned());
+        lines.push(marker('>', &self.theirs_label));
+        lines
+    }
+}
+
+/// The kind of conflict marker `line` is, with its label
+fn marker(line: &str) -> Option<(char, &str)> {
+    let line = line.trim_end_matches('\r');
+    let first = line.chars().next()?;
+    if !matches!(first, '<' | '|' | '=' | '>') || line.len() < MARKER_LEN {
+        return None;
+    }
+    let (marker, rest) = line.split_at(MARKER_LEN);
+    if marker.chars().any(|c| c != first) {
+        return None;
+    }
+    match rest.strip_prefix(' ') {
+        Some(label) if first != '=' => Some((first, label)),
+        None if rest.is_empty() => Some((first, "")),
+        _ => None,
+    }
+}
+
+/// Whether `line` is a conflict marker git would write, like `<<<<<<< HEAD`
+pub fn is_conflict_marker(line: &str) -> bool {
+    marker(line).is_some()
+}
+
+/// Split `text` into clean regions and conflicts
+///
+/// A conflict missing its closing marker is left as clean text.
+pub fn parse_conflicts(text: &str) -> Vec<Region> {
+    let mut regions = Vec::new();
+    let mut clean: Vec<String> = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let Some(('<', ours_label)) = marker(line) else {
+            clean.push(line.to_string());
+            continue;
+        };
+
+        // Gather the sides, remembering the raw lines in case the conflict never closes
+        let mut raw = vec![line.to_string()];
+        let mut conflict = Conflict {
+            ours_label: ours_label.to_string(),
+            theirs_label: String::new(),
+            base_label: String::new(),
+            ours: Vec::new(),
+            base: None,
+            theirs: Vec::new(),
+            resolution: None,
+        };
+        let mut section = '<';
+        let mut closed = false;
+        for line in lines.by_ref() {
+            raw.push(line.to_string());
+            match (section, marker(line)) {
+                ('<', Some(('|', label))) => {
+                    section = '|';
+                    conflict.base_label = label.to_string();
+                    conflict.base = Some(Vec::new());
+                }
+                ('<' | '|', Some(('=', _))) => section = '=',
+                ('=', Some(('>', label))) => {
+                    conflict.theirs_label = label.to_string();
+                    closed = true;
+                    break;
+                }
+                ('<', _) => conflict.ours.push(line.to_string()),
+                ('|', _) => conflict.base.get_or_insert_with(Vec::new).push(line.to_string()),
+                (_, _) => conflict.theirs.push(line.to_string()),
+            }
+        }
+
+        if closed {
+            if !clean.is_empty() {
+                regions.push(Region::Clean(std::mem::take(&mut clean)));
+            }
+            regions.push(Region::Conflict(conflict));
+        } else {
+            clean.extend(raw);
+        }
+    }
+    if !clean.is_empty() {
+        regions.push(Region::Clean(clean));
+    }
+    regions
+}
+
+/// Text of the file with resolved conflicts replaced and the rest still marked
+pub fn render_resolution(regions: &[Region], trailing_newline: bool) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for region in regions {
+        match region {
+            Region::Clean(clean) => lines.extend(clean.iter().cloned()),
+            Region::Conflict(conflict) => {
+                lines.extend(conflict.resolved_lines().unwrap_or_else(|| conflict.marked_lines()));
+            }
+        }
+    }
+    let mut text = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_resolve() {
+        let text = "a\n<<<<<<< HEAD\nours\n||||||| base\nold\n=======\ntheirs\n>>>>>>> feature\nb\n<<<<<<< HEAD\nx\n=======\ny\n>>>>>>> feature\n";
+        let mut regions = parse_conflicts(text);
+        assert_eq!(regions.len(), 4);
+        let Region::Conflict(first) = &mut regions[1] else {
+            panic!("expected a conflict");
+        };
+        assert_eq!((first.ours_label.as_str(), first.theirs_label.as_str()), ("HEAD", "feature"));
+        assert_eq!(first.base, Some(vec!["old".to_string()]));
+        first.resolution = Some(Resolution::Both);
+
+        assert_eq!(render_resolution(&regions[1..2], false), "ours\ntheirs");
+        regions[1] = parse_conflicts(text).swap_remove(1);
+        assert_eq!(render_resolution(&regions, true), text);
+
+        let Region::Conflict(first) = &mut regions[1] else {
+            panic!("expected a conflict");
+        };
+        first.resolution = Some(Resolution::Both);
+        let resolved = render_resolution(&regions, true);
+        assert_eq!(resolved, "a\nours\ntheirs\nb\n<<<<<<< HEAD\nx\n=======\ny\n>>>>>>> feature\n");
+        assert_eq!(parse_conflicts("<<<<<<< HEAD\nnever closed\n"), vec![Region::Clean(vec![
+            "<<<<<<< HEAD".to_string(),
+            "never closed".to_string(),
+        ])]);
+    }
+
+    #[test]
+    fn test_is_conflict_marker() {
+        assert!(is_conflict_marker("<<<<<<< HEAD"));
+        assert!(is_conflict_marker("======="));
+        assert!(is_conflict_marker(">>>>>>> feature\r"));
+        assert!(!is_conflict_marker("========"));
+        assert!(!is_conflict_marker("======= x"));
+        assert!(!is_conflict_marker("<<<<<<<<< HEAD"));
+    }
+}
@@ -5,8 +5,10 @@
 
 use std::path::Path;
 use std::fs;
-use anyhow::{Context, Result};
-use git2::{Diff, DiffOptions, Repository, DiffFormat, Tree};
+use anyhow::Result;
+use git2::{Delta, Diff, DiffDelta, DiffFindOptions, DiffHunk, DiffOptions, Index, ObjectType, Oid, Patch, Repository, DiffFormat, Tree};
+use super::binary::{detect_mime, BinaryInfo, BinarySide};
+use super::repo::{RepoPath, open_repo};
 
 /// Type of a diff line
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,6 +23,124 @@ pub enum LineType {
     Header,
 }
 
+/// Kind of change made to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// File was created
+    Added,
+    /// File was deleted
+    Deleted,
+    /// File content changed in place
+    Modified,
+    /// File was renamed or copied
+    Renamed,
+}
+
+/// Which uncommitted changes a diff of the working directory covers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Stage {
+    /// Staged and unstaged changes together, as the working directory has them
+    #[default]
+    Both,
+    /// Only changes added to the index
+    Staged,
+    /// Only changes not yet added to the index
+    Unstaged,
+}
+
+impl Stage {
+    /// The next filter in the cycle both → staged → unstaged
+    pub fn next(self) -> Self {
+        match self {
+            Stage::Both => Stage::Staged,
+            Stage::Staged => Stage::Unstaged,
+            Stage::Unstaged => Stage::Both,
+        }
+    }
+
+    /// Short lowercase label for display, None when not filtering
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Stage::Both => None,
+            Stage::Staged => Some("staged"),
+            Stage::Unstaged => Some("unstaged"),
+        }
+    }
+}
+
+/// How lines are matched up between the old and new file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffAlgorithm {
+    /// git's default, fast but apt to interleave moved blocks
+    #[default]
+    Myers,
+    /// Myers, spending extra time to find the smallest diff
+    Minimal,
+    /// Anchors on lines that appear once on each side, keeping blocks whole
+    Patience,
+}
+
+impl DiffAlgorithm {
+    /// All algorithms, in the order they cycle
+    pub const ALL: &[DiffAlgorithm] = &[DiffAlgorithm::Myers, DiffAlgorithm::Minimal, DiffAlgorithm::Patience];
+
+    /// Look up an algorithm by the name git's `--diff-algorithm` takes
+    ///
+    /// libgit2 has no histogram diff, so `histogram` gets patience, the
+    /// closest it has; `default` is myers, as in git.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(DiffAlgorithm::Myers),
+            "histogram" => Some(DiffAlgorithm::Patience),
+            name => Self::ALL.iter().copied().find(|a| a.name() == name),
+        }
+    }
+
+    /// Name used on the command line and in config
+    pub fn name(self) -> &'static str {
+        match self {
+            DiffAlgorithm::Myers => "myers",
+            DiffAlgorithm::Minimal => "minimal",
+            DiffAlgorithm::Patience => "patience",
+        }
+    }
+
+    /// The next algorithm in the cycle myers → minimal → patience
+    pub fn next(self) -> Self {
+        match self {
+            DiffAlgorithm::Myers => DiffAlgorithm::Minimal,
+            DiffAlgorithm::Minimal => DiffAlgorithm::Patience,
+            DiffAlgorithm::Patience => DiffAlgorithm::Myers,
+        }
+    }
+
+    fn apply(self, opts: &mut DiffOptions) {
+        opts.minimal(self == DiffAlgorithm::Minimal);
+        opts.patience(self == DiffAlgorithm::Patience);
+    }
+}
+
+impl ChangeKind {
+    /// Short lowercase label for display
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Deleted => "deleted",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Renamed => "renamed",
+        }
+    }
+
+    fn from_delta(status: Delta) -> Self {
+        match status {
+            Delta::Added | Delta::Untracked => ChangeKind::Added,
+            Delta::Deleted => ChangeKind::Deleted,
+            Delta::Renamed | Delta::Copied => ChangeKind::Renamed,
+            _ => ChangeKind::Modified,
+        }
+    }
+}
+
 /// A single line in a diff
 #[derive(Debug, Clone)]
 pub struct DiffLine {
@@ -32,6 +152,8 @@ pub struct DiffLine {
     pub old_lineno: Option<u32>,
     /// Line number in the new file (if applicable)
     pub new_lineno: Option<u32>,
+    /// Whether the line was removed here and added verbatim elsewhere, or added here after being removed elsewhere
+    pub moved: bool,
 }
 
 /// A hunk (section) of a diff
@@ -70,49 +192,104 @@ pub struct FileDiff {
     pub hunks: Vec<Hunk>,
     /// Whether the file is collapsed in the UI
     pub collapsed: bool,
+    /// Whether the split view shows the whole file rather than its hunks
+    pub whole: bool,
     /// Whether this is a binary file
     pub is_binary: bool,
+    /// Sizes, blob ids and type of a binary file
+    pub binary: Option<BinaryInfo>,
+    /// Heading shown above this file, e.g. the commit starting a group
+    pub section: Option<String>,
+    /// Kind of change (added, deleted, modified, renamed)
+    pub change: ChangeKind,
 }
 
-/// Compute diff between base branch and HEAD (or working directory)
+impl FileDiff {
+    /// Hash of what the diff changes: its lines added and removed, or for a
+    /// binary file the blobs on either side
+    ///
+    /// Context and line numbers are left out, so it holds while the context
+    /// is widened or edits elsewhere shift the file.
+    pub fn change_id(&self) -> String {
+        let mut text = String::new();
+        if let Some(binary) = &self.binary {
+            for side in [&binary.old, &binary.new] {
+                text.push_str(side.as_ref().map_or("-", |side| side.id.as_str()));
+                text.push('\n');
+            }
+        }
+        for line in self.hunks.iter().flat_map(|hunk| &hunk.lines) {
+            let prefix = match line.line_type {
+                LineType::Added => '+',
+                LineType::Removed => '-',
+                LineType::Context | LineType::Header => continue,
+            };
+            text.push(prefix);
+            text.push_str(&line.content);
+            text.push('\n');
+        }
+        Oid::hash_object(ObjectType::Blob, text.as_bytes()).map_or_else(|_| text, |oid| oid.to_string())
+    }
+}
+
+/// Compute diff between base branch and `head` (or working directory)
 ///
 /// # Arguments
 /// * `repo_path` - Path to the repository
 /// * `base_branch` - The base branch to diff against (e.g., "origin/main")
+/// * `head` - The revision to diff (usually "HEAD")
 /// * `include_uncommitted` - Whether to include uncommitted changes
 /// * `selected_commits` - Specific commit hashes to include (empty = all)
 /// * `context_lines` - Number of context lines around changes
+/// * `ignore_submodules` - Whether to leave out changed submodule pointers
+/// * `stage` - Which uncommitted changes to include: staged ones are diffed
+///   against the index, unstaged ones from the index to the working directory
+/// * `algorithm` - How to match up lines between the two sides
+#[allow(clippy::too_many_arguments)]
 pub fn compute_diff(
-    repo_path: &Path,
+    repo_path: &RepoPath,
     base_branch: &str,
+    head: &str,
     include_uncommitted: bool,
     selected_commits: &[String],
     context_lines: u32,
+    ignore_submodules: bool,
+    stage: Stage,
+    algorithm: DiffAlgorithm,
 ) -> Result<Vec<FileDiff>> {
-    let repo = Repository::discover(repo_path)
-        .context("Failed to discover git repository")?;
+    let repo = open_repo(repo_path)?;
 
     let mut opts = DiffOptions::new();
     opts.context_lines(context_lines);
     opts.ignore_whitespace_change(false);
+    opts.ignore_submodules(ignore_submodules);
+    algorithm.apply(&mut opts);
+
+    let index = repo.index()?;
 
     // Determine what to diff
-    let (diff, old_tree, new_tree, new_is_workdir) = if include_uncommitted && selected_commits.is_empty() {
-        // Diff HEAD against working directory
-        let head_tree = repo.head()?.peel_to_tree()?;
-        let diff = repo.diff_tree_to_workdir_with_index(Some(&head_tree), Some(&mut opts))?;
-        (diff, Some(head_tree), None, true)
+    let (diff, old_tree, new_tree, new_is_workdir) = if include_uncommitted && stage == Stage::Unstaged {
+        // Diff the index against the working directory, whatever the commits
+        let diff = repo.diff_index_to_workdir(Some(&index), Some(&mut opts))?;
+        (diff, None, None, true)
     } else if include_uncommitted {
-        // Diff base branch against working directory
-        let base_obj = repo.revparse_single(base_branch)?;
-        let base_tree = base_obj.peel_to_tree()?;
-        let diff = repo.diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut opts))?;
-        (diff, Some(base_tree), None, true)
+        // Diff HEAD, or the base branch when commits are selected too, against
+        // the working directory or just the index
+        let old_tree = if selected_commits.is_empty() {
+            repo.head()?.peel_to_tree()?
+        } else {
+            repo.revparse_single(base_branch)?.peel_to_tree()?
+        };
+        let diff = match stage {
+            Stage::Staged => repo.diff_tree_to_index(Some(&old_tree), Some(&index), Some(&mut opts))?,
+            _ => repo.diff_tree_to_workdir_with_index(Some(&old_tree), Some(&mut opts))?,
+        };
+        (diff, Some(old_tree), None, stage != Stage::Staged)
     } else if !selected_commits.is_empty() {
-        // Diff base branch against HEAD
+        // Diff base branch against head
         let base_obj = repo.revparse_single(base_branch)?;
         let base_tree = base_obj.peel_to_tree()?;
-        let head_tree = repo.head()?.peel_to_tree()?;
+        let head_tree = repo.revparse_single(head)?.peel_to_tree()?;
         let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))?;
         (diff, Some(base_tree), Some(head_tree), false)
     } else {
@@ -120,28 +297,100 @@ pub fn compute_diff(
         return Ok(Vec::new());
     };
 
+    let mut diff = diff;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
     let mut files = parse_diff(&diff)?;
 
     if !files.is_empty() {
         let workdir = repo.workdir().unwrap_or(repo_path);
-        let old_source = old_tree.as_ref().map(ContentSource::Tree);
+        // A missing tree on either side stands for the index
+        let old_source = old_tree.as_ref().map_or(ContentSource::Index(&index), ContentSource::Tree);
         let new_source = if new_is_workdir {
-            Some(ContentSource::Workdir(workdir))
+            ContentSource::Workdir(workdir)
         } else {
-            new_tree.as_ref().map(ContentSource::Tree)
+            new_tree.as_ref().map_or(ContentSource::Index(&index), ContentSource::Tree)
         };
-
-        if let (Some(old_source), Some(new_source)) = (old_source, new_source) {
-            populate_file_contents(&repo, old_source, new_source, &mut files);
-        }
+        populate_file_contents(&repo, old_source, new_source, &mut files);
     }
 
     Ok(files)
 }
 
+/// Compute the changes one commit made, against its first parent
+///
+/// A root commit is diffed against nothing, so every file shows as added.
+pub fn compute_commit_diff(
+    repo_path: &RepoPath,
+    commit: &str,
+    context_lines: u32,
+    ignore_submodules: bool,
+    algorithm: DiffAlgorithm,
+) -> Result<Vec<FileDiff>> {
+    let repo = open_repo(repo_path)?;
+    let commit = repo.revparse_single(commit)?.peel_to_commit()?;
+    let new_tree = commit.tree()?;
+    let old_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+
+    let mut opts = DiffOptions::new();
+    opts.context_lines(context_lines);
+    opts.ignore_submodules(ignore_submodules);
+    algorithm.apply(&mut opts);
+    let mut diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), Some(&mut opts))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let mut files = parse_diff(&diff)?;
+    let old_source = old_tree.as_ref().map_or(ContentSource::Empty, ContentSource::Tree);
+    populate_file_contents(&repo, old_source, ContentSource::Tree(&new_tree), &mut files);
+    Ok(files)
+}
+
+/// Compute the changes a stash entry holds, against the commit it was made on
+///
+/// A stash commit's tree is the work tree as stashed, and its first parent
+/// is the HEAD it was made on. Untracked files stashed along (`git stash -u`)
+/// live in a third parent and show as added.
+pub fn compute_stash_diff(
+    repo_path: &RepoPath,
+    stash: &str,
+    context_lines: u32,
+    ignore_submodules: bool,
+    algorithm: DiffAlgorithm,
+) -> Result<Vec<FileDiff>> {
+    let repo = open_repo(repo_path)?;
+    let stash = repo.revparse_single(stash)?.peel_to_commit()?;
+    let base_tree = stash.parent(0)?.tree()?;
+    let stashed_tree = stash.tree()?;
+
+    let mut opts = DiffOptions::new();
+    opts.context_lines(context_lines);
+    opts.ignore_submodules(ignore_submodules);
+    algorithm.apply(&mut opts);
+    let mut diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&stashed_tree), Some(&mut opts))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    let mut files = parse_diff(&diff)?;
+    populate_file_contents(&repo, ContentSource::Tree(&base_tree), ContentSource::Tree(&stashed_tree), &mut files);
+
+    if let Ok(untracked) = stash.parent(2) {
+        let untracked_tree = untracked.tree()?;
+        let diff = repo.diff_tree_to_tree(None, Some(&untracked_tree), Some(&mut opts))?;
+        let mut added = parse_diff(&diff)?;
+        populate_file_contents(&repo, ContentSource::Empty, ContentSource::Tree(&untracked_tree), &mut added);
+        files.extend(added);
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    Ok(files)
+}
+
 enum ContentSource<'a> {
     Tree(&'a Tree<'a>),
+    Index(&'a Index),
     Workdir(&'a Path),
+    /// No files at all, like the parent of a root commit
+    Empty,
 }
 
 fn populate_file_contents(
@@ -151,13 +400,22 @@ fn populate_file_contents(
     files: &mut [FileDiff],
 ) {
     for diff in files.iter_mut() {
+        let old_path = diff.old_path.as_deref().unwrap_or(&diff.path);
+        let new_path = diff.path.as_str();
+
         if diff.is_binary {
+            let old = load_file_bytes(repo, &old_source, old_path);
+            let new = load_file_bytes(repo, &new_source, new_path);
+            let sniffed = new.as_deref().or(old.as_deref()).unwrap_or_default();
+            diff.binary = Some(BinaryInfo {
+                mime: detect_mime(new_path, sniffed),
+                old: old.as_deref().map(BinarySide::from_bytes),
+                new: new.as_deref().map(BinarySide::from_bytes),
+                hexdump: false,
+            });
             continue;
         }
 
-        let old_path = diff.old_path.as_deref().unwrap_or(&diff.path);
-        let new_path = diff.path.as_str();
-
         diff.old_content = load_file_lines(repo, &old_source, old_path);
         diff.new_content = load_file_lines(repo, &new_source, new_path);
     }
@@ -170,10 +428,38 @@ fn load_file_lines(
 ) -> Option<Vec<String>> {
     match source {
         ContentSource::Tree(tree) => load_tree_lines(repo, tree, path),
+        ContentSource::Index(index) => {
+            let blob = load_index_blob(repo, index, path)?;
+            Some(split_lines(std::str::from_utf8(&blob).ok()?))
+        }
+        ContentSource::Empty => None,
         ContentSource::Workdir(workdir) => load_workdir_lines(workdir, path),
     }
 }
 
+fn load_file_bytes(
+    repo: &Repository,
+    source: &ContentSource<'_>,
+    path: &str,
+) -> Option<Vec<u8>> {
+    match source {
+        ContentSource::Tree(tree) => {
+            let entry = tree.get_path(Path::new(path)).ok()?;
+            let object = entry.to_object(repo).ok()?;
+            Some(object.as_blob()?.content().to_vec())
+        }
+        ContentSource::Index(index) => load_index_blob(repo, index, path),
+        ContentSource::Workdir(workdir) => fs::read(workdir.join(path)).ok(),
+        ContentSource::Empty => None,
+    }
+}
+
+/// Contents of the staged blob at `path`
+fn load_index_blob(repo: &Repository, index: &Index, path: &str) -> Option<Vec<u8>> {
+    let entry = index.get_path(Path::new(path), 0)?;
+    Some(repo.find_blob(entry.id).ok()?.content().to_vec())
+}
+
 fn load_tree_lines(repo: &Repository, tree: &Tree<'_>, path: &str) -> Option<Vec<String>> {
     let entry = tree.get_path(Path::new(path)).ok()?;
     let object = entry.to_object(repo).ok()?;
@@ -192,28 +478,67 @@ fn split_lines(contents: &str) -> Vec<String> {
     contents.lines().map(|line| line.to_string()).collect()
 }
 
+/// Diff two in-memory files, labelled with the paths shown for them
+///
+/// Returns no files when the contents are identical.
+pub(super) fn diff_buffers(
+    old_path: &str,
+    old: &[u8],
+    new_path: &str,
+    new: &[u8],
+    context_lines: u32,
+) -> Result<Vec<FileDiff>> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(context_lines);
+    let mut patch = Patch::from_buffers(old, Some(Path::new(old_path)), new, Some(Path::new(new_path)), Some(&mut opts))?;
+    let mut files = parse_lines(|on_line| patch.print(on_line))?;
+
+    for diff in &mut files {
+        if diff.is_binary {
+            diff.binary = Some(BinaryInfo {
+                mime: detect_mime(new_path, new),
+                old: Some(BinarySide::from_bytes(old)),
+                new: Some(BinarySide::from_bytes(new)),
+                hexdump: false,
+            });
+        } else {
+            diff.old_content = std::str::from_utf8(old).ok().map(split_lines);
+            diff.new_content = std::str::from_utf8(new).ok().map(split_lines);
+        }
+    }
+    Ok(files)
+}
+
 /// Parse a git2 Diff into our FileDiff structures
-fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
+pub(super) fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
+    parse_lines(|on_line| diff.print(DiffFormat::Patch, on_line))
+}
+
+/// Callback receiving each printed line of a diff or patch
+type LineCallback<'a> = dyn FnMut(DiffDelta<'_>, Option<DiffHunk<'_>>, git2::DiffLine<'_>) -> bool + 'a;
+
+/// Build FileDiffs from the lines `print` feeds to its callback
+fn parse_lines(print: impl FnOnce(&mut LineCallback<'_>) -> Result<(), git2::Error>) -> Result<Vec<FileDiff>> {
     let mut files: Vec<FileDiff> = Vec::new();
     let mut current_file: Option<FileDiff> = None;
     let mut current_hunk: Option<Hunk> = None;
     let mut last_hunk_header: Option<String> = None;
 
-    diff.print(DiffFormat::Patch, |delta, hunk, line| {
+    print(&mut |delta, hunk, line| {
         // Handle file changes
         if let Some(new_file) = delta.new_file().path() {
             let new_path = new_file.to_string_lossy().to_string();
 
             // Check if we need to start a new file
             let should_start_new = current_file.as_ref()
-                .map_or(true, |f| f.path != new_path);
+                .is_none_or(|f| f.path != new_path);
 
             if should_start_new {
                 // Save previous hunk and file
-                if let Some(h) = current_hunk.take() {
-                    if let Some(ref mut f) = current_file {
-                        f.hunks.push(h);
-                    }
+                if let Some(h) = current_hunk.take()
+                    && let Some(ref mut f) = current_file
+                {
+                    f.hunks.push(h);
                 }
                 if let Some(f) = current_file.take() {
                     files.push(f);
@@ -234,7 +559,11 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
                     removed: 0,
                     hunks: Vec::new(),
                     collapsed: false,
+                    whole: false,
                     is_binary: delta.flags().is_binary(),
+                    binary: None,
+                    section: None,
+                    change: ChangeKind::from_delta(delta.status()),
                 });
             }
         }
@@ -249,10 +578,10 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
 
             if is_new_hunk {
                 // Save previous hunk
-                if let Some(prev_hunk) = current_hunk.take() {
-                    if let Some(ref mut f) = current_file {
-                        f.hunks.push(prev_hunk);
-                    }
+                if let Some(prev_hunk) = current_hunk.take()
+                    && let Some(ref mut f) = current_file
+                {
+                    f.hunks.push(prev_hunk);
                 }
 
                 // Start new hunk
@@ -283,6 +612,7 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
             content: content.trim_end_matches(['\n', '\r']).to_string(),
             old_lineno: line.old_lineno(),
             new_lineno: line.new_lineno(),
+            moved: false,
         };
 
         if let Some(ref mut h) = current_hunk {
@@ -290,13 +620,13 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
         }
 
         // Update stats
-        if update_stats {
-            if let Some(ref mut f) = current_file {
-                match line_type {
-                    LineType::Added => f.added += 1,
-                    LineType::Removed => f.removed += 1,
-                    _ => {}
-                }
+        if update_stats
+            && let Some(ref mut f) = current_file
+        {
+            match line_type {
+                LineType::Added => f.added += 1,
+                LineType::Removed => f.removed += 1,
+                _ => {}
             }
         }
 
@@ -304,10 +634,10 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
     })?;
 
     // Save final hunk and file
-    if let Some(h) = current_hunk {
-        if let Some(ref mut f) = current_file {
-            f.hunks.push(h);
-        }
+    if let Some(h) = current_hunk
+        && let Some(ref mut f) = current_file
+    {
+        f.hunks.push(h);
     }
     if let Some(f) = current_file {
         files.push(f);
@@ -316,11 +646,192 @@ fn parse_diff(diff: &Diff) -> Result<Vec<FileDiff>> {
     Ok(files)
 }
 
+/// Narrow hunks down to `context` lines of context
+///
+/// The hunks must have been computed with at least `context` lines of
+/// context. Context lines further than that from any change are dropped
+/// and hunks split where the remaining gap exceeds `2 * context`, which is
+/// what git would have produced. Function context in the `@@` headers is
+/// re-derived from the dropped lines where possible.
+pub fn reduce_context(hunks: &[Hunk], context: u32) -> Vec<Hunk> {
+    let context = context as usize;
+    let mut result = Vec::new();
+
+    for hunk in hunks {
+        let lines = &hunk.lines;
+        let changes: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| matches!(l.line_type, LineType::Added | LineType::Removed))
+            .map(|(i, _)| i)
+            .collect();
+
+        // Keep lines within `context` of a change
+        let mut keep = vec![false; lines.len()];
+        for &i in &changes {
+            let start = i.saturating_sub(context);
+            let end = (i + context).min(lines.len().saturating_sub(1));
+            keep[start..=end].iter_mut().for_each(|k| *k = true);
+        }
+
+        // Positions of the next old/new line; zero-length sides start one line early
+        let mut old_pos = hunk.old_start + u32::from(hunk.old_count == 0);
+        let mut new_pos = hunk.new_start + u32::from(hunk.new_count == 0);
+        let mut current: Option<(Hunk, Option<&str>)> = None;
+        let mut function: Option<&str> = None;
+
+        for (line, &kept) in lines.iter().zip(&keep) {
+            if kept {
+                let (sub, _) = current.get_or_insert_with(|| (Hunk {
+                    old_start: old_pos,
+                    old_count: 0,
+                    new_start: new_pos,
+                    new_count: 0,
+                    header: String::new(),
+                    lines: Vec::new(),
+                }, function));
+                match line.line_type {
+                    LineType::Context => {
+                        sub.old_count += 1;
+                        sub.new_count += 1;
+                    }
+                    LineType::Removed => sub.old_count += 1,
+                    LineType::Added => sub.new_count += 1,
+                    LineType::Header => {}
+                }
+                sub.lines.push(line.clone());
+            } else if let Some((sub, function)) = current.take() {
+                result.push(finish_hunk(sub, function, &hunk.header));
+            }
+
+            if line.line_type != LineType::Added && is_function_line(&line.content) {
+                function = Some(&line.content);
+            }
+            match line.line_type {
+                LineType::Context => {
+                    old_pos += 1;
+                    new_pos += 1;
+                }
+                LineType::Removed => old_pos += 1,
+                LineType::Added => new_pos += 1,
+                LineType::Header => {}
+            }
+        }
+
+        if let Some((sub, function)) = current {
+            result.push(finish_hunk(sub, function, &hunk.header));
+        }
+    }
+
+    result
+}
+
+/// Re-derive hunks with more context, taking the extra lines from the file
+///
+/// `lines` is the full new file (the old one for a deletion), which agrees
+/// with the old file everywhere outside the hunks. The file is laid out as
+/// one hunk covering every line and then narrowed to `context`, so the
+/// result matches what git would have produced.
+pub fn widen_context(hunks: &[Hunk], lines: &[String], context: u32) -> Vec<Hunk> {
+    let mut whole = Hunk {
+        old_start: 1,
+        old_count: 0,
+        new_start: 1,
+        new_count: 0,
+        header: String::new(),
+        lines: Vec::new(),
+    };
+    let (mut old_pos, mut new_pos) = (1u32, 1u32);
+    let fill_to = |whole: &mut Hunk, old_pos: &mut u32, new_pos: &mut u32, end: u32| {
+        while *new_pos < end {
+            let Some(content) = lines.get(*new_pos as usize - 1) else {
+                break;
+            };
+            whole.lines.push(DiffLine {
+                line_type: LineType::Context,
+                content: content.clone(),
+                old_lineno: Some(*old_pos),
+                new_lineno: Some(*new_pos),
+                moved: false,
+            });
+            *old_pos += 1;
+            *new_pos += 1;
+        }
+    };
+
+    for hunk in hunks {
+        // A side with no lines starts on the line before the hunk
+        let start = hunk.new_start + u32::from(hunk.new_count == 0);
+        fill_to(&mut whole, &mut old_pos, &mut new_pos, start);
+        for line in &hunk.lines {
+            match line.line_type {
+                LineType::Context => {
+                    old_pos += 1;
+                    new_pos += 1;
+                }
+                LineType::Removed => old_pos += 1,
+                LineType::Added => new_pos += 1,
+                LineType::Header => {}
+            }
+            whole.lines.push(line.clone());
+        }
+    }
+    fill_to(&mut whole, &mut old_pos, &mut new_pos, lines.len() as u32 + 1);
+
+    whole.old_count = old_pos - 1;
+    whole.new_count = new_pos - 1;
+    reduce_context(&[whole], context)
+}
+
+/// Whether git's default function-name heuristic would pick this line
+fn is_function_line(content: &str) -> bool {
+    content.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+}
+
+/// Fix up a narrowed hunk's starts and build its `@@` header
+///
+/// Uses `function` as the function context, falling back to the original header's.
+fn finish_hunk(mut hunk: Hunk, function: Option<&str>, original_header: &str) -> Hunk {
+    if hunk.old_count == 0 {
+        hunk.old_start -= 1;
+    }
+    if hunk.new_count == 0 {
+        hunk.new_start -= 1;
+    }
+
+    let range = |start: u32, count: u32| {
+        if count == 1 {
+            start.to_string()
+        } else {
+            format!("{},{}", start, count)
+        }
+    };
+    let function_context = match function {
+        Some(line) => {
+            // git caps function context at 80 bytes
+            let line = line.trim_end();
+            let end = (0..=line.len().min(80)).rev().find(|&i| line.is_char_boundary(i)).unwrap_or(0);
+            format!(" {}", &line[..end])
+        }
+        None => original_header
+            .trim_start_matches("@@")
+            .split_once("@@")
+            .map(|(_, rest)| rest.to_string())
+            .unwrap_or_default(),
+    };
+
+    hunk.header = format!(
+        "@@ -{} +{} @@{}",
+        range(hunk.old_start, hunk.old_count),
+        range(hunk.new_start, hunk.new_count),
+        function_context,
+    );
+    hunk
+}
+
 /// Compute aggregate stats for a list of diffs
-pub fn compute_stats(diffs: &[FileDiff]) -> (usize, usize) {
-    let added: usize = diffs.iter().map(|d| d.added).sum();
-    let removed: usize = diffs.iter().map(|d| d.removed).sum();
-    (added, removed)
+pub fn compute_stats<'a>(diffs: impl IntoIterator<Item = &'a FileDiff>) -> (usize, usize) {
+    diffs.into_iter().fold((0, 0), |(added, removed), d| (added + d.added, removed + d.removed))
 }
 
 #[cfg(test)]
@@ -332,4 +843,167 @@ mod tests {
         assert_eq!(LineType::Added, LineType::Added);
         assert_ne!(LineType::Added, LineType::Removed);
     }
+
+    #[test]
+    fn test_reduce_context_splits_hunks() {
+        // Two changes separated by four context lines, computed with 3 lines of context
+        let mut lines = Vec::new();
+        let mut old = 7;
+        let mut new = 7;
+        for kind in "ccc-+cccc+ccc".chars() {
+            let (line_type, old_lineno, new_lineno) = match kind {
+                '-' => (LineType::Removed, Some(old), None),
+                '+' => (LineType::Added, None, Some(new)),
+                _ => (LineType::Context, Some(old), Some(new)),
+            };
+            old += u32::from(old_lineno.is_some());
+            new += u32::from(new_lineno.is_some());
+            lines.push(DiffLine { line_type, content: String::new(), old_lineno, new_lineno, moved: false });
+        }
+        let hunk = Hunk {
+            old_start: 7,
+            old_count: 11,
+            new_start: 7,
+            new_count: 12,
+            header: "@@ -7,11 +7,12 @@ fn main()".to_string(),
+            lines,
+        };
+
+        assert_eq!(reduce_context(std::slice::from_ref(&hunk), 3).len(), 1);
+
+        let narrowed = reduce_context(std::slice::from_ref(&hunk), 1);
+        let headers: Vec<&str> = narrowed.iter().map(|h| h.header.as_str()).collect();
+        assert_eq!(headers, ["@@ -9,3 +9,3 @@ fn main()", "@@ -14,2 +14,3 @@ fn main()"]);
+
+        let bare = reduce_context(&[hunk], 0);
+        let headers: Vec<&str> = bare.iter().map(|h| h.header.as_str()).collect();
+        assert_eq!(headers, ["@@ -10 +10 @@ fn main()", "@@ -14,0 +15 @@ fn main()"]);
+    }
+
+    #[test]
+    fn test_widen_context() {
+        // New file: "fn main() {", then a..o with `j` replaced by `J` and `X` inserted after `m`
+        let mut file = vec!["fn main() {".to_string()];
+        file.extend("abcdefghiJklmXno".chars().map(|c| format!("    {}", c)));
+        let line = |line_type, old_lineno, new_lineno, content: &str| DiffLine {
+            line_type,
+            content: content.to_string(),
+            old_lineno,
+            new_lineno,
+            moved: false,
+        };
+        let hunks = vec![
+            Hunk {
+                old_start: 11,
+                old_count: 1,
+                new_start: 11,
+                new_count: 1,
+                header: String::new(),
+                lines: vec![line(LineType::Removed, Some(11), None, "    j"), line(LineType::Added, None, Some(11), "    J")],
+            },
+            Hunk {
+                old_start: 14,
+                old_count: 0,
+                new_start: 15,
+                new_count: 1,
+                header: String::new(),
+                lines: vec![line(LineType::Added, None, Some(15), "    X")],
+            },
+        ];
+
+        // Widening adds context but changes nothing reviewed
+        let diff = |hunks: Vec<Hunk>| FileDiff {
+            path: "src/main.rs".to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: Some(file.clone()),
+            added: 2,
+            removed: 1,
+            hunks,
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Modified,
+        };
+        assert_eq!(diff(widen_context(&hunks, &file, 3)).change_id(), diff(hunks.clone()).change_id());
+        assert_ne!(diff(hunks[..1].to_vec()).change_id(), diff(hunks.clone()).change_id());
+
+        let one = widen_context(&hunks, &file, 1);
+        let headers: Vec<&str> = one.iter().map(|h| h.header.as_str()).collect();
+        assert_eq!(headers, ["@@ -10,3 +10,3 @@ fn main() {", "@@ -14,2 +14,3 @@ fn main() {"]);
+        assert_eq!(one[1].lines[0].content, "    m");
+
+        let wide = widen_context(&hunks, &file, 20);
+        assert_eq!(wide.len(), 1);
+        assert_eq!(wide[0].header, "@@ -1,16 +1,17 @@");
+        assert_eq!(wide[0].lines.len(), 18);
+    }
+
+    #[test]
+    fn test_diff_algorithm_names() {
+        assert_eq!(DiffAlgorithm::from_name("Patience"), Some(DiffAlgorithm::Patience));
+        assert_eq!(DiffAlgorithm::from_name("histogram"), Some(DiffAlgorithm::Patience));
+        assert_eq!(DiffAlgorithm::from_name("default"), Some(DiffAlgorithm::Myers));
+        assert_eq!(DiffAlgorithm::from_name("lcs"), None);
+        for &algorithm in DiffAlgorithm::ALL {
+            assert_eq!(DiffAlgorithm::from_name(algorithm.name()), Some(algorithm));
+        }
+    }
+
+    #[test]
+    fn test_compute_diff_stages() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-stage-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+        fs::write(dir.join("a.txt"), "one\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[]).unwrap();
+
+        // Stage "two", then change it to "three" without staging
+        fs::write(dir.join("a.txt"), "two\n").unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("a.txt"), "three\n").unwrap();
+
+        let lines = |stage| -> Vec<String> {
+            let files = compute_diff(&dir, "HEAD", "HEAD", true, &[], 3, false, stage, DiffAlgorithm::Myers).unwrap();
+            assert_eq!(files.len(), 1);
+            let contents = [&files[0].old_content, &files[0].new_content];
+            contents.iter().map(|c| c.as_ref().unwrap().join("")).collect()
+        };
+        assert_eq!(lines(Stage::Both), vec!["one", "three"]);
+        assert_eq!(lines(Stage::Staged), vec!["one", "two"]);
+        assert_eq!(lines(Stage::Unstaged), vec!["two", "three"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compute_commit_diff() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-commit-diff-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        let mut commit = |content: &str, parents: &[&git2::Commit]| {
+            fs::write(dir.join("a.txt"), content).unwrap();
+            index.add_path(Path::new("a.txt")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            repo.commit(Some("HEAD"), &sig, &sig, content, &tree, parents).unwrap()
+        };
+        let root = commit("one\n", &[]);
+        let root_commit = repo.find_commit(root).unwrap();
+        let second = commit("two\n", &[&root_commit]);
+
+        let files = compute_commit_diff(&dir, &root.to_string(), 3, false, DiffAlgorithm::Myers).unwrap();
+        assert_eq!((files[0].change, files[0].added, files[0].removed), (ChangeKind::Added, 1, 0));
+        let files = compute_commit_diff(&dir, &second.to_string(), 3, false, DiffAlgorithm::Patience).unwrap();
+        assert_eq!(files[0].old_content, Some(vec!["one".to_string()]));
+        assert_eq!((files[0].added, files[0].removed), (1, 1));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,88 @@
+//! Remote fetching
+//!
+//! Fetches the remote that backs the base branch so the comparison
+//! reflects the latest upstream state.
+
+use anyhow::{Context, Result};
+use git2::{Cred, CredentialType, FetchOptions, RemoteCallbacks};
+use super::repo::{RepoPath, open_repo};
+
+/// Progress of an in-flight fetch
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchProgress {
+    /// Objects received so far
+    pub received_objects: usize,
+    /// Total objects to receive
+    pub total_objects: usize,
+    /// Bytes received so far
+    pub received_bytes: usize,
+}
+
+/// Determine which remote the base branch lives on
+///
+/// For remote-tracking refs like `origin/main` this is the prefix;
+/// for local branches it is the configured upstream remote.
+/// Falls back to `origin`.
+pub fn remote_for_base(repo_path: &RepoPath, base_branch: &str) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+
+    if let Some((prefix, _)) = base_branch.split_once('/')
+        && repo.find_remote(prefix).is_ok()
+    {
+        return Ok(prefix.to_string());
+    }
+
+    let local_ref = format!("refs/heads/{}", base_branch);
+    if let Ok(remote) = repo.branch_upstream_remote(&local_ref)
+        && let Some(name) = remote.as_str()
+    {
+        return Ok(name.to_string());
+    }
+
+    Ok("origin".to_string())
+}
+
+/// Fetch `refspecs` from a remote, or its configured refspecs if empty
+///
+/// Credentials are tried in order: ssh-agent, git credential helpers,
+/// then default. `on_progress` is invoked as objects are transferred.
+pub fn fetch_remote<F>(repo_path: &RepoPath, remote_name: &str, refspecs: &[&str], mut on_progress: F) -> Result<()>
+where
+    F: FnMut(FetchProgress),
+{
+    let repo = open_repo(repo_path)?;
+    let mut remote = repo.find_remote(remote_name)
+        .with_context(|| format!("Remote '{}' not found", remote_name))?;
+    let config = repo.config()?;
+
+    let mut tried_agent = false;
+    let mut tried_helper = false;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) && !tried_agent {
+            tried_agent = true;
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried_helper {
+            tried_helper = true;
+            return Cred::credential_helper(&config, url, username);
+        }
+        Cred::default()
+    });
+    callbacks.transfer_progress(|stats| {
+        on_progress(FetchProgress {
+            received_objects: stats.received_objects(),
+            total_objects: stats.total_objects(),
+            received_bytes: stats.received_bytes(),
+        });
+        true
+    });
+
+    let mut opts = FetchOptions::new();
+    opts.remote_callbacks(callbacks);
+
+    remote.fetch(refspecs, Some(&mut opts), None)
+        .with_context(|| format!("Failed to fetch '{}'", remote_name))?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! JSON form of diffs and commits
+//!
+//! What `--json` prints for scripts: each file with its hunks and typed
+//! lines, and each commit, as plain values with snake_case keys.
+
+use serde_json::{json, Value};
+
+use super::{Commit, FileDiff, Hunk, LineType};
+
+/// Name of a line type in JSON output
+fn line_type(line_type: LineType) -> &'static str {
+    match line_type {
+        LineType::Context => "context",
+        LineType::Added => "added",
+        LineType::Removed => "removed",
+        LineType::Header => "header",
+    }
+}
+
+fn hunk_json(hunk: &Hunk) -> Value {
+    let lines: Vec<Value> = hunk
+        .lines
+        .iter()
+        .filter(|line| line.line_type != LineType::Header)
+        .map(|line| {
+            json!({
+                "type": line_type(line.line_type),
+                "content": line.content,
+                "old_line": line.old_lineno,
+                "new_line": line.new_lineno,
+            })
+        })
+        .collect();
+    json!({
+        "header": hunk.header,
+        "old_start": hunk.old_start,
+        "old_count": hunk.old_count,
+        "new_start": hunk.new_start,
+        "new_count": hunk.new_count,
+        "lines": lines,
+    })
+}
+
+/// A file's diff: its paths, kind of change, stats and hunks
+pub fn file_json(diff: &FileDiff) -> Value {
+    json!({
+        "path": diff.path,
+        "old_path": diff.old_path,
+        "change": diff.change.label(),
+        "added": diff.added,
+        "removed": diff.removed,
+        "binary": diff.is_binary,
+        "hunks": diff.hunks.iter().map(hunk_json).collect::<Vec<_>>(),
+    })
+}
+
+/// A commit of the branch; the uncommitted-changes entry has no hash
+pub fn commit_json(commit: &Commit) -> Value {
+    let hash = (!commit.is_uncommitted).then_some(commit.full_hash.as_str());
+    json!({
+        "hash": hash,
+        "subject": commit.subject,
+        "author": (!commit.is_uncommitted).then_some(commit.author.as_str()),
+        "email": (!commit.is_uncommitted).then_some(commit.email.as_str()),
+        "time": (!commit.is_uncommitted).then_some(commit.time),
+        "selected": commit.selected,
+        "note": commit.note,
+        "signer": commit.signer,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{ChangeKind, DiffLine};
+
+    #[test]
+    fn test_file_json() {
+        let diff = FileDiff {
+            path: "src/lib.rs".to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: None,
+            added: 1,
+            removed: 0,
+            hunks: vec![Hunk {
+                old_start: 3,
+                old_count: 1,
+                new_start: 3,
+                new_count: 2,
+                header: "@@ -3 +3,2 @@".to_string(),
+                lines: vec![
+                    DiffLine { line_type: LineType::Context, content: "a".to_string(), old_lineno: Some(3), new_lineno: Some(3), moved: false },
+                    DiffLine { line_type: LineType::Added, content: "b".to_string(), old_lineno: None, new_lineno: Some(4), moved: false },
+                ],
+            }],
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Modified,
+        };
+        let value = file_json(&diff);
+        assert_eq!(value["change"], "modified");
+        assert_eq!(value["hunks"][0]["new_count"], 2);
+        assert_eq!(value["hunks"][0]["lines"][1], json!({ "type": "added", "content": "b", "old_line": null, "new_line": 4 }));
+    }
+}
@@ -0,0 +1,88 @@
+//! Merge conflict prediction
+//!
+//! Performs an in-memory merge of the revision under review into the base
+//! branch to find files that would conflict, without touching the index or
+//! worktree.
+
+use anyhow::{Context, Result};
+use git2::MergeOptions;
+use super::repo::{RepoPath, open_repo};
+
+/// List files that would conflict when merging `head_rev` into the base branch
+///
+/// Returns an empty list when the merge would be clean.
+pub fn predict_conflicts(repo_path: &RepoPath, base_branch: &str, head_rev: &str) -> Result<Vec<String>> {
+    let repo = open_repo(repo_path)?;
+
+    let base_commit = repo.revparse_single(base_branch)
+        .with_context(|| format!("Unknown base '{}'", base_branch))?
+        .peel_to_commit()?;
+    let head_commit = repo.revparse_single(head_rev)
+        .with_context(|| format!("Unknown revision '{}'", head_rev))?
+        .peel_to_commit()?;
+
+    let ancestor_oid = repo.merge_base(base_commit.id(), head_commit.id())
+        .context("No common ancestor with base")?;
+    let ancestor_tree = repo.find_commit(ancestor_oid)?.tree()?;
+
+    let mut opts = MergeOptions::new();
+    opts.fail_on_conflict(false);
+
+    let index = repo.merge_trees(
+        &ancestor_tree,
+        &base_commit.tree()?,
+        &head_commit.tree()?,
+        Some(&opts),
+    )?;
+
+    let mut paths = Vec::new();
+    if index.has_conflicts() {
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            // Prefer "their" (reviewed) side, then "our" (base), then ancestor
+            let entry = conflict.their.or(conflict.our).or(conflict.ancestor);
+            if let Some(entry) = entry {
+                paths.push(String::from_utf8_lossy(&entry.path).to_string());
+            }
+        }
+    }
+
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Repository, Signature};
+
+    /// Commit `content` as `file.txt` on `branch`, on top of `parent`
+    fn commit(repo: &Repository, branch: &str, parent: Option<git2::Oid>, content: &str) -> git2::Oid {
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("file.txt", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let sig = Signature::now("gv", "gv@example.com").unwrap();
+        let parents: Vec<git2::Commit> = parent.map(|oid| repo.find_commit(oid).unwrap()).into_iter().collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some(&format!("refs/heads/{}", branch)), &sig, &sig, branch, &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn test_predict_conflicts_for_reviewed_revision() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-conflicts-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let repo = Repository::init(&dir).unwrap();
+        let start = commit(&repo, "start", None, "one\n");
+        commit(&repo, "base", Some(start), "base\n");
+        commit(&repo, "feature", Some(start), "feature\n");
+        repo.set_head("refs/heads/start").unwrap();
+
+        // HEAD itself merges cleanly; the revision under review doesn't
+        assert!(predict_conflicts(&dir, "base", "HEAD").unwrap().is_empty());
+        assert_eq!(predict_conflicts(&dir, "base", "feature").unwrap(), vec!["file.txt"]);
+        assert!(predict_conflicts(&dir, "base", "missing").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,14 +1,55 @@
 //! Git operations module
 //!
 //! Provides functionality for interacting with git repositories:
+//! - Repository discovery, honoring GIT_DIR and GIT_WORK_TREE
 //! - Worktree discovery and management
 //! - Diff computation with context lines
+//! - Comparing arbitrary files or revisions of a file
+//! - `A..B` and `A...B` revision ranges
+//! - Parsing diffs piped in when running as git's pager
+//! - Parsing and resolving conflict markers
+//! - Caching of computed diffs
+//! - Size, blob id and type metadata for binary files
 //! - Commit listing and filtering
+//! - Stash listing
+//! - Line blame for the blame gutter
+//! - Fetching the base branch's remote
+//! - Predicting merge conflicts with the base
 
 mod worktree;
+mod binary;
+mod blame;
 mod diff;
+mod cache;
 mod commits;
+mod compare;
+mod conflicts;
+mod fetch;
+mod json;
+mod merge;
+mod moved;
+mod pager;
+mod patches;
+mod range;
+mod repo;
+mod stash;
+mod unified;
 
-pub use worktree::{Worktree, list_worktrees, find_current_worktree, get_main_branch};
-pub use diff::{FileDiff, Hunk, DiffLine, LineType, compute_diff, compute_stats};
-pub use commits::{Commit, list_commits};
+pub use worktree::{Worktree, WorktreeStatus, list_worktrees, worktree_status, create_worktree, remove_worktree, stale_worktrees, prune_worktrees, find_current_worktree, find_worktree, get_main_branch, repo_root, resolve_base, resolve_commit, config_string, config_strings, config_bool, config_i64};
+pub use diff::{ChangeKind, DiffAlgorithm, FileDiff, Hunk, DiffLine, LineType, Stage, compute_diff, compute_commit_diff, compute_stash_diff, compute_stats, reduce_context, widen_context};
+pub use binary::{BinarySide, Thumbnail, HEXDUMP_LIMIT};
+pub use blame::{BlameLine, blame_lines};
+pub use cache::{DiffCache, DiffKey};
+pub use compare::{CompareSide, compare_files, external_spool_dir, resolve_side, spool_external};
+pub use conflicts::{Conflict, Region, Resolution, is_conflict_marker, parse_conflicts, render_resolution};
+pub use commits::{Commit, SignatureStatus, commit_message, has_uncommitted_changes, walk_commits};
+pub use fetch::{FetchProgress, fetch_remote, remote_for_base};
+pub use json::{commit_json, file_json};
+pub use stash::{Stash, list_stashes};
+pub use merge::predict_conflicts;
+pub use moved::mark_moved;
+pub use pager::{parse_piped_diff, read_piped_diff};
+pub use patches::{apply_series, read_series};
+pub use range::{RevRange, merge_base};
+pub use repo::{RepoPath, load_state, save_state, state_dir};
+pub use unified::{diff_patch, file_patch, hunk_patch};
@@ -0,0 +1,163 @@
+//! Moved-line detection
+//!
+//! Finds lines removed in one place and added verbatim in another, in the
+//! same file or across files, so the view can tell a moved block from code
+//! that really went away or is really new, like `git diff --color-moved`.
+
+use std::collections::HashMap;
+
+use super::{FileDiff, LineType};
+
+/// Fewest letters and digits a moved block needs, as in git, so lone
+/// braces, blank lines and other common lines aren't marked
+const MIN_ALNUM: usize = 20;
+
+/// Mark the removed lines added back elsewhere, and the added lines removed elsewhere
+///
+/// A line counts as moved when it's part of a block of consecutive lines
+/// found in the same order on the other side, carrying at least
+/// [`MIN_ALNUM`] letters and digits. Lines that merely turn up somewhere
+/// else on their own aren't marked.
+pub fn mark_moved(diffs: &mut [FileDiff]) {
+    let removed = Runs::new(diffs, LineType::Removed);
+    let added = Runs::new(diffs, LineType::Added);
+
+    for lines in diffs.iter_mut().flat_map(|diff| &mut diff.hunks).map(|hunk| &mut hunk.lines) {
+        let mut i = 0;
+        while i < lines.len() {
+            let line_type = lines[i].line_type;
+            let other = match line_type {
+                LineType::Removed => &added,
+                LineType::Added => &removed,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let run = lines[i..].iter().take_while(|l| l.line_type == line_type).count();
+            let contents: Vec<&str> = lines[i..i + run].iter().map(|l| l.content.as_str()).collect();
+            let moved = other.moved(&contents);
+            for (line, moved) in lines[i..i + run].iter_mut().zip(moved) {
+                line.moved = moved;
+            }
+            i += run;
+        }
+    }
+}
+
+/// The runs of consecutive removed (or added) lines, and where each line occurs in them
+struct Runs {
+    runs: Vec<Vec<String>>,
+    at: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl Runs {
+    fn new(diffs: &[FileDiff], line_type: LineType) -> Self {
+        let mut runs: Vec<Vec<String>> = Vec::new();
+        for hunk in diffs.iter().flat_map(|diff| &diff.hunks) {
+            let mut previous = None;
+            for line in &hunk.lines {
+                if line.line_type == line_type {
+                    if previous != Some(line_type) {
+                        runs.push(Vec::new());
+                    }
+                    runs.last_mut().unwrap().push(line.content.clone());
+                }
+                previous = Some(line.line_type);
+            }
+        }
+
+        let mut at: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for (r, run) in runs.iter().enumerate() {
+            for (p, line) in run.iter().enumerate() {
+                at.entry(line.clone()).or_default().push((r, p));
+            }
+        }
+        Self { runs, at }
+    }
+
+    /// Which of `lines`, a run from the other side, lie in a block these runs have too
+    fn moved(&self, lines: &[&str]) -> Vec<bool> {
+        let mut moved = vec![false; lines.len()];
+        let mut i = 0;
+        while i < lines.len() {
+            // Longest block starting here that one of the runs has
+            let len = self.at
+                .get(lines[i])
+                .into_iter()
+                .flatten()
+                .map(|&(r, p)| lines[i..].iter().zip(&self.runs[r][p..]).take_while(|(a, b)| **a == b.as_str()).count())
+                .max()
+                .unwrap_or(0);
+            let alnum: usize = lines[i..i + len].iter().map(|l| l.chars().filter(|c| c.is_alphanumeric()).count()).sum();
+            if len > 0 && alnum >= MIN_ALNUM {
+                moved[i..i + len].fill(true);
+                i += len;
+            } else {
+                i += 1;
+            }
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{ChangeKind, DiffLine, Hunk};
+
+    fn file(path: &str, lines: &[(LineType, &str)]) -> FileDiff {
+        let lines = lines
+            .iter()
+            .map(|&(line_type, content)| DiffLine {
+                line_type,
+                content: content.to_string(),
+                old_lineno: None,
+                new_lineno: None,
+                moved: false,
+            })
+            .collect();
+        FileDiff {
+            path: path.to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: None,
+            added: 0,
+            removed: 0,
+            hunks: vec![Hunk { old_start: 1, old_count: 0, new_start: 1, new_count: 0, header: String::new(), lines }],
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Modified,
+        }
+    }
+
+    #[test]
+    fn test_mark_moved() {
+        use LineType::*;
+        let mut diffs = [
+            file("a.rs", &[(Removed, "fn helper() {"), (Removed, "    compute_total(items)"), (Removed, "}"), (Removed, "let gone = 1;")]),
+            file("b.rs", &[(Context, "}"), (Added, "fn helper() {"), (Added, "    compute_total(items)"), (Added, "}"), (Added, "}")]),
+        ];
+        mark_moved(&mut diffs);
+        let moved = |diff: &FileDiff| -> Vec<bool> { diff.hunks[0].lines.iter().map(|l| l.moved).collect() };
+        assert_eq!(moved(&diffs[0]), [true, true, true, false]);
+        // The extra brace was removed somewhere too, but not as part of the block
+        assert_eq!(moved(&diffs[1]), [false, true, true, true, false]);
+
+        // Common lines turning up elsewhere one by one aren't a move
+        let mut diffs = [
+            file("a.rs", &[(Removed, "fn other_function() {"), (Removed, "    setup();"), (Removed, "    return Ok(());"), (Removed, "}")]),
+            file("b.rs", &[(Added, "    return Ok(());"), (Added, "fn other_function() {"), (Added, "}"), (Added, "")]),
+        ];
+        mark_moved(&mut diffs);
+        assert!(diffs.iter().flat_map(|d| &d.hunks[0].lines).all(|l| !l.moved));
+
+        // Too little moved to tell it from coincidence
+        let mut diffs = [file("a.rs", &[(Removed, "}"), (Removed, "x += 1;")]), file("b.rs", &[(Added, "x += 1;"), (Added, "}")])];
+        mark_moved(&mut diffs);
+        assert!(diffs.iter().flat_map(|d| &d.hunks[0].lines).all(|l| !l.moved));
+    }
+}
@@ -0,0 +1,125 @@
+//! Diffs piped in by git
+//!
+//! When gv runs as git's pager (`core.pager` or `pager.diff`), `git diff`
+//! and `git show` write their patch to its stdin, colored if git thinks a
+//! terminal is listening. The patch is parsed into the usual structures;
+//! anything before the first `diff --git`, like a commit header, is skipped.
+//! Output without a patch near the start is streamed straight through.
+
+use std::io::{self, BufRead, Write};
+use anyhow::Result;
+use git2::Diff;
+use super::diff::{parse_diff, FileDiff};
+
+/// How much output to hold back while looking for the first patch
+const PEEK_LIMIT: usize = 64 * 1024;
+
+/// Read a diff piped in by git, or pass other output through
+///
+/// Output is held back up to its first `diff --git` line, then read in
+/// full and parsed. When none turns up within `PEEK_LIMIT` bytes, as with
+/// plain `git log`, it's copied to `passthrough` as it arrives instead and
+/// `None` is returned.
+pub fn read_piped_diff(
+    mut input: impl BufRead,
+    mut passthrough: impl Write,
+) -> Result<Option<Vec<FileDiff>>> {
+    let mut output = Vec::new();
+    while output.len() <= PEEK_LIMIT {
+        let start = output.len();
+        if input.read_until(b'\n', &mut output)? == 0 {
+            break;
+        }
+        if is_diff_header(&output[start..]) {
+            input.read_to_end(&mut output)?;
+            let diffs = parse_piped_diff(&output)?;
+            if diffs.is_empty() {
+                break;
+            }
+            return Ok(Some(diffs));
+        }
+    }
+    passthrough.write_all(&output)?;
+    io::copy(&mut input, &mut passthrough)?;
+    Ok(None)
+}
+
+/// Parse the output of `git diff`, `git show` or `git log -p`
+///
+/// Returns no files for output without a patch, e.g. plain `git log`.
+pub fn parse_piped_diff(output: &[u8]) -> Result<Vec<FileDiff>> {
+    let text = strip_ansi(&String::from_utf8_lossy(output));
+    if !text.lines().any(|line| line.starts_with("diff --git ")) {
+        return Ok(Vec::new());
+    }
+    let diff = Diff::from_buffer(text.as_bytes())?;
+    parse_diff(&diff)
+}
+
+/// Whether a line of output, colored or not, starts a file's patch
+fn is_diff_header(line: &[u8]) -> bool {
+    strip_ansi(&String::from_utf8_lossy(line)).starts_with("diff --git ")
+}
+
+/// Remove the color and style escape sequences git adds
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        // A control sequence runs from `ESC [` to a final letter
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() || c == '~' {
+                    break;
+                }
+            }
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_piped_diff() {
+        let output = "\x1b[33mcommit 1234abcd\x1b[m\nAuthor: A <a@example.com>\n\n    Tweak\n\n\
+            \x1b[1mdiff --git a/src/lib.rs b/src/lib.rs\x1b[m\n\x1b[1mindex 1111111..2222222 100644\x1b[m\n\
+            \x1b[1m--- a/src/lib.rs\x1b[m\n\x1b[1m+++ b/src/lib.rs\x1b[m\n\x1b[36m@@ -1,2 +1,2 @@\x1b[m\n \
+            fn a() {}\n\x1b[31m-fn b() {}\x1b[m\n\x1b[32m+fn c() {}\x1b[m\n";
+        let diffs = parse_piped_diff(output.as_bytes()).unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].path, "src/lib.rs");
+        assert_eq!((diffs[0].added, diffs[0].removed), (1, 1));
+        assert_eq!(diffs[0].hunks[0].lines[2].content, "fn c() {}");
+
+        assert!(parse_piped_diff(b"commit 1234abcd\n\n    Tweak\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_piped_diff() {
+        let patch = "commit 1234abcd\n\n    Tweak\n\n\x1b[1mdiff --git a/a.txt b/a.txt\x1b[m\n\
+            --- a/a.txt\n+++ b/a.txt\n@@ -1 +1 @@\n-old\n+new\n";
+        let mut passthrough = Vec::new();
+        let diffs = read_piped_diff(patch.as_bytes(), &mut passthrough).unwrap().unwrap();
+        assert_eq!(diffs[0].path, "a.txt");
+        assert!(passthrough.is_empty());
+
+        // Output without a patch is passed on whole, however long
+        let log = "commit 1234abcd\n\n    Tweak\n\n".repeat(PEEK_LIMIT / 8);
+        let mut passthrough = Vec::new();
+        assert!(read_piped_diff(log.as_bytes(), &mut passthrough).unwrap().is_none());
+        assert_eq!(passthrough, log.as_bytes());
+
+        // A patch past the peek limit is streamed too
+        let late = log.clone() + patch;
+        let mut passthrough = Vec::new();
+        assert!(read_piped_diff(late.as_bytes(), &mut passthrough).unwrap().is_none());
+        assert_eq!(passthrough, late.as_bytes());
+    }
+}
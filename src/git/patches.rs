@@ -0,0 +1,316 @@
+//! Patch series review
+//!
+//! Reads `git format-patch` output or a mailbox of patches and replays the
+//! series in memory on top of a base commit. Each patch becomes an
+//! unreferenced commit object, so the series can be browsed like a branch
+//! without touching refs, the index or the worktree.
+
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use git2::{Diff, Signature, Time};
+use super::repo::{RepoPath, open_repo};
+
+/// One patch of a series
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// Author name from the `From:` header
+    pub author_name: String,
+    /// Author email from the `From:` header
+    pub author_email: String,
+    /// Author date as seconds since the epoch, and UTC offset in minutes
+    pub time: Option<(i64, i32)>,
+    /// Commit message: subject without the `[PATCH n/m]` prefix, then the body
+    pub message: String,
+    /// The diff itself, from the first `diff --git` line
+    pub diff: String,
+}
+
+/// A parsed patch series
+#[derive(Debug, Default)]
+pub struct PatchSeries {
+    /// Patches in order, cover letters left out
+    pub patches: Vec<Patch>,
+    /// `base-commit:` recorded by `git format-patch --base`
+    pub base_commit: Option<String>,
+}
+
+/// Read patches from files and directories, in the order given
+///
+/// Directories contribute their `*.patch` and `*.eml` files sorted by
+/// name, matching `git format-patch`'s numbering.
+pub fn read_series(paths: &[PathBuf]) -> Result<PatchSeries> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "patch" || ext == "eml"))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut series = PatchSeries::default();
+    for file in &files {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        for message in split_mailbox(&text) {
+            if series.base_commit.is_none() {
+                series.base_commit = message
+                    .lines()
+                    .find_map(|line| line.strip_prefix("base-commit: "))
+                    .map(|hash| hash.trim().to_string());
+            }
+            if let Some(patch) = parse_patch(message)
+                .with_context(|| format!("Invalid patch in {}", file.display()))?
+            {
+                series.patches.push(patch);
+            }
+        }
+    }
+    if series.patches.is_empty() {
+        bail!("No patches found");
+    }
+    Ok(series)
+}
+
+/// Apply the series on top of `base`, one commit per patch
+///
+/// Returns the abbreviated hash of the last commit.
+pub fn apply_series(repo_path: &RepoPath, base: &str, patches: &[Patch]) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+    let mut parent = repo.revparse_single(base)
+        .with_context(|| format!("Unknown base '{}'", base))?
+        .peel_to_commit()?;
+
+    for (i, patch) in patches.iter().enumerate() {
+        let subject = patch.message.lines().next().unwrap_or("");
+        let context = || format!("Patch {} ({}) doesn't apply to {}", i + 1, subject, base);
+
+        let diff = Diff::from_buffer(patch.diff.as_bytes()).with_context(context)?;
+        let mut index = repo.apply_to_tree(&parent.tree()?, &diff, None).with_context(context)?;
+        let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+
+        // Using the author as committer keeps replays of the same series identical
+        let author = match patch.time {
+            Some((seconds, offset)) => {
+                Signature::new(&patch.author_name, &patch.author_email, &Time::new(seconds, offset))?
+            }
+            None => Signature::now(&patch.author_name, &patch.author_email)?,
+        };
+        let oid = repo.commit(None, &author, &author, &patch.message, &tree, &[&parent])?;
+        parent = repo.find_commit(oid)?;
+    }
+
+    let short = parent.as_object().short_id()?;
+    Ok(short.as_str().unwrap_or_default().to_string())
+}
+
+/// Split a mailbox into messages at `From <hash> <date>` lines
+///
+/// A single patch without an mbox separator is returned whole.
+fn split_mailbox(text: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if is_mbox_separator(line) {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&text.len()]))
+        .map(|(&start, &end)| &text[start..end])
+        .filter(|message| !message.trim().is_empty())
+        .collect()
+}
+
+/// Whether `line` is an mbox `From <40-hex hash> ` separator rather than body text
+fn is_mbox_separator(line: &str) -> bool {
+    let Some(rest) = line.strip_prefix("From ") else {
+        return false;
+    };
+    rest.len() > 40 && rest.as_bytes()[..40].iter().all(u8::is_ascii_hexdigit) && rest.as_bytes()[40] == b' '
+}
+
+/// Parse one mail into a patch, or `None` for a message without a diff
+fn parse_patch(message: &str) -> Result<Option<Patch>> {
+    let Some(diff_start) = message
+        .match_indices("diff --git ")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || message.as_bytes()[i - 1] == b'\n')
+    else {
+        return Ok(None);
+    };
+
+    let (head, diff) = message.split_at(diff_start);
+    // Drop the `-- ` signature git appends after the diff
+    let diff = match diff.find("\n-- \n") {
+        Some(end) => &diff[..end + 1],
+        None => diff,
+    };
+
+    let (headers, body) = head.split_once("\n\n").unwrap_or((head, ""));
+    let mut from = None;
+    let mut date = None;
+    let mut subject = None;
+    for (name, value) in unfold_headers(headers) {
+        match name.to_ascii_lowercase().as_str() {
+            "from" => from = Some(value),
+            "date" => date = Some(value),
+            "subject" => subject = Some(value),
+            _ => {}
+        }
+    }
+    let Some(from) = from else {
+        bail!("missing From: header");
+    };
+    let (author_name, author_email) = parse_address(&from);
+
+    // The body ends at the `---` line before the diffstat
+    let body = body.split("\n---\n").next().unwrap_or(body);
+    let body = body.strip_suffix("---\n").unwrap_or(body).trim();
+    let subject = strip_patch_prefix(subject.as_deref().unwrap_or(""));
+    let message = if body.is_empty() {
+        format!("{}\n", subject)
+    } else {
+        format!("{}\n\n{}\n", subject, body)
+    };
+
+    Ok(Some(Patch {
+        author_name,
+        author_email,
+        time: date.as_deref().and_then(parse_date),
+        message,
+        diff: diff.to_string(),
+    }))
+}
+
+/// Mail headers as name/value pairs, with folded lines joined
+fn unfold_headers(headers: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in headers.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = fields.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            fields.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    fields
+}
+
+/// Split `Name <email>` into its parts
+fn parse_address(from: &str) -> (String, String) {
+    match from.rsplit_once('<') {
+        Some((name, email)) => (
+            name.trim().trim_matches('"').to_string(),
+            email.trim_end_matches('>').trim().to_string(),
+        ),
+        None => (from.trim().to_string(), from.trim().to_string()),
+    }
+}
+
+/// Remove a leading `[PATCH v2 3/7]`-style tag from a subject
+fn strip_patch_prefix(subject: &str) -> &str {
+    match subject.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((tag, rest)) if tag.to_ascii_uppercase().contains("PATCH") => rest.trim_start(),
+        _ => subject,
+    }
+}
+
+/// Parse an RFC 2822 date like `Tue, 4 Jun 2024 10:15:00 +0200`
+///
+/// Returns seconds since the epoch and the UTC offset in minutes.
+fn parse_date(date: &str) -> Option<(i64, i32)> {
+    // The weekday is optional
+    let date = date.split_once(", ").map_or(date, |(_, rest)| rest);
+    let mut parts = date.split_whitespace();
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?.get(..3)?;
+    let month = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"]
+        .iter()
+        .position(|&m| m == month_name)? as i64
+        + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut clock = parts.next()?.split(':').map(|n| n.parse::<i64>().ok());
+    let (hour, minute) = (clock.next()??, clock.next()??);
+    let second = clock.next().flatten().unwrap_or(0);
+    let zone = parts.next()?;
+    let sign = if zone.starts_with('-') { -1 } else { 1 };
+    let zone: i64 = zone.trim_start_matches(['+', '-']).parse().ok()?;
+    let offset = sign * (zone / 100 * 60 + zone % 100);
+
+    // Days since the epoch for a proleptic Gregorian date
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset * 60;
+    Some((seconds, offset as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "From 1234567890abcdef1234567890abcdef12345678 Mon Sep 17 00:00:00 2001\n\
+From: Ada Lovelace <ada@example.com>\n\
+Date: Tue, 4 Jun 2024 10:15:00 +0200\n\
+Subject: [PATCH 2/3] Fix the parser\n\
+\x20for long lines\n\
+\n\
+Handle lines over 4k.\n\
+---\n\
+ src/lib.rs | 2 +-\n\
+ 1 file changed, 1 insertion(+), 1 deletion(-)\n\
+\n\
+diff --git a/src/lib.rs b/src/lib.rs\n\
+--- a/src/lib.rs\n\
++++ b/src/lib.rs\n\
+@@ -1 +1 @@\n\
+-old\n\
++new\n\
+-- \n\
+2.45.0\n\
+\n";
+
+    #[test]
+    fn test_parse_patch() {
+        let messages = split_mailbox(PATCH);
+        assert_eq!(messages.len(), 1);
+        let patch = parse_patch(messages[0]).unwrap().unwrap();
+        assert_eq!(patch.author_name, "Ada Lovelace");
+        assert_eq!(patch.author_email, "ada@example.com");
+        assert_eq!(patch.message, "Fix the parser for long lines\n\nHandle lines over 4k.\n");
+        assert!(patch.diff.starts_with("diff --git") && patch.diff.ends_with("+new\n"));
+        assert_eq!(patch.time, Some((1_717_488_900, 120)));
+        assert_eq!(strip_patch_prefix("[RFC PATCH v2 1/2] Add x"), "Add x");
+        assert_eq!(strip_patch_prefix("[ci] Add x"), "[ci] Add x");
+    }
+
+    #[test]
+    fn test_split_mailbox_body_from_line() {
+        let body_from = PATCH.replace("Handle lines over 4k.\n", "From the docs: lines can be long.\n");
+        let series = format!("{}{}", body_from, PATCH);
+        let messages = split_mailbox(&series);
+        assert_eq!(messages.len(), 2);
+        let patch = parse_patch(messages[0]).unwrap().unwrap();
+        assert_eq!(patch.message, "Fix the parser for long lines\n\nFrom the docs: lines can be long.\n");
+    }
+}
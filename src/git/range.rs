@@ -0,0 +1,65 @@
+//! Revision ranges
+//!
+//! The `A..B` and `A...B` forms `git diff` takes to name both sides of a
+//! comparison: the second diffs from where the two histories forked, so
+//! only B's side of the divergence shows. A branch is diffed against its
+//! base that way too unless asked not to.
+
+use anyhow::{Context, Result};
+use super::repo::{RepoPath, open_repo};
+
+/// Old and new revision of a comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevRange {
+    /// Old side as written
+    pub old: String,
+    /// New side as written
+    pub new: String,
+    /// Whether the diff starts from the merge base of both sides (`A...B`)
+    pub merge_base: bool,
+}
+
+impl RevRange {
+    /// Parse `A..B` or `A...B`, None when `spec` is neither
+    ///
+    /// An empty side means HEAD, as in git.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (old, new, merge_base) = match spec.split_once("...") {
+            Some((old, new)) => (old, new, true),
+            None => {
+                let (old, new) = spec.split_once("..")?;
+                (old, new, false)
+            }
+        };
+        if old.is_empty() && new.is_empty() {
+            return None;
+        }
+        let side = |rev: &str| if rev.is_empty() { "HEAD".to_string() } else { rev.to_string() };
+        Some(Self { old: side(old), new: side(new), merge_base })
+    }
+}
+
+/// Full hash of the best common ancestor of two revisions
+pub fn merge_base(repo_path: &RepoPath, one: &str, two: &str) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+    let one = repo.revparse_single(one)?.peel_to_commit()?.id();
+    let two = repo.revparse_single(two)?.peel_to_commit()?.id();
+    let base = repo.merge_base(one, two).context("The revisions have no common ancestor")?;
+    Ok(base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let range = |old: &str, new: &str, merge_base| RevRange { old: old.to_string(), new: new.to_string(), merge_base };
+        assert_eq!(RevRange::parse("main..feature"), Some(range("main", "feature", false)));
+        assert_eq!(RevRange::parse("origin/main...HEAD~2"), Some(range("origin/main", "HEAD~2", true)));
+        assert_eq!(RevRange::parse("v1.0.."), Some(range("v1.0", "HEAD", false)));
+        assert_eq!(RevRange::parse("...feature"), Some(range("HEAD", "feature", true)));
+        assert_eq!(RevRange::parse("main"), None);
+        assert_eq!(RevRange::parse(".."), None);
+    }
+}
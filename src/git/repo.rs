@@ -0,0 +1,203 @@
+//! Repository location
+//!
+//! Opens repositories by discovery from a path, unless an explicit git
+//! directory and work tree were given (`--git-dir`/`--work-tree` or
+//! `GIT_DIR`/`GIT_WORK_TREE`), as with bare dotfile repos.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// Explicit git directory and work tree
+#[derive(Debug, Clone, PartialEq)]
+struct RepoLocation {
+    git_dir: PathBuf,
+    work_tree: PathBuf,
+}
+
+/// A path to open repositories from, with any explicit location
+///
+/// Dereferences to the path, so it can be joined and displayed like one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoPath {
+    path: PathBuf,
+    location: Option<RepoLocation>,
+}
+
+impl RepoPath {
+    /// Resolve an explicit git directory and work tree for `path`
+    ///
+    /// Missing values fall back to `GIT_DIR` and `GIT_WORK_TREE`. Like git,
+    /// a git directory without a work tree uses the repository's configured
+    /// work tree, or else the current directory. When either is set the
+    /// result points at the work tree; otherwise it is `path` and
+    /// repositories are found by discovery.
+    pub fn resolve(
+        git_dir: Option<PathBuf>,
+        work_tree: Option<PathBuf>,
+        path: &Path,
+    ) -> Result<Self> {
+        let git_dir = git_dir.or_else(|| env::var_os("GIT_DIR").map(PathBuf::from));
+        let work_tree = work_tree.or_else(|| env::var_os("GIT_WORK_TREE").map(PathBuf::from));
+        if git_dir.is_none() && work_tree.is_none() {
+            return Ok(Self::from(path));
+        }
+
+        let repo = match &git_dir {
+            Some(dir) => Repository::open(dir)
+                .with_context(|| format!("Failed to open git directory '{}'", dir.display()))?,
+            None => Repository::discover(path).context("Failed to discover git repository")?,
+        };
+        let work_tree = match work_tree {
+            Some(dir) => dir,
+            None => match repo.workdir() {
+                Some(dir) => dir.to_path_buf(),
+                None => env::current_dir()?,
+            },
+        };
+        let work_tree = work_tree.canonicalize()
+            .with_context(|| format!("Invalid work tree '{}'", work_tree.display()))?;
+
+        Ok(Self {
+            path: work_tree.clone(),
+            location: Some(RepoLocation {
+                git_dir: repo.path().to_path_buf(),
+                work_tree,
+            }),
+        })
+    }
+
+    /// Another path, opened with the same explicit location
+    pub fn with_path(&self, path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), location: self.location.clone() }
+    }
+}
+
+impl From<PathBuf> for RepoPath {
+    fn from(path: PathBuf) -> Self {
+        Self { path, location: None }
+    }
+}
+
+impl From<&Path> for RepoPath {
+    fn from(path: &Path) -> Self {
+        Self::from(path.to_path_buf())
+    }
+}
+
+impl Deref for RepoPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for RepoPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Open the repository containing `path`
+///
+/// Paths inside the configured work tree open the configured git
+/// directory, unless a repository nested deeper in the work tree holds
+/// them; anything else is found by discovery.
+pub fn open_repo(path: &RepoPath) -> Result<Repository> {
+    let discovered = Repository::discover(&path.path);
+    if let Some(location) = &path.location
+        && path.canonicalize().is_ok_and(|p| p.starts_with(&location.work_tree))
+        && !discovered.as_ref().is_ok_and(|repo| is_nested(repo, location))
+    {
+        let repo = Repository::open(&location.git_dir)
+            .context("Failed to open git repository")?;
+        repo.set_workdir(&location.work_tree, false)?;
+        return Ok(repo);
+    }
+
+    discovered.context("Failed to discover git repository")
+}
+
+/// Whether `repo` is a separate repository below the configured work tree
+fn is_nested(repo: &Repository, location: &RepoLocation) -> bool {
+    let Some(workdir) = repo.workdir().and_then(|dir| dir.canonicalize().ok()) else {
+        return false;
+    };
+    workdir != location.work_tree
+        && workdir.starts_with(&location.work_tree)
+        && repo.path().canonicalize().ok() != location.git_dir.canonicalize().ok()
+}
+
+/// Directory for gv's own files: `gv/` in the common git directory
+///
+/// Worktrees of one repository share it.
+pub fn state_dir(repo_path: &RepoPath) -> Result<PathBuf> {
+    let repo = open_repo(repo_path)?;
+    Ok(repo.commondir().join("gv"))
+}
+
+/// Read a JSON file from the state directory
+///
+/// A missing or unreadable file reads as the default.
+pub fn load_state<T: DeserializeOwned + Default>(repo_path: &RepoPath, file: &str) -> T {
+    state_dir(repo_path)
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(file)).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Write `value` as JSON to a file in the state directory
+pub fn save_state<T: Serialize>(repo_path: &RepoPath, file: &str, value: &T) -> Result<()> {
+    let dir = state_dir(repo_path)?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(file);
+    let json = serde_json::to_string_pretty(value)?;
+    fs::write(&path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_repo_nested() {
+        let base = std::env::temp_dir().join(format!("gv-repo-nested-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let home = base.join("home");
+        let project = home.join("src/project");
+        std::fs::create_dir_all(project.join("lib")).unwrap();
+        std::fs::create_dir_all(home.join("notes")).unwrap();
+        let dotfiles = Repository::init_bare(base.join("dotfiles")).unwrap();
+        let nested = Repository::init(&project).unwrap();
+        let repo_path = RepoPath::resolve(
+            Some(dotfiles.path().to_path_buf()),
+            Some(home.clone()),
+            &home,
+        ).unwrap();
+        let work_tree = home.canonicalize().unwrap();
+
+        // Plain work tree paths use the configured git directory
+        let repo = open_repo(&repo_path.with_path(home.join("notes"))).unwrap();
+        assert_eq!(repo.path(), dotfiles.path());
+        assert_eq!(repo.workdir().unwrap().canonicalize().unwrap(), work_tree);
+
+        // A closer .git wins inside its own work tree
+        let repo = open_repo(&repo_path.with_path(project.join("lib"))).unwrap();
+        assert_eq!(repo.path().canonicalize().unwrap(), nested.path().canonicalize().unwrap());
+
+        // Without an override everything is discovered, even alongside one
+        let repo = open_repo(&RepoPath::from(project.clone())).unwrap();
+        assert_eq!(repo.path().canonicalize().unwrap(), nested.path().canonicalize().unwrap());
+        let repo = open_repo(&repo_path).unwrap();
+        assert_eq!(repo.path(), dotfiles.path());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,96 @@
+//! Stash listing
+//!
+//! Lists the `git stash` entries, newest first, for the stash browser.
+//! Their changes are diffed by `compute_stash_diff`.
+
+use anyhow::Result;
+use super::repo::{RepoPath, open_repo};
+
+/// A `git stash` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stash {
+    /// Position in the stash, 0 being the newest (`stash@{0}`)
+    pub index: usize,
+    /// Stash message, e.g. `WIP on main: 1a2b3c4 Fix tests`
+    pub message: String,
+    /// Abbreviated hash of the stash commit
+    pub hash: String,
+    /// Full hash of the stash commit
+    pub full_hash: String,
+    /// When it was stashed, in seconds since the epoch
+    pub time: i64,
+}
+
+impl Stash {
+    /// The name git knows it by, `stash@{N}`
+    pub fn name(&self) -> String {
+        format!("stash@{{{}}}", self.index)
+    }
+}
+
+/// List the stash entries, newest first
+///
+/// The stash is shared by every worktree of the repository.
+pub fn list_stashes(repo_path: &RepoPath) -> Result<Vec<Stash>> {
+    let mut repo = open_repo(repo_path)?;
+    let mut entries = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        entries.push((index, message.to_string(), *oid));
+        true
+    })?;
+
+    Ok(entries
+        .into_iter()
+        .map(|(index, message, oid)| {
+            let full_hash = oid.to_string();
+            Stash {
+                index,
+                message,
+                hash: full_hash[..7].to_string(),
+                time: repo.find_commit(oid).map_or(0, |c| c.time().seconds()),
+                full_hash,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{compute_stash_diff, DiffAlgorithm};
+    use git2::{Repository, Signature, StashFlags};
+    use std::path::Path;
+
+    #[test]
+    fn test_list_and_diff_stashes() {
+        let dir = RepoPath::from(std::env::temp_dir().join(format!("gv-stash-test-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut repo = Repository::init(&dir).unwrap();
+        let sig = Signature::now("gv", "gv@example.com").unwrap();
+        std::fs::write(dir.join("a.txt"), "one\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[]).unwrap();
+        drop(tree);
+
+        std::fs::write(dir.join("a.txt"), "two\n").unwrap();
+        repo.stash_save(&sig, "first", None).unwrap();
+        std::fs::write(dir.join("a.txt"), "three\n").unwrap();
+        std::fs::write(dir.join("new.txt"), "untracked\n").unwrap();
+        repo.stash_save(&sig, "second", Some(StashFlags::INCLUDE_UNTRACKED)).unwrap();
+
+        let stashes = list_stashes(&dir).unwrap();
+        let messages: Vec<&str> = stashes.iter().map(|s| s.message.as_str()).collect();
+        assert!(messages[0].ends_with("second") && messages[1].ends_with("first"), "{:?}", messages);
+        assert_eq!(stashes[1].name(), "stash@{1}");
+
+        // Untracked files stashed along come out as added
+        let diffs = compute_stash_diff(&dir, &stashes[0].full_hash, 3, false, DiffAlgorithm::default()).unwrap();
+        let paths: Vec<&str> = diffs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "new.txt"]);
+        assert!(diffs[0].hunks[0].lines.iter().any(|l| l.content.contains("three")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
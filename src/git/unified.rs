@@ -0,0 +1,138 @@
+//! Unified diff text
+//!
+//! Turns diffs back into the patch text `git diff` prints and `git apply`
+//! reads, with the hunks as currently shown, so a file or a single hunk can
+//! be pasted into a review comment or applied elsewhere.
+
+use super::{ChangeKind, FileDiff, Hunk, LineType};
+
+/// The file's `diff --git`, rename and `---`/`+++` lines
+fn file_header(diff: &FileDiff) -> String {
+    let old_path = diff.old_path.as_deref().unwrap_or(&diff.path);
+    let mut header = format!("diff --git a/{} b/{}\n", old_path, diff.path);
+    // git apply needs these to create or delete a file; the mode isn't tracked, so assume a plain file
+    match diff.change {
+        ChangeKind::Added => header.push_str("new file mode 100644\n"),
+        ChangeKind::Deleted => header.push_str("deleted file mode 100644\n"),
+        _ => {}
+    }
+    if old_path != diff.path {
+        header.push_str(&format!("rename from {}\nrename to {}\n", old_path, diff.path));
+    }
+    let old = match diff.change {
+        ChangeKind::Added => "/dev/null".to_string(),
+        _ => format!("a/{}", old_path),
+    };
+    let new = match diff.change {
+        ChangeKind::Deleted => "/dev/null".to_string(),
+        _ => format!("b/{}", diff.path),
+    };
+    header.push_str(&format!("--- {}\n+++ {}\n", old, new));
+    header
+}
+
+/// A hunk's `@@` line and body
+fn hunk_text(hunk: &Hunk) -> String {
+    let mut text = if hunk.header.starts_with("@@") {
+        hunk.header.clone()
+    } else {
+        format!("@@ -{},{} +{},{} @@", hunk.old_start, hunk.old_count, hunk.new_start, hunk.new_count)
+    };
+    text.push('\n');
+    for line in &hunk.lines {
+        let prefix = match line.line_type {
+            LineType::Context => ' ',
+            LineType::Added => '+',
+            LineType::Removed => '-',
+            LineType::Header => continue,
+        };
+        text.push(prefix);
+        text.push_str(&line.content);
+        text.push('\n');
+    }
+    text
+}
+
+/// The whole file's diff as a patch, or None for a binary file
+pub fn file_patch(diff: &FileDiff) -> Option<String> {
+    if diff.is_binary {
+        return None;
+    }
+    let mut patch = file_header(diff);
+    for hunk in &diff.hunks {
+        patch.push_str(&hunk_text(hunk));
+    }
+    Some(patch)
+}
+
+/// Several files' diffs as one patch, the way `git diff` prints them
+///
+/// Binary files get git's `Binary files … differ` note in place of hunks.
+pub fn diff_patch<'a>(diffs: impl IntoIterator<Item = &'a FileDiff>) -> String {
+    let mut patch = String::new();
+    for diff in diffs {
+        match file_patch(diff) {
+            Some(text) => patch.push_str(&text),
+            None => {
+                let old_path = diff.old_path.as_deref().unwrap_or(&diff.path);
+                patch.push_str(&format!("diff --git a/{} b/{}\n", old_path, diff.path));
+                patch.push_str(&format!("Binary files a/{} and b/{} differ\n", old_path, diff.path));
+            }
+        }
+    }
+    patch
+}
+
+/// One hunk of the file's diff as a patch, file header included
+pub fn hunk_patch(diff: &FileDiff, hunk: usize) -> Option<String> {
+    let hunk = diff.hunks.get(hunk).filter(|_| !diff.is_binary)?;
+    Some(file_header(diff) + &hunk_text(hunk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::DiffLine;
+
+    #[test]
+    fn test_patch_text() {
+        let line = |line_type, content: &str| DiffLine { line_type, content: content.to_string(), old_lineno: None, new_lineno: None, moved: false };
+        let hunk = |header: &str, start| Hunk {
+            old_start: start,
+            old_count: 2,
+            new_start: start,
+            new_count: 2,
+            header: header.to_string(),
+            lines: vec![line(LineType::Context, "a"), line(LineType::Removed, "b"), line(LineType::Added, "c")],
+        };
+        let diff = FileDiff {
+            path: "src/new.rs".to_string(),
+            old_path: Some("src/old.rs".to_string()),
+            old_content: None,
+            new_content: None,
+            added: 2,
+            removed: 2,
+            hunks: vec![hunk("@@ -1,2 +1,2 @@ fn main()", 1), hunk("", 10)],
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Renamed,
+        };
+
+        let header = "diff --git a/src/old.rs b/src/new.rs\nrename from src/old.rs\nrename to src/new.rs\n--- a/src/old.rs\n+++ b/src/new.rs\n";
+        assert_eq!(hunk_patch(&diff, 1).unwrap(), format!("{}@@ -10,2 +10,2 @@\n a\n-b\n+c\n", header));
+        assert_eq!(
+            file_patch(&diff).unwrap(),
+            format!("{}@@ -1,2 +1,2 @@ fn main()\n a\n-b\n+c\n@@ -10,2 +10,2 @@\n a\n-b\n+c\n", header),
+        );
+        assert_eq!(hunk_patch(&diff, 2), None);
+
+        let binary = FileDiff { is_binary: true, old_path: None, path: "logo.png".to_string(), ..diff.clone() };
+        assert_eq!(
+            diff_patch([&diff, &binary]),
+            format!("{}diff --git a/logo.png b/logo.png\nBinary files a/logo.png and b/logo.png differ\n", file_patch(&diff).unwrap()),
+        );
+    }
+}
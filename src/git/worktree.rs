@@ -1,11 +1,12 @@
 //! Git worktree discovery and management
 //!
 //! Handles listing worktrees, finding the current worktree,
-//! and detecting the main branch.
+//! detecting the main branch, and resolving the base revision.
 
 use std::path::{Path, PathBuf};
-use anyhow::{Context, Result};
-use git2::Repository;
+use anyhow::{bail, Context, Result};
+use git2::{BranchType, Repository, StatusOptions, WorktreeAddOptions, WorktreePruneOptions};
+use super::repo::{RepoPath, open_repo};
 
 /// Represents a git worktree
 #[derive(Debug, Clone)]
@@ -22,24 +23,29 @@ pub struct Worktree {
 ///
 /// Returns a vector of worktrees including the main worktree
 /// and any linked worktrees.
-pub fn list_worktrees(repo_path: &Path) -> Result<Vec<Worktree>> {
-    let repo = Repository::discover(repo_path)
-        .context("Failed to discover git repository")?;
+pub fn list_worktrees(repo_path: &RepoPath) -> Result<Vec<Worktree>> {
+    let repo = open_repo(repo_path)?;
 
     let mut worktrees = Vec::new();
 
     // Get the common .git directory (shared by all worktrees)
-    // The parent of commondir is the main worktree
+    // The parent of commondir is the main worktree, unless this is the
+    // main repository itself, whose work tree may live elsewhere
     let commondir = repo.commondir();
-    let main_workdir = commondir.parent();
+    let main_workdir: Option<PathBuf> = if repo.path() == commondir {
+        repo.workdir().map(|p| p.components().collect())
+    } else {
+        commondir.parent().map(Path::to_path_buf)
+    };
+    let main_workdir = main_workdir.as_deref();
 
     // Add the main worktree
     if let Some(main_path) = main_workdir {
         // Check if we're already in the main worktree
-        let is_main = repo.workdir().map_or(false, |wd| wd == main_path);
+        let is_main = repo.workdir() == Some(main_path);
         let branch = if is_main {
             get_current_branch(&repo)
-        } else if let Ok(main_repo) = Repository::open(main_path) {
+        } else if let Ok(main_repo) = open_repo(&repo_path.with_path(main_path)) {
             get_current_branch(&main_repo)
         } else {
             None
@@ -54,7 +60,7 @@ pub fn list_worktrees(repo_path: &Path) -> Result<Vec<Worktree>> {
     // Open main repository to get linked worktrees list
     // (linked worktrees can only be listed from the main repo)
     let main_repo = if let Some(main_path) = main_workdir {
-        Repository::open(main_path).ok()
+        open_repo(&repo_path.with_path(main_path)).ok()
     } else {
         None
     };
@@ -81,6 +87,181 @@ pub fn list_worktrees(repo_path: &Path) -> Result<Vec<Worktree>> {
     Ok(worktrees)
 }
 
+/// Working state of a worktree, for the dashboard
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeStatus {
+    /// Files with uncommitted changes, including untracked ones
+    pub changed: usize,
+    /// Commits on HEAD that the base doesn't have
+    pub ahead: usize,
+    /// Commits on the base that HEAD doesn't have
+    pub behind: usize,
+    /// Abbreviated hash of HEAD
+    pub head: Option<String>,
+    /// Subject of the HEAD commit
+    pub subject: String,
+    /// Commit time of HEAD, in seconds since the epoch
+    pub time: i64,
+    /// Commits ahead of and behind the branch's upstream, if it has one
+    pub upstream: Option<(usize, usize)>,
+}
+
+/// Read a worktree's dirty state, HEAD commit and divergence from `base`
+pub fn worktree_status(path: &RepoPath, base: &str) -> Result<WorktreeStatus> {
+    let repo = open_repo(path)?;
+    let mut status = WorktreeStatus {
+        changed: uncommitted_changes(&repo)?,
+        ..Default::default()
+    };
+
+    let Ok(head) = repo.head().and_then(|h| h.peel_to_commit()) else {
+        return Ok(status);
+    };
+    status.head = Some(head.id().to_string()[..7].to_string());
+    status.subject = head.summary().unwrap_or("").to_string();
+    status.time = head.time().seconds();
+
+    if let Ok(base) = repo.revparse_single(base).and_then(|o| o.peel_to_commit()) {
+        let (ahead, behind) = repo.graph_ahead_behind(head.id(), base.id())?;
+        status.ahead = ahead;
+        status.behind = behind;
+    }
+
+    let upstream = repo
+        .head()
+        .ok()
+        .filter(|h| h.is_branch())
+        .and_then(|h| git2::Branch::wrap(h).upstream().ok())
+        .and_then(|upstream| upstream.get().target());
+    if let Some(upstream) = upstream {
+        status.upstream = Some(repo.graph_ahead_behind(head.id(), upstream)?);
+    }
+
+    Ok(status)
+}
+
+/// Number of uncommitted changes, untracked files included
+fn uncommitted_changes(repo: &Repository) -> Result<usize> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).include_ignored(false);
+    Ok(repo.statuses(Some(&mut opts))?.len())
+}
+
+/// Remove a linked worktree's directory and administrative files
+///
+/// The branch is kept. Refuses the main worktree, worktrees with
+/// uncommitted changes, and worktrees whose status can't be read.
+pub fn remove_worktree(repo_path: &RepoPath, worktree_path: &Path) -> Result<()> {
+    let main_repo = open_main_repo(repo_path)?;
+    let target = worktree_path.canonicalize().unwrap_or_else(|_| worktree_path.to_path_buf());
+    let names = main_repo.worktrees()?;
+    let worktree = names
+        .iter()
+        .flatten()
+        .filter_map(|name| main_repo.find_worktree(name).ok())
+        .find(|wt| wt.path().canonicalize().is_ok_and(|p| p == target));
+    let Some(worktree) = worktree else {
+        bail!("{} is the main worktree and can't be removed", worktree_path.display());
+    };
+
+    let dirty = open_repo(&repo_path.with_path(worktree_path))
+        .and_then(|repo| uncommitted_changes(&repo))
+        .with_context(|| format!("Can't read the status of {}", worktree_path.display()))?;
+    if dirty > 0 {
+        bail!("{} has {} uncommitted change(s)", worktree_path.display(), dirty);
+    }
+
+    worktree
+        .prune(Some(WorktreePruneOptions::new().valid(true).working_tree(true)))
+        .with_context(|| format!("Failed to remove {}", worktree_path.display()))
+}
+
+/// Add a linked worktree at `path` with `branch` checked out
+///
+/// An existing local branch is checked out, unless another worktree already
+/// has it; otherwise the branch is created from HEAD. A relative `path` is
+/// taken from the root of the worktree at `repo_path`. Returns the new
+/// worktree's path.
+pub fn create_worktree(repo_path: &RepoPath, branch: &str, path: &Path) -> Result<PathBuf> {
+    let branch = branch.trim();
+    if branch.is_empty() {
+        bail!("No branch name given");
+    }
+    let root = repo_root(repo_path)?;
+    let path = root.join(path);
+    if path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+        bail!("{} isn't a directory name", path.display());
+    };
+    if let Some(other) = list_worktrees(repo_path)?.into_iter().find(|w| w.branch.as_deref() == Some(branch)) {
+        bail!("{} is already checked out at {}", branch, other.path.display());
+    }
+
+    let repo = open_repo(repo_path)?;
+    let (reference, created) = match repo.find_branch(branch, BranchType::Local) {
+        Ok(existing) => (existing, false),
+        Err(_) => {
+            let head = repo.head().and_then(|h| h.peel_to_commit()).context("HEAD has no commit to branch from")?;
+            let new = repo
+                .branch(branch, &head, false)
+                .with_context(|| format!("Can't create branch '{}'", branch))?;
+            (new, true)
+        }
+    };
+
+    let mut opts = WorktreeAddOptions::new();
+    opts.reference(Some(reference.get()));
+    if let Err(e) = repo.worktree(&name, &path, Some(&opts)) {
+        // Don't leave behind a branch nobody asked for without its worktree
+        if created {
+            let _ = reference.into_reference().delete();
+        }
+        return Err(e).with_context(|| format!("Failed to add a worktree at {}", path.display()));
+    }
+    Ok(path.canonicalize().unwrap_or(path))
+}
+
+/// Names of linked worktrees whose directories are gone
+///
+/// Locked worktrees are left out, as git keeps them for a reason.
+pub fn stale_worktrees(repo_path: &RepoPath) -> Result<Vec<String>> {
+    let main_repo = open_main_repo(repo_path)?;
+    let names = main_repo.worktrees()?;
+    Ok(names
+        .iter()
+        .flatten()
+        .filter(|name| main_repo.find_worktree(name).is_ok_and(|wt| wt.is_prunable(None).unwrap_or(false)))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Remove the administrative files of stale worktrees, like `git worktree prune`
+///
+/// Returns the names of the worktrees pruned.
+pub fn prune_worktrees(repo_path: &RepoPath) -> Result<Vec<String>> {
+    let main_repo = open_main_repo(repo_path)?;
+    let stale = stale_worktrees(repo_path)?;
+    for name in &stale {
+        main_repo
+            .find_worktree(name)
+            .and_then(|wt| wt.prune(None))
+            .with_context(|| format!("Failed to prune worktree {}", name))?;
+    }
+    Ok(stale)
+}
+
+/// Open the main worktree's repository, which knows about the linked ones
+fn open_main_repo(repo_path: &RepoPath) -> Result<Repository> {
+    let repo = open_repo(repo_path)?;
+    let main_path = repo.commondir().parent().map(Path::to_path_buf);
+    match main_path {
+        Some(path) if repo.path() != repo.commondir() => open_repo(&repo_path.with_path(path)),
+        _ => Ok(repo),
+    }
+}
+
 /// Find which worktree contains the given path
 ///
 /// Returns the index of the matching worktree in the list,
@@ -91,12 +272,12 @@ pub fn find_current_worktree(worktrees: &mut [Worktree], current_path: &Path) ->
     let mut best_match: Option<(usize, usize)> = None; // (index, path_len)
 
     for (i, wt) in worktrees.iter().enumerate() {
-        if let Ok(wt_canonical) = wt.path.canonicalize() {
-            if canonical.starts_with(&wt_canonical) {
-                let len = wt_canonical.as_os_str().len();
-                if best_match.map_or(true, |(_, best_len)| len > best_len) {
-                    best_match = Some((i, len));
-                }
+        if let Ok(wt_canonical) = wt.path.canonicalize()
+            && canonical.starts_with(&wt_canonical)
+        {
+            let len = wt_canonical.as_os_str().len();
+            if best_match.is_none_or(|(_, best_len)| len > best_len) {
+                best_match = Some((i, len));
             }
         }
     }
@@ -109,13 +290,59 @@ pub fn find_current_worktree(worktrees: &mut [Worktree], current_path: &Path) ->
     }
 }
 
-/// Get the main branch name (main or master)
+/// Find a worktree by branch name or directory name
 ///
-/// Checks for origin/main first, then falls back to origin/master.
-/// If neither exists, defaults to "main".
-pub fn get_main_branch(repo_path: &Path) -> Result<String> {
-    let repo = Repository::discover(repo_path)
-        .context("Failed to discover git repository")?;
+/// Branch matches win over directory names, so `feature` finds the worktree
+/// with `feature` checked out even if another directory is called `feature`.
+pub fn find_worktree(repo_path: &RepoPath, name: &str) -> Result<PathBuf> {
+    let worktrees = list_worktrees(repo_path)?;
+
+    let by_branch = worktrees.iter().find(|w| w.branch.as_deref() == Some(name));
+    let by_dir = || worktrees.iter().find(|w| w.path.file_name().is_some_and(|f| f == name));
+    if let Some(worktree) = by_branch.or_else(by_dir) {
+        return Ok(worktree.path.clone());
+    }
+
+    let available: Vec<String> = worktrees
+        .iter()
+        .map(|w| match &w.branch {
+            Some(branch) => branch.clone(),
+            None => w.path.display().to_string(),
+        })
+        .collect();
+    bail!("No worktree named '{}' (available: {})", name, available.join(", "))
+}
+
+/// Get the root directory of the worktree containing `repo_path`
+pub fn repo_root(repo_path: &RepoPath) -> Result<PathBuf> {
+    let repo = open_repo(repo_path)?;
+    repo.workdir()
+        .map(|p| p.to_path_buf())
+        .context("Repository has no working directory")
+}
+
+/// Get the main branch name (the default branch, main or master)
+///
+/// Prefers the current branch's upstream (`branch.<name>.merge`), so forks
+/// and non-standard workflows compare against the right branch. Otherwise
+/// uses the remote's default branch (`origin/HEAD`), then checks for
+/// origin/main, then falls back to origin/master.
+/// If none exists, defaults to "main".
+pub fn get_main_branch(repo_path: &RepoPath) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+
+    if let Some(upstream) = upstream_base(&repo) {
+        return Ok(upstream);
+    }
+
+    // The remote's default branch, as recorded by clone or `git remote set-head`
+    if let Ok(origin_head) = repo.find_reference("refs/remotes/origin/HEAD")
+        && let Some(target) = origin_head.symbolic_target()
+        && let Some(branch) = target.strip_prefix("refs/remotes/")
+        && repo.find_reference(target).is_ok()
+    {
+        return Ok(branch.to_string());
+    }
 
     // Try origin/main first
     if repo.find_reference("refs/remotes/origin/main").is_ok() {
@@ -140,6 +367,83 @@ pub fn get_main_branch(repo_path: &Path) -> Result<String> {
     Ok("main".to_string())
 }
 
+/// Upstream of the current branch, when it is a base rather than a push target
+///
+/// An upstream with the branch's own name (`feature` tracking
+/// `origin/feature`) only says where the branch is pushed, so it is skipped.
+fn upstream_base(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let local = git2::Branch::wrap(head);
+    let local_name = local.name().ok()??.to_string();
+    let upstream = local.upstream().ok()?;
+    let upstream_name = upstream.name().ok()??;
+
+    let branch_part = upstream_name.split_once('/').map_or(upstream_name, |(_, b)| b);
+    if upstream.get().is_remote() && branch_part == local_name {
+        return None;
+    }
+    Some(upstream_name.to_string())
+}
+
+/// Resolve a base revision to the short SHA of its commit
+///
+/// Accepts anything `git rev-parse` does: branches, tags, SHAs,
+/// `HEAD~5`, `@{upstream}`.
+pub fn resolve_base(repo_path: &RepoPath, base: &str) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+    let commit = repo.revparse_single(base)
+        .and_then(|obj| obj.peel_to_commit())
+        .with_context(|| format!("'{}' is not a valid revision (expected a branch, tag, SHA or expression like HEAD~5)", base))?;
+    let short = commit.as_object().short_id()?;
+    Ok(short.as_str().unwrap_or_default().to_string())
+}
+
+/// Resolve a revision to the full hash of its commit
+pub fn resolve_commit(repo_path: &RepoPath, rev: &str) -> Result<String> {
+    let repo = open_repo(repo_path)?;
+    let commit = repo.revparse_single(rev)
+        .and_then(|obj| obj.peel_to_commit())
+        .with_context(|| format!("'{}' is not a valid revision", rev))?;
+    Ok(commit.id().to_string())
+}
+
+/// Read a string value from the repository's git config
+pub fn config_string(repo_path: &RepoPath, key: &str) -> Option<String> {
+    let repo = open_repo(repo_path).ok()?;
+    repo.config().ok()?.get_string(key).ok()
+}
+
+/// Read every value of a multi-valued key from the repository's git config
+pub fn config_strings(repo_path: &RepoPath, key: &str) -> Vec<String> {
+    let Ok(config) = open_repo(repo_path).and_then(|repo| Ok(repo.config()?)) else {
+        return Vec::new();
+    };
+    let mut values = Vec::new();
+    if let Ok(mut entries) = config.multivar(key, None) {
+        while let Some(Ok(entry)) = entries.next() {
+            if let Some(value) = entry.value() {
+                values.push(value.to_string());
+            }
+        }
+    }
+    values
+}
+
+/// Read a boolean value from the repository's git config
+pub fn config_bool(repo_path: &RepoPath, key: &str) -> Option<bool> {
+    let repo = open_repo(repo_path).ok()?;
+    repo.config().ok()?.get_bool(key).ok()
+}
+
+/// Read an integer value from the repository's git config
+pub fn config_i64(repo_path: &RepoPath, key: &str) -> Option<i64> {
+    let repo = open_repo(repo_path).ok()?;
+    repo.config().ok()?.get_i64(key).ok()
+}
+
 /// Get the current branch name from a repository
 fn get_current_branch(repo: &Repository) -> Option<String> {
     repo.head().ok().and_then(|head| {
@@ -177,4 +481,67 @@ mod tests {
         let current = env::current_dir().unwrap();
         let _ = find_current_worktree(&mut worktrees, &current);
     }
+
+    #[test]
+    fn test_create_and_prune_worktree() {
+        let dir = env::temp_dir().join(format!("gv-create-worktree-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let main = RepoPath::from(dir.join("main"));
+        let repo = Repository::init(&main).unwrap();
+        let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[]).unwrap();
+
+        let path = create_worktree(&main, "feature", Path::new("../feature")).unwrap();
+        assert!(path.join(".git").exists());
+        let worktrees = list_worktrees(&main).unwrap();
+        assert!(worktrees.iter().any(|w| w.branch.as_deref() == Some("feature")));
+
+        // A branch can only be checked out once, and the path must be new
+        let err = create_worktree(&main, "feature", Path::new("../other")).unwrap_err();
+        assert!(err.to_string().contains("already checked out"), "{}", err);
+        assert!(create_worktree(&main, "fresh", Path::new("../feature")).is_err());
+        assert!(repo.find_branch("fresh", BranchType::Local).is_err());
+
+        // Once its directory is gone, the worktree is stale until pruned
+        assert!(stale_worktrees(&main).unwrap().is_empty());
+        std::fs::remove_dir_all(&path).unwrap();
+        assert_eq!(stale_worktrees(&main).unwrap(), vec!["feature"]);
+        assert_eq!(prune_worktrees(&main).unwrap(), vec!["feature"]);
+        assert!(stale_worktrees(&main).unwrap().is_empty());
+        assert!(repo.worktrees().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_remove_worktree() {
+        let dir = env::temp_dir().join(format!("gv-remove-worktree-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let main = RepoPath::from(dir.join("main"));
+        let repo = Repository::init(&main).unwrap();
+        let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial", &tree, &[]).unwrap();
+        let path = create_worktree(&main, "feature", Path::new("../feature")).unwrap();
+
+        // Uncommitted work is kept
+        std::fs::write(path.join("draft.txt"), "wip\n").unwrap();
+        let err = remove_worktree(&main, &path).unwrap_err();
+        assert!(err.to_string().contains("1 uncommitted change"), "{}", err);
+        assert!(path.join("draft.txt").exists());
+        std::fs::remove_file(path.join("draft.txt")).unwrap();
+
+        // So is everything when the status can't be read
+        let index = repo.path().join("worktrees/feature/index");
+        std::fs::write(&index, "not an index").unwrap();
+        let err = remove_worktree(&main, &path).unwrap_err();
+        assert!(err.to_string().contains("Can't read the status"), "{}", err);
+        assert!(path.exists());
+
+        std::fs::remove_file(&index).unwrap();
+        remove_worktree(&main, &path).unwrap();
+        assert!(!path.exists());
+        assert!(repo.find_branch("feature", BranchType::Local).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
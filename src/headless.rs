@@ -0,0 +1,100 @@
+//! Headless driver
+//!
+//! Runs an [`App`] without a terminal: key presses go in as synthetic
+//! events and each frame is drawn into ratatui's `TestBackend`, so tests
+//! can check what a reviewer would see and other tools can embed gv's views.
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use vibed::app::{App, ViewOptions};
+//! use vibed::headless::Headless;
+//!
+//! let app = App::new(PathBuf::from(".").into(), Some("main".into()), None, ViewOptions::default())?;
+//! let mut gv = Headless::new(app, 120, 40)?;
+//! gv.keys("n")?;
+//! assert!(gv.screen()?.contains("src/"));
+//! # anyhow::Ok(())
+//! ```
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+use crate::app::App;
+
+/// An [`App`] drawn into an in-memory terminal
+pub struct Headless {
+    app: App,
+    terminal: Terminal<TestBackend>,
+    /// Whether a key asked the app to quit
+    quit: bool,
+}
+
+impl Headless {
+    /// Drive `app` in a terminal of `width` × `height` cells
+    pub fn new(mut app: App, width: u16, height: u16) -> Result<Self> {
+        app.wait_for_diffs();
+        app.wait_for_commits();
+        let terminal = Terminal::new(TestBackend::new(width, height))?;
+        Ok(Self { app, terminal, quit: false })
+    }
+
+    /// The app being driven
+    pub fn app(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Whether a key press has asked the app to quit
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Press a key with modifiers, drawing a frame first as the event loop would
+    ///
+    /// Keys like `n` act on the layout of the last frame, so one is always
+    /// drawn before the press.
+    pub fn press(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        self.draw()?;
+        self.quit |= self.app.press(KeyEvent::new(code, modifiers));
+        Ok(())
+    }
+
+    /// Type each character of `keys` as a plain key press, e.g. `"jjn"`
+    pub fn keys(&mut self, keys: &str) -> Result<()> {
+        for c in keys.chars() {
+            let modifiers = if c.is_ascii_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+            self.press(KeyCode::Char(c), modifiers)?;
+        }
+        Ok(())
+    }
+
+    /// Click the left mouse button on a cell, drawing a frame first
+    pub fn click(&mut self, column: u16, row: u16, modifiers: KeyModifiers) -> Result<()> {
+        self.draw()?;
+        self.app.mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column, row, modifiers });
+        Ok(())
+    }
+
+    /// Draw a frame and return the terminal's cells
+    pub fn draw(&mut self) -> Result<&Buffer> {
+        let app = &mut self.app;
+        self.terminal.draw(|frame| app.draw(frame))?;
+        Ok(self.terminal.backend().buffer())
+    }
+
+    /// Draw a frame and return its text, one line per row with trailing spaces trimmed
+    pub fn screen(&mut self) -> Result<String> {
+        let buffer = self.draw()?;
+        let area = buffer.area;
+        let mut screen = String::new();
+        for y in area.top()..area.bottom() {
+            let mut row = String::new();
+            for x in area.left()..area.right() {
+                row.push_str(buffer[(x, y)].symbol());
+            }
+            screen.push_str(row.trim_end());
+            screen.push('\n');
+        }
+        Ok(screen)
+    }
+}
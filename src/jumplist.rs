@@ -0,0 +1,104 @@
+//! Vim-style jump list
+//!
+//! Records positions before significant jumps so they can be revisited
+//! with Ctrl+o (back) and Ctrl+i (forward).
+
+/// Maximum number of remembered positions
+const MAX_JUMPS: usize = 100;
+
+/// A history of positions with a cursor for back/forward navigation
+#[derive(Debug, Clone)]
+pub struct JumpList<T> {
+    entries: Vec<T>,
+    /// Index of the next entry to move back from; `entries.len()` when at the head
+    index: usize,
+}
+
+impl<T> Default for JumpList<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: 0,
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> JumpList<T> {
+    /// Record a position before jumping away from it
+    ///
+    /// Discards any forward history, like vim.
+    pub fn record(&mut self, position: T) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&position) {
+            self.entries.push(position);
+        }
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// Move back in the list, remembering `current` so forward can return to it
+    pub fn back(&mut self, current: T) -> Option<T> {
+        if self.index == self.entries.len() {
+            if self.entries.last() == Some(&current) {
+                self.index = self.entries.len().saturating_sub(1);
+            } else {
+                self.entries.push(current);
+            }
+        }
+
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        self.entries.get(self.index).cloned()
+    }
+
+    /// Move forward in the list
+    pub fn forward(&mut self) -> Option<T> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        self.entries.get(self.index).cloned()
+    }
+
+    /// Forget all positions
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.record(1);
+        jumps.record(2);
+
+        assert_eq!(jumps.back(3), Some(2));
+        assert_eq!(jumps.back(2), Some(1));
+        assert_eq!(jumps.back(1), None);
+        assert_eq!(jumps.forward(), Some(2));
+        assert_eq!(jumps.forward(), Some(3));
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn test_record_discards_forward_history() {
+        let mut jumps = JumpList::default();
+        jumps.record(1);
+        jumps.record(2);
+        assert_eq!(jumps.back(3), Some(2));
+
+        jumps.record(5);
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(6), Some(5));
+        assert_eq!(jumps.back(5), Some(1));
+    }
+}
@@ -0,0 +1,23 @@
+//! vibed - Terminal UI diff viewer for git worktrees
+//!
+//! The viewer as a library: [`app::App`] holds the review state and
+//! handles keys, [`git`] computes diffs and reads history, and [`ui`]
+//! renders them as ratatui widgets. [`headless::Headless`] drives an app
+//! without a terminal, for UI tests and tools embedding gv's views.
+
+pub mod annotations;
+pub mod app;
+mod clipboard;
+pub mod config;
+mod difftool;
+mod editor;
+pub mod git;
+pub mod headless;
+mod jumplist;
+pub mod mergetool;
+mod notes;
+mod pulls;
+mod review;
+mod session;
+pub mod syntax;
+pub mod ui;
@@ -9,41 +9,508 @@
 //! vibed                    # Run in current directory
 //! vibed /path/to/repo      # Run in specified repository
 //! vibed -b origin/develop  # Use custom base branch
+//! vibed -b v1.2.0          # Diff against a tag (or SHA, HEAD~5, @{upstream})
+//! vibed --no-merge-base    # Diff against the base's tip, its newer commits included
+//! vibed --path src/ui --path '*.md'  # Only the files under src/ui and Markdown at the root
+//! vibed --annotations clippy.sarif  # Show linter findings in the diff
+//! vibed --git-dir ~/.dotfiles --work-tree ~  # Review a bare repo
+//! vibed -w feature-x       # Open the worktree for branch feature-x
+//! vibed show HEAD~2        # Show a single commit against its parent
+//! vibed show v1.0 v1.1     # Show the changes between two revisions
+//! vibed diff a.rs b.rs     # Compare two files (or REV:PATH blobs) side by side
+//! vibed diff old/ new/    # Compare two directory trees file by file
+//! vibed mergetool --resolve src/lib.rs  # Pick sides for the conflicts in a file
+//! vibed --patches outgoing/  # Review a format-patch series without applying it
+//! vibed --unified --context 1  # Start in a compact unified view
+//! vibed --word-diff        # Mark changed words inline, for prose and config
+//! vibed --algorithm patience  # Keep moved blocks whole instead of interleaving them
+//! vibed --light            # Use the light palette and syntax theme on a light terminal
+//! vibed --sidebar bottom   # List files below the diff for full-width hunks
+//! vibed --summary          # Print what was reviewed when quitting
+//! vibed --ansi > review.ansi  # Save the whole colored diff for a ticket or archive
+//! vibed --markdown -       # Print a Markdown report of the review for a PR description
+//! vibed -o review.patch    # Write the diff as a patch for git apply (- for stdout)
+//! vibed --json | jq '.files[].path'  # Print files, hunks and commits as JSON for scripts
+//! vibed -F                # Just print the diff when it fits on one screen, like less -F
+//! vibed --check            # Exit 1 if there are changes against the base, 0 if not
+//! vibed --since-review     # Only what changed since the branch was last reviewed
+//! vibed main..feature     # Compare two revisions, like git diff main..feature
+//! vibed main...feature    # Only feature's side, from where it forked off main
+//! git -c core.pager=vibed show  # Open git's own diff output in vibed
 //! ```
 
-mod app;
-mod git;
-mod syntax;
-mod ui;
-
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use anyhow::Result;
-use clap::Parser;
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use regex::Regex;
+
+use vibed::app::{self, DEFAULT_CONTEXT, DEFAULT_HEXDUMP_MAX_BYTES, DEFAULT_MAX_COMMITS};
+use vibed::{annotations, config, git, mergetool};
+use vibed::ui::{DiffMode, SidebarPosition, Theme};
 
 /// Terminal UI diff viewer for git worktrees
 #[derive(Parser, Debug)]
 #[command(name = "vibed")]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the repository (defaults to current directory)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the repository (defaults to current directory), or the
+    /// revisions to compare in it as A..B, or A...B from where they forked
     #[arg(default_value = ".")]
     path: PathBuf,
 
-    /// Base branch to diff against (defaults to origin/main or origin/master)
+    /// Base revision to diff against: branch, tag, SHA, HEAD~5, @{upstream}
+    /// (defaults to $GV_BASE, gv.base or .gv.toml's base, else the upstream, origin/main or origin/master)
     #[arg(short, long)]
     base: Option<String>,
+
+    /// Findings to show alongside the diff (SARIF or JSON list of path/line/message)
+    #[arg(long, value_name = "FILE")]
+    annotations: Option<PathBuf>,
+
+    /// Path to the git directory (defaults to $GIT_DIR, else discovered from PATH)
+    #[arg(long, value_name = "DIR")]
+    git_dir: Option<PathBuf>,
+
+    /// Path to the work tree (defaults to $GIT_WORK_TREE, else the repository's)
+    #[arg(long, value_name = "DIR")]
+    work_tree: Option<PathBuf>,
+
+    /// Review a format-patch series or mailbox (files or directories of
+    /// *.patch), replayed on --base, its base-commit, or HEAD
+    #[arg(long, value_name = "PATH", num_args = 1.., conflicts_with = "worktree")]
+    patches: Vec<PathBuf>,
+
+    /// Open the worktree with this branch or directory name
+    #[arg(short, long, value_name = "NAME")]
+    worktree: Option<String>,
+
+    /// Start in the unified view
+    #[arg(long, conflicts_with_all = ["split", "full", "word_diff"])]
+    unified: bool,
+
+    /// Start in the unified view with changed words marked inline
+    #[arg(long, conflicts_with_all = ["split", "full"])]
+    word_diff: bool,
+
+    /// Start in the side-by-side view (the default)
+    #[arg(long, conflicts_with = "full")]
+    split: bool,
+
+    /// Start in the full-file side-by-side view
+    #[arg(long)]
+    full: bool,
+
+    /// Context lines around changes (defaults to $GV_CONTEXT, gv.context or .gv.toml's context, else 3)
+    #[arg(long, value_name = "N")]
+    context: Option<u32>,
+
+    /// Most commits to list before asking to load more (defaults to $GV_MAX_COMMITS, gv.maxCommits or .gv.toml's max_commits, else 1000)
+    #[arg(long, value_name = "N")]
+    max_commits: Option<usize>,
+
+    /// Show files with fewer lines whole in the split view; 0 turns it off (defaults to $GV_FULL_FILE_LINES, gv.fullFileLines or .gv.toml's full_file_lines)
+    #[arg(long, value_name = "N")]
+    full_file_lines: Option<usize>,
+
+    /// Expand hidden files (dotfiles, lock files) on startup
+    #[arg(long)]
+    show_hidden: bool,
+
+    /// Don't capture the mouse, leaving text selection and scrollback to the terminal
+    #[arg(long)]
+    no_mouse: bool,
+
+    /// Color theme for the UI and syntax highlighting (dark, light, solarized, gruvbox, high-contrast, auto)
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+
+    /// Use the light theme, for terminals with a light background (same as --theme light)
+    #[arg(long, conflicts_with = "theme")]
+    light: bool,
+
+    /// Where to put the file list (left, bottom)
+    #[arg(long, value_name = "POSITION")]
+    sidebar: Option<String>,
+
+    /// Leave changed submodule pointers out of the diff
+    #[arg(long)]
+    ignore_submodules: bool,
+
+    /// Diff against the base's tip, showing its changes since the branch forked
+    /// off too, rather than from where the branch forked (base..HEAD, not base...HEAD)
+    #[arg(long)]
+    no_merge_base: bool,
+
+    /// Only show files matching a glob, or in a folder (repeatable; `l` changes it while viewing)
+    #[arg(long = "path", value_name = "GLOB")]
+    paths: Vec<String>,
+
+    /// Diff algorithm: myers, minimal, patience, or histogram, which runs patience
+    /// (defaults to git's diff.algorithm, else myers)
+    #[arg(long, value_name = "NAME")]
+    algorithm: Option<String>,
+
+    /// Print a summary of the review to stdout on quit
+    #[arg(long)]
+    summary: bool,
+
+    /// Show only what changed since the branch was last reviewed
+    #[arg(long)]
+    since_review: bool,
+
+    /// Print the diff and exit instead of opening the UI when it fits on one screen
+    #[arg(short = 'F', long)]
+    quit_if_one_screen: bool,
+
+    /// Print the whole diff with its colors to stdout instead of opening the UI
+    #[arg(long, conflicts_with = "check")]
+    ansi: bool,
+
+    /// Write the diff as a unified patch to FILE (- for stdout) instead of opening the UI
+    #[arg(short = 'o', long, value_name = "FILE", conflicts_with_all = ["check", "ansi"])]
+    output: Option<PathBuf>,
+
+    /// Print the diff's files, hunks, stats and commits as JSON instead of opening the UI
+    #[arg(long, conflicts_with_all = ["check", "ansi", "output"])]
+    json: bool,
+
+    /// Write a Markdown report of the review (files, stats, what's reviewed,
+    /// notes) to FILE (- for stdout) instead of opening the UI
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["check", "ansi", "output", "json"])]
+    markdown: Option<PathBuf>,
+
+    /// Don't open the UI; exit 1 if there are changes against the base, 0 if
+    /// not, 2 on errors (with --summary, print the summary first)
+    #[arg(long)]
+    check: bool,
+}
+
+/// Columns `--ansi` lays the diff out in when not writing to a terminal
+const DEFAULT_ANSI_WIDTH: u16 = 160;
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Show a single revision against its parent, or the changes from REV to REV2
+    Show {
+        /// Revision to show (or the old side when REV2 is given), or a range: A..B, A...B
+        rev: String,
+
+        /// New side of the comparison
+        rev2: Option<String>,
+    },
+    /// Compare two files or directories, which needn't be tracked; either file may be REV:PATH
+    ///
+    /// Also takes the seven arguments git passes a GIT_EXTERNAL_DIFF program, showing
+    /// every file of the git command together once its last one arrives.
+    Diff {
+        /// Old side
+        old: String,
+
+        /// New side
+        new: String,
+
+        /// Rest of git's external diff arguments: OLD-HEX OLD-MODE NEW-FILE NEW-HEX NEW-MODE
+        #[arg(hide = true, num_args = 5, value_name = "ARG")]
+        external: Vec<String>,
+    },
+    /// Show the conflicts git left in a file, e.g. as merge.tool with "$MERGED";
+    /// exits 0 only once the file is resolved
+    Mergetool {
+        /// Conflicted file
+        file: PathBuf,
+
+        /// Allow picking ours, theirs or both per conflict and writing the result
+        #[arg(long)]
+        resolve: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+    let check = args.check;
+
+    // Keep 1 meaning "has changes" for scripts, like diff(1)
+    match run(args) {
+        Ok(changed) if check => std::process::exit(changed as i32),
+        Err(err) if check => {
+            eprintln!("Error: {:?}", err);
+            std::process::exit(2);
+        }
+        result => result.map(|_| ()),
+    }
+}
+
+/// Open the viewer, or with `--check` just load the diff
+///
+/// Returns whether anything differs from the base.
+fn run(args: Args) -> Result<bool> {
+
+    // `A..B` in place of the path compares two revisions of the current repository
+    let range = match &args.command {
+        None if !args.path.exists() => args.path.to_str().and_then(git::RevRange::parse),
+        _ => None,
+    };
+    let path = if range.is_some() { PathBuf::from(".") } else { args.path.clone() };
 
     // Resolve the repository path
-    let repo_path = args.path.canonicalize()
-        .unwrap_or_else(|_| args.path.clone());
+    let repo_path = path.canonicalize()
+        .unwrap_or_else(|_| path.clone());
+
+    // An explicit git directory or work tree replaces discovery
+    let repo_path = git::RepoPath::resolve(args.git_dir, args.work_tree, &repo_path)?;
+
+    // Jump straight into a named worktree of the same repository
+    let repo_path = match &args.worktree {
+        Some(name) => repo_path.with_path(git::find_worktree(&repo_path, name)?),
+        None => repo_path,
+    };
+
+    let config = config::RepoConfig::load(&repo_path)?;
+
+    // As git's pager (`core.pager` / `pager.diff`), show the diff git writes to stdin;
+    // output without one, like plain `git log`, is passed straight through
+    let piped = if std::env::var_os("GIT_PAGER_IN_USE").is_some()
+        && !io::stdin().is_terminal()
+        && args.command.is_none()
+        && args.patches.is_empty()
+    {
+        match git::read_piped_diff(io::stdin().lock(), io::stdout().lock())? {
+            Some(diffs) => Some(diffs),
+            None => return Ok(false),
+        }
+    } else {
+        None
+    };
+
+    if args.command.is_some() && !args.patches.is_empty() {
+        bail!("--patches can't be combined with `show` or `diff`");
+    }
+    if range.is_some() && (args.base.is_some() || !args.patches.is_empty()) {
+        bail!("A revision range names both sides; it can't be combined with --base or --patches");
+    }
+
+    let mergetool = match &args.command {
+        Some(command @ Command::Mergetool { .. }) => Some(command.clone()),
+        _ => None,
+    };
+
+    // `diff` names files relative to where it was run, not the repository
+    let mut spool = None;
+    let compare = match &args.command {
+        // GIT_EXTERNAL_DIFF: PATH OLD-FILE OLD-HEX OLD-MODE NEW-FILE NEW-HEX NEW-MODE, with
+        // temporary or /dev/null files standing in for each side. The files of one git
+        // command are gathered up and shown together by its last call
+        Some(Command::Diff { old: path, new: old_file, external }) if !external.is_empty() => {
+            match git::spool_external(&repo_path, path, Path::new(old_file), Path::new(&external[2]))? {
+                Some(sides) => {
+                    spool = Some(git::external_spool_dir(&repo_path));
+                    Some(sides)
+                }
+                None => return Ok(false),
+            }
+        }
+        Some(Command::Diff { old, new, .. }) => {
+            let cwd = std::env::current_dir()?;
+            Some((
+                git::resolve_side(&repo_path, &cwd, old, None)?,
+                git::resolve_side(&repo_path, &cwd, new, None)?,
+            ))
+        }
+        _ => None,
+    };
+
+    // `show` and `--patches` fix both sides; otherwise fall back to the repository's configured base.
+    // Explicit sides say whether to diff from their merge base, as git's `A..B` and `A...B` do
+    let (base, head, merge_base) = match args.command {
+        Some(Command::Show { rev, rev2: Some(rev2) }) => (Some(rev), Some(rev2), Some(false)),
+        Some(Command::Show { rev, rev2: None }) if let Some(range) = git::RevRange::parse(&rev) => {
+            (Some(range.old), Some(range.new), Some(range.merge_base))
+        }
+        Some(Command::Show { rev, rev2: None }) => {
+            let parent = format!("{}^", rev);
+            if git::resolve_base(&repo_path, &parent).is_err() {
+                git::resolve_base(&repo_path, &rev)?;
+                bail!("'{}' has no parent to compare against", rev);
+            }
+            (Some(parent), Some(rev), Some(false))
+        }
+        Some(Command::Diff { .. } | Command::Mergetool { .. }) => (None, None, None),
+        None if !args.patches.is_empty() => {
+            let series = git::read_series(&args.patches)?;
+            let base = args.base.or(series.base_commit).unwrap_or_else(|| "HEAD".to_string());
+            let base = git::resolve_base(&repo_path, &base)?;
+            let tip = git::apply_series(&repo_path, &base, &series.patches)?;
+            (Some(base), Some(tip), Some(false))
+        }
+        None if let Some(range) = range => (Some(range.old), Some(range.new), Some(range.merge_base)),
+        None => (args.base.or(config.base), None, None),
+    };
+    if let Some(head) = &head {
+        git::resolve_base(&repo_path, head)?;
+    }
+
+    let diff_mode = if args.unified {
+        DiffMode::Unified
+    } else if args.word_diff {
+        DiffMode::WordDiff
+    } else if args.full {
+        DiffMode::SideBySideFull
+    } else {
+        DiffMode::SideBySide
+    };
+    let theme = match args.theme.or(args.light.then(|| "light".to_string())).or(config.theme) {
+        // Without a choice, follow the terminal's background
+        None => Theme::detect(),
+        Some(name) if name.eq_ignore_ascii_case("auto") => Theme::detect(),
+        Some(name) => match Theme::from_name(&name) {
+            Some(theme) => theme,
+            None => {
+                let names: Vec<&str> = Theme::ALL.iter().map(|t| t.name()).collect();
+                bail!("Unknown theme '{}' (available: {}, auto)", name, names.join(", "));
+            }
+        },
+    };
+    // Like git's own merge tools, exit 1 while conflicts remain so the file stays unresolved
+    if let Some(Command::Mergetool { file, resolve }) = &mergetool {
+        if !mergetool::run(file, *resolve, theme)? {
+            std::process::exit(1);
+        }
+        return Ok(false);
+    }
+    let sidebar = match args.sidebar.or(config.sidebar) {
+        Some(name) => match SidebarPosition::from_name(&name) {
+            Some(position) => position,
+            None => {
+                let names: Vec<&str> = SidebarPosition::ALL.iter().map(|p| p.name()).collect();
+                bail!("Unknown sidebar position '{}' (available: {})", name, names.join(", "));
+            }
+        },
+        None => SidebarPosition::default(),
+    };
+    let algorithm = match args.algorithm.or_else(|| git::config_string(&repo_path, "diff.algorithm")) {
+        Some(name) => match git::DiffAlgorithm::from_name(&name) {
+            Some(algorithm) => algorithm,
+            None => {
+                let names: Vec<&str> = git::DiffAlgorithm::ALL.iter().map(|a| a.name()).collect();
+                bail!("Unknown diff algorithm '{}' (available: {}, histogram)", name, names.join(", "));
+            }
+        },
+        None => git::DiffAlgorithm::default(),
+    };
+    let generated = config
+        .generated
+        .iter()
+        .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid generated-file pattern '{}'", pattern)))
+        .collect::<Result<Vec<_>>>()?;
+    let view = app::ViewOptions {
+        diff_mode,
+        context_lines: args.context.or(config.context).unwrap_or(DEFAULT_CONTEXT),
+        max_commits: args.max_commits.or(config.max_commits).unwrap_or(DEFAULT_MAX_COMMITS).max(1),
+        show_hidden: args.show_hidden,
+        mouse: !args.no_mouse && config.mouse.unwrap_or(true),
+        theme,
+        hexdump_max_bytes: config.hexdump_max_bytes.unwrap_or(DEFAULT_HEXDUMP_MAX_BYTES),
+        full_file_lines: args.full_file_lines.or(config.full_file_lines).filter(|&lines| lines > 0),
+        skip_reviewed: config.skip_reviewed.unwrap_or(false),
+        ignore_submodules: args.ignore_submodules || config.ignore_submodules.unwrap_or(false),
+        merge_base: merge_base.unwrap_or(!args.no_merge_base && config.merge_base.unwrap_or(true)),
+        algorithm,
+        collapse: config.collapse,
+        hidden: match config.hidden.is_empty() {
+            true => app::ViewOptions::default().hidden,
+            false => config.hidden,
+        },
+        paths: args.paths,
+        generated,
+        sidebar,
+        difftool: config.difftool,
+    };
 
     // Create and run the application
-    let mut app = app::App::new(repo_path, args.base)?;
+    let mut app = match compare {
+        Some((old, new)) => app::App::compare(repo_path.clone(), old, new, view)?,
+        None if let Some(diffs) = piped => app::App::piped(repo_path.clone(), diffs, view)?,
+        None => app::App::new(repo_path.clone(), base, head, view)?,
+    };
+    if let Some(file) = args.annotations {
+        let root = git::repo_root(&repo_path).unwrap_or_else(|_| repo_path.to_path_buf());
+        app.set_annotations(annotations::load_annotations(&file, &root)?);
+    }
+    if args.since_review {
+        app.since_last_review()?;
+    }
+    // The report covers the review as the last session left it
+    if args.markdown.is_some() {
+        app.resume_session();
+    }
+    // Printing rather than browsing needs the whole diff and every commit before it starts
+    if args.check || args.ansi || args.json || args.output.is_some() || args.markdown.is_some() || args.quit_if_one_screen {
+        app.wait_for_diffs();
+        app.wait_for_commits();
+    }
+    if args.check {
+        if args.summary {
+            print!("{}", app.summary());
+        }
+        return Ok(app.has_changes());
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&app.json())?);
+        return Ok(app.has_changes());
+    }
+    if let Some(output) = &args.output {
+        let patch = app.patch_text();
+        if output.as_os_str() == "-" {
+            io::stdout().write_all(patch.as_bytes())?;
+        } else {
+            std::fs::write(output, patch).with_context(|| format!("Failed to write {}", output.display()))?;
+        }
+        return Ok(app.has_changes());
+    }
+    if let Some(markdown) = &args.markdown {
+        let report = app.markdown();
+        if markdown.as_os_str() == "-" {
+            io::stdout().write_all(report.as_bytes())?;
+        } else {
+            std::fs::write(markdown, report).with_context(|| format!("Failed to write {}", markdown.display()))?;
+        }
+        return Ok(app.has_changes());
+    }
+    if args.ansi {
+        let width = crossterm::terminal::size().map_or(DEFAULT_ANSI_WIDTH, |(width, _)| width);
+        app.print_ansi(width)?;
+        return Ok(app.has_changes());
+    }
+    if args.quit_if_one_screen
+        && let Ok((width, height)) = crossterm::terminal::size()
+        && app.print_if_fits(width, height)?
+    {
+        if args.summary {
+            print!("{}", app.summary());
+        }
+        return Ok(app.has_changes());
+    }
+
+    app.resume_session();
     app.run()?;
+    if let Some(spool) = spool {
+        let _ = std::fs::remove_dir_all(spool);
+    }
+    if args.summary || config.summary.unwrap_or(false) {
+        app.wait_for_diffs();
+        app.wait_for_commits();
+        print!("{}", app.summary());
+    }
+    app.save_session()?;
+    // The session is saved either way; a failed snapshot only affects `U`
+    if let Err(err) = app.record_review() {
+        eprintln!("Warning: failed to record the review: {:#}", err);
+    }
 
-    Ok(())
+    Ok(app.has_changes())
 }
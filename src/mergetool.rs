@@ -0,0 +1,184 @@
+//! Merge tool mode
+//!
+//! `vibed mergetool FILE` opens the conflicts git left in a file, ours beside
+//! theirs with the base above when recorded, so gv can serve as `merge.tool`.
+//! Viewing is read-only; with `--resolve` each conflict can be settled as
+//! ours, theirs or both and the result written back over the file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
+
+use crate::app::{enter_terminal, leave_terminal};
+use crate::git::{parse_conflicts, render_resolution, Region, Resolution};
+use crate::ui::{merge_rows, render_merge_view, Styles, Theme};
+
+/// Conflicts of one file and where the reviewer is in them
+struct MergeTool {
+    path: PathBuf,
+    regions: Vec<Region>,
+    trailing_newline: bool,
+    /// Whether conflicts may be resolved and written
+    resolve: bool,
+    /// Index of the selected conflict
+    current: usize,
+    scroll: usize,
+    /// Rows the file gets above the footer
+    height: usize,
+    status: Option<String>,
+    /// Whether the file on disk has no conflicts left
+    resolved: bool,
+    styles: Styles,
+}
+
+/// Show the conflicts in `path`, letting them be resolved when `resolve` is set
+///
+/// Returns whether the file ended up free of conflicts, which `git mergetool`
+/// takes as resolved when `mergetool.<tool>.trustExitCode` is set.
+pub fn run(path: &Path, resolve: bool, theme: Theme) -> Result<bool> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let regions = parse_conflicts(&text);
+    if !regions.iter().any(|region| matches!(region, Region::Conflict(_))) {
+        bail!("{} has no conflict markers", path.display());
+    }
+
+    let mut tool = MergeTool {
+        path: path.to_path_buf(),
+        regions,
+        trailing_newline: text.ends_with('\n'),
+        resolve,
+        current: 0,
+        scroll: 0,
+        height: 0,
+        status: None,
+        resolved: false,
+        styles: Styles::for_theme(theme),
+    };
+
+    enter_terminal(false, false)?;
+    let result = tool.event_loop();
+    leave_terminal(false, false)?;
+    result.map(|()| tool.resolved)
+}
+
+impl MergeTool {
+    fn event_loop(&mut self) -> Result<()> {
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        loop {
+            terminal.draw(|frame| {
+                let area = frame.area();
+                self.height = area.height.saturating_sub(1) as usize;
+                let body = Rect { height: self.height as u16, ..area };
+                render_merge_view(frame.buffer_mut(), body, &self.regions, self.current, self.scroll, &self.styles);
+                self.render_footer(frame.buffer_mut(), Rect { y: area.bottom().saturating_sub(1), height: 1, ..area });
+            })?;
+
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && self.handle_key(key)?
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handle a key press. Returns true when done.
+    fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
+        self.status = None;
+        let rows = merge_rows(&self.regions).0.len();
+        let page = self.height.max(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(true),
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = (self.scroll + 1).min(rows.saturating_sub(1)),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll = (self.scroll + page).min(rows.saturating_sub(1)),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::Char('n') => self.select(self.current + 1),
+            KeyCode::Char('N') => self.select(self.current.saturating_sub(1)),
+            KeyCode::Char('o' | 't' | 'b' | 'u') if !self.resolve => {
+                self.status = Some("Read-only: start with --resolve to pick sides".to_string());
+            }
+            KeyCode::Char('o') => self.settle(Some(Resolution::Ours)),
+            KeyCode::Char('t') => self.settle(Some(Resolution::Theirs)),
+            KeyCode::Char('b') => self.settle(Some(Resolution::Both)),
+            KeyCode::Char('u') => self.settle(None),
+            KeyCode::Char('w') if self.resolve => {
+                self.write()?;
+                if self.resolved {
+                    return Ok(true);
+                }
+                self.status = Some(format!("Wrote {}; {} conflicts left", self.path.display(), self.unresolved()));
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Select conflict `index` and scroll its heading to the top third of the screen
+    fn select(&mut self, index: usize) {
+        let headers = merge_rows(&self.regions).1;
+        self.current = index.min(headers.len().saturating_sub(1));
+        self.scroll = headers[self.current].saturating_sub(self.height / 3);
+    }
+
+    /// Settle the selected conflict, then move on to the next unresolved one
+    fn settle(&mut self, resolution: Option<Resolution>) {
+        if let Some(Region::Conflict(conflict)) = self
+            .regions
+            .iter_mut()
+            .filter(|region| matches!(region, Region::Conflict(_)))
+            .nth(self.current)
+        {
+            conflict.resolution = resolution;
+        }
+        let next = self.conflicts().enumerate().skip(self.current + 1).find(|(_, c)| c.is_none());
+        match (resolution, next) {
+            (Some(_), Some((index, _))) => self.select(index),
+            _ => self.select(self.current),
+        }
+    }
+
+    /// Resolution of each conflict, in order
+    fn conflicts(&self) -> impl Iterator<Item = Option<Resolution>> + '_ {
+        self.regions.iter().filter_map(|region| match region {
+            Region::Conflict(conflict) => Some(conflict.resolution),
+            Region::Clean(_) => None,
+        })
+    }
+
+    /// Number of conflicts not settled yet
+    fn unresolved(&self) -> usize {
+        self.conflicts().filter(Option::is_none).count()
+    }
+
+    /// Write the file with resolved conflicts replaced and the rest left marked
+    fn write(&mut self) -> Result<()> {
+        let text = render_resolution(&self.regions, self.trailing_newline);
+        fs::write(&self.path, text).with_context(|| format!("Failed to write {}", self.path.display()))?;
+        self.resolved = self.unresolved() == 0;
+        Ok(())
+    }
+
+    /// Footer line: the file, how many conflicts remain and the keys, or a status message
+    fn render_footer(&self, buf: &mut ratatui::buffer::Buffer, area: Rect) {
+        let total = self.conflicts().count();
+        let text = match &self.status {
+            Some(status) => format!(" {}", status),
+            None if self.resolve => format!(
+                " {} · {} of {} unresolved · n/N conflict  o ours  t theirs  b both  u undo  w write  q quit",
+                self.path.display(),
+                self.unresolved(),
+                total
+            ),
+            None => format!(" {} · {} conflicts · n/N conflict  j/k scroll  q quit", self.path.display(), total),
+        };
+        for x in area.x..area.right() {
+            buf[(x, area.y)].set_char(' ').set_style(self.styles.footer);
+        }
+        buf.set_stringn(area.x, area.y, text, area.width as usize, self.styles.footer);
+    }
+}
@@ -0,0 +1,63 @@
+//! Line notes
+//!
+//! Free-text notes a reviewer leaves on lines of the new files, kept per
+//! branch (or revision) as JSON in `.git/gv/notes.json`, shared by all
+//! worktrees. Each edit is saved straight away.
+
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+
+/// File in the state directory holding the notes
+const NOTES_FILE: &str = "notes.json";
+
+/// A note on one line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    /// Repository-relative path
+    pub path: String,
+    /// 1-based line number in the new file
+    pub line: u32,
+    /// What the reviewer wrote
+    pub text: String,
+}
+
+/// Notes on a branch's lines, by path then new-file line
+pub type LineNotes = BTreeMap<String, BTreeMap<u32, String>>;
+
+/// Branch name to its notes, sorted by path then line
+type Notes = BTreeMap<String, Vec<Note>>;
+
+/// Notes left on `branch`
+pub fn load(repo_path: &git::RepoPath, branch: &str) -> LineNotes {
+    let mut notes = LineNotes::new();
+    for note in load_all(repo_path).remove(branch).unwrap_or_default() {
+        notes.entry(note.path).or_default().insert(note.line, note.text);
+    }
+    notes
+}
+
+/// Replace the notes kept for `branch`
+pub fn save(repo_path: &git::RepoPath, branch: &str, notes: &LineNotes) -> Result<()> {
+    let notes: Vec<Note> = notes
+        .iter()
+        .flat_map(|(path, lines)| {
+            lines.iter().map(|(&line, text)| Note { path: path.clone(), line, text: text.clone() })
+        })
+        .collect();
+    let mut all = load_all(repo_path);
+    if notes.is_empty() {
+        all.remove(branch);
+    } else {
+        all.insert(branch.to_string(), notes);
+    }
+
+    git::save_state(repo_path, NOTES_FILE, &all)
+}
+
+/// Every branch's notes
+fn load_all(repo_path: &git::RepoPath) -> Notes {
+    git::load_state(repo_path, NOTES_FILE)
+}
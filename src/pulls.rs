@@ -0,0 +1,271 @@
+//! GitHub pull requests
+//!
+//! Lists the repository's open pull requests through the `gh` CLI, which
+//! takes care of authentication and of finding the GitHub repository from
+//! the remotes. Their heads are fetched from `refs/pull/N/head`, so no
+//! local branch or checkout is needed. Line notes can be sent back as a
+//! draft review, to be looked over and submitted on GitHub.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::git::{self, Hunk, LineType};
+
+/// Most pull requests listed
+const MAX_PULL_REQUESTS: usize = 100;
+
+/// Context lines around changes in GitHub's diff, the only lines comments can go on
+const GITHUB_CONTEXT_LINES: u32 = 3;
+
+/// Combined CI status of a pull request's head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// No checks reported
+    None,
+    /// Checks still running
+    Pending,
+    /// Every check passed or was skipped
+    Passing,
+    /// At least one check failed
+    Failing,
+}
+
+impl CheckStatus {
+    /// Badge shown next to the pull request
+    pub fn badge(self) -> &'static str {
+        match self {
+            Self::None => " ",
+            Self::Pending => "●",
+            Self::Passing => "✓",
+            Self::Failing => "✗",
+        }
+    }
+}
+
+/// An open pull request
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    /// Pull request number
+    pub number: u64,
+    /// Title
+    pub title: String,
+    /// Author's login
+    pub author: String,
+    /// Commit at the head of the pull request
+    pub head_oid: String,
+    /// Branch it targets
+    pub base_ref: String,
+    /// CI status of the head commit
+    pub checks: CheckStatus,
+}
+
+impl PullRequest {
+    /// Refspec fetching the pull request's head from GitHub
+    pub fn refspec(&self) -> String {
+        format!("refs/pull/{}/head", self.number)
+    }
+}
+
+/// A note on a line of the new side of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewComment {
+    /// Repository-relative path
+    pub path: String,
+    /// 1-based line number in the new file
+    pub line: u32,
+    /// Markdown text
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhPullRequest {
+    number: u64,
+    title: String,
+    author: Option<GhAuthor>,
+    head_ref_oid: String,
+    base_ref_name: String,
+    #[serde(default)]
+    status_check_rollup: Vec<GhCheck>,
+}
+
+#[derive(Deserialize)]
+struct GhAuthor {
+    login: String,
+}
+
+/// A check run (`status`/`conclusion`) or commit status (`state`)
+#[derive(Deserialize)]
+struct GhCheck {
+    status: Option<String>,
+    conclusion: Option<String>,
+    state: Option<String>,
+}
+
+/// List open pull requests with `gh pr list`
+pub fn list_pull_requests(repo_path: &Path) -> Result<Vec<PullRequest>> {
+    let output = Command::new("gh")
+        .current_dir(repo_path)
+        .args(["pr", "list", "--state", "open", "--limit", &MAX_PULL_REQUESTS.to_string()])
+        .args(["--json", "number,title,author,headRefOid,baseRefName,statusCheckRollup"])
+        .output()
+        .context("Failed to run `gh` (is the GitHub CLI installed?)")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("gh pr list failed: {}", stderr.trim());
+    }
+    parse_pull_requests(&output.stdout)
+}
+
+/// Parse `gh pr list --json` output
+fn parse_pull_requests(json: &[u8]) -> Result<Vec<PullRequest>> {
+    let pulls: Vec<GhPullRequest> = serde_json::from_slice(json).context("Unexpected output from gh")?;
+    Ok(pulls
+        .into_iter()
+        .map(|pr| PullRequest {
+            number: pr.number,
+            title: pr.title,
+            author: pr.author.map(|a| a.login).unwrap_or_default(),
+            head_oid: pr.head_ref_oid,
+            base_ref: pr.base_ref_name,
+            checks: rollup(&pr.status_check_rollup),
+        })
+        .collect())
+}
+
+/// Split notes into those on lines GitHub shows in the pull request's diff and the rest
+///
+/// GitHub only accepts comments on changed lines and the few lines of
+/// context around them, so each file's hunks are narrowed to its context first.
+pub fn place_comments(comments: Vec<ReviewComment>, files: &[(&str, &[Hunk])]) -> (Vec<ReviewComment>, Vec<ReviewComment>) {
+    comments.into_iter().partition(|comment| {
+        files.iter().filter(|(path, _)| *path == comment.path).any(|(_, hunks)| {
+            git::reduce_context(hunks, GITHUB_CONTEXT_LINES).iter().flat_map(|h| &h.lines).any(|line| {
+                line.line_type != LineType::Removed && line.new_lineno == Some(comment.line)
+            })
+        })
+    })
+}
+
+/// Request body creating a pending review with `comments`
+///
+/// Notes GitHub wouldn't place on a line are listed in the review's summary.
+fn review_payload(commit: &str, comments: &[ReviewComment], unplaced: &[ReviewComment]) -> serde_json::Value {
+    let mut body = String::new();
+    if !unplaced.is_empty() {
+        body.push_str("Notes on lines outside the diff:\n\n");
+        for comment in unplaced {
+            body.push_str(&format!("- `{}:{}`: {}\n", comment.path, comment.line, comment.body));
+        }
+    }
+    let comments: Vec<_> = comments
+        .iter()
+        .map(|c| json!({ "path": c.path, "line": c.line, "side": "RIGHT", "body": c.body }))
+        .collect();
+    // Leaving out `event` keeps the review pending, a draft only its author sees
+    json!({ "commit_id": commit, "body": body, "comments": comments })
+}
+
+/// Create a draft review of pull request `number` at `commit` with `gh api`
+///
+/// Returns the review's URL.
+pub fn post_draft_review(
+    repo_path: &Path,
+    number: u64,
+    commit: &str,
+    comments: &[ReviewComment],
+    unplaced: &[ReviewComment],
+) -> Result<String> {
+    let payload = review_payload(commit, comments, unplaced);
+    let mut child = Command::new("gh")
+        .current_dir(repo_path)
+        .args(["api", "--method", "POST", &format!("repos/{{owner}}/{{repo}}/pulls/{}/reviews", number)])
+        .args(["--input", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run `gh` (is the GitHub CLI installed?)")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.to_string().as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("gh api failed: {}", stderr.trim());
+    }
+
+    let review: serde_json::Value = serde_json::from_slice(&output.stdout).context("Unexpected output from gh")?;
+    Ok(review["html_url"].as_str().unwrap_or_default().to_string())
+}
+
+/// Fold individual checks into one status
+fn rollup(checks: &[GhCheck]) -> CheckStatus {
+    if checks.is_empty() {
+        return CheckStatus::None;
+    }
+    let mut status = CheckStatus::Passing;
+    for check in checks {
+        let outcome = check.conclusion.as_deref().filter(|c| !c.is_empty()).or(check.state.as_deref());
+        match outcome {
+            Some("SUCCESS" | "NEUTRAL" | "SKIPPED") => {}
+            Some("PENDING" | "EXPECTED") | None => status = CheckStatus::Pending,
+            Some(_) if check.status.as_deref().is_some_and(|s| s != "COMPLETED") => status = CheckStatus::Pending,
+            Some(_) => return CheckStatus::Failing,
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pull_requests() {
+        let json = br#"[
+            {"number": 12, "title": "Add x", "author": {"login": "ada"}, "headRefOid": "abc",
+             "baseRefName": "main", "statusCheckRollup": [
+                {"status": "COMPLETED", "conclusion": "SUCCESS"},
+                {"state": "SUCCESS"}]},
+            {"number": 13, "title": "Fix y", "author": {"login": "bob"}, "headRefOid": "def",
+             "baseRefName": "main", "statusCheckRollup": [
+                {"status": "IN_PROGRESS", "conclusion": ""},
+                {"status": "COMPLETED", "conclusion": "FAILURE"}]},
+            {"number": 14, "title": "Docs", "author": null, "headRefOid": "123",
+             "baseRefName": "develop", "statusCheckRollup": []}
+        ]"#;
+        let pulls = parse_pull_requests(json).unwrap();
+        assert_eq!(pulls.len(), 3);
+        assert_eq!(pulls[0].author, "ada");
+        assert_eq!(pulls[0].checks, CheckStatus::Passing);
+        assert_eq!(pulls[1].checks, CheckStatus::Failing);
+        assert_eq!(pulls[2].checks, CheckStatus::None);
+        assert_eq!(pulls[2].refspec(), "refs/pull/14/head");
+    }
+
+    #[test]
+    fn test_place_comments() {
+        let line = |line_type, new_lineno| git::DiffLine { line_type, content: String::new(), old_lineno: None, new_lineno, moved: false };
+        let mut lines: Vec<_> = (1..=10).map(|n| line(LineType::Context, Some(n))).collect();
+        lines.insert(5, line(LineType::Added, Some(6)));
+        for (n, l) in lines.iter_mut().enumerate().skip(6) {
+            l.new_lineno = Some(n as u32 + 1);
+        }
+        let hunks = vec![Hunk { old_start: 1, old_count: 10, new_start: 1, new_count: 11, header: String::new(), lines }];
+        let comment = |path: &str, line| ReviewComment { path: path.to_string(), line, body: "hm".to_string() };
+        let (placed, unplaced) =
+            place_comments(vec![comment("src/lib.rs", 6), comment("src/lib.rs", 9), comment("src/lib.rs", 1), comment("other.rs", 6)], &[("src/lib.rs", &hunks)]);
+        assert_eq!(placed, vec![comment("src/lib.rs", 6), comment("src/lib.rs", 9)]);
+        assert_eq!(unplaced.len(), 2);
+
+        let payload = review_payload("abc", &placed, &unplaced);
+        assert_eq!(payload["comments"][0]["line"], 6);
+        assert!(payload.get("event").is_none());
+        assert!(payload["body"].as_str().unwrap().contains("`src/lib.rs:1`: hm"));
+    }
+}
@@ -0,0 +1,34 @@
+//! Review snapshots
+//!
+//! Remembers the commit each branch was at when it was last reviewed, so a
+//! later session can show only what changed since. Snapshots are kept as
+//! JSON in `.git/gv/reviewed.json`, shared by all worktrees.
+
+use std::collections::BTreeMap;
+use anyhow::Result;
+
+use crate::git;
+
+/// File in the state directory holding the snapshots
+const SNAPSHOT_FILE: &str = "reviewed.json";
+
+/// Branch name to the full hash of the commit last reviewed on it
+type Snapshots = BTreeMap<String, String>;
+
+/// Commit `branch` was at when it was last reviewed
+pub fn last_reviewed(repo_path: &git::RepoPath, branch: &str) -> Option<String> {
+    load(repo_path).remove(branch)
+}
+
+/// Record `commit` as reviewed for `branch`
+pub fn record_reviewed(repo_path: &git::RepoPath, branch: &str, commit: &str) -> Result<()> {
+    let mut snapshots = load(repo_path);
+    snapshots.insert(branch.to_string(), commit.to_string());
+
+    git::save_state(repo_path, SNAPSHOT_FILE, &snapshots)
+}
+
+/// Every recorded snapshot, by branch
+fn load(repo_path: &git::RepoPath) -> Snapshots {
+    git::load_state(repo_path, SNAPSHOT_FILE)
+}
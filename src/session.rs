@@ -0,0 +1,83 @@
+//! Saved review sessions
+//!
+//! What a long review of a branch had got to — files marked reviewed or
+//! collapsed, commits left out and where the view was scrolled — so gv can
+//! pick it up when reopened on the branch. Sessions are kept as JSON in
+//! `.git/gv/sessions.json`, one per branch, shared by all worktrees.
+
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+
+/// File in the state directory holding the sessions
+const SESSION_FILE: &str = "sessions.json";
+
+/// Review state of one branch
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Files marked reviewed, with the change id they had then
+    pub reviewed: BTreeMap<String, String>,
+    /// Whether each file was collapsed
+    pub collapsed: BTreeMap<String, bool>,
+    /// File at the top of the view
+    pub file: Option<String>,
+    /// Rows into that file the view was scrolled
+    pub offset: usize,
+    /// Full hashes of the commits left out of the diff; the uncommitted
+    /// changes have an empty one. Commits added since start selected.
+    pub unselected: Vec<String>,
+}
+
+/// Branch name to its session
+type Sessions = BTreeMap<String, Session>;
+
+/// Session last saved for `branch`
+pub fn load(repo_path: &git::RepoPath, branch: &str) -> Option<Session> {
+    load_all(repo_path).remove(branch)
+}
+
+/// Save `session` as the one to resume on `branch`
+pub fn save(repo_path: &git::RepoPath, branch: &str, session: Session) -> Result<()> {
+    let mut sessions = load_all(repo_path);
+    sessions.insert(branch.to_string(), session);
+
+    git::save_state(repo_path, SESSION_FILE, &sessions)
+}
+
+/// Every saved session, by branch
+fn load_all(repo_path: &git::RepoPath) -> Sessions {
+    git::load_state(repo_path, SESSION_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = git::RepoPath::from(std::env::temp_dir().join(format!("gv-session-{}", std::process::id())));
+        let _ = std::fs::remove_dir_all(&dir);
+        git2::Repository::init(&dir).unwrap();
+        assert_eq!(load(&dir, "feature"), None);
+
+        let session = Session {
+            reviewed: BTreeMap::from([("a.txt".to_string(), "1234".to_string())]),
+            collapsed: BTreeMap::from([("a.txt".to_string(), true)]),
+            file: Some("b.txt".to_string()),
+            offset: 3,
+            unselected: vec![String::new()],
+        };
+        save(&dir, "feature", session.clone()).unwrap();
+        save(&dir, "other", Session::default()).unwrap();
+        assert_eq!(load(&dir, "feature"), Some(session));
+        assert_eq!(load(&dir, "other"), Some(Session::default()));
+
+        // Fields added later read as their defaults
+        std::fs::write(dir.join(".git/gv").join(SESSION_FILE), r#"{"feature": {"offset": 2}}"#).unwrap();
+        assert_eq!(load(&dir, "feature"), Some(Session { offset: 2, ..Session::default() }));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -2,12 +2,14 @@
 //!
 //! Provides syntax highlighting for code using syntect.
 //! Supports detection of languages from file paths and caching
-//! of highlighted lines for performance.
+//! of highlighted lines for performance, and finds the functions
+//! and types a file defines from the same grammars.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use syntect::highlighting::{ThemeSet, Style, FontStyle};
-use syntect::parsing::SyntaxSet;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxSet};
 use syntect::easy::HighlightLines;
 use ratatui::style::{Color, Modifier, Style as RatatuiStyle};
 
@@ -23,10 +25,63 @@ pub struct Token {
 /// A line of highlighted tokens
 pub type HighlightedLine = Vec<Token>;
 
+/// What a symbol defines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    /// A struct, class, enum, trait, interface or type alias
+    Type,
+}
+
+impl SymbolKind {
+    /// Short label for the symbol picker
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Function => "fn",
+            Self::Type => "type",
+        }
+    }
+}
+
+/// A function or type defined in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Line of the definition, from 1
+    pub line: u32,
+}
+
+/// A symbol of a changed file, as listed by the symbol picker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSymbol {
+    pub path: String,
+    pub symbol: Symbol,
+    /// Last line before the next symbol, taken as where this one ends
+    pub end: u32,
+    /// Whether lines were added between the symbol and its end
+    pub changed: bool,
+}
+
+/// Scopes grammars give the name in a definition
+const SYMBOL_SCOPES: &[(&str, SymbolKind)] = &[
+    ("entity.name.function", SymbolKind::Function),
+    ("entity.name.type", SymbolKind::Type),
+    ("entity.name.class", SymbolKind::Type),
+    ("entity.name.struct", SymbolKind::Type),
+    ("entity.name.enum", SymbolKind::Type),
+    ("entity.name.trait", SymbolKind::Type),
+    ("entity.name.interface", SymbolKind::Type),
+    ("entity.name.union", SymbolKind::Type),
+];
+
 /// Syntax highlighter with caching
 pub struct Highlighter {
-    syntax_set: SyntaxSet,
-    theme_set: ThemeSet,
+    /// Shared with detached copies
+    syntax_set: Arc<SyntaxSet>,
+    theme_set: Arc<ThemeSet>,
+    /// Name of the syntect theme in use
+    theme: &'static str,
     /// Cache of highlighted lines by cache key
     cache: HashMap<String, Vec<HighlightedLine>>,
     /// Base path for resolving relative filenames
@@ -37,13 +92,40 @@ impl Highlighter {
     /// Create a new highlighter
     pub fn new() -> Self {
         Self {
-            syntax_set: SyntaxSet::load_defaults_newlines(),
-            theme_set: ThemeSet::load_defaults(),
+            syntax_set: Arc::new(SyntaxSet::load_defaults_newlines()),
+            theme_set: Arc::new(ThemeSet::load_defaults()),
+            theme: "base16-ocean.dark",
             cache: HashMap::new(),
             base_path: None,
         }
     }
 
+    /// Switch to another syntect theme, dropping highlights made with the old one
+    ///
+    /// Unknown names keep the current theme.
+    pub fn set_theme(&mut self, theme: &'static str) {
+        if self.theme_set.themes.contains_key(theme) {
+            self.theme = theme;
+            self.cache.clear();
+        }
+    }
+
+    /// A copy with an empty cache, for highlighting on another thread
+    pub fn detached(&self) -> Self {
+        Self {
+            syntax_set: self.syntax_set.clone(),
+            theme_set: self.theme_set.clone(),
+            theme: self.theme,
+            cache: HashMap::new(),
+            base_path: self.base_path.clone(),
+        }
+    }
+
+    /// Take over the highlights a detached copy made
+    pub fn merge(&mut self, other: Highlighter) {
+        self.cache.extend(other.cache);
+    }
+
     /// Set the base path for resolving relative filenames
     pub fn set_base_path(&mut self, base_path: PathBuf) {
         self.base_path = Some(base_path);
@@ -73,14 +155,14 @@ impl Highlighter {
     pub fn highlight_hunks(&mut self, cache_key: &str, filename: &str, hunks: &[Vec<&str>]) -> Vec<HighlightedLine> {
         // Check cache first
         let total_lines: usize = hunks.iter().map(|h| h.len()).sum();
-        if let Some(cached) = self.cache.get(cache_key) {
-            if cached.len() == total_lines {
-                return cached.clone();
-            }
+        if let Some(cached) = self.cache.get(cache_key)
+            && cached.len() == total_lines
+        {
+            return cached.clone();
         }
 
         let syntax = self.detect_syntax(filename, hunks.first().and_then(|h| h.first().copied()));
-        let theme = &self.theme_set.themes["base16-ocean.dark"];
+        let theme = &self.theme_set.themes[self.theme];
 
         let mut result = Vec::with_capacity(total_lines);
 
@@ -130,10 +212,10 @@ impl Highlighter {
 
     fn highlight_lines_stateful(&mut self, cache_key: &str, filename: &str, lines: &[&str], stateful: bool) -> Vec<HighlightedLine> {
         // Check cache first
-        if let Some(cached) = self.cache.get(cache_key) {
-            if cached.len() == lines.len() {
-                return cached.clone();
-            }
+        if let Some(cached) = self.cache.get(cache_key)
+            && cached.len() == lines.len()
+        {
+            return cached.clone();
         }
 
         let highlighted = if stateful {
@@ -151,7 +233,7 @@ impl Highlighter {
     /// Perform the actual highlighting (stateful - maintains state across lines)
     fn do_highlight(&self, filename: &str, lines: &[&str]) -> Vec<HighlightedLine> {
         let syntax = self.detect_syntax(filename, lines.first().copied());
-        let theme = &self.theme_set.themes["base16-ocean.dark"];
+        let theme = &self.theme_set.themes[self.theme];
 
         let mut highlighter = HighlightLines::new(syntax, theme);
         let mut result = Vec::with_capacity(lines.len());
@@ -199,7 +281,7 @@ impl Highlighter {
     /// where lines may have gaps (missing context) between them.
     fn do_highlight_stateless(&self, filename: &str, lines: &[&str]) -> Vec<HighlightedLine> {
         let syntax = self.detect_syntax(filename, lines.first().copied());
-        let theme = &self.theme_set.themes["base16-ocean.dark"];
+        let theme = &self.theme_set.themes[self.theme];
 
         let mut result = Vec::with_capacity(lines.len());
 
@@ -243,6 +325,61 @@ impl Highlighter {
         result
     }
 
+    /// Functions and types defined in `lines` of `filename`, in order
+    ///
+    /// Definitions are recognized by the scope the file's grammar gives their
+    /// names, so any language syntect knows works without a separate parser.
+    pub fn symbols(&self, filename: &str, lines: &[String]) -> Vec<Symbol> {
+        let syntax = self.detect_syntax(filename, lines.first().map(String::as_str));
+        let scopes: Vec<(Scope, SymbolKind)> = SYMBOL_SCOPES
+            .iter()
+            .filter_map(|&(name, kind)| Some((Scope::new(name).ok()?, kind)))
+            .collect();
+        let kind_of = |stack: &ScopeStack| {
+            stack.as_slice().iter().rev().find_map(|scope| {
+                scopes.iter().find(|(prefix, _)| prefix.is_prefix_of(*scope)).map(|&(_, kind)| kind)
+            })
+        };
+
+        let mut symbols: Vec<Symbol> = Vec::new();
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        for (index, line) in lines.iter().enumerate() {
+            let text = format!("{}\n", line);
+            let Ok(ops) = state.parse_line(&text, &self.syntax_set) else {
+                break;
+            };
+            // A name is the text between an op that opens its scope and the one that closes it
+            let mut naming: Option<Symbol> = None;
+            let mut start = 0;
+            for (end, op) in ops.iter().map(|(offset, op)| (*offset, Some(op))).chain([(text.len(), None)]) {
+                if end > start
+                    && let Some(kind) = kind_of(&stack)
+                {
+                    let piece = &text[start..end];
+                    naming.get_or_insert_with(|| Symbol { name: String::new(), kind, line: index as u32 + 1 }).name.push_str(piece);
+                } else if end > start
+                    && let Some(symbol) = naming.take()
+                {
+                    symbols.push(symbol);
+                }
+                if let Some(op) = op
+                    && stack.apply(op).is_err()
+                {
+                    break;
+                }
+                start = end;
+            }
+            symbols.extend(naming);
+        }
+
+        for symbol in &mut symbols {
+            symbol.name = symbol.name.trim().to_string();
+        }
+        symbols.retain(|symbol| !symbol.name.is_empty());
+        symbols
+    }
+
     /// Detect the syntax for a file based on its path
     fn detect_syntax(&self, filename: &str, first_line: Option<&str>) -> &syntect::parsing::SyntaxReference {
         let path = Path::new(filename);
@@ -258,10 +395,10 @@ impl Highlighter {
             return syntax;
         }
 
-        if let Some(line) = first_line {
-            if let Some(syntax) = self.syntax_set.find_syntax_by_first_line(line) {
-                return syntax;
-            }
+        if let Some(line) = first_line
+            && let Some(syntax) = self.syntax_set.find_syntax_by_first_line(line)
+        {
+            return syntax;
         }
 
         // Try by extension first
@@ -288,18 +425,18 @@ impl Highlighter {
                 _ => None,
             };
 
-            if let Some(fallback) = fallback_ext {
-                if let Some(syntax) = self.syntax_set.find_syntax_by_extension(fallback) {
-                    return syntax;
-                }
+            if let Some(fallback) = fallback_ext
+                && let Some(syntax) = self.syntax_set.find_syntax_by_extension(fallback)
+            {
+                return syntax;
             }
         }
 
         // Try by filename
-        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if let Some(syntax) = self.syntax_set.find_syntax_by_token(name) {
-                return syntax;
-            }
+        if let Some(name) = path.file_name().and_then(|n| n.to_str())
+            && let Some(syntax) = self.syntax_set.find_syntax_by_token(name)
+        {
+            return syntax;
         }
 
         // Default to plain text
@@ -311,13 +448,18 @@ impl Highlighter {
         self.cache.clear();
     }
 
+    /// Drop the cached highlights for a single key
+    pub fn invalidate(&mut self, cache_key: &str) {
+        self.cache.remove(cache_key);
+    }
+
     /// Get a cached highlighted line, or highlight it on demand
     pub fn get_line(&mut self, cache_key: &str, filename: &str, line_index: usize, line_content: &str) -> HighlightedLine {
         // Check if we have this file cached
-        if let Some(cached) = self.cache.get(cache_key) {
-            if let Some(line) = cached.get(line_index) {
-                return line.clone();
-            }
+        if let Some(cached) = self.cache.get(cache_key)
+            && let Some(line) = cached.get(line_index)
+        {
+            return line.clone();
         }
 
         // Highlight just this one line
@@ -356,8 +498,7 @@ fn syntect_style_to_ratatui(style: Style) -> RatatuiStyle {
     ratatui_style
 }
 
-/// Detect language from a filename (for display purposes)
-#[allow(dead_code)]
+/// Detect language from a filename (for display and filtering)
 pub fn detect_language(filename: &str) -> &'static str {
     let path = Path::new(filename);
     let ext = path.extension()
@@ -403,6 +544,26 @@ mod tests {
         assert_eq!(detect_language("unknown.xyz"), "Plain Text");
     }
 
+    #[test]
+    fn test_symbols() {
+        let source = "use std::fmt;\n\npub struct Parser {\n    pos: usize,\n}\n\nimpl Parser {\n    pub fn parse_line(&mut self) -> bool {\n        helper()\n    }\n}\n\nenum Token { A }\n";
+        let lines: Vec<String> = source.lines().map(str::to_string).collect();
+        let symbols = Highlighter::new().symbols("lib.rs", &lines);
+        let found: Vec<(&str, SymbolKind, u32)> = symbols.iter().map(|s| (s.name.as_str(), s.kind, s.line)).collect();
+        assert!(found.contains(&("Parser", SymbolKind::Type, 3)), "{:?}", found);
+        assert!(found.contains(&("parse_line", SymbolKind::Function, 8)), "{:?}", found);
+        assert!(found.contains(&("Token", SymbolKind::Type, 13)), "{:?}", found);
+        assert!(!found.iter().any(|(name, _, _)| *name == "helper"));
+    }
+
+    #[test]
+    fn test_theme_syntax_themes() {
+        let highlighter = Highlighter::new();
+        for theme in crate::ui::Theme::ALL {
+            assert!(highlighter.theme_set.themes.contains_key(theme.syntax_theme()), "{:?}", theme);
+        }
+    }
+
     #[test]
     fn test_highlighter_creation() {
         let highlighter = Highlighter::new();
@@ -0,0 +1,124 @@
+//! ANSI text output
+//!
+//! Turns a rendered buffer into text with SGR escape sequences, so views
+//! can be printed to a terminal or saved with their colors.
+
+use ratatui::{
+    buffer::{Buffer, Cell},
+    style::{Color, Modifier, Style},
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Escape sequence resetting all attributes
+const RESET: &str = "\x1b[0m";
+
+/// Text of every row of `buf`, colored with escape sequences
+///
+/// Trailing blank cells without a background are dropped, and each line
+/// ends with a reset so colors never bleed into what follows.
+pub fn buffer_to_ansi(buf: &Buffer) -> String {
+    let area = buf.area;
+    let mut out = String::new();
+    // Style of a blank cell, which needs no escape sequence
+    let plain = Cell::EMPTY.style();
+
+    for y in area.top()..area.bottom() {
+        // Keep cells up to the last one that shows something
+        let end = (area.left()..area.right())
+            .rev()
+            .find(|&x| {
+                let cell = &buf[(x, y)];
+                cell.symbol() != " " || cell.bg != Color::Reset
+            })
+            .map_or(area.left(), |x| x + 1);
+
+        let mut current = plain;
+        let mut x = area.left();
+        while x < end {
+            let cell = &buf[(x, y)];
+            let style = cell.style();
+            if style != current {
+                out.push_str(&sgr(style));
+                current = style;
+            }
+            out.push_str(cell.symbol());
+            // Wide characters cover the cells after them
+            x += cell.symbol().width().max(1) as u16;
+        }
+        if current != plain {
+            out.push_str(RESET);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Escape sequence switching to `style` from any other
+fn sgr(style: Style) -> String {
+    let mut codes = vec!["0".to_string()];
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+    for (modifier, code) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(fg) = style.fg.and_then(|c| color_code(c, false)) {
+        codes.push(fg);
+    }
+    if let Some(bg) = style.bg.and_then(|c| color_code(c, true)) {
+        codes.push(bg);
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// SGR parameters for a foreground or background color
+fn color_code(color: Color, background: bool) -> Option<String> {
+    let offset = if background { 10 } else { 0 };
+    let basic = |code: u8| Some((code + offset).to_string());
+    match color {
+        Color::Reset => None,
+        Color::Black => basic(30),
+        Color::Red => basic(31),
+        Color::Green => basic(32),
+        Color::Yellow => basic(33),
+        Color::Blue => basic(34),
+        Color::Magenta => basic(35),
+        Color::Cyan => basic(36),
+        Color::Gray => basic(37),
+        Color::DarkGray => basic(90),
+        Color::LightRed => basic(91),
+        Color::LightGreen => basic(92),
+        Color::LightYellow => basic(93),
+        Color::LightBlue => basic(94),
+        Color::LightMagenta => basic(95),
+        Color::LightCyan => basic(96),
+        Color::White => basic(97),
+        Color::Indexed(i) => Some(format!("{};5;{}", 38 + offset, i)),
+        Color::Rgb(r, g, b) => Some(format!("{};2;{};{};{}", 38 + offset, r, g, b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+
+    #[test]
+    fn test_buffer_to_ansi() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 2));
+        buf.set_string(0, 0, "ab", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+        buf.set_string(2, 0, "c", Style::default());
+        buf.set_string(0, 1, "日x", Style::default().bg(Color::Rgb(1, 2, 3)));
+        assert_eq!(
+            buffer_to_ansi(&buf),
+            "\x1b[0;1;31mab\x1b[0mc\n\x1b[0;48;2;1;2;3m日x\x1b[0m\n"
+        );
+    }
+}
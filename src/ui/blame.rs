@@ -0,0 +1,73 @@
+//! Blame gutter
+//!
+//! A column left of the diff naming the commit and author that last
+//! changed each line of the new file, lined up with the rows the diff view
+//! draws. Rows without a new line, like headers and removed lines, stay blank.
+
+use std::collections::HashMap;
+
+use ratatui::{buffer::Buffer, layout::Rect, text::{Line, Span}};
+
+use super::Styles;
+use super::diff_view::{file_line_count, header_rows, new_line_rows, DiffMode};
+use super::text::{pad, truncate};
+use crate::git::{BlameLine, FileDiff};
+use crate::notes::LineNotes;
+
+/// Columns the gutter takes: a short hash, the author and a space either side
+pub const BLAME_WIDTH: u16 = 22;
+
+/// Render the blame of each visible new-file line, for the diff scrolled to `scroll`
+///
+/// `blame` holds each file's lines by path; files missing from it are left blank.
+/// `wrap` is the width the diff's lines wrap at, as given to the diff view.
+#[allow(clippy::too_many_arguments)]
+pub fn render_blame_gutter(
+    buf: &mut Buffer,
+    area: Rect,
+    diffs: &[FileDiff],
+    visible: &[usize],
+    scroll: usize,
+    notes: &LineNotes,
+    mode: DiffMode,
+    wrap: Option<u16>,
+    blame: &HashMap<String, Vec<BlameLine>>,
+    styles: &Styles,
+) {
+    buf.set_style(area, styles.gutter_context);
+    let end = scroll + area.height as usize;
+    let author_width = (area.width as usize).saturating_sub(10);
+
+    let mut top = 0;
+    for diff in visible.iter().filter_map(|&i| diffs.get(i)) {
+        let rows = file_line_count(diff, notes, mode, wrap);
+        if top >= end {
+            break;
+        }
+        if top + rows > scroll
+            && let Some(lines) = blame.get(&diff.path)
+        {
+            let first = top + header_rows(diff);
+            for (row, lineno) in new_line_rows(diff, notes, mode, wrap).into_iter().enumerate() {
+                let row = first + row;
+                if row < scroll {
+                    continue;
+                }
+                if row >= end {
+                    break;
+                }
+                let Some(line) = lineno.and_then(|n| lines.get(n as usize - 1)) else {
+                    continue;
+                };
+                let hash = match &line.hash {
+                    Some(hash) => Span::styled(format!(" {} ", hash), styles.hunk_header),
+                    None => Span::styled(" ······· ", styles.line_number),
+                };
+                let author = Span::styled(pad(&truncate(&line.author, author_width), author_width), styles.line_number);
+                let y = area.y + (row - scroll) as u16;
+                buf.set_line(area.x, y, &Line::from(vec![hash, author]), area.width);
+            }
+        }
+        top += rows;
+    }
+}
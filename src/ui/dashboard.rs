@@ -0,0 +1,240 @@
+//! Worktree dashboard
+//!
+//! Full-screen table of every worktree with its branch, dirty state,
+//! divergence from the base, last commit and path.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Widget},
+};
+use crate::git::{Worktree, WorktreeStatus};
+use super::Styles;
+use super::text::{pad, truncate, width};
+
+/// Dashboard row order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WorktreeSort {
+    /// As listed by git, main worktree first
+    #[default]
+    Path,
+    /// By branch name
+    Branch,
+    /// Most recent commit first
+    Recent,
+    /// Most uncommitted changes first
+    Dirty,
+}
+
+impl WorktreeSort {
+    /// The next sort order in the cycle
+    pub fn next(self) -> Self {
+        match self {
+            Self::Path => Self::Branch,
+            Self::Branch => Self::Recent,
+            Self::Recent => Self::Dirty,
+            Self::Dirty => Self::Path,
+        }
+    }
+
+    /// Short name shown in the title
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Branch => "branch",
+            Self::Recent => "recent",
+            Self::Dirty => "dirty",
+        }
+    }
+
+    /// Indices of `worktrees` in this order
+    pub fn order(self, worktrees: &[Worktree], statuses: &[Option<WorktreeStatus>]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..worktrees.len()).collect();
+        let status = |i: usize| statuses.get(i).and_then(Option::as_ref);
+        match self {
+            Self::Path => {}
+            Self::Branch => order.sort_by_key(|&i| worktrees[i].branch.clone()),
+            Self::Recent => order.sort_by_key(|&i| std::cmp::Reverse(status(i).map_or(0, |s| s.time))),
+            Self::Dirty => order.sort_by_key(|&i| std::cmp::Reverse(status(i).map_or(0, |s| s.changed))),
+        }
+        order
+    }
+}
+
+/// Worktree dashboard widget
+pub struct Dashboard<'a> {
+    /// All worktrees
+    pub worktrees: &'a [Worktree],
+    /// Status per worktree, `None` where it couldn't be read
+    pub statuses: &'a [Option<WorktreeStatus>],
+    /// Statuses are still being read in the background
+    pub loading: bool,
+    /// Display order, as indices into `worktrees`
+    pub order: &'a [usize],
+    /// Cursor position within `order`
+    pub cursor: usize,
+    /// Current sort order
+    pub sort: WorktreeSort,
+    /// Base the ahead/behind counts are measured against
+    pub base: &'a str,
+    /// Status or confirmation message for the bottom line
+    pub message: Option<&'a str>,
+    /// Styles
+    pub styles: &'a Styles,
+}
+
+impl Widget for Dashboard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = format!(
+            " Worktrees ({}) · vs {} · sorted by {} ",
+            self.worktrees.len(),
+            self.base,
+            self.sort.label()
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.styles.border_focus)
+            .title(Span::styled(title, self.styles.popup_title));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.height < 3 {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let branch_width = self.worktrees
+            .iter()
+            .map(|wt| width(wt.branch.as_deref().unwrap_or("(detached)")))
+            .max()
+            .unwrap_or(0)
+            .clamp(6, 30);
+
+        let header = format!(
+            "   {:<bw$}  {:<9}  {:<11}  {:<11}  {:<40}  PATH",
+            "BRANCH",
+            "STATUS",
+            "VS BASE",
+            "UPSTREAM",
+            "LAST COMMIT",
+            bw = branch_width
+        );
+        buf.set_line(inner.x, inner.y, &Line::styled(header, self.styles.line_number), inner.width);
+
+        // Keep the cursor in view, leaving the last line for keys and messages
+        let visible = inner.height.saturating_sub(2) as usize;
+        let scroll = self.cursor.saturating_sub(visible.saturating_sub(1));
+
+        for (row, &i) in self.order.iter().enumerate().skip(scroll).take(visible) {
+            let y = inner.y + 1 + (row - scroll) as u16;
+            let wt = &self.worktrees[i];
+            let is_cursor = row == self.cursor;
+            let style = if is_cursor {
+                self.styles.sidebar_cursor
+            } else {
+                self.styles.sidebar_normal
+            };
+
+            let branch = truncate(wt.branch.as_deref().unwrap_or("(detached)"), branch_width);
+            let mut spans = vec![
+                Span::styled(if wt.is_current { " * " } else { "   " }, self.styles.worktree_current),
+                Span::styled(format!("{}  ", pad(&branch, branch_width)), self.styles.worktree_branch),
+            ];
+
+            match self.statuses.get(i).and_then(Option::as_ref) {
+                Some(status) => {
+                    let (dirty, dirty_style) = if status.changed > 0 {
+                        (format!("● {} chg", status.changed), self.styles.stats_removed)
+                    } else {
+                        ("clean".to_string(), self.styles.stats_added)
+                    };
+                    spans.push(Span::styled(format!("{:<9}  ", dirty), dirty_style));
+                    spans.push(Span::styled(
+                        format!("{:<11}  ", format!("↑{} ↓{}", status.ahead, status.behind)),
+                        style,
+                    ));
+                    let upstream = match status.upstream {
+                        Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+                        None => "-".to_string(),
+                    };
+                    spans.push(Span::styled(format!("{:<11}  ", upstream), style));
+                    let commit = match &status.head {
+                        Some(hash) => format!("{} {} ({})", hash, status.subject, format_age(now - status.time)),
+                        None => "(no commits)".to_string(),
+                    };
+                    spans.push(Span::styled(format!("{}  ", pad(&truncate(&commit, 40), 40)), style));
+                }
+                None => {
+                    let unknown = if self.loading { "…" } else { "?" };
+                    spans.push(Span::styled(format!("{:<9}  {:<11}  {:<11}  {:<40}  ", unknown, "", "", ""), self.styles.line_number));
+                }
+            }
+            spans.push(Span::styled(wt.path.to_string_lossy().into_owned(), self.styles.worktree_path));
+
+            buf.set_line(inner.x, y, &Line::from(spans), inner.width);
+            if is_cursor {
+                for x in inner.x..inner.x + inner.width {
+                    buf[(x, y)].set_style(style);
+                }
+            }
+        }
+
+        let bottom = inner.y + inner.height - 1;
+        let line = match self.message {
+            Some(message) => Line::styled(format!(" {}", message), self.styles.popup_title),
+            None => Line::styled(
+                " Enter open · t tab · d delete · p prune · f fetch · s sort · r refresh · Esc back",
+                self.styles.footer,
+            ),
+        };
+        buf.set_line(inner.x, bottom, &line, inner.width);
+    }
+}
+
+/// Human-readable age of a commit, e.g. `5m ago`, `3d ago`
+pub(super) fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        86400..2_592_000 => format!("{}d ago", seconds / 86400),
+        2_592_000..31_536_000 => format!("{}mo ago", seconds / 2_592_000),
+        _ => format!("{}y ago", seconds / 31_536_000),
+    }
+}
+
+/// Render the worktree dashboard
+#[allow(clippy::too_many_arguments)]
+pub fn render_worktree_dashboard(
+    buf: &mut Buffer,
+    area: Rect,
+    worktrees: &[Worktree],
+    statuses: &[Option<WorktreeStatus>],
+    loading: bool,
+    order: &[usize],
+    cursor: usize,
+    sort: WorktreeSort,
+    base: &str,
+    message: Option<&str>,
+    styles: &Styles,
+) {
+    Dashboard { worktrees, statuses, loading, order, cursor, sort, base, message, styles }.render(area, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(-5), "just now");
+        assert_eq!(format_age(90), "1m ago");
+        assert_eq!(format_age(3 * 86400), "3d ago");
+        assert_eq!(format_age(400 * 86400), "1y ago");
+    }
+}
@@ -1,6 +1,7 @@
 //! Diff content rendering
 //!
-//! Renders the main diff view in side-by-side, unified, or full-file side-by-side modes.
+//! Renders the main diff view in side-by-side, unified, word-diff, or full-file
+//! side-by-side modes.
 
 use ratatui::{
     buffer::Buffer,
@@ -9,11 +10,19 @@ use ratatui::{
     text::{Line, Span},
     widgets::Widget,
 };
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use unicode_width::UnicodeWidthChar;
 
-use crate::git::{FileDiff, Hunk, LineType};
+use std::collections::BTreeMap;
+
+use crate::annotations::AnnotationIndex;
+use crate::git::{is_conflict_marker, FileDiff, Hunk, LineType};
+use crate::notes::LineNotes;
 use crate::syntax::{Highlighter, Token};
 use super::Styles;
+use super::text;
+use super::hexdump::Hexdump;
+use super::image_preview::ImagePreview;
+use super::word_diff::{word_diff, word_row_count, word_rows, WordChange, WordRow};
 
 /// Diff display mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -22,51 +31,144 @@ pub enum DiffMode {
     SideBySide,
     /// Unified view showing all changes in one column
     Unified,
+    /// Unified view with changed line pairs merged, changes marked word by word
+    WordDiff,
     /// Full-file side-by-side view with highlighted changes
     SideBySideFull,
 }
 
 /// Diff content widget
 pub struct DiffContent<'a> {
-    /// List of file diffs to display
-    pub diffs: &'a [&'a FileDiff],
+    /// All file diffs
+    pub diffs: &'a [FileDiff],
+    /// Indices into `diffs` of the files to display, in order
+    pub visible: &'a [usize],
     /// Scroll offset (in lines)
     pub scroll: usize,
     /// Current diff mode
     pub mode: DiffMode,
     /// Syntax highlighter
     pub highlighter: &'a mut Highlighter,
+    /// External annotations shown as gutter markers
+    pub annotations: &'a AnnotationIndex,
+    /// The reviewer's notes, shown under their lines
+    pub notes: &'a LineNotes,
+    /// View width long lines wrap at in the unified views; None cuts them off
+    pub wrap: Option<u16>,
     /// Styles
     pub styles: &'a Styles,
 }
 
+/// Gutter marker style for an annotated line in the new file
+fn line_marker(annotations: &AnnotationIndex, styles: &Styles, path: &str, lineno: Option<u32>) -> Option<Style> {
+    let severity = annotations.severity_at(path, lineno?)?;
+    Some(styles.severity(severity))
+}
+
 const TAB_WIDTH: usize = 4;
 
+/// Iterate the displayed files without collecting them
+fn visible_files<'a>(diffs: &'a [FileDiff], visible: &'a [usize]) -> impl Iterator<Item = &'a FileDiff> {
+    visible.iter().filter_map(|&i| diffs.get(i))
+}
+
+/// Mode `diff` renders in when the view is in `mode`
+///
+/// Files marked whole show in full in the split view.
+pub fn file_mode(diff: &FileDiff, mode: DiffMode) -> DiffMode {
+    if diff.whole && mode == DiffMode::SideBySide {
+        DiffMode::SideBySideFull
+    } else {
+        mode
+    }
+}
+
 impl Widget for DiffContent<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        match self.mode {
-            DiffMode::Unified => render_unified(self, area, buf),
-            DiffMode::SideBySide => render_side_by_side(self, area, buf),
-            DiffMode::SideBySideFull => render_side_by_side_full(self, area, buf),
+        let DiffContent { diffs, visible, scroll, mode, highlighter, annotations, notes, wrap, styles } = self;
+
+        // Render each run of files sharing a mode on its own, below the runs before it
+        let mut run_top = 0;
+        let mut rest = visible;
+        while let Some(&first) = rest.first() {
+            let run_mode = diffs.get(first).map_or(mode, |diff| file_mode(diff, mode));
+            let len = rest
+                .iter()
+                .take_while(|&&i| diffs.get(i).is_none_or(|diff| file_mode(diff, mode) == run_mode))
+                .count();
+            let (run, next) = rest.split_at(len);
+            rest = next;
+
+            let rows: usize = visible_files(diffs, run).map(|diff| file_line_count(diff, notes, run_mode, wrap)).sum();
+            if run_top + rows <= scroll {
+                run_top += rows;
+                continue;
+            }
+            let offset = run_top.saturating_sub(scroll);
+            if offset >= area.height as usize {
+                break;
+            }
+            let run_area = Rect { y: area.y + offset as u16, height: area.height - offset as u16, ..area };
+            let content = DiffContent {
+                diffs,
+                visible: run,
+                scroll: scroll.saturating_sub(run_top),
+                mode: run_mode,
+                highlighter: &mut *highlighter,
+                annotations,
+                notes,
+                wrap,
+                styles,
+            };
+            match run_mode {
+                DiffMode::Unified | DiffMode::WordDiff => render_unified(content, run_area, buf),
+                DiffMode::SideBySide => render_side_by_side(content, run_area, buf),
+                DiffMode::SideBySideFull => render_side_by_side_full(content, run_area, buf),
+            }
+            run_top += rows;
         }
     }
 }
 
 /// Render unified diff view
 fn render_unified(content: DiffContent<'_>, area: Rect, buf: &mut Buffer) {
+    let columns = wrap_columns(content.mode, content.wrap);
     let mut current_line: usize = 0;
     let visible_start = content.scroll;
     let visible_end = content.scroll + area.height as usize;
 
-    for diff in content.diffs.iter().copied() {
+    for diff in visible_files(content.diffs, content.visible) {
+        // Files entirely above the viewport are skipped by their line count
+        let notes = file_notes(content.notes, &diff.path);
+        let file_lines = file_line_count(diff, content.notes, content.mode, content.wrap);
+        if current_line + file_lines <= visible_start {
+            current_line += file_lines;
+            continue;
+        }
+
         let mut line_index = 0;
         // File header
+        if let Some(section) = &diff.section {
+            if current_line >= visible_start && current_line < visible_end {
+                let y = area.y + (current_line - visible_start) as u16;
+                render_section_header(buf, area.x, y, area.width, section, content.styles);
+            }
+            current_line += 1;
+        }
+
         if current_line >= visible_start && current_line < visible_end {
             let y = area.y + (current_line - visible_start) as u16;
             render_file_header(buf, area.x, y, area.width, diff, content.styles);
         }
         current_line += 1;
 
+        if !diff.collapsed && let Some(contents) = BinaryRows::of(diff) {
+            if render_binary_rows(buf, area, contents, &mut current_line, visible_start, visible_end, content.styles) {
+                return;
+            }
+            continue;
+        }
+
         if diff.collapsed || diff.is_binary {
             continue;
         }
@@ -79,23 +181,74 @@ fn render_unified(content: DiffContent<'_>, area: Rect, buf: &mut Buffer) {
             }
             current_line += 1;
 
+            // Changed line pairs share a row in the word-diff view
+            if content.mode == DiffMode::WordDiff {
+                for row in word_rows(hunk) {
+                    let (WordRow::Line(i) | WordRow::Pair(_, i)) = row;
+                    let rows = word_row_height(hunk, row, columns);
+                    let height = rows + note_rows(&hunk.lines[i], notes, content.mode);
+                    if current_line + height > visible_start && current_line < visible_end {
+                        let top = current_line as isize - visible_start as isize;
+                        if let Some(note) = line_note(&hunk.lines[i], notes) {
+                            render_note_row(buf, area, top + rows as isize, note, content.styles);
+                        }
+                        match row {
+                            WordRow::Line(i) => {
+                                let line = &hunk.lines[i];
+                                let marker = line_marker(content.annotations, content.styles, &diff.path, line.new_lineno);
+                                render_unified_line(
+                                    buf,
+                                    area,
+                                    top,
+                                    line,
+                                    &diff.path,
+                                    line_index + i,
+                                    content.highlighter,
+                                    marker,
+                                    columns,
+                                    content.styles,
+                                );
+                            }
+                            WordRow::Pair(old, new) => {
+                                let (old, new) = (&hunk.lines[old], &hunk.lines[new]);
+                                let marker = line_marker(content.annotations, content.styles, &diff.path, new.new_lineno);
+                                render_word_line(buf, area, top, old, new, marker, columns, content.styles);
+                            }
+                        }
+                    }
+                    current_line += height;
+                    if current_line >= visible_end {
+                        return;
+                    }
+                }
+                line_index += hunk.lines.len();
+                continue;
+            }
+
             // Lines
             for line in &hunk.lines {
-                if current_line >= visible_start && current_line < visible_end {
-                    let y = area.y + (current_line - visible_start) as u16;
+                let rows = line_height(line, columns);
+                let height = rows + note_rows(line, notes, content.mode);
+                if current_line + height > visible_start && current_line < visible_end {
+                    let top = current_line as isize - visible_start as isize;
+                    if let Some(note) = line_note(line, notes) {
+                        render_note_row(buf, area, top + rows as isize, note, content.styles);
+                    }
+                    let marker = line_marker(content.annotations, content.styles, &diff.path, line.new_lineno);
                     render_unified_line(
                         buf,
-                        area.x,
-                        y,
-                        area.width,
+                        area,
+                        top,
                         line,
                         &diff.path,
                         line_index,
                         content.highlighter,
+                        marker,
+                        columns,
                         content.styles,
                     );
                 }
-                current_line += 1;
+                current_line += height;
                 line_index += 1;
 
                 if current_line >= visible_end {
@@ -116,15 +269,38 @@ fn render_side_by_side(content: DiffContent<'_>, area: Rect, buf: &mut Buffer) {
     let half_width = area.width / 2;
     let line_num_width: u16 = 6;
 
-    for diff in content.diffs.iter().copied() {
+    for diff in visible_files(content.diffs, content.visible) {
+        // Files entirely above the viewport are skipped by their line count
+        let notes = file_notes(content.notes, &diff.path);
+        let file_lines = file_line_count(diff, content.notes, content.mode, content.wrap);
+        if current_line + file_lines <= visible_start {
+            current_line += file_lines;
+            continue;
+        }
+
         let mut line_index = 0;
         // File header (spans both columns)
+        if let Some(section) = &diff.section {
+            if current_line >= visible_start && current_line < visible_end {
+                let y = area.y + (current_line - visible_start) as u16;
+                render_section_header(buf, area.x, y, area.width, section, content.styles);
+            }
+            current_line += 1;
+        }
+
         if current_line >= visible_start && current_line < visible_end {
             let y = area.y + (current_line - visible_start) as u16;
             render_file_header(buf, area.x, y, area.width, diff, content.styles);
         }
         current_line += 1;
 
+        if !diff.collapsed && let Some(contents) = BinaryRows::of(diff) {
+            if render_binary_rows(buf, area, contents, &mut current_line, visible_start, visible_end, content.styles) {
+                return;
+            }
+            continue;
+        }
+
         if diff.collapsed || diff.is_binary {
             continue;
         }
@@ -154,11 +330,13 @@ fn render_side_by_side(content: DiffContent<'_>, area: Rect, buf: &mut Buffer) {
                         old_line,
                         &diff.path,
                         content.highlighter,
+                        None,
                         content.styles,
                         true, // is_old
                     );
 
                     // Right column (new)
+                    let marker = new_line.and_then(|l| line_marker(content.annotations, content.styles, &diff.path, l.line.new_lineno));
                     render_side_column(
                         buf,
                         area.x + half_width,
@@ -168,12 +346,22 @@ fn render_side_by_side(content: DiffContent<'_>, area: Rect, buf: &mut Buffer) {
                         new_line,
                         &diff.path,
                         content.highlighter,
+                        marker,
                         content.styles,
                         false, // is_old
                     );
                 }
                 current_line += 1;
 
+                // A note on the new line goes under it, in the new column
+                if let Some(note) = new_line.and_then(|l| line_note(l.line, notes)) {
+                    if current_line >= visible_start && current_line < visible_end {
+                        let column = Rect { x: area.x + half_width, width: half_width, ..area };
+                        render_note_row(buf, column, (current_line - visible_start) as isize, note, content.styles);
+                    }
+                    current_line += 1;
+                }
+
                 if current_line >= visible_end {
                     return;
                 }
@@ -194,21 +382,43 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
     let half_width = area.width / 2;
     let line_num_width: u16 = 6;
 
-    for diff in content.diffs.iter().copied() {
+    for diff in visible_files(content.diffs, content.visible) {
+        let notes = file_notes(content.notes, &diff.path);
+        let file_lines = file_line_count(diff, content.notes, content.mode, content.wrap);
+        if current_line + file_lines <= visible_start {
+            current_line += file_lines;
+            continue;
+        }
+
         // File header (spans both columns)
+        if let Some(section) = &diff.section {
+            if current_line >= visible_start && current_line < visible_end {
+                let y = area.y + (current_line - visible_start) as u16;
+                render_section_header(buf, area.x, y, area.width, section, content.styles);
+            }
+            current_line += 1;
+        }
+
         if current_line >= visible_start && current_line < visible_end {
             let y = area.y + (current_line - visible_start) as u16;
             render_file_header(buf, area.x, y, area.width, diff, content.styles);
         }
         current_line += 1;
 
+        if !diff.collapsed && let Some(contents) = BinaryRows::of(diff) {
+            if render_binary_rows(buf, area, contents, &mut current_line, visible_start, visible_end, content.styles) {
+                return;
+            }
+            continue;
+        }
+
         if diff.collapsed || diff.is_binary {
             continue;
         }
 
         let has_full_content = diff.old_content.is_some() || diff.new_content.is_some();
-        let old_lines = diff.old_content.as_ref().map(|lines| lines.as_slice()).unwrap_or(&[]);
-        let new_lines = diff.new_content.as_ref().map(|lines| lines.as_slice()).unwrap_or(&[]);
+        let old_lines = diff.old_content.as_deref().unwrap_or(&[]);
+        let new_lines = diff.new_content.as_deref().unwrap_or(&[]);
         let old_filename = diff.old_path.as_deref().unwrap_or(&diff.path);
         let new_filename = diff.path.as_str();
         let old_cache_key = format!("{}::full::old", old_filename);
@@ -242,6 +452,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                             content.highlighter,
                             content.styles.gutter_context,
                             content.styles.line_context,
+                            None,
                             content.styles,
                         );
                         render_full_column(
@@ -258,6 +469,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                             content.highlighter,
                             content.styles.gutter_context,
                             content.styles.line_context,
+                            new_line.and(line_marker(content.annotations, content.styles, new_filename, Some(new_idx as u32 + 1))),
                             content.styles,
                         );
                     }
@@ -307,6 +519,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 content.highlighter,
                                 content.styles.gutter_context,
                                 content.styles.line_context,
+                                None,
                                 content.styles,
                             );
                             render_full_column(
@@ -323,6 +536,10 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 content.highlighter,
                                 content.styles.gutter_context,
                                 content.styles.line_context,
+                                notes
+                                    .get(&(new_lineno as u32))
+                                    .map(|_| content.styles.annotation_note)
+                                    .or_else(|| line_marker(content.annotations, content.styles, new_filename, Some(new_lineno as u32))),
                                 content.styles,
                             );
                         }
@@ -332,6 +549,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 .map(|s| s.as_str())
                                 .unwrap_or(line.content.as_str());
                             let old_lineno = line.old_lineno.map(|n| n as usize).unwrap_or(old_idx + 1);
+                            let (_, removed_gutter, removed_style) = line_look(line, content.styles);
                             render_full_column(
                                 buf,
                                 area.x,
@@ -344,8 +562,9 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 old_filename,
                                 old_idx,
                                 content.highlighter,
-                                content.styles.gutter_removed,
-                                content.styles.line_removed,
+                                removed_gutter,
+                                removed_style,
+                                None,
                                 content.styles,
                             );
                             render_full_column(
@@ -362,6 +581,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 content.highlighter,
                                 content.styles.gutter_context,
                                 content.styles.line_context,
+                                None,
                                 content.styles,
                             );
                         }
@@ -371,6 +591,12 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 .map(|s| s.as_str())
                                 .unwrap_or(line.content.as_str());
                             let new_lineno = line.new_lineno.map(|n| n as usize).unwrap_or(new_idx + 1);
+                            let (added_gutter, added_style) = if is_conflict_marker(new_line) {
+                                (content.styles.conflict_marker, content.styles.conflict_marker)
+                            } else {
+                                let (_, gutter, style) = line_look(line, content.styles);
+                                (gutter, style)
+                            };
                             render_full_column(
                                 buf,
                                 area.x,
@@ -385,6 +611,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 content.highlighter,
                                 content.styles.gutter_context,
                                 content.styles.line_context,
+                                None,
                                 content.styles,
                             );
                             render_full_column(
@@ -399,8 +626,12 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                                 new_filename,
                                 new_idx,
                                 content.highlighter,
-                                content.styles.gutter_added,
-                                content.styles.line_added,
+                                added_gutter,
+                                added_style,
+                                notes
+                                    .get(&(new_lineno as u32))
+                                    .map(|_| content.styles.annotation_note)
+                                    .or_else(|| line_marker(content.annotations, content.styles, new_filename, Some(new_lineno as u32))),
                                 content.styles,
                             );
                         }
@@ -451,6 +682,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                         content.highlighter,
                         content.styles.gutter_context,
                         content.styles.line_context,
+                        None,
                         content.styles,
                     );
                     render_full_column(
@@ -467,6 +699,7 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
                         content.highlighter,
                         content.styles.gutter_context,
                         content.styles.line_context,
+                        new_line.and(line_marker(content.annotations, content.styles, new_filename, Some(new_idx as u32 + 1))),
                         content.styles,
                     );
                 }
@@ -487,31 +720,6 @@ fn render_side_by_side_full(content: DiffContent<'_>, area: Rect, buf: &mut Buff
     }
 }
 
-/// Pair old and new lines for side-by-side display
-fn pair_lines(lines: &[crate::git::DiffLine]) -> Vec<(Option<&crate::git::DiffLine>, Option<&crate::git::DiffLine>)> {
-    let mut pairs = Vec::new();
-
-    for line in lines {
-        match line.line_type {
-            LineType::Removed => {
-                // Removed lines appear on left only
-                pairs.push((Some(line), None));
-            }
-            LineType::Added => {
-                // Added lines appear on right only
-                pairs.push((None, Some(line)));
-            }
-            LineType::Context => {
-                // Context lines appear on both sides
-                pairs.push((Some(line), Some(line)));
-            }
-            LineType::Header => {}
-        }
-    }
-
-    pairs
-}
-
 #[derive(Clone, Copy)]
 struct IndexedLine<'a> {
     line: &'a crate::git::DiffLine,
@@ -552,8 +760,10 @@ fn render_file_header(buf: &mut Buffer, x: u16, y: u16, width: u16, diff: &FileD
         buf[(i, y)].set_char(' ').set_style(styles.file_header);
     }
 
-    let stats = format!(" +{} -{} ", diff.added, diff.removed);
-    let path_width = (width as usize).saturating_sub(stats.len() + 2);
+    // Binary files have no line stats; show what changed about the blob instead
+    let binary = diff.binary.as_ref().map(|info| format!(" binary · {} ", info.summary()));
+    let stats = binary.clone().unwrap_or_else(|| format!(" +{} -{} ", diff.added, diff.removed));
+    let path_width = (width as usize).saturating_sub(text::width(&stats) + 2);
 
     let display_path = if let Some(old_path) = &diff.old_path {
         format!("{} → {}", old_path, diff.path)
@@ -561,20 +771,23 @@ fn render_file_header(buf: &mut Buffer, x: u16, y: u16, width: u16, diff: &FileD
         diff.path.clone()
     };
 
-    let path = if display_path.len() > path_width && path_width > 3 {
-        format!("...{}", &display_path[display_path.len() - path_width + 3..])
-    } else {
-        display_path
-    };
+    let path = if path_width > 3 { text::truncate_start(&display_path, path_width) } else { display_path };
 
     let mut spans = vec![
         Span::styled(format!(" {} ", path), styles.file_header),
     ];
 
     // Add stats on the right
-    let current_len = path.len() + 2;
-    if current_len + stats.len() < width as usize {
-        let padding = width as usize - current_len - stats.len();
+    let current_len = text::width(&path) + 2;
+    let stats_width = text::width(&stats);
+    if let Some(binary) = binary {
+        if current_len + stats_width < width as usize {
+            let padding = width as usize - current_len - stats_width;
+            spans.push(Span::styled(" ".repeat(padding), styles.file_header));
+            spans.push(Span::styled(binary, styles.file_header));
+        }
+    } else if current_len + stats_width < width as usize {
+        let padding = width as usize - current_len - stats_width;
         spans.push(Span::styled(" ".repeat(padding), styles.file_header));
         spans.push(Span::styled(format!("+{}", diff.added), styles.stats_added));
         spans.push(Span::styled(" ", styles.file_header));
@@ -586,6 +799,71 @@ fn render_file_header(buf: &mut Buffer, x: u16, y: u16, width: u16, diff: &FileD
     buf.set_line(x, y, &line, width);
 }
 
+/// Render a section heading, e.g. the commit a group of files belongs to
+fn render_section_header(buf: &mut Buffer, x: u16, y: u16, width: u16, section: &str, styles: &Styles) {
+    for i in x..x + width {
+        buf[(i, y)].set_char('━').set_style(styles.section_header);
+    }
+    let title = format!("━━ {} ", section);
+    buf.set_line(x, y, &Line::styled(title, styles.section_header), width);
+}
+
+/// A binary file's contents as display rows: a hexdump or an image preview
+#[derive(Clone, Copy)]
+enum BinaryRows<'a> {
+    Hexdump(Hexdump<'a>),
+    Image(ImagePreview<'a>),
+}
+
+impl<'a> BinaryRows<'a> {
+    /// How a file's contents are shown, if it's binary and they are
+    fn of(diff: &'a FileDiff) -> Option<Self> {
+        Hexdump::of(diff).map(Self::Hexdump).or_else(|| ImagePreview::of(diff).map(Self::Image))
+    }
+
+    /// Number of display rows
+    fn rows(&self) -> usize {
+        match self {
+            Self::Hexdump(hex) => hex.rows(),
+            Self::Image(image) => image.rows(),
+        }
+    }
+
+    /// Render one row, old contents on the left half and new on the right
+    fn render_row(&self, buf: &mut Buffer, x: u16, y: u16, width: u16, row: usize, styles: &Styles) {
+        match self {
+            Self::Hexdump(hex) => hex.render_row(buf, x, y, width, row, styles),
+            Self::Image(image) => image.render_row(buf, x, y, width, row, styles),
+        }
+    }
+}
+
+/// Render a binary file's hexdump or image preview rows, advancing `current_line`
+///
+/// Returns true once the bottom of the viewport is reached.
+fn render_binary_rows(
+    buf: &mut Buffer,
+    area: Rect,
+    contents: BinaryRows<'_>,
+    current_line: &mut usize,
+    visible_start: usize,
+    visible_end: usize,
+    styles: &Styles,
+) -> bool {
+    let rows = contents.rows();
+    let first = visible_start.saturating_sub(*current_line).min(rows);
+    *current_line += first;
+    for row in first..rows {
+        if *current_line >= visible_end {
+            return true;
+        }
+        let y = area.y + (*current_line - visible_start) as u16;
+        contents.render_row(buf, area.x, y, area.width, row, styles);
+        *current_line += 1;
+    }
+    *current_line >= visible_end
+}
+
 /// Render a hunk header
 fn render_hunk_header(buf: &mut Buffer, x: u16, y: u16, width: u16, hunk: &Hunk, styles: &Styles) {
     let header = if hunk.header.is_empty() {
@@ -600,21 +878,179 @@ fn render_hunk_header(buf: &mut Buffer, x: u16, y: u16, width: u16, hunk: &Hunk,
     buf.set_line(x, y, &Line::styled(header, styles.hunk_header), width);
 }
 
-/// Render a unified diff line
-fn render_unified_line(
+/// Columns before a unified line's text: its line number and gutter
+const UNIFIED_PREFIX: u16 = 8;
+
+/// Text columns lines wrap at in `mode`, when wrapping and the mode is a unified one
+fn wrap_columns(mode: DiffMode, wrap: Option<u16>) -> Option<usize> {
+    match mode {
+        DiffMode::Unified | DiffMode::WordDiff => wrap.map(|width| width.saturating_sub(UNIFIED_PREFIX).max(1) as usize),
+        DiffMode::SideBySide | DiffMode::SideBySideFull => None,
+    }
+}
+
+/// Rows a unified line takes, wrapped at `columns` text columns
+fn line_height(line: &crate::git::DiffLine, columns: Option<usize>) -> usize {
+    match columns {
+        Some(columns) if line.line_type != LineType::Header => {
+            text::wrap(&expand_tabs(&line.content, TAB_WIDTH), columns).len()
+        }
+        _ => 1,
+    }
+}
+
+/// Rows a word-diff row takes, wrapped at `columns` text columns
+fn word_row_height(hunk: &Hunk, row: WordRow, columns: Option<usize>) -> usize {
+    match (row, columns) {
+        (WordRow::Line(i), _) => line_height(&hunk.lines[i], columns),
+        (WordRow::Pair(old, new), Some(columns)) => {
+            let text: String = word_spans(&hunk.lines[old], &hunk.lines[new], &Styles::default())
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect();
+            text::wrap(&text, columns).len()
+        }
+        (WordRow::Pair(..), None) => 1,
+    }
+}
+
+/// Split styled text into rows of at most `columns` columns, the way [`text::wrap`] does
+fn wrap_spans(spans: Vec<Span<'static>>, columns: usize) -> Vec<Vec<Span<'static>>> {
+    let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+    let mut rows = Vec::new();
+    let mut row_start = 0;
+    for row in text::wrap(&text, columns) {
+        let row_end = row_start + row.len();
+        let mut row_spans = Vec::new();
+        let mut span_start = 0;
+        for span in &spans {
+            let span_end = span_start + span.content.len();
+            let (from, to) = (row_start.max(span_start), row_end.min(span_end));
+            if from < to {
+                let part = span.content[from - span_start..to - span_start].to_string();
+                row_spans.push(Span::styled(part, span.style));
+            }
+            span_start = span_end;
+        }
+        rows.push(row_spans);
+        row_start = row_end;
+    }
+    rows
+}
+
+/// Draw a unified row's line number (or nothing), gutter and text at `y`, filling the rest with `style`
+#[allow(clippy::too_many_arguments)]
+fn render_unified_row(
     buf: &mut Buffer,
-    x: u16,
+    area: Rect,
     y: u16,
-    width: u16,
+    lineno: &str,
+    gutter: Span<'static>,
+    content: Vec<Span<'static>>,
+    style: Style,
+    styles: &Styles,
+) {
+    buf.set_line(area.x, y, &Line::styled(lineno, styles.line_number), 6);
+    buf.set_line(area.x + 6, y, &Line::from(gutter), 2);
+    let content_x = area.x + UNIFIED_PREFIX;
+    let content_width = area.width.saturating_sub(UNIFIED_PREFIX);
+    for i in content_x..(content_x + content_width) {
+        buf[(i, y)].set_char(' ').set_style(style);
+    }
+    buf.set_line(content_x, y, &Line::from(content), content_width);
+}
+
+/// Draw the rows of a line whose first row is `top` rows below the top of `area`, wrapped at `columns`
+///
+/// Rows above or below `area` are skipped; continuation rows leave the line number blank.
+#[allow(clippy::too_many_arguments)]
+fn render_wrapped_rows(
+    buf: &mut Buffer,
+    area: Rect,
+    top: isize,
+    lineno: String,
+    gutter: Span<'static>,
+    content: Vec<Span<'static>>,
+    columns: Option<usize>,
+    style: Style,
+    styles: &Styles,
+) {
+    let rows = match columns {
+        Some(columns) => wrap_spans(content, columns),
+        None => vec![content],
+    };
+    for (i, row) in rows.into_iter().enumerate() {
+        let y = top + i as isize;
+        if y < 0 {
+            continue;
+        }
+        if y >= area.height as isize {
+            break;
+        }
+        let lineno = if i == 0 { lineno.as_str() } else { "" };
+        render_unified_row(buf, area, area.y + y as u16, lineno, gutter.clone(), row, style, styles);
+    }
+}
+
+/// Gutter glyph, gutter style and text style of a diff line
+///
+/// Conflict markers left in added lines stand out most, then lines moved
+/// from or to elsewhere, then plain additions and removals.
+fn line_look(line: &crate::git::DiffLine, styles: &Styles) -> (&'static str, Style, Style) {
+    match line.line_type {
+        LineType::Added if is_conflict_marker(&line.content) => ("! ", styles.conflict_marker, styles.conflict_marker),
+        LineType::Added if line.moved => ("┆ ", styles.gutter_moved, styles.line_moved_added),
+        LineType::Removed if line.moved => ("┆ ", styles.gutter_moved, styles.line_moved_removed),
+        LineType::Added => ("│ ", styles.gutter_added, styles.line_added),
+        LineType::Removed => ("│ ", styles.gutter_removed, styles.line_removed),
+        LineType::Context => ("│ ", styles.gutter_context, styles.line_context),
+        LineType::Header => ("  ", styles.line_context, styles.hunk_header),
+    }
+}
+
+/// A file's notes, by new-file line
+fn file_notes<'a>(notes: &'a LineNotes, path: &str) -> &'a BTreeMap<u32, String> {
+    static NONE: BTreeMap<u32, String> = BTreeMap::new();
+    notes.get(path).unwrap_or(&NONE)
+}
+
+/// Note on a line of the new file, from its file's notes
+fn line_note<'a>(line: &crate::git::DiffLine, notes: &'a BTreeMap<u32, String>) -> Option<&'a String> {
+    notes.get(&line.new_lineno?)
+}
+
+/// Rows a line's note takes under it; the full-file view marks the line instead
+fn note_rows(line: &crate::git::DiffLine, notes: &BTreeMap<u32, String>, mode: DiffMode) -> usize {
+    usize::from(mode != DiffMode::SideBySideFull && line_note(line, notes).is_some())
+}
+
+/// Draw a note on the row `top` rows below the top of `area`, if that's inside it
+fn render_note_row(buf: &mut Buffer, area: Rect, top: isize, note: &str, styles: &Styles) {
+    if top < 0 || top >= area.height as isize {
+        return;
+    }
+    let line = Line::from(vec![
+        Span::raw(" ".repeat(6)),
+        Span::styled("✎ ", styles.annotation_note),
+        Span::styled(note.to_string(), styles.annotation_note),
+    ]);
+    buf.set_line(area.x, area.y + top as u16, &line, area.width);
+}
+
+/// Render a unified diff line, its first row `top` rows below the top of `area`
+#[allow(clippy::too_many_arguments)]
+fn render_unified_line(
+    buf: &mut Buffer,
+    area: Rect,
+    top: isize,
     line: &crate::git::DiffLine,
     filename: &str,
     line_index: usize,
     highlighter: &mut Highlighter,
+    marker: Option<Style>,
+    columns: Option<usize>,
     styles: &Styles,
 ) {
-    let line_num_width: u16 = 6;
-    let gutter_width: u16 = 2;
-
     // Line number
     let lineno = line.new_lineno.or(line.old_lineno).unwrap_or(0);
     let lineno_str = if lineno > 0 {
@@ -622,36 +1058,26 @@ fn render_unified_line(
     } else {
         "      ".to_string()
     };
-    buf.set_line(x, y, &Line::styled(&lineno_str, styles.line_number), line_num_width);
 
     // Gutter indicator
-    let (gutter_char, gutter_style, line_style) = match line.line_type {
-        LineType::Added => ("│ ", styles.gutter_added, styles.line_added),
-        LineType::Removed => ("│ ", styles.gutter_removed, styles.line_removed),
-        LineType::Context => ("│ ", styles.gutter_context, styles.line_context),
-        LineType::Header => ("  ", styles.line_context, styles.hunk_header),
+    let (gutter_char, gutter_style, line_style) = line_look(line, styles);
+    let gutter = match marker {
+        Some(style) => Span::styled("● ", style),
+        None => Span::styled(gutter_char, gutter_style),
     };
-    buf.set_line(
-        x + line_num_width,
-        y,
-        &Line::styled(gutter_char, gutter_style),
-        gutter_width,
-    );
-
-    // Content
-    let content_x = x + line_num_width + gutter_width;
-    let content_width = width.saturating_sub(line_num_width + gutter_width);
 
     if line.line_type == LineType::Header {
-        let content = truncate_str(&line.content, content_width as usize);
-        buf.set_line(content_x, y, &Line::styled(content, styles.hunk_header), content_width);
+        if top >= 0 {
+            let y = area.y + top as u16;
+            buf.set_line(area.x, y, &Line::styled(&lineno_str, styles.line_number), 6);
+            buf.set_line(area.x + 6, y, &Line::from(gutter), 2);
+            let content_width = area.width.saturating_sub(UNIFIED_PREFIX);
+            let content = text::take_width(&line.content, content_width as usize);
+            buf.set_line(area.x + UNIFIED_PREFIX, y, &Line::styled(content, styles.hunk_header), content_width);
+        }
         return;
     }
 
-    for i in content_x..(content_x + content_width) {
-        buf[(i, y)].set_char(' ').set_style(line_style);
-    }
-
     let spans = highlight_spans(
         filename,
         filename,
@@ -660,12 +1086,54 @@ fn render_unified_line(
         highlighter,
         line_style,
     );
+    render_wrapped_rows(buf, area, top, lineno_str, gutter, spans, columns, line_style, styles);
+}
 
-    let content_line = Line::from(spans);
-    buf.set_line(content_x, y, &content_line, content_width);
+/// A changed line pair as one line of text, changed words marked `[-old-]{+new+}`
+fn word_spans(old: &crate::git::DiffLine, new: &crate::git::DiffLine, styles: &Styles) -> Vec<Span<'static>> {
+    let old_content = expand_tabs(&old.content, TAB_WIDTH);
+    let new_content = expand_tabs(&new.content, TAB_WIDTH);
+    word_diff(&old_content, &new_content)
+        .into_iter()
+        .flat_map(|(change, text)| match change {
+            WordChange::Same => vec![Span::styled(text.to_string(), styles.line_context)],
+            WordChange::Removed => vec![
+                Span::styled("[-", styles.gutter_removed),
+                Span::styled(text.to_string(), styles.word_removed),
+                Span::styled("-]", styles.gutter_removed),
+            ],
+            WordChange::Added => vec![
+                Span::styled("{+", styles.gutter_added),
+                Span::styled(text.to_string(), styles.word_added),
+                Span::styled("+}", styles.gutter_added),
+            ],
+        })
+        .collect()
+}
+
+/// Render a changed line pair as one line, its first row `top` rows below the top of `area`
+#[allow(clippy::too_many_arguments)]
+fn render_word_line(
+    buf: &mut Buffer,
+    area: Rect,
+    top: isize,
+    old: &crate::git::DiffLine,
+    new: &crate::git::DiffLine,
+    marker: Option<Style>,
+    columns: Option<usize>,
+    styles: &Styles,
+) {
+    let lineno_str = match new.new_lineno {
+        Some(lineno) => format!("{:>5} ", lineno),
+        None => "      ".to_string(),
+    };
+    let gutter = Span::styled(if marker.is_some() { "● " } else { "│ " }, marker.unwrap_or(styles.gutter_context));
+    let spans = word_spans(old, new, styles);
+    render_wrapped_rows(buf, area, top, lineno_str, gutter, spans, columns, styles.line_context, styles);
 }
 
 /// Render one side of a side-by-side column
+#[allow(clippy::too_many_arguments)]
 fn render_side_column(
     buf: &mut Buffer,
     x: u16,
@@ -675,6 +1143,7 @@ fn render_side_column(
     line: Option<IndexedLine<'_>>,
     filename: &str,
     highlighter: &mut Highlighter,
+    marker: Option<Style>,
     styles: &Styles,
     is_old: bool,
 ) {
@@ -692,11 +1161,10 @@ fn render_side_column(
             buf.set_line(x, y, &Line::styled(&lineno_str, styles.line_number), line_num_width);
 
             // Gutter
-            let (gutter_char, gutter_style, line_style) = match l.line_type {
-                LineType::Added => ("│ ", styles.gutter_added, styles.line_added),
-                LineType::Removed => ("│ ", styles.gutter_removed, styles.line_removed),
-                LineType::Context => ("│ ", styles.gutter_context, styles.line_context),
-                LineType::Header => ("  ", styles.line_context, styles.hunk_header),
+            let (gutter_char, gutter_style, line_style) = line_look(l, styles);
+            let (gutter_char, gutter_style) = match marker {
+                Some(style) => ("● ", style),
+                None => (gutter_char, gutter_style),
             };
             buf.set_line(
                 x + line_num_width,
@@ -710,7 +1178,7 @@ fn render_side_column(
             let content_width = width.saturating_sub(line_num_width + gutter_width);
 
             if l.line_type == LineType::Header {
-                let content = truncate_str(&l.content, content_width as usize);
+                let content = text::take_width(&l.content, content_width as usize);
                 buf.set_line(content_x, y, &Line::styled(content, styles.hunk_header), content_width);
                 return;
             }
@@ -740,6 +1208,7 @@ fn render_side_column(
 }
 
 /// Render one column in full-file mode
+#[allow(clippy::too_many_arguments)]
 fn render_full_column(
     buf: &mut Buffer,
     x: u16,
@@ -754,11 +1223,16 @@ fn render_full_column(
     highlighter: &mut Highlighter,
     gutter_style: Style,
     line_style: Style,
+    marker: Option<Style>,
     styles: &Styles,
 ) {
     let gutter_width: u16 = 2;
 
     if let Some(content) = content {
+        let (gutter_char, gutter_style) = match marker {
+            Some(style) => ("● ", style),
+            None => ("│ ", gutter_style),
+        };
         let lineno_str = match lineno {
             Some(n) if n > 0 => format!("{:>5} ", n),
             _ => "      ".to_string(),
@@ -767,7 +1241,7 @@ fn render_full_column(
         buf.set_line(
             x + line_num_width,
             y,
-            &Line::styled("│ ", gutter_style),
+            &Line::styled(gutter_char, gutter_style),
             gutter_width,
         );
 
@@ -825,7 +1299,7 @@ fn expand_tabs_tokens(tokens: &[Token], tab_width: usize) -> Vec<Token> {
         for ch in token.text.chars() {
             if ch == '\t' {
                 let spaces = tab_width.saturating_sub(col % tab_width).max(1);
-                text.extend(std::iter::repeat(' ').take(spaces));
+                text.extend(std::iter::repeat_n(' ', spaces));
                 col += spaces;
             } else {
                 text.push(ch);
@@ -851,7 +1325,7 @@ fn expand_tabs(content: &str, tab_width: usize) -> String {
     for ch in content.chars() {
         if ch == '\t' {
             let spaces = tab_width.saturating_sub(col % tab_width).max(1);
-            expanded.extend(std::iter::repeat(' ').take(spaces));
+            expanded.extend(std::iter::repeat_n(' ', spaces));
             col += spaces;
         } else {
             expanded.push(ch);
@@ -862,51 +1336,299 @@ fn expand_tabs(content: &str, tab_width: usize) -> String {
     expanded
 }
 
-/// Truncate a string to fit width
-fn truncate_str(s: &str, max_width: usize) -> String {
-    if s.width() <= max_width {
-        s.to_string()
-    } else {
-        let mut result = String::new();
-        let mut width = 0;
-        for c in s.chars() {
-            let cw = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
-            if width + cw > max_width {
-                break;
+/// Rows above a file's first hunk: its section heading, if any, and file header
+pub fn header_rows(diff: &FileDiff) -> usize {
+    1 + usize::from(diff.section.is_some())
+}
+
+/// Rows a file's block takes, long lines wrapped at `wrap` columns in the unified views
+pub fn file_line_count(diff: &FileDiff, notes: &LineNotes, mode: DiffMode, wrap: Option<u16>) -> usize {
+    let notes = file_notes(notes, &diff.path);
+    let mode = file_mode(diff, mode);
+    let columns = wrap_columns(mode, wrap);
+    let mut total = header_rows(diff);
+
+    if diff.collapsed {
+        return total;
+    }
+    if let Some(contents) = BinaryRows::of(diff) {
+        return total + contents.rows();
+    }
+    if diff.is_binary {
+        return total;
+    }
+
+    match mode {
+        DiffMode::SideBySide | DiffMode::Unified | DiffMode::WordDiff => {
+            for hunk in &diff.hunks {
+                total += 1; // Hunk header
+                total += displayed_line_count(hunk, notes, mode, columns);
             }
-            result.push(c);
-            width += cw;
         }
-        result
+        DiffMode::SideBySideFull => {
+            total += full_line_count(diff);
+        }
     }
+
+    total
 }
 
-/// Calculate total number of lines in the diff view
-pub fn calculate_total_lines(diffs: &[&FileDiff], mode: DiffMode) -> usize {
-    diffs.iter().map(|diff| file_line_count(*diff, mode)).sum()
+/// Row offset of a new-file line within a file's rendered block
+///
+/// The block starts with [`header_rows`] header rows. Returns `None` if the
+/// line is not displayed.
+pub fn new_line_offset(diff: &FileDiff, notes: &LineNotes, mode: DiffMode, wrap: Option<u16>, lineno: u32) -> Option<usize> {
+    let notes = file_notes(notes, &diff.path);
+    let mode = file_mode(diff, mode);
+    if diff.collapsed || diff.is_binary {
+        return None;
+    }
+
+    let target = lineno as usize;
+    let mut row = header_rows(diff);
+
+    if mode != DiffMode::SideBySideFull {
+        let columns = wrap_columns(mode, wrap);
+        for hunk in &diff.hunks {
+            row += 1; // Hunk header
+            for (height, new_lineno) in hunk_rows(hunk, notes, mode, columns) {
+                if new_lineno == Some(lineno) {
+                    return Some(row);
+                }
+                row += height;
+            }
+        }
+        return None;
+    }
+
+    let has_full_content = diff.old_content.is_some() || diff.new_content.is_some();
+    let old_len = diff.old_content.as_ref().map_or(0, |lines| lines.len());
+    let new_len = diff.new_content.as_ref().map_or(0, |lines| lines.len());
+    let mut old_idx = 0usize;
+    let mut new_idx = 0usize;
+
+    for hunk in &diff.hunks {
+        if has_full_content {
+            let old_target = hunk.old_start.saturating_sub(1) as usize;
+            let new_target = hunk.new_start.saturating_sub(1) as usize;
+            while old_idx < old_target || new_idx < new_target {
+                if new_idx < new_target && new_idx + 1 == target {
+                    return Some(row);
+                }
+                row += 1;
+                if old_idx < old_target {
+                    old_idx += 1;
+                }
+                if new_idx < new_target {
+                    new_idx += 1;
+                }
+            }
+        }
+
+        for line in &hunk.lines {
+            match line.line_type {
+                LineType::Context | LineType::Added if line.new_lineno == Some(lineno) => {
+                    return Some(row);
+                }
+                _ => {}
+            }
+            match line.line_type {
+                LineType::Context => {
+                    old_idx += 1;
+                    new_idx += 1;
+                }
+                LineType::Removed => old_idx += 1,
+                LineType::Added => new_idx += 1,
+                LineType::Header => {}
+            }
+            row += 1;
+        }
+    }
+
+    if has_full_content {
+        while old_idx < old_len || new_idx < new_len {
+            if new_idx < new_len && new_idx + 1 == target {
+                return Some(row);
+            }
+            row += 1;
+            if old_idx < old_len {
+                old_idx += 1;
+            }
+            if new_idx < new_len {
+                new_idx += 1;
+            }
+        }
+    }
+
+    None
 }
 
-pub fn file_line_count(diff: &FileDiff, mode: DiffMode) -> usize {
-    let mut total = 1; // File header
+/// New-file line shown at `offset` within a file's rendered block
+///
+/// Rows without one, like headers and removed lines, give the next line
+/// that has one, or the last before them at the end of the file. `None`
+/// when the file shows no new lines at all, e.g. when deleted.
+pub fn line_at_offset(diff: &FileDiff, notes: &LineNotes, mode: DiffMode, wrap: Option<u16>, offset: usize) -> Option<u32> {
+    let rows = new_line_rows(diff, notes, mode, wrap);
+    let row = offset.saturating_sub(header_rows(diff)).min(rows.len().saturating_sub(1));
+    rows.get(row..)?
+        .iter()
+        .flatten()
+        .next()
+        .or_else(|| rows[..row].iter().rev().flatten().next())
+        .copied()
+}
 
+/// New-file line of each row below a file's [`header_rows`], if it shows one
+///
+/// Empty when the file is collapsed or binary. A wrapped line's continuation
+/// rows show none.
+pub fn new_line_rows(diff: &FileDiff, notes: &LineNotes, mode: DiffMode, wrap: Option<u16>) -> Vec<Option<u32>> {
+    let notes = file_notes(notes, &diff.path);
+    let mode = file_mode(diff, mode);
+    let mut rows: Vec<Option<u32>> = Vec::new();
     if diff.collapsed || diff.is_binary {
-        return total;
+        return rows;
     }
 
     match mode {
-        DiffMode::SideBySide | DiffMode::Unified => {
+        DiffMode::SideBySide | DiffMode::Unified | DiffMode::WordDiff => {
+            let columns = wrap_columns(mode, wrap);
             for hunk in &diff.hunks {
-                total += 1; // Hunk header
-                let pairs = pair_lines(&hunk.lines);
-                total += pairs.len();
+                rows.push(None);
+                for (height, new_lineno) in hunk_rows(hunk, notes, mode, columns) {
+                    rows.push(new_lineno);
+                    rows.extend(std::iter::repeat_n(None, height - 1));
+                }
             }
         }
         DiffMode::SideBySideFull => {
-            total += full_line_count(diff);
+            let has_full_content = diff.old_content.is_some() || diff.new_content.is_some();
+            let old_len = diff.old_content.as_ref().map_or(0, |lines| lines.len());
+            let new_len = diff.new_content.as_ref().map_or(0, |lines| lines.len());
+            let (mut old_idx, mut new_idx) = (0usize, 0usize);
+            // Unchanged rows between hunks, advancing both sides toward the targets
+            let gap = |rows: &mut Vec<Option<u32>>, old_idx: &mut usize, new_idx: &mut usize, old_to: usize, new_to: usize| {
+                while *old_idx < old_to || *new_idx < new_to {
+                    rows.push((*new_idx < new_to).then_some(*new_idx as u32 + 1));
+                    if *old_idx < old_to {
+                        *old_idx += 1;
+                    }
+                    if *new_idx < new_to {
+                        *new_idx += 1;
+                    }
+                }
+            };
+            for hunk in &diff.hunks {
+                if has_full_content {
+                    let (old_to, new_to) = (hunk.old_start.saturating_sub(1) as usize, hunk.new_start.saturating_sub(1) as usize);
+                    gap(&mut rows, &mut old_idx, &mut new_idx, old_to, new_to);
+                }
+                for line in &hunk.lines {
+                    match line.line_type {
+                        LineType::Context => {
+                            old_idx += 1;
+                            new_idx += 1;
+                        }
+                        LineType::Removed => old_idx += 1,
+                        LineType::Added => new_idx += 1,
+                        LineType::Header => continue,
+                    }
+                    rows.push(line.new_lineno);
+                }
+            }
+            if has_full_content {
+                gap(&mut rows, &mut old_idx, &mut new_idx, old_len, new_len);
+            }
         }
     }
+    rows
+}
 
-    total
+/// Row offsets of each hunk's first line within a file's rendered block
+///
+/// The block starts with [`header_rows`] header rows. In split and unified
+/// modes these are the hunk header rows. Empty when the file is collapsed or
+/// binary.
+pub fn hunk_offsets(diff: &FileDiff, notes: &LineNotes, mode: DiffMode, wrap: Option<u16>) -> Vec<usize> {
+    let notes = file_notes(notes, &diff.path);
+    let mode = file_mode(diff, mode);
+    if diff.collapsed || diff.is_binary {
+        return Vec::new();
+    }
+
+    let mut offsets = Vec::with_capacity(diff.hunks.len());
+    let mut row = header_rows(diff);
+
+    if mode != DiffMode::SideBySideFull {
+        let columns = wrap_columns(mode, wrap);
+        for hunk in &diff.hunks {
+            offsets.push(row);
+            row += 1 + displayed_line_count(hunk, notes, mode, columns);
+        }
+        return offsets;
+    }
+
+    let has_full_content = diff.old_content.is_some() || diff.new_content.is_some();
+    let mut old_idx = 0usize;
+    let mut new_idx = 0usize;
+
+    for hunk in &diff.hunks {
+        if has_full_content {
+            let old_target = hunk.old_start.saturating_sub(1) as usize;
+            let new_target = hunk.new_start.saturating_sub(1) as usize;
+            row += old_target.saturating_sub(old_idx).max(new_target.saturating_sub(new_idx));
+            old_idx = old_idx.max(old_target);
+            new_idx = new_idx.max(new_target);
+        }
+        offsets.push(row);
+
+        for line in &hunk.lines {
+            match line.line_type {
+                LineType::Context => {
+                    old_idx += 1;
+                    new_idx += 1;
+                }
+                LineType::Removed => old_idx += 1,
+                LineType::Added => new_idx += 1,
+                LineType::Header => continue,
+            }
+            row += 1;
+        }
+    }
+
+    offsets
+}
+
+/// Rows a hunk's lines occupy in split, unified and word-diff modes
+fn displayed_line_count(hunk: &Hunk, notes: &BTreeMap<u32, String>, mode: DiffMode, columns: Option<usize>) -> usize {
+    let noted: usize = hunk.lines.iter().map(|line| note_rows(line, notes, mode)).sum();
+    match (mode, columns) {
+        (DiffMode::WordDiff, None) => word_row_count(hunk) + noted,
+        (_, None) => hunk.lines.iter().filter(|l| l.line_type != LineType::Header).count() + noted,
+        (_, Some(_)) => hunk_rows(hunk, notes, mode, columns).map(|(height, _)| height).sum(),
+    }
+}
+
+/// Height, note included, and new-file line of each line a hunk shows in split, unified and word-diff modes
+fn hunk_rows<'a>(
+    hunk: &'a Hunk,
+    notes: &'a BTreeMap<u32, String>,
+    mode: DiffMode,
+    columns: Option<usize>,
+) -> Box<dyn Iterator<Item = (usize, Option<u32>)> + 'a> {
+    if mode == DiffMode::WordDiff {
+        return Box::new(word_rows(hunk).into_iter().map(move |row| {
+            let (WordRow::Line(i) | WordRow::Pair(_, i)) = row;
+            (word_row_height(hunk, row, columns) + note_rows(&hunk.lines[i], notes, mode), hunk.lines[i].new_lineno)
+        }));
+    }
+    Box::new(
+        hunk.lines
+            .iter()
+            .filter(|l| l.line_type != LineType::Header)
+            .map(move |line| (line_height(line, columns) + note_rows(line, notes, mode), line.new_lineno)),
+    )
 }
 
 fn full_line_count(diff: &FileDiff) -> usize {
@@ -914,7 +1636,7 @@ fn full_line_count(diff: &FileDiff) -> usize {
     let new_len = diff.new_content.as_ref().map(|lines| lines.len()).unwrap_or(0);
 
     if diff.old_content.is_none() && diff.new_content.is_none() {
-        return diff.hunks.iter().map(|h| pair_lines(&h.lines).len()).sum();
+        return diff.hunks.iter().map(|hunk| displayed_line_count(hunk, &BTreeMap::new(), DiffMode::SideBySideFull, None)).sum();
     }
 
     if old_len >= new_len {
@@ -925,20 +1647,29 @@ fn full_line_count(diff: &FileDiff) -> usize {
 }
 
 /// Render the diff content
+#[allow(clippy::too_many_arguments)]
 pub fn render_diff_content(
     buf: &mut Buffer,
     area: Rect,
-    diffs: &[&FileDiff],
+    diffs: &[FileDiff],
+    visible: &[usize],
     scroll: usize,
     mode: DiffMode,
     highlighter: &mut Highlighter,
+    annotations: &AnnotationIndex,
+    notes: &LineNotes,
+    wrap: Option<u16>,
     styles: &Styles,
 ) {
     let content = DiffContent {
         diffs,
+        visible,
         scroll,
         mode,
         highlighter,
+        annotations,
+        notes,
+        wrap,
         styles,
     };
     content.render(area, buf);
@@ -0,0 +1,235 @@
+//! Diffstat
+//!
+//! Full-screen table of the changed files and the folders holding them,
+//! each with its added and removed lines and a `+`/`-` bar like
+//! `git diff --stat`, folders rolling up everything under them.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Widget},
+};
+use super::{Styles, TreeNode};
+use super::text::{pad, truncate};
+
+/// Widest the `+`/`-` bar gets
+const MAX_BAR: usize = 50;
+
+/// Diffstat row order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffstatSort {
+    /// As the sidebar lists them
+    #[default]
+    Path,
+    /// Most changed lines first within each folder
+    Churn,
+}
+
+impl DiffstatSort {
+    /// The other sort order
+    pub fn next(self) -> Self {
+        match self {
+            Self::Path => Self::Churn,
+            Self::Churn => Self::Path,
+        }
+    }
+
+    /// Short name shown in the title
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::Churn => "churn",
+        }
+    }
+
+    /// Indices of `nodes`, a tree from `build_file_tree`, in this order
+    ///
+    /// Sorting by churn keeps each entry under its folder, so the folder
+    /// totals still read as roll-ups.
+    pub fn order(self, nodes: &[TreeNode]) -> Vec<usize> {
+        if self == Self::Path {
+            return (0..nodes.len()).collect();
+        }
+        let parent = |path: &str| path.rsplit_once('/').map_or(String::new(), |(parent, _)| parent.to_string());
+        let mut children: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            children.entry(parent(&node.path)).or_default().push(i);
+        }
+        for entries in children.values_mut() {
+            entries.sort_by_key(|&i| Reverse(nodes[i].added + nodes[i].removed));
+        }
+
+        fn walk(folder: &str, nodes: &[TreeNode], children: &HashMap<String, Vec<usize>>, order: &mut Vec<usize>) {
+            for &i in children.get(folder).into_iter().flatten() {
+                order.push(i);
+                if nodes[i].is_folder {
+                    walk(&nodes[i].path, nodes, children, order);
+                }
+            }
+        }
+        let mut order = Vec::with_capacity(nodes.len());
+        walk("", nodes, &children, &mut order);
+        order
+    }
+}
+
+/// Columns of `+` and `-` for a row, scaled so the largest row fills `width`
+fn bar(added: usize, removed: usize, largest: usize, width: usize) -> (usize, usize) {
+    let total = added + removed;
+    if total == 0 || largest == 0 {
+        return (0, 0);
+    }
+    // Scale the total, then split it, so neither side of a small change vanishes
+    let sides = usize::from(added > 0) + usize::from(removed > 0);
+    let columns = if largest <= width { total } else { (total * width).div_ceil(largest) }.max(sides);
+    let plus = ((added * columns + total / 2) / total).clamp(usize::from(added > 0), columns - usize::from(removed > 0));
+    (plus, columns - plus)
+}
+
+/// Diffstat widget
+pub struct Diffstat<'a> {
+    /// Files and folders, as built for the sidebar
+    pub nodes: &'a [TreeNode],
+    /// Display order, as indices into `nodes`
+    pub order: &'a [usize],
+    /// Cursor position within `order`
+    pub cursor: usize,
+    /// Current sort order
+    pub sort: DiffstatSort,
+    /// Styles
+    pub styles: &'a Styles,
+}
+
+impl Widget for Diffstat<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let files: Vec<&TreeNode> = self.nodes.iter().filter(|n| !n.is_folder).collect();
+        let added: usize = files.iter().map(|n| n.added).sum();
+        let removed: usize = files.iter().map(|n| n.removed).sum();
+        let title = format!(
+            " Diffstat ({} files, +{} -{}) · sorted by {} ",
+            files.len(),
+            added,
+            removed,
+            self.sort.label()
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.styles.border_focus)
+            .title(Span::styled(title, self.styles.popup_title));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if inner.height < 3 {
+            return;
+        }
+
+        // Name, count and bar columns, the bar taking what the names leave
+        let largest = self.nodes.iter().map(|n| n.added + n.removed).max().unwrap_or(0);
+        let count_width = largest.to_string().len().max(7);
+        let name_width = self.nodes
+            .iter()
+            .map(|n| n.depth * 2 + n.name.chars().count() + usize::from(n.is_folder))
+            .max()
+            .unwrap_or(0)
+            .clamp(20, (inner.width as usize / 2).max(20));
+        let bar_width = (inner.width as usize)
+            .saturating_sub(name_width + count_width + 5)
+            .min(MAX_BAR);
+
+        let header = format!(" {}  {:>cw$}  ", pad("PATH", name_width), "CHANGES", cw = count_width);
+        buf.set_line(inner.x, inner.y, &Line::styled(header, self.styles.line_number), inner.width);
+
+        // Keep the cursor in view, leaving the last line for keys
+        let visible = inner.height.saturating_sub(2) as usize;
+        let scroll = self.cursor.saturating_sub(visible.saturating_sub(1));
+
+        for (row, &i) in self.order.iter().enumerate().skip(scroll).take(visible) {
+            let y = inner.y + 1 + (row - scroll) as u16;
+            let node = &self.nodes[i];
+            let is_cursor = row == self.cursor;
+            let style = if is_cursor {
+                self.styles.sidebar_cursor
+            } else {
+                self.styles.sidebar_normal
+            };
+
+            let name = if node.is_folder { format!("{}/", node.name) } else { node.name.clone() };
+            let name = format!("{}{}", "  ".repeat(node.depth), name);
+            let name_style = if node.is_folder && !is_cursor { self.styles.folder_icon } else { style };
+            let (plus, minus) = bar(node.added, node.removed, largest, bar_width);
+            let line = Line::from(vec![
+                Span::styled(format!(" {}  ", pad(&truncate(&name, name_width), name_width)), name_style),
+                Span::styled(format!("{:>cw$}  ", node.added + node.removed, cw = count_width), style),
+                Span::styled("+".repeat(plus), self.styles.stats_added),
+                Span::styled("-".repeat(minus), self.styles.stats_removed),
+            ]);
+            buf.set_line(inner.x, y, &line, inner.width);
+            if is_cursor {
+                for x in inner.x..inner.x + inner.width {
+                    buf[(x, y)].set_bg(self.styles.sidebar_cursor.bg.unwrap_or_default());
+                }
+            }
+        }
+
+        let bottom = inner.y + inner.height - 1;
+        let keys = " Enter jump to file · s sort by path/churn · Esc back";
+        buf.set_line(inner.x, bottom, &Line::styled(keys, self.styles.footer), inner.width);
+    }
+}
+
+/// Render the diffstat table
+pub fn render_diffstat(
+    buf: &mut Buffer,
+    area: Rect,
+    nodes: &[TreeNode],
+    order: &[usize],
+    cursor: usize,
+    sort: DiffstatSort,
+    styles: &Styles,
+) {
+    Diffstat { nodes, order, cursor, sort, styles }.render(area, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str, is_folder: bool, added: usize, removed: usize) -> TreeNode {
+        TreeNode {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: path.to_string(),
+            is_folder,
+            depth: path.matches('/').count(),
+            added,
+            removed,
+            diff_index: (!is_folder).then_some(0),
+            hunk_index: None,
+            expanded: true,
+            is_hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_churn_order() {
+        let nodes = vec![
+            node("README.md", false, 1, 0),
+            node("src", true, 30, 5),
+            node("src/app.rs", false, 5, 5),
+            node("src/ui.rs", false, 25, 0),
+        ];
+        assert_eq!(DiffstatSort::Path.order(&nodes), vec![0, 1, 2, 3]);
+        assert_eq!(DiffstatSort::Churn.order(&nodes), vec![1, 3, 2, 0]);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(3, 2, 10, 50), (3, 2));
+        assert_eq!(bar(100, 100, 400, 40), (10, 10));
+        // A small change still shows
+        assert_eq!(bar(1, 1, 1000, 40), (1, 1));
+        assert_eq!(bar(0, 0, 10, 40), (0, 0));
+    }
+}
@@ -3,11 +3,13 @@
 //! Builds a tree of files and folders from a list of file paths,
 //! supporting collapsible folders and path disambiguation.
 
-use std::collections::HashMap;
-use crate::git::FileDiff;
+use std::collections::{HashMap, HashSet};
+use regex::Regex;
+use crate::git::{FileDiff, Hunk, LineType};
+use crate::syntax::detect_language;
 
-/// Lock files that are considered hidden
-const HIDDEN_PATTERNS: &[&str] = &[
+/// Lock files hidden unless the repository configures its own patterns
+pub const DEFAULT_HIDDEN: &[&str] = &[
     "go.sum",
     "package-lock.json",
     "yarn.lock",
@@ -18,16 +20,103 @@ const HIDDEN_PATTERNS: &[&str] = &[
     "composer.lock",
 ];
 
-/// Check if a file path is considered hidden (dotfile or lock file)
-pub fn is_hidden_file(path: &str) -> bool {
+/// Check if a file path is considered hidden: a dotfile, or matched by one of `patterns`
+///
+/// A pattern without a `/` is a glob for the file name; one with a `/`
+/// matches the whole path.
+pub fn is_hidden_file(path: &str, patterns: &[String]) -> bool {
     // Check for dotfiles/dotfolders (any path component starting with ".")
     if path.split('/').any(|part| part.starts_with('.')) {
         return true;
     }
 
-    // Check against specific hidden patterns
-    let filename = path.split('/').last().unwrap_or(path);
-    HIDDEN_PATTERNS.iter().any(|p| filename == *p)
+    // Check against the configured hidden patterns
+    let filename = path.split('/').next_back().unwrap_or(path);
+    patterns.iter().any(|pattern| match pattern.contains('/') {
+        true => glob_matches(pattern, path),
+        false => glob_matches(pattern, filename),
+    })
+}
+
+/// Lines at the top of a file searched for a generated-code marker
+const GENERATED_SCAN_LINES: usize = 20;
+
+/// Check if a file says it was generated: any of `patterns` matches near its top
+///
+/// Looks at the new content, or the old content of a deleted file.
+pub fn is_generated(diff: &FileDiff, patterns: &[Regex]) -> bool {
+    let Some(lines) = diff.new_content.as_ref().or(diff.old_content.as_ref()) else {
+        return false;
+    };
+    lines
+        .iter()
+        .take(GENERATED_SCAN_LINES)
+        .any(|line| patterns.iter().any(|pattern| pattern.is_match(line)))
+}
+
+/// Check if a path is marked directly or through a marked parent folder
+pub fn is_marked(path: &str, marked: &HashSet<String>) -> bool {
+    marked.iter().any(|m| {
+        path == m || (path.starts_with(m.as_str()) && path.as_bytes().get(m.len()) == Some(&b'/'))
+    })
+}
+
+/// Match a path against a glob
+///
+/// `*` and `?` stay within one path segment; a `**` segment spans any
+/// number of them, so `vendor/**` matches `vendor` itself and everything in it.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    segments_match(&pattern, &path)
+}
+
+/// Check if a path is matched by any of the path filter's globs
+///
+/// A glob naming a folder also matches everything in it, as a git pathspec would.
+pub fn path_filter_matches(globs: &[String], path: &str) -> bool {
+    globs.iter().any(|glob| glob_matches(&format!("{}/**", glob.trim_end_matches('/')), path))
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(name, path)| {
+            wildcard_match(segment.as_bytes(), name.as_bytes()) && segments_match(rest, path)
+        }),
+    }
+}
+
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+/// Check if `query`'s characters appear in order in `path`, ignoring case
+pub fn fuzzy_matches(query: &str, path: &str) -> bool {
+    let mut chars = path.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+/// Check if a file's extension or language starts with any of the
+/// space-separated words in `query`, ignoring case, so `rs` and `type` match
+/// `main.rs` and `app.tsx` alike
+pub fn language_matches(query: &str, path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let extension = name.rsplit_once('.').map_or("", |(_, ext)| ext).to_lowercase();
+    let language = detect_language(name).to_lowercase();
+    query
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('.').to_lowercase())
+        .any(|word| !word.is_empty() && (extension.starts_with(&word) || language.starts_with(&word)))
 }
 
 /// A node in the file tree
@@ -45,17 +134,28 @@ pub struct TreeNode {
     pub added: usize,
     /// Aggregated lines removed
     pub removed: usize,
-    /// Index into the diffs array (for files only)
+    /// Index into the diffs array (for files and hunks)
     pub diff_index: Option<usize>,
-    /// Whether this folder is expanded
+    /// Index into the file's hunks (for hunk entries only)
+    pub hunk_index: Option<usize>,
+    /// Whether this folder (or file's hunk list) is expanded
     pub expanded: bool,
     /// Whether this is a hidden file (dotfile or lock file)
     pub is_hidden: bool,
 }
 
-/// Build a file tree from a list of diffs
-pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bool>) -> Vec<TreeNode> {
-    if diffs.is_empty() {
+/// Build a file tree from the visible subset of a list of diffs
+///
+/// `visible` holds indices into `diffs`; node `diff_index` values refer to `diffs`.
+/// Files in `expanded_files` are followed by one child node per hunk.
+pub fn build_file_tree(
+    diffs: &[FileDiff],
+    visible: &[usize],
+    expanded_folders: &HashMap<String, bool>,
+    expanded_files: &HashSet<String>,
+    hidden: &[String],
+) -> Vec<TreeNode> {
+    if visible.is_empty() {
         return Vec::new();
     }
 
@@ -64,12 +164,15 @@ pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bo
     let mut all_nodes: Vec<TreeNode> = Vec::new();
 
     // First pass: collect all folders and their stats
-    for (i, diff) in diffs.iter().enumerate() {
+    for &i in visible {
+        let Some(diff) = diffs.get(i) else {
+            continue;
+        };
         let parts: Vec<&str> = diff.path.split('/').collect();
 
         // Add folder entries
         let mut current_path = String::new();
-        for (_depth, part) in parts.iter().take(parts.len() - 1).enumerate() {
+        for part in parts.iter().take(parts.len() - 1) {
             if !current_path.is_empty() {
                 current_path.push('/');
             }
@@ -89,8 +192,9 @@ pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bo
             added: diff.added,
             removed: diff.removed,
             diff_index: Some(i),
-            expanded: false,
-            is_hidden: is_hidden_file(&diff.path),
+            hunk_index: None,
+            expanded: expanded_files.contains(&diff.path) && !diff.hunks.is_empty(),
+            is_hidden: is_hidden_file(&diff.path, hidden),
         });
     }
 
@@ -99,7 +203,7 @@ pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bo
         .into_iter()
         .map(|(path, (added, removed))| {
             let depth = path.matches('/').count();
-            let name = path.split('/').last().unwrap_or(&path).to_string();
+            let name = path.split('/').next_back().unwrap_or(&path).to_string();
             let expanded = expanded_folders.get(&path).copied().unwrap_or(true);
 
             TreeNode {
@@ -110,8 +214,9 @@ pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bo
                 added,
                 removed,
                 diff_index: None,
+                hunk_index: None,
                 expanded,
-                is_hidden: is_hidden_file(&path),
+                is_hidden: is_hidden_file(&path, hidden),
             }
         })
         .collect();
@@ -120,15 +225,77 @@ pub fn build_file_tree(diffs: &[FileDiff], expanded_folders: &HashMap<String, bo
     folder_nodes.extend(all_nodes);
     folder_nodes.sort_by(|a, b| a.path.cmp(&b.path));
 
-    folder_nodes
+    // Insert hunks after their expanded files (kept in diff order)
+    let mut result = Vec::with_capacity(folder_nodes.len());
+    for node in folder_nodes {
+        let hunks: &[Hunk] = match node.diff_index {
+            Some(i) if node.expanded => &diffs[i].hunks,
+            _ => &[],
+        };
+        let children: Vec<TreeNode> = hunks
+            .iter()
+            .enumerate()
+            .map(|(h, hunk)| hunk_node(&node, h, hunk))
+            .collect();
+        result.push(node);
+        result.extend(children);
+    }
+
+    result
+}
+
+/// Build the sidebar entry for a single hunk of a file node
+fn hunk_node(file: &TreeNode, hunk_index: usize, hunk: &Hunk) -> TreeNode {
+    let added = hunk.lines.iter().filter(|l| l.line_type == LineType::Added).count();
+    let removed = hunk.lines.iter().filter(|l| l.line_type == LineType::Removed).count();
+
+    TreeNode {
+        name: hunk_label(hunk),
+        path: file.path.clone(),
+        is_folder: false,
+        depth: file.depth + 1,
+        added,
+        removed,
+        diff_index: file.diff_index,
+        hunk_index: Some(hunk_index),
+        expanded: false,
+        is_hidden: file.is_hidden,
+    }
+}
+
+/// Label for a hunk: its new-file line range and function context, if any
+///
+/// `@@ -10,4 +12,6 @@ fn main()` becomes `12-17 fn main()`.
+pub fn hunk_label(hunk: &Hunk) -> String {
+    let start = hunk.new_start;
+    let end = start + hunk.new_count.saturating_sub(1);
+    let range = if hunk.new_count == 0 {
+        format!("{}", hunk.old_start)
+    } else {
+        format!("{}-{}", start, end)
+    };
+
+    let context = hunk.header
+        .trim_start_matches("@@")
+        .split_once("@@")
+        .map(|(_, rest)| rest.trim())
+        .unwrap_or("");
+
+    if context.is_empty() {
+        range
+    } else {
+        format!("{} {}", range, context)
+    }
 }
 
 /// Flatten the tree for display, respecting collapsed folders
-pub fn flatten_tree(nodes: &[TreeNode]) -> Vec<&TreeNode> {
+///
+/// Returns the indices of the displayed nodes, to be used with [`FlatTree`].
+pub fn flatten_tree(nodes: &[TreeNode]) -> Vec<usize> {
     let mut result = Vec::new();
     let mut collapsed_prefixes: Vec<String> = Vec::new();
 
-    for node in nodes {
+    for (i, node) in nodes.iter().enumerate() {
         // Check if this node is under a collapsed folder
         let is_hidden = collapsed_prefixes.iter().any(|prefix| {
             node.path.starts_with(prefix) && node.path != *prefix
@@ -138,7 +305,7 @@ pub fn flatten_tree(nodes: &[TreeNode]) -> Vec<&TreeNode> {
             continue;
         }
 
-        result.push(node);
+        result.push(i);
 
         // If this is a collapsed folder, add it to the prefix list
         if node.is_folder && !node.expanded {
@@ -149,6 +316,44 @@ pub fn flatten_tree(nodes: &[TreeNode]) -> Vec<&TreeNode> {
     result
 }
 
+/// The displayed rows of a file tree
+///
+/// Pairs the tree with its cached [`flatten_tree`] result so row lookups
+/// don't re-walk the tree.
+#[derive(Clone, Copy)]
+pub struct FlatTree<'a> {
+    nodes: &'a [TreeNode],
+    rows: &'a [usize],
+}
+
+impl<'a> FlatTree<'a> {
+    /// Create a view over `nodes` showing `rows`
+    pub fn new(nodes: &'a [TreeNode], rows: &'a [usize]) -> Self {
+        Self { nodes, rows }
+    }
+
+    /// Node displayed at a given row
+    pub fn get(&self, row: usize) -> Option<&'a TreeNode> {
+        self.nodes.get(*self.rows.get(row)?)
+    }
+
+    /// Number of displayed rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether no rows are displayed
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Displayed nodes in order
+    pub fn iter(&self) -> impl Iterator<Item = &'a TreeNode> + 'a {
+        let nodes = self.nodes;
+        self.rows.iter().filter_map(move |&i| nodes.get(i))
+    }
+}
+
 /// Get display names for files, disambiguating duplicates
 #[allow(dead_code)]
 pub fn get_display_names(diffs: &[FileDiff]) -> HashMap<String, String> {
@@ -157,7 +362,7 @@ pub fn get_display_names(diffs: &[FileDiff]) -> HashMap<String, String> {
 
     // Group files by basename
     for diff in diffs {
-        let basename = diff.path.split('/').last().unwrap_or(&diff.path).to_string();
+        let basename = diff.path.split('/').next_back().unwrap_or(&diff.path).to_string();
         basename_counts
             .entry(basename)
             .or_default()
@@ -166,7 +371,7 @@ pub fn get_display_names(diffs: &[FileDiff]) -> HashMap<String, String> {
 
     // Assign display names
     for diff in diffs {
-        let basename = diff.path.split('/').last().unwrap_or(&diff.path).to_string();
+        let basename = diff.path.split('/').next_back().unwrap_or(&diff.path).to_string();
 
         if let Some(paths) = basename_counts.get(&basename) {
             if paths.len() > 1 {
@@ -205,6 +410,84 @@ fn find_unique_suffix(path: &str, all_paths: &[String]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::git::ChangeKind;
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("vendor/**", "vendor"));
+        assert!(glob_matches("vendor/**", "vendor/lib/a.rs"));
+        assert!(!glob_matches("vendor/**", "src/vendor/a.rs"));
+        assert!(glob_matches("**/snapshots/**", "src/tests/snapshots/a.snap"));
+        assert!(glob_matches("*.lock", "Cargo.lock"));
+        assert!(!glob_matches("*.lock", "web/yarn.lock"));
+        assert!(glob_matches("gen/?.rs", "gen/a.rs"));
+    }
+
+    #[test]
+    fn test_path_filter_matches() {
+        let globs = vec!["src/ui".to_string(), "*.md".to_string()];
+        assert!(path_filter_matches(&globs, "src/ui/header.rs"));
+        assert!(path_filter_matches(&globs, "README.md"));
+        assert!(!path_filter_matches(&globs, "src/app.rs"));
+        assert!(!path_filter_matches(&globs, "docs/guide.md"));
+        assert!(path_filter_matches(&["**/*.rs".to_string()], "src/app.rs"));
+    }
+
+    #[test]
+    fn test_is_hidden_file() {
+        let patterns = vec!["*.lock".to_string(), "dist/**".to_string()];
+        assert!(is_hidden_file(".github/ci.yml", &patterns));
+        assert!(is_hidden_file("web/yarn.lock", &patterns));
+        assert!(is_hidden_file("dist/app.js", &patterns));
+        assert!(!is_hidden_file("web/dist/app.js", &patterns));
+        assert!(!is_hidden_file("go.sum", &patterns));
+    }
+
+    #[test]
+    fn test_language_matches() {
+        assert!(language_matches("rs", "src/main.rs"));
+        assert!(language_matches(".RS", "src/main.rs"));
+        assert!(language_matches("typescript", "web/app.tsx"));
+        assert!(language_matches("go toml", "Cargo.toml"));
+        assert!(!language_matches("rs", "README.md"));
+        assert!(!language_matches("", "src/main.rs"));
+    }
+
+    #[test]
+    fn test_is_generated() {
+        let diff = |content: &[&str]| FileDiff {
+            path: "src/api.pb.go".to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: Some(content.iter().map(|l| l.to_string()).collect()),
+            added: 0,
+            removed: 0,
+            hunks: vec![],
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Added,
+        };
+        let patterns = [Regex::new("@generated").unwrap(), Regex::new(r"^// Code generated .* DO NOT EDIT\.$").unwrap()];
+        assert!(is_generated(&diff(&["// Code generated by protoc-gen-go. DO NOT EDIT.", "package api"]), &patterns));
+        assert!(is_generated(&diff(&["/**", " * @generated", " */"]), &patterns));
+        assert!(!is_generated(&diff(&["package api", "// DO NOT EDIT this by hand"]), &patterns));
+
+        let mut late = vec!["x"; GENERATED_SCAN_LINES];
+        late.push("// @generated");
+        assert!(!is_generated(&diff(&late), &patterns));
+        assert!(!is_generated(&diff(&["// @generated"]), &[]));
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(fuzzy_matches("", "src/app.rs"));
+        assert!(fuzzy_matches("sapp", "src/app.rs"));
+        assert!(fuzzy_matches("APP", "src/app.rs"));
+        assert!(!fuzzy_matches("ppa", "src/app.rs"));
+    }
 
     #[test]
     fn test_get_display_names() {
@@ -218,7 +501,11 @@ mod tests {
                 removed: 5,
                 hunks: vec![],
                 collapsed: false,
+                whole: false,
                 is_binary: false,
+                binary: None,
+                section: None,
+                change: ChangeKind::Modified,
             },
             FileDiff {
                 path: "src/pages/Button.tsx".to_string(),
@@ -229,7 +516,11 @@ mod tests {
                 removed: 1,
                 hunks: vec![],
                 collapsed: false,
+                whole: false,
                 is_binary: false,
+                binary: None,
+                section: None,
+                change: ChangeKind::Modified,
             },
         ];
 
@@ -237,4 +528,40 @@ mod tests {
         assert_eq!(names.get("src/components/Button.tsx"), Some(&"components/Button.tsx".to_string()));
         assert_eq!(names.get("src/pages/Button.tsx"), Some(&"pages/Button.tsx".to_string()));
     }
+
+    #[test]
+    fn test_expanded_file_lists_hunks() {
+        let hunk = |new_start, header: &str| Hunk {
+            old_start: new_start,
+            old_count: 3,
+            new_start,
+            new_count: 4,
+            header: header.to_string(),
+            lines: vec![],
+        };
+        let diffs = vec![FileDiff {
+            path: "src/main.rs".to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: None,
+            added: 2,
+            removed: 0,
+            hunks: vec![hunk(10, "@@ -10,3 +10,4 @@ fn main() {"), hunk(40, "@@ -40,3 +40,4 @@")],
+            collapsed: false,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Modified,
+        }];
+
+        let expanded: HashSet<String> = ["src/main.rs".to_string()].into();
+        let tree = build_file_tree(&diffs, &[0], &HashMap::new(), &expanded, &[]);
+        let names: Vec<&str> = tree.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["src", "main.rs", "10-13 fn main() {", "40-43"]);
+        assert_eq!(tree[3].hunk_index, Some(1));
+
+        let collapsed = build_file_tree(&diffs, &[0], &HashMap::new(), &HashSet::new(), &[]);
+        assert_eq!(collapsed.len(), 2);
+    }
 }
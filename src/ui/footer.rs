@@ -11,6 +11,7 @@ use ratatui::{
 
 use super::Styles;
 use super::DiffMode;
+use super::text::width;
 
 /// Focus area indicator
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,12 +24,16 @@ pub enum FocusArea {
 pub struct Footer<'a> {
     /// Current focus area
     pub focus: FocusArea,
+    /// Whether the content follows the sidebar cursor
+    pub follow: bool,
     /// Current diff view mode
     pub diff_mode: DiffMode,
     /// Whether hidden files are shown
     pub show_hidden: bool,
     /// Current context lines setting
     pub context_lines: u32,
+    /// Transient status message (replaces the focus indicator)
+    pub status: Option<&'a str>,
     /// Styles
     pub styles: &'a Styles,
 }
@@ -52,6 +57,7 @@ impl Widget for Footer<'_> {
         // View mode label
         let view_mode = match self.diff_mode {
             DiffMode::Unified => "unified",
+            DiffMode::WordDiff => "words",
             DiffMode::SideBySide => "split",
             DiffMode::SideBySideFull => "full",
         };
@@ -79,41 +85,56 @@ impl Widget for Footer<'_> {
             spans.push(Span::styled(format!(" {}", desc), self.styles.footer));
         }
 
-        // Focus indicator (right-aligned)
-        let focus_text = match self.focus {
-            FocusArea::Sidebar => " [SIDEBAR] ",
-            FocusArea::Content => " [CONTENT] ",
+        // Status message or focus indicator (right-aligned)
+        let focus_text = match (self.status, self.focus) {
+            (Some(status), _) => format!(" {} ", status),
+            (None, FocusArea::Sidebar) if self.follow => " [SIDEBAR FOLLOW] ".to_string(),
+            (None, FocusArea::Sidebar) => " [SIDEBAR] ".to_string(),
+            (None, FocusArea::Content) => " [CONTENT] ".to_string(),
         };
 
-        let left_width: u16 = spans.iter().map(|s| s.content.len() as u16).sum();
-        let focus_width = focus_text.len() as u16;
+        let left_width: u16 = spans.iter().map(|s| s.width() as u16).sum();
+        let focus_width = width(&focus_text) as u16;
 
-        if left_width + focus_width < area.width {
+        let fits = left_width + focus_width < area.width;
+        if fits {
             let padding = area.width - left_width - focus_width;
             spans.push(Span::styled(" ".repeat(padding as usize), self.styles.footer));
-            spans.push(Span::styled(focus_text, self.styles.footer_key));
+            spans.push(Span::styled(focus_text.clone(), self.styles.footer_key));
         }
 
         let line = Line::from(spans);
         buf.set_line(area.x, area.y, &line, area.width);
+
+        // Status messages take priority over hints when space is short
+        if !fits && self.status.is_some() {
+            let x = area.x + area.width.saturating_sub(focus_width);
+            let line = Line::from(Span::styled(focus_text, self.styles.footer_key));
+            buf.set_line(x, area.y, &line, area.width.min(focus_width));
+        }
     }
 }
 
 /// Render the footer bar
+#[allow(clippy::too_many_arguments)]
 pub fn render_footer(
     buf: &mut Buffer,
     area: Rect,
     focus: FocusArea,
+    follow: bool,
     diff_mode: DiffMode,
     show_hidden: bool,
     context_lines: u32,
+    status: Option<&str>,
     styles: &Styles,
 ) {
     let footer = Footer {
         focus,
+        follow,
         diff_mode,
         show_hidden,
         context_lines,
+        status,
         styles,
     };
     footer.render(area, buf);
@@ -1,15 +1,28 @@
 //! Header rendering
 //!
-//! Displays branch info, commit stats, and current file indicator.
+//! Displays branch info, commit stats, and current file indicator, plus
+//! the tab strip when several worktrees are open.
+
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     text::{Line, Span},
-    widgets::Widget,
+    widgets::{Block, Borders, Clear, Widget},
 };
 
 use super::Styles;
+use super::text::{breadcrumb, truncate_start, width};
+
+/// Frames of the loading spinner
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Spinner frame for the current moment, advancing every 100ms
+fn spinner() -> char {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    SPINNER[(millis / 100) as usize % SPINNER.len()]
+}
 
 /// Header widget showing branch and stats info
 pub struct Header<'a> {
@@ -17,16 +30,26 @@ pub struct Header<'a> {
     pub branch: &'a str,
     /// Main/base branch name
     pub main_branch: &'a str,
+    /// Short SHA the base resolves to
+    pub base_sha: Option<&'a str>,
     /// Number of selected commits
     pub selected_commits: usize,
     /// Total number of commits
     pub total_commits: usize,
+    /// Which uncommitted changes are shown, when only the staged or unstaged ones
+    pub stage: Option<&'a str>,
+    /// Files marked reviewed and files shown, once any are marked
+    pub reviewed: Option<(usize, usize)>,
     /// Lines added
     pub added: usize,
     /// Lines removed
     pub removed: usize,
     /// Current file being viewed
     pub current_file: Option<&'a str>,
+    /// Active file filter description
+    pub filter: Option<&'a str>,
+    /// What's still loading in the background
+    pub loading: Option<&'a str>,
     /// Styles
     pub styles: &'a Styles,
 }
@@ -56,6 +79,9 @@ impl Widget for Header<'_> {
             format!("{} ", self.main_branch),
             self.styles.header,
         ));
+        if let Some(sha) = self.base_sha.filter(|sha| !self.main_branch.starts_with(sha)) {
+            spans.push(Span::styled(format!("({}) ", sha), self.styles.footer));
+        }
 
         // Separator
         spans.push(Span::styled(" │ ", self.styles.footer));
@@ -67,6 +93,10 @@ impl Widget for Header<'_> {
                 self.styles.header,
             ));
         }
+        if let Some(stage) = self.stage {
+            spans.push(Span::styled(format!("[{}]", stage), self.styles.popup_title));
+            spans.push(Span::styled(" ", self.styles.header));
+        }
 
         // Stats
         if self.added > 0 || self.removed > 0 {
@@ -81,17 +111,34 @@ impl Widget for Header<'_> {
             ));
         }
 
-        // Current file (right-aligned)
-        if let Some(file) = self.current_file {
-            let file_info = format!(" {} ", file);
-            let file_width = file_info.len() as u16;
+        // Review progress
+        if let Some((done, total)) = self.reviewed {
+            spans.push(Span::styled(" │ ", self.styles.footer));
+            spans.push(Span::styled(format!("✓ {}/{} reviewed", done, total), self.styles.stats_added));
+        }
+
+        // Active filter
+        if let Some(filter) = self.filter {
+            spans.push(Span::styled(" │ ", self.styles.footer));
+            spans.push(Span::styled(format!("[{}]", filter), self.styles.popup_title));
+        }
+
+        // Spinner while data is in flight
+        if let Some(loading) = self.loading {
+            spans.push(Span::styled(" │ ", self.styles.footer));
+            spans.push(Span::styled(format!("{} {}", spinner(), loading), self.styles.footer));
+        }
 
-            // Calculate position for right alignment
+        // Current file (right-aligned), its directories shortened to fit
+        if let Some(file) = self.current_file {
             let left_content_width: u16 = spans.iter()
-                .map(|s| s.content.len() as u16)
+                .map(|s| s.width() as u16)
                 .sum();
+            let room = area.width.saturating_sub(left_content_width + 3) as usize;
+            let file_info = format!(" {} ", breadcrumb(file, room));
+            let file_width = width(&file_info) as u16;
 
-            if left_content_width + file_width < area.width {
+            if room > 0 && left_content_width + file_width < area.width {
                 let padding = area.width - left_content_width - file_width;
                 spans.push(Span::styled(
                     " ".repeat(padding as usize),
@@ -107,27 +154,89 @@ impl Widget for Header<'_> {
 }
 
 /// Render the header bar
+#[allow(clippy::too_many_arguments)]
 pub fn render_header(
     buf: &mut Buffer,
     area: Rect,
     branch: &str,
     main_branch: &str,
+    base_sha: Option<&str>,
     selected_commits: usize,
     total_commits: usize,
+    stage: Option<&str>,
+    reviewed: Option<(usize, usize)>,
     added: usize,
     removed: usize,
     current_file: Option<&str>,
+    filter: Option<&str>,
+    loading: Option<&str>,
     styles: &Styles,
 ) {
     let header = Header {
         branch,
         main_branch,
+        base_sha,
         selected_commits,
         total_commits,
+        stage,
+        reviewed,
         added,
         removed,
         current_file,
+        filter,
+        loading,
         styles,
     };
     header.render(area, buf);
 }
+
+/// Render the tab strip: one numbered label per tab, the active one highlighted
+pub fn render_tab_bar(buf: &mut Buffer, area: Rect, labels: &[String], active: usize, styles: &Styles) {
+    if area.height == 0 {
+        return;
+    }
+    for x in area.x..area.x + area.width {
+        buf[(x, area.y)].set_char(' ').set_style(styles.footer);
+    }
+
+    let spans: Vec<Span> = labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let style = if i == active { styles.popup_title } else { styles.footer };
+            Span::styled(format!(" {}:{} ", i + 1, label), style)
+        })
+        .collect();
+    buf.set_line(area.x, area.y, &Line::from(spans), area.width);
+}
+
+/// Render the warning strip listing files whose added lines hold conflict markers
+pub fn render_conflict_banner(buf: &mut Buffer, area: Rect, files: &[String], styles: &Styles) {
+    if area.height == 0 {
+        return;
+    }
+    for x in area.x..area.x + area.width {
+        buf[(x, area.y)].set_char(' ').set_style(styles.conflict_marker);
+    }
+    let text = format!(" ⚠ conflicts present: {}", files.join(", "));
+    buf.set_stringn(area.x, area.y, text, area.width as usize, styles.conflict_marker);
+}
+
+/// Render the full path of the current file in a box under the header's right end
+pub fn render_path_tooltip(buf: &mut Buffer, area: Rect, path: &str, styles: &Styles) {
+    let inner_width = (width(path) as u16 + 2).min(area.width.saturating_sub(2));
+    if area.height < 3 || inner_width == 0 {
+        return;
+    }
+    let tooltip = Rect::new(area.right() - inner_width - 2, area.y, inner_width + 2, 3);
+    Clear.render(tooltip, buf);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(styles.border_focus)
+        .style(styles.popup);
+    let inner = block.inner(tooltip);
+    block.render(tooltip, buf);
+    // Too long for the screen even so: keep the end, where the file name is
+    let text = format!(" {} ", truncate_start(path, inner.width.saturating_sub(2) as usize));
+    buf.set_stringn(inner.x, inner.y, text, inner.width as usize, styles.popup);
+}
@@ -0,0 +1,92 @@
+//! Hexdump rendering for small binary files
+//!
+//! Shows old and new bytes side by side, sixteen per row, highlighting
+//! bytes that differ at the same offset.
+
+use ratatui::{
+    buffer::Buffer,
+    style::Style,
+    text::{Line, Span},
+};
+
+use crate::git::FileDiff;
+use super::Styles;
+
+/// Bytes shown per row
+const BYTES_PER_ROW: usize = 16;
+
+/// Old and new contents of a binary file shown as a hexdump
+#[derive(Clone, Copy)]
+pub struct Hexdump<'a> {
+    old: &'a [u8],
+    new: &'a [u8],
+}
+
+impl<'a> Hexdump<'a> {
+    /// The hexdump for a file, if it has hexdump display turned on
+    pub fn of(diff: &'a FileDiff) -> Option<Self> {
+        let info = diff.binary.as_ref().filter(|info| info.hexdump)?;
+        let bytes = |side: &'a Option<crate::git::BinarySide>| {
+            side.as_ref().and_then(|s| s.bytes.as_deref()).unwrap_or_default()
+        };
+        Some(Self {
+            old: bytes(&info.old),
+            new: bytes(&info.new),
+        })
+    }
+
+    /// Number of display rows
+    pub fn rows(&self) -> usize {
+        self.old.len().max(self.new.len()).div_ceil(BYTES_PER_ROW)
+    }
+
+    /// Render one row: old bytes on the left half, new bytes on the right
+    pub fn render_row(&self, buf: &mut Buffer, x: u16, y: u16, width: u16, row: usize, styles: &Styles) {
+        let half = width / 2;
+        let old = self.render_side(self.old, self.new, row, styles.line_removed, styles);
+        let new = self.render_side(self.new, self.old, row, styles.line_added, styles);
+        buf.set_line(x, y, &old, half);
+        buf.set_line(x + half, y, &new, width - half);
+    }
+
+    /// Spans for one side of a row, highlighting bytes that differ from `other`
+    fn render_side(&self, bytes: &[u8], other: &[u8], row: usize, changed: Style, styles: &Styles) -> Line<'static> {
+        let start = row * BYTES_PER_ROW;
+        let end = (start + BYTES_PER_ROW).min(bytes.len());
+        let mut spans = vec![Span::styled(format!("{:08x}  ", start), styles.line_number)];
+        if start >= bytes.len() {
+            return Line::from(spans);
+        }
+
+        let style_at = |i: usize| {
+            if other.get(i) == Some(&bytes[i]) { styles.line_context } else { changed }
+        };
+
+        for (i, byte) in bytes.iter().enumerate().take(end).skip(start) {
+            spans.push(Span::styled(format!("{:02x}", byte), style_at(i)));
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::raw("   ".repeat(start + BYTES_PER_ROW - end)));
+
+        spans.push(Span::raw(" "));
+        for (i, &byte) in bytes.iter().enumerate().take(end).skip(start) {
+            let c = if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' };
+            spans.push(Span::styled(c.to_string(), style_at(i)));
+        }
+
+        Line::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hexdump_rows() {
+        let old = [0u8; 20];
+        let new = [1u8; 40];
+        assert_eq!(Hexdump { old: &old, new: &new }.rows(), 3);
+        assert_eq!(Hexdump { old: &[], new: &[] }.rows(), 0);
+    }
+}
@@ -0,0 +1,87 @@
+//! Image previews for binary image files
+//!
+//! Shows old and new images side by side, downscaled, drawing two pixels
+//! per cell with half blocks: the upper one as the `▀` and the lower one
+//! as the cell's background.
+
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::git::{FileDiff, Thumbnail};
+use super::Styles;
+
+/// Old and new previews of an image file
+#[derive(Clone, Copy)]
+pub struct ImagePreview<'a> {
+    old: Option<&'a Thumbnail>,
+    new: Option<&'a Thumbnail>,
+}
+
+impl<'a> ImagePreview<'a> {
+    /// The preview for a file, if either side decodes as an image
+    ///
+    /// Hexdump display, when turned on for the file, wins over the preview.
+    pub fn of(diff: &'a FileDiff) -> Option<Self> {
+        let info = diff.binary.as_ref().filter(|info| !info.hexdump)?;
+        let old = info.old.as_ref().and_then(|s| s.preview.as_ref());
+        let new = info.new.as_ref().and_then(|s| s.preview.as_ref());
+        (old.is_some() || new.is_some()).then_some(Self { old, new })
+    }
+
+    /// Number of display rows: a caption, then two pixel rows per row
+    pub fn rows(&self) -> usize {
+        let height = |side: Option<&Thumbnail>| side.map_or(0, Thumbnail::rows);
+        1 + height(self.old).max(height(self.new)).div_ceil(2) as usize
+    }
+
+    /// Render one row: the old image on the left half, the new one on the right
+    pub fn render_row(&self, buf: &mut Buffer, x: u16, y: u16, width: u16, row: usize, styles: &Styles) {
+        let half = width / 2;
+        self.render_side(buf, x, y, half, self.old, "old", row, styles.line_removed, styles);
+        self.render_side(buf, x + half, y, width - half, self.new, "new", row, styles.line_added, styles);
+    }
+
+    /// One side of a row: the caption on the first row, pixels after it
+    #[allow(clippy::too_many_arguments)]
+    fn render_side(
+        &self,
+        buf: &mut Buffer,
+        x: u16,
+        y: u16,
+        width: u16,
+        side: Option<&Thumbnail>,
+        label: &str,
+        row: usize,
+        label_style: Style,
+        styles: &Styles,
+    ) {
+        if row == 0 {
+            let size = side.map_or("none".to_string(), |t| format!("{}×{}", t.width, t.height));
+            let caption = Line::from(vec![
+                Span::styled(format!(" {} ", label), label_style),
+                Span::styled(size, styles.line_number),
+            ]);
+            buf.set_line(x, y, &caption, width);
+            return;
+        }
+        let Some(thumbnail) = side else {
+            return;
+        };
+
+        let top = (row as u32 - 1) * 2;
+        let rgb = |[r, g, b]: [u8; 3]| Color::Rgb(r, g, b);
+        for column in 0..thumbnail.columns.min(width.saturating_sub(1) as u32) {
+            let Some(upper) = thumbnail.pixel(column, top) else {
+                break;
+            };
+            let cell = &mut buf[(x + 1 + column as u16, y)];
+            cell.set_char('▀').set_fg(rgb(upper));
+            if let Some(lower) = thumbnail.pixel(column, top + 1) {
+                cell.set_bg(rgb(lower));
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Line offset index
+//!
+//! Maps between content lines and files/hunks for the current diff mode.
+//! Built once per layout change so scroll lookups are binary searches
+//! instead of walks over every file.
+
+use std::collections::HashMap;
+
+use crate::git::FileDiff;
+use crate::notes::LineNotes;
+use super::diff_view::{file_line_count, hunk_offsets, DiffMode};
+
+/// Prefix sums of rendered line counts for the visible files
+#[derive(Debug, Default)]
+pub struct LineIndex {
+    /// Diff index of each visible file, in display order
+    files: Vec<usize>,
+    /// First line of each visible file, followed by the total line count
+    starts: Vec<usize>,
+    /// Absolute first line of each hunk, per visible file
+    hunk_starts: Vec<Vec<usize>>,
+    /// Visible position of each diff index
+    positions: HashMap<usize, usize>,
+}
+
+impl LineIndex {
+    /// Index the visible diffs as rendered in `mode`, long lines wrapped at `wrap` columns
+    pub fn build(diffs: &[FileDiff], visible: &[usize], notes: &LineNotes, mode: DiffMode, wrap: Option<u16>) -> Self {
+        let mut index = Self::default();
+        let mut line = 0;
+
+        for &i in visible {
+            let Some(diff) = diffs.get(i) else {
+                continue;
+            };
+            index.positions.insert(i, index.files.len());
+            index.files.push(i);
+            index.starts.push(line);
+            index.hunk_starts.push(hunk_offsets(diff, notes, mode, wrap).into_iter().map(|o| line + o).collect());
+            line += file_line_count(diff, notes, mode, wrap);
+        }
+        index.starts.push(line);
+
+        index
+    }
+
+    /// Total rendered lines
+    pub fn total_lines(&self) -> usize {
+        self.starts.last().copied().unwrap_or(0)
+    }
+
+    /// Diff index of the file containing `line`
+    pub fn file_at(&self, line: usize) -> Option<usize> {
+        if line >= self.total_lines() {
+            return None;
+        }
+        let position = self.starts.partition_point(|&start| start <= line) - 1;
+        self.files.get(position).copied()
+    }
+
+    /// First line of a visible diff
+    pub fn file_start(&self, diff_index: usize) -> Option<usize> {
+        self.positions.get(&diff_index).map(|&p| self.starts[p])
+    }
+
+    /// Rendered line count of a visible diff
+    pub fn file_len(&self, diff_index: usize) -> Option<usize> {
+        self.positions.get(&diff_index).map(|&p| self.starts[p + 1] - self.starts[p])
+    }
+
+    /// First line of a hunk in a visible, expanded diff
+    pub fn hunk_start(&self, diff_index: usize, hunk_index: usize) -> Option<usize> {
+        let &position = self.positions.get(&diff_index)?;
+        self.hunk_starts[position].get(hunk_index).copied()
+    }
+
+    /// Diff and hunk index of the hunk showing `line`
+    ///
+    /// A file's header rows count toward its first hunk.
+    pub fn hunk_at(&self, line: usize) -> Option<(usize, usize)> {
+        let diff_index = self.file_at(line)?;
+        let starts = &self.hunk_starts[self.positions[&diff_index]];
+        if starts.is_empty() {
+            return None;
+        }
+        Some((diff_index, starts.partition_point(|&start| start <= line).saturating_sub(1)))
+    }
+
+    /// First line of the first file starting after `line`
+    pub fn next_file_start(&self, line: usize) -> Option<usize> {
+        let file_starts = &self.starts[..self.files.len()];
+        file_starts.get(file_starts.partition_point(|&start| start <= line)).copied()
+    }
+
+    /// First line of the last file starting before `line`
+    pub fn prev_file_start(&self, line: usize) -> Option<usize> {
+        let file_starts = &self.starts[..self.files.len()];
+        let count = file_starts.partition_point(|&start| start < line);
+        count.checked_sub(1).map(|p| file_starts[p])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::ChangeKind;
+
+    fn diff(path: &str, collapsed: bool) -> FileDiff {
+        FileDiff {
+            path: path.to_string(),
+            old_path: None,
+            old_content: None,
+            new_content: None,
+            added: 0,
+            removed: 0,
+            hunks: vec![],
+            collapsed,
+            whole: false,
+            is_binary: false,
+            binary: None,
+            section: None,
+            change: ChangeKind::Modified,
+        }
+    }
+
+    #[test]
+    fn test_file_lookup() {
+        // Each file without hunks renders as a single header line
+        let diffs = vec![diff("a", false), diff("b", true), diff("c", false)];
+        let index = LineIndex::build(&diffs, &[0, 2], &LineNotes::new(), DiffMode::Unified, None);
+
+        assert_eq!(index.total_lines(), 2);
+        assert_eq!(index.file_at(0), Some(0));
+        assert_eq!(index.file_at(1), Some(2));
+        assert_eq!(index.file_at(2), None);
+        assert_eq!(index.file_start(2), Some(1));
+        assert_eq!(index.file_start(1), None);
+        assert_eq!(index.next_file_start(0), Some(1));
+        assert_eq!(index.next_file_start(1), None);
+        assert_eq!(index.prev_file_start(1), Some(0));
+        assert_eq!(index.prev_file_start(0), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Merge conflict view
+//!
+//! Shows a conflicted file top to bottom: clean text as context, each
+//! unresolved conflict as our lines beside theirs (with the base above them
+//! when the markers recorded it), and resolved conflicts as what they became.
+
+use ratatui::{buffer::Buffer, layout::Rect};
+
+use super::Styles;
+use super::text;
+use crate::git::{Conflict, Region, Resolution};
+
+/// One screen row of the merge view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeRow<'a> {
+    /// A line outside any conflict
+    Clean(&'a str),
+    /// Heading of conflict `index`
+    Header(usize),
+    /// A line of the common base
+    Base(&'a str),
+    /// Our line and their line at the same offset
+    Sides(Option<&'a str>, Option<&'a str>),
+    /// A line a resolved conflict kept
+    Resolved(&'a str),
+}
+
+/// Rows of the whole file, with each conflict's heading index alongside
+pub fn merge_rows(regions: &[Region]) -> (Vec<MergeRow<'_>>, Vec<usize>) {
+    let mut rows = Vec::new();
+    let mut headers = Vec::new();
+    for region in regions {
+        match region {
+            Region::Clean(lines) => rows.extend(lines.iter().map(|l| MergeRow::Clean(l))),
+            Region::Conflict(conflict) => {
+                headers.push(rows.len());
+                rows.push(MergeRow::Header(headers.len() - 1));
+                push_conflict_rows(&mut rows, conflict);
+            }
+        }
+    }
+    (rows, headers)
+}
+
+/// Rows below a conflict's heading
+fn push_conflict_rows<'a>(rows: &mut Vec<MergeRow<'a>>, conflict: &'a Conflict) {
+    let Some(resolution) = conflict.resolution else {
+        if let Some(base) = &conflict.base {
+            rows.extend(base.iter().map(|l| MergeRow::Base(l)));
+        }
+        let height = conflict.ours.len().max(conflict.theirs.len());
+        rows.extend((0..height).map(|i| {
+            MergeRow::Sides(conflict.ours.get(i).map(String::as_str), conflict.theirs.get(i).map(String::as_str))
+        }));
+        return;
+    };
+    let kept: Box<dyn Iterator<Item = &String>> = match resolution {
+        Resolution::Ours => Box::new(conflict.ours.iter()),
+        Resolution::Theirs => Box::new(conflict.theirs.iter()),
+        Resolution::Both => Box::new(conflict.ours.iter().chain(&conflict.theirs)),
+    };
+    rows.extend(kept.map(|l| MergeRow::Resolved(l)));
+}
+
+/// Render the conflicts of `regions` from row `scroll`, highlighting conflict `current`
+pub fn render_merge_view(
+    buf: &mut Buffer,
+    area: Rect,
+    regions: &[Region],
+    current: usize,
+    scroll: usize,
+    styles: &Styles,
+) {
+    let conflicts: Vec<&Conflict> = regions
+        .iter()
+        .filter_map(|region| match region {
+            Region::Conflict(conflict) => Some(conflict),
+            Region::Clean(_) => None,
+        })
+        .collect();
+    let (rows, _) = merge_rows(regions);
+
+    let width = area.width as usize;
+    let half = area.width / 2;
+    for (y, row) in (area.top()..area.bottom()).zip(rows.iter().skip(scroll)) {
+        match *row {
+            MergeRow::Clean(line) => {
+                buf.set_stringn(area.x, y, format!("  {}", line), width, styles.line_context);
+            }
+            MergeRow::Header(index) => {
+                let conflict = conflicts[index];
+                let state = match conflict.resolution {
+                    Some(resolution) => format!("took {}", resolution.label()),
+                    None => format!("ours: {}  │  theirs: {}", conflict.ours_label, conflict.theirs_label),
+                };
+                let style = if index == current { styles.popup_title } else { styles.hunk_header };
+                let text = format!("── conflict {}/{} · {} ", index + 1, conflicts.len(), state);
+                buf.set_stringn(area.x, y, format!("{:─<width$}", text, width = width), width, style);
+            }
+            MergeRow::Base(line) => {
+                buf.set_stringn(area.x, y, format!("│ {}", line), width, styles.line_number);
+            }
+            MergeRow::Sides(ours, theirs) => {
+                for x in area.x..area.x + area.width {
+                    buf[(x, y)].set_style(styles.line_context);
+                }
+                if let Some(ours) = ours {
+                    buf.set_stringn(area.x, y, format!("- {}", text::pad(ours, half as usize)), half as usize, styles.line_removed);
+                }
+                if let Some(theirs) = theirs {
+                    let w = (area.width - half) as usize;
+                    buf.set_stringn(area.x + half, y, format!("+ {}", text::pad(theirs, w)), w, styles.line_added);
+                }
+            }
+            MergeRow::Resolved(line) => {
+                buf.set_stringn(area.x, y, format!("✓ {}", line), width, styles.line_added);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::parse_conflicts;
+
+    #[test]
+    fn test_merge_rows() {
+        let mut regions = parse_conflicts("a\n<<<<<<< HEAD\nx\ny\n=======\nz\n>>>>>>> other\nb\n");
+        let (rows, headers) = merge_rows(&regions);
+        assert_eq!(headers, vec![1]);
+        assert_eq!(rows[2], MergeRow::Sides(Some("x"), Some("z")));
+        assert_eq!(rows[3], MergeRow::Sides(Some("y"), None));
+        assert_eq!(rows.len(), 5);
+
+        if let Region::Conflict(conflict) = &mut regions[1] {
+            conflict.resolution = Some(Resolution::Theirs);
+        }
+        let (rows, _) = merge_rows(&regions);
+        assert_eq!(rows, vec![MergeRow::Clean("a"), MergeRow::Header(0), MergeRow::Resolved("z"), MergeRow::Clean("b")]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Commit message panel
+//!
+//! Shows the full messages of the selected commits, and any git notes on
+//! them, beside the diff with its own scroll position.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::Styles;
+use super::text;
+
+/// Narrowest the panel gets
+pub const MIN_MESSAGE_PANEL_WIDTH: u16 = 30;
+/// Widest the panel gets
+pub const MAX_MESSAGE_PANEL_WIDTH: u16 = 72;
+
+/// A commit message to display
+pub struct CommitMessage {
+    /// Abbreviated commit hash
+    pub hash: String,
+    /// Full commit message
+    pub message: String,
+    /// Note from `refs/notes/commits`
+    pub note: Option<String>,
+    /// Signature verdict and signer, e.g. `✓ Good signature from …`
+    pub signature: Option<String>,
+}
+
+/// Panel width for a diff area of `width` columns (0 if it doesn't fit)
+pub fn message_panel_width(width: u16) -> u16 {
+    let panel = (width / 3).clamp(MIN_MESSAGE_PANEL_WIDTH, MAX_MESSAGE_PANEL_WIDTH);
+    if panel > width / 2 { 0 } else { panel }
+}
+
+/// Lay out the messages as display lines `width` columns wide
+pub fn message_lines(messages: &[CommitMessage], width: u16, styles: &Styles) -> Vec<Line<'static>> {
+    let width = width as usize;
+    let mut lines = Vec::new();
+
+    for (i, commit) in messages.iter().enumerate() {
+        if i > 0 {
+            lines.push(Line::default());
+        }
+        let mut paragraphs = commit.message.lines();
+        let subject = paragraphs.next().unwrap_or("");
+        let mut first = true;
+        for row in wrap(&format!("{} {}", commit.hash, subject), width) {
+            if first {
+                let (hash, rest) = row.split_at(commit.hash.len().min(row.len()));
+                lines.push(Line::from(vec![
+                    Span::styled(hash.to_string(), styles.worktree_branch),
+                    Span::styled(rest.to_string(), styles.section_header),
+                ]));
+                first = false;
+            } else {
+                lines.push(Line::styled(row, styles.section_header));
+            }
+        }
+        if let Some(signature) = &commit.signature {
+            for row in wrap(signature, width) {
+                lines.push(Line::styled(row, styles.footer));
+            }
+        }
+        for paragraph in paragraphs {
+            for row in wrap(paragraph, width) {
+                lines.push(Line::styled(row, styles.line_context));
+            }
+        }
+        if let Some(note) = &commit.note {
+            lines.push(Line::default());
+            lines.push(Line::styled("Notes:", styles.worktree_branch));
+            for paragraph in note.lines() {
+                for row in wrap(paragraph, width.saturating_sub(2)) {
+                    lines.push(Line::styled(format!("  {}", row), styles.line_context));
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+/// Render the panel with its left border at `area.x`
+pub fn render_message_panel(
+    buf: &mut Buffer,
+    area: Rect,
+    messages: &[CommitMessage],
+    scroll: usize,
+    styles: &Styles,
+) {
+    if area.width < 3 {
+        return;
+    }
+    for y in area.y..area.y + area.height {
+        buf[(area.x, y)].reset();
+        buf[(area.x, y)].set_char('│').set_style(styles.border);
+        for x in area.x + 1..area.x + area.width {
+            buf[(x, y)].reset();
+        }
+    }
+
+    let inner = Rect::new(area.x + 2, area.y, area.width - 3, area.height);
+    if messages.is_empty() {
+        buf.set_string(inner.x, inner.y, "No commits selected", styles.footer);
+        return;
+    }
+
+    let lines = message_lines(messages, inner.width, styles);
+    for (row, line) in lines.iter().skip(scroll).take(inner.height as usize).enumerate() {
+        buf.set_line(inner.x, inner.y + row as u16, line, inner.width);
+    }
+}
+
+/// Greedily wrap `text` at word boundaries, splitting words that don't fit
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut row = String::new();
+
+    for word in text.split_inclusive(' ') {
+        if !row.is_empty() && text::width(&row) + text::width(word.trim_end()) > width {
+            rows.push(row.trim_end().to_string());
+            row.clear();
+        }
+        for grapheme in word.graphemes(true) {
+            if grapheme != " " && text::width(&row) + text::width(grapheme) > width {
+                rows.push(std::mem::take(&mut row));
+            }
+            row.push_str(grapheme);
+        }
+    }
+    rows.push(row.trim_end().to_string());
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(wrap("fix the parser bug", 10), vec!["fix the", "parser bug"]);
+        assert_eq!(wrap("abcdefghijkl", 5), vec!["abcde", "fghij", "kl"]);
+        assert_eq!(message_panel_width(60), 30);
+        assert_eq!(message_panel_width(40), 0);
+    }
+}
@@ -2,11 +2,20 @@
 //!
 //! Contains all terminal UI components:
 //! - Styles for consistent theming
-//! - Diff view rendering
+//! - Diff view rendering and line offset indexing
+//! - Word-level diffs for the word-diff view
+//! - Blame gutter beside the diff
+//! - Merge conflict view
+//! - Outline of the symbols a file's changes touch
+//! - Diffstat table of changed files and folders
 //! - File sidebar
 //! - Header and footer
 //! - Popups and overlays
+//! - ANSI text output of rendered views
+//! - Display-width text measurement and truncation
 
+mod ansi;
+mod blame;
 mod styles;
 pub mod diff_view;
 pub mod sidebar;
@@ -14,14 +23,37 @@ mod header;
 pub mod footer;
 mod popup;
 mod file_tree;
+mod line_index;
+mod hexdump;
+mod image_preview;
+mod timeline;
+mod messages;
+mod dashboard;
+mod word_diff;
+mod merge_view;
+mod outline;
+mod diffstat;
+pub mod text;
 
-pub use styles::Styles;
+pub use ansi::buffer_to_ansi;
+pub use blame::{render_blame_gutter, BLAME_WIDTH};
+pub use merge_view::{merge_rows, render_merge_view};
+pub use outline::{outline_entries, render_outline_popup, OutlineEntry};
+pub use styles::{Styles, Theme};
 pub use diff_view::{render_diff_content, DiffMode};
 pub use sidebar::{
-    render_sidebar, DEFAULT_SIDEBAR_WIDTH, MIN_SIDEBAR_WIDTH,
+    render_sidebar, SidebarPosition, DEFAULT_SIDEBAR_WIDTH, MIN_SIDEBAR_WIDTH,
     MAX_SIDEBAR_WIDTH, SIDEBAR_RESIZE_STEP,
 };
-pub use header::render_header;
+pub use header::{render_conflict_banner, render_header, render_path_tooltip, render_tab_bar};
 pub use footer::{render_footer, FocusArea};
-pub use popup::{render_commit_popup, render_worktree_popup, render_help_popup};
-pub use file_tree::{TreeNode, build_file_tree, flatten_tree, is_hidden_file};
+pub use popup::{
+    render_commit_popup, commit_popup_hit, CommitSort, render_worktree_popup, render_worktree_form, render_confirm_popup, render_stash_popup, render_help_popup, help_section_starts, render_conflict_popup,
+    render_issues_popup, render_recent_popup, render_pull_popup, render_symbol_popup, signature_label,
+};
+pub use line_index::LineIndex;
+pub use timeline::{render_timeline, TimelineStep};
+pub use dashboard::{render_worktree_dashboard, WorktreeSort};
+pub use diffstat::{render_diffstat, DiffstatSort};
+pub use messages::{message_lines, message_panel_width, render_message_panel, CommitMessage};
+pub use file_tree::{DEFAULT_HIDDEN, FlatTree, TreeNode, build_file_tree, flatten_tree, fuzzy_matches, glob_matches, is_generated, is_hidden_file, is_marked, language_matches, path_filter_matches};
@@ -0,0 +1,195 @@
+//! Changed-symbol outline
+//!
+//! Lists the functions and types of one file that its hunks touch, each
+//! with the lines it gained and lost, as a table of contents for the
+//! file's changes. A changed line belongs to the last symbol defined at or
+//! above it; changes above the first symbol are listed as the file's top.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Widget},
+};
+
+use super::Styles;
+use super::text::{self, pad, truncate};
+use crate::git::{Hunk, LineType};
+use crate::syntax::Symbol;
+
+/// A symbol the changes touch, or the top of the file when `symbol` is None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    pub symbol: Option<Symbol>,
+    /// Hunks changing lines of the symbol, in order
+    pub hunks: Vec<usize>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Entries for the symbols `hunks` change, top of the file first
+pub fn outline_entries(hunks: &[Hunk], symbols: &[Symbol]) -> Vec<OutlineEntry> {
+    // Index into `symbols` plus one, zero being the top of the file
+    let owner = |line: u32| symbols.partition_point(|symbol| symbol.line <= line);
+    let mut entries: Vec<(usize, OutlineEntry)> = Vec::new();
+
+    for (hunk_index, hunk) in hunks.iter().enumerate() {
+        // New-file line the next line of the hunk lines up with
+        let mut next_new = hunk.new_start;
+        for line in &hunk.lines {
+            let (anchor, added) = match line.line_type {
+                LineType::Added => (line.new_lineno.unwrap_or(next_new), true),
+                // A removal sits just after the new line above it
+                LineType::Removed => (next_new.saturating_sub(1).max(1), false),
+                _ => {
+                    if let Some(lineno) = line.new_lineno {
+                        next_new = lineno + 1;
+                    }
+                    continue;
+                }
+            };
+            if added {
+                next_new = anchor + 1;
+            }
+
+            let key = owner(anchor);
+            let position = match entries.iter().position(|(k, _)| *k == key) {
+                Some(position) => position,
+                None => {
+                    let symbol = key.checked_sub(1).map(|i| symbols[i].clone());
+                    entries.push((key, OutlineEntry { symbol, hunks: Vec::new(), added: 0, removed: 0 }));
+                    entries.len() - 1
+                }
+            };
+            let entry = &mut entries[position].1;
+            if entry.hunks.last() != Some(&hunk_index) {
+                entry.hunks.push(hunk_index);
+            }
+            if added {
+                entry.added += 1;
+            } else {
+                entry.removed += 1;
+            }
+        }
+    }
+
+    entries.sort_by_key(|(key, _)| *key);
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Render the outline of `path` as a popup, highlighting entry `cursor`
+pub fn render_outline_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    path: &str,
+    entries: &[OutlineEntry],
+    cursor: usize,
+    styles: &Styles,
+) {
+    let width = 80.min(area.width.saturating_sub(4));
+    let height = (entries.len().max(1) as u16 + 2).min(area.height.saturating_sub(4));
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    Clear.render(popup, buf);
+    let title = format!(" Outline: {} ", text::breadcrumb(path, (width as usize).saturating_sub(14)));
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(styles.border_focus)
+        .title(Span::styled(title, styles.popup_title))
+        .style(styles.popup);
+    let inner = block.inner(popup);
+    block.render(popup, buf);
+
+    // Keep the cursor in view
+    let visible = inner.height as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
+
+    for (i, entry) in entries.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + (i - scroll) as u16;
+        let style = if i == cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+
+        let (kind, name) = match &entry.symbol {
+            Some(symbol) => (symbol.kind.label(), format!("{} :{}", symbol.name, symbol.line)),
+            None => ("", "(top of file)".to_string()),
+        };
+        let hunks = match entry.hunks.len() {
+            1 => "1 hunk".to_string(),
+            n => format!("{} hunks", n),
+        };
+        let counts = format!("+{} -{} ", entry.added, entry.removed);
+        let name_width = (inner.width as usize).saturating_sub(text::width(&counts) + 16);
+        let line = Line::from(vec![
+            Span::styled(format!(" {}", pad(kind, 5)), styles.line_number),
+            Span::styled(pad(&truncate(&name, name_width), name_width + 1), style),
+            Span::styled(format!("+{}", entry.added), styles.line_added),
+            Span::styled(format!(" -{} ", entry.removed), styles.line_removed),
+            Span::styled(hunks, styles.footer),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+
+        if i == cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::DiffLine;
+    use crate::syntax::SymbolKind;
+
+    #[test]
+    fn test_outline_entries() {
+        let line = |line_type, old, new| DiffLine { line_type, content: String::new(), old_lineno: old, new_lineno: new, moved: false };
+        let symbol = |name: &str, line| Symbol { name: name.to_string(), kind: SymbolKind::Function, line };
+        let symbols = [symbol("parse", 5), symbol("render", 20)];
+        let hunks = vec![
+            Hunk {
+                old_start: 1,
+                old_count: 3,
+                new_start: 1,
+                new_count: 8,
+                header: String::new(),
+                lines: vec![
+                    line(LineType::Added, None, Some(1)),
+                    line(LineType::Context, Some(1), Some(2)),
+                    line(LineType::Added, None, Some(6)),
+                ],
+            },
+            Hunk {
+                old_start: 18,
+                old_count: 3,
+                new_start: 22,
+                new_count: 2,
+                header: String::new(),
+                lines: vec![
+                    line(LineType::Context, Some(18), Some(22)),
+                    line(LineType::Removed, Some(19), None),
+                    line(LineType::Context, Some(20), Some(23)),
+                ],
+            },
+        ];
+
+        let entries = outline_entries(&hunks, &symbols);
+        let summary: Vec<(Option<&str>, Vec<usize>, usize, usize)> = entries
+            .iter()
+            .map(|e| (e.symbol.as_ref().map(|s| s.name.as_str()), e.hunks.clone(), e.added, e.removed))
+            .collect();
+        assert_eq!(summary, vec![
+            (None, vec![0], 1, 0),
+            (Some("parse"), vec![0], 1, 0),
+            (Some("render"), vec![1], 0, 1),
+        ]);
+    }
+}
@@ -1,6 +1,10 @@
 //! Popup overlays
 //!
-//! Commit filter, worktree switcher, and help overlay.
+//! Commit filter, worktree switcher, conflict prediction, issues panel,
+//! pull request picker, symbol picker and help overlay.
+
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use ratatui::{
     buffer::Buffer,
@@ -9,8 +13,13 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Widget},
 };
 
-use crate::git::{Commit, Worktree};
+use crate::annotations::Annotation;
+use crate::git::{Commit, SignatureStatus, Stash, Worktree, WorktreeStatus};
+use crate::pulls::{CheckStatus, PullRequest};
+use crate::syntax::FileSymbol;
 use super::Styles;
+use super::dashboard::format_age;
+use super::text::{self, pad, truncate};
 
 /// Render a centered popup overlay
 fn render_centered_popup(buf: &mut Buffer, area: Rect, width: u16, height: u16, title: &str, styles: &Styles) -> Rect {
@@ -41,27 +50,150 @@ fn render_centered_popup(buf: &mut Buffer, area: Rect, width: u16, height: u16,
     inner
 }
 
+/// Most lines of a commit note shown in the commit popup
+const MAX_NOTE_LINES: usize = 4;
+
+/// Describe a commit's signature, e.g. for a details line
+pub fn signature_label(status: SignatureStatus, signer: Option<&str>) -> Option<String> {
+    let verdict = match status {
+        SignatureStatus::Unsigned => return None,
+        SignatureStatus::Good => "Good signature",
+        SignatureStatus::Bad => "Bad signature",
+        SignatureStatus::Unknown => "Unverified signature",
+    };
+    Some(match signer {
+        Some(signer) => format!("{} {} from {}", status.badge(), verdict, signer),
+        None => format!("{} {}", status.badge(), verdict),
+    })
+}
+
+/// Width of the commit filter popup
+const COMMIT_POPUP_WIDTH: u16 = 80;
+
+/// Widest the author column of the commit filter gets
+const MAX_AUTHOR_WIDTH: usize = 14;
+
+/// Commit filter list order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitSort {
+    /// As the walk lists them, each commit before its parents
+    #[default]
+    Topological,
+    /// Newest author date first
+    Date,
+}
+
+impl CommitSort {
+    /// The other sort order
+    pub fn next(self) -> Self {
+        match self {
+            Self::Topological => Self::Date,
+            Self::Date => Self::Topological,
+        }
+    }
+
+    /// Put `indices` into `commits` in this order, the uncommitted changes staying first
+    pub fn sort(self, commits: &[Commit], indices: &mut [usize]) {
+        if self == Self::Date {
+            indices.sort_by_key(|&i| Reverse((commits[i].is_uncommitted, commits[i].time)));
+        }
+    }
+}
+
+/// The cursor commit's author, signer and note, shown below the commit list
+fn commit_details(commits: &[&Commit], cursor: usize) -> Vec<String> {
+    let mut details = Vec::new();
+    if let Some(commit) = commits.get(cursor) {
+        if !commit.is_uncommitted {
+            details.push(format!(" {} <{}>", commit.author, commit.email));
+        }
+        details.extend(signature_label(commit.signature, commit.signer.as_deref()).map(|l| format!(" {}", l)));
+        if let Some(note) = &commit.note {
+            details.extend(note.lines().take(MAX_NOTE_LINES).map(|l| format!(" ✎ {}", l)));
+        }
+    }
+    details
+}
+
+/// Outer area of the commit filter popup, and the rows its list takes up
+fn commit_popup_areas(area: Rect, commits: &[&Commit], details: usize, status: bool) -> (Rect, Rect) {
+    let width = COMMIT_POPUP_WIDTH.min(area.width.saturating_sub(4));
+    let details_height = if details == 0 { 0 } else { details as u16 + 1 };
+    let status_height = u16::from(status);
+    let height = (commits.len().max(1) as u16 + 4 + status_height + details_height).min(area.height.saturating_sub(4));
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width.min(area.width),
+        height.min(area.height),
+    );
+    // Inside the border, below the instructions and separator
+    let list_top = popup.y + 3;
+    let list_bottom = (popup.y + popup.height).saturating_sub(1 + details_height + status_height);
+    let list = Rect::new(popup.x + 1, list_top, popup.width.saturating_sub(2), list_bottom.saturating_sub(list_top));
+    (popup, list)
+}
+
+/// First commit the list shows, scrolled so the cursor stays in view
+fn commit_list_offset(list: Rect, cursor: usize) -> usize {
+    (cursor + 1).saturating_sub(list.height as usize)
+}
+
+/// Index into `commits` of the commit drawn at a screen cell of the commit filter popup
+pub fn commit_popup_hit(area: Rect, commits: &[&Commit], cursor: usize, status: bool, column: u16, row: u16) -> Option<usize> {
+    let (_, list) = commit_popup_areas(area, commits, commit_details(commits, cursor).len(), status);
+    if column < list.x || column >= list.right() || row < list.y || row >= list.bottom() {
+        return None;
+    }
+    let index = commit_list_offset(list, cursor) + (row - list.y) as usize;
+    (index < commits.len()).then_some(index)
+}
+
 /// Render commit filter popup
+///
+/// `commits` are the ones matching `filter`, which is being typed while
+/// `typing`, in `sort` order. While a range is being picked, `anchor` is the
+/// commit it started at and the commits between it and the cursor are
+/// highlighted.
+#[allow(clippy::too_many_arguments)]
 pub fn render_commit_popup(
     buf: &mut Buffer,
     area: Rect,
-    commits: &[Commit],
+    commits: &[&Commit],
     cursor: usize,
+    anchor: Option<usize>,
+    filter: &str,
+    typing: bool,
+    sort: CommitSort,
+    status: Option<&str>,
     styles: &Styles,
 ) {
-    let width = 60.min(area.width - 4);
-    let height = (commits.len() as u16 + 4).min(area.height - 4);
-
-    let inner = render_centered_popup(buf, area, width, height, "Select Commits", styles);
-
-    // Instructions
-    let instructions = "Space: toggle  a: all  n: none  Enter: apply  Esc: cancel";
-    buf.set_line(
-        inner.x,
-        inner.y,
-        &Line::styled(instructions, styles.footer),
-        inner.width,
-    );
+    let details = commit_details(commits, cursor);
+    let (popup, list) = commit_popup_areas(area, commits, details.len(), status.is_some());
+    let mut title = "Select Commits".to_string();
+    if !filter.is_empty() && !typing {
+        title.push_str(&format!(" /{}", filter));
+    }
+    if sort == CommitSort::Date {
+        title.push_str(" · newest first");
+    }
+    let inner = render_centered_popup(buf, area, popup.width, popup.height, &title, styles);
+    let list_bottom = list.bottom();
+    let offset = commit_list_offset(list, cursor);
+    let range = anchor.map(|anchor| anchor.min(cursor)..=anchor.max(cursor));
+
+    // Instructions, or the filter while it is typed
+    let instructions = if typing {
+        Line::from(vec![
+            Span::styled(format!("/{}_", filter), styles.sidebar_normal),
+            Span::styled("  Enter: done  Esc: clear", styles.footer),
+        ])
+    } else if anchor.is_some() {
+        Line::styled("Move to extend  Space: toggle range  Esc: cancel", styles.footer)
+    } else {
+        Line::styled("Space: toggle  v: range  a/n: all/none  o: only  /: filter  s: sort", styles.footer)
+    };
+    buf.set_line(inner.x, inner.y, &instructions, inner.width);
 
     // Separator
     buf.set_line(
@@ -71,16 +203,31 @@ pub fn render_commit_popup(
         inner.width,
     );
 
+    if commits.is_empty() {
+        buf.set_line(list.x, list.y, &Line::styled(" No commits match", styles.footer), list.width);
+    }
+
+    // Hash, age, author and subject columns
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let age = |commit: &Commit| if commit.is_uncommitted { String::new() } else { format_age(now - commit.time) };
+    let age_width = commits.iter().map(|c| text::width(&age(c))).max().unwrap_or(0);
+    let author_width = commits.iter().map(|c| text::width(&c.author)).max().unwrap_or(0).min(MAX_AUTHOR_WIDTH);
+
     // Commits list
-    for (i, commit) in commits.iter().enumerate() {
-        let y = inner.y + 2 + i as u16;
-        if y >= inner.y + inner.height {
+    for (i, commit) in commits.iter().enumerate().skip(offset) {
+        let y = list.y + (i - offset) as u16;
+        if y >= list_bottom {
             break;
         }
 
         let is_cursor = i == cursor;
+        let in_range = range.as_ref().is_some_and(|range| range.contains(&i));
         let style = if is_cursor {
             styles.sidebar_cursor
+        } else if in_range {
+            styles.sidebar_marked
         } else {
             styles.sidebar_normal
         };
@@ -92,37 +239,92 @@ pub fn render_commit_popup(
             commit.hash.clone()
         };
 
-        let subject = truncate(&commit.subject, (inner.width as usize).saturating_sub(15));
+        let subject_width = (inner.width as usize).saturating_sub(24 + age_width + author_width);
+        let subject = truncate(&commit.subject, subject_width);
+        let note_marker = if commit.note.is_some() { "✎ " } else { "" };
+        let badge_style = match commit.signature {
+            SignatureStatus::Good => styles.stats_added,
+            SignatureStatus::Bad => styles.stats_removed,
+            _ => styles.footer,
+        };
 
         let line = Line::from(vec![
             Span::styled(format!(" {} ", checkbox), style),
-            Span::styled(format!("{} ", hash), styles.worktree_branch),
+            Span::styled(format!("{} ", commit.signature.badge()), badge_style),
+            Span::styled(format!("{}  ", hash), styles.worktree_branch),
+            Span::styled(format!("{}  ", pad(&age(commit), age_width)), styles.footer),
+            Span::styled(format!("{}  ", pad(&truncate(&commit.author, author_width), author_width)), styles.line_number),
+            Span::styled(note_marker, styles.worktree_current),
             Span::styled(subject, style),
         ]);
 
         buf.set_line(inner.x, y, &line, inner.width);
 
-        if is_cursor {
+        if is_cursor || in_range {
             for x in inner.x..inner.x + inner.width {
                 buf[(x, y)].set_style(style);
             }
         }
     }
+
+    // Whether the walk is still going or stopped at its limit
+    if let Some(status) = status
+        && list_bottom < inner.y + inner.height
+    {
+        buf.set_line(inner.x, list_bottom, &Line::styled(format!(" {}", status), styles.footer), inner.width);
+    }
+    let list_bottom = list_bottom + u16::from(status.is_some());
+
+    // Details of the commit under the cursor
+    if !details.is_empty() && list_bottom < inner.y + inner.height {
+        buf.set_line(inner.x, list_bottom, &Line::styled("─".repeat(inner.width as usize), styles.border), inner.width);
+        for (i, line) in details.iter().enumerate() {
+            let y = list_bottom + 1 + i as u16;
+            if y >= inner.y + inner.height {
+                break;
+            }
+            buf.set_line(inner.x, y, &Line::styled(line.as_str(), styles.footer), inner.width);
+        }
+    }
+}
+
+/// Dirty/clean and upstream badges of a worktree in the switcher, with their styles
+///
+/// Blank while the status is still being read, or if it couldn't be.
+fn worktree_badges<'a>(status: Option<&WorktreeStatus>, styles: &'a Styles) -> [Span<'a>; 2] {
+    let Some(status) = status else {
+        return [Span::raw(pad("", 7)), Span::raw(pad("", 10))];
+    };
+    let dirty = if status.changed > 0 {
+        Span::styled(pad(&format!("● {}", status.changed), 7), styles.stats_removed)
+    } else {
+        Span::styled(pad("✓", 7), styles.stats_added)
+    };
+    let upstream = match status.upstream {
+        Some((0, 0)) => "=".to_string(),
+        Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+        None => String::new(),
+    };
+    [dirty, Span::styled(pad(&upstream, 10), styles.line_number)]
 }
 
 /// Render worktree switcher popup
+///
+/// `statuses` line up with `worktrees`, `None` where a status isn't read yet.
 pub fn render_worktree_popup(
     buf: &mut Buffer,
     area: Rect,
     worktrees: &[Worktree],
+    statuses: &[Option<WorktreeStatus>],
     cursor: usize,
     filter: &str,
     styles: &Styles,
 ) {
-    let width = 70.min(area.width - 4);
-    let height = (worktrees.len() as u16 + 5).min(area.height - 4);
+    let width = 80.min(area.width.saturating_sub(4));
+    let height = (worktrees.len() as u16 + 6).min(area.height.saturating_sub(4));
 
     let inner = render_centered_popup(buf, area, width, height, "Switch Worktree", styles);
+    let list_bottom = inner.y + inner.height.saturating_sub(1);
 
     // Filter input
     let filter_line = format!("> {}", filter);
@@ -139,16 +341,17 @@ pub fn render_worktree_popup(
     // Worktrees list
     let filtered: Vec<_> = worktrees
         .iter()
-        .filter(|wt| {
+        .enumerate()
+        .filter(|(_, wt)| {
             filter.is_empty()
                 || wt.path.to_string_lossy().to_lowercase().contains(&filter.to_lowercase())
-                || wt.branch.as_ref().map_or(false, |b| b.to_lowercase().contains(&filter.to_lowercase()))
+                || wt.branch.as_ref().is_some_and(|b| b.to_lowercase().contains(&filter.to_lowercase()))
         })
         .collect();
 
-    for (i, wt) in filtered.iter().enumerate() {
+    for (i, &(index, wt)) in filtered.iter().enumerate() {
         let y = inner.y + 2 + i as u16;
-        if y >= inner.y + inner.height {
+        if y >= list_bottom {
             break;
         }
 
@@ -161,7 +364,8 @@ pub fn render_worktree_popup(
 
         let branch = wt.branch.as_deref().unwrap_or("(detached)");
         let path = wt.path.to_string_lossy();
-        let path_display = truncate(&path, (inner.width as usize).saturating_sub(branch.len() + 10));
+        let branch = if text::width(branch) < 20 { pad(branch, 20) } else { branch.to_string() };
+        let path_display = truncate(&path, (inner.width as usize).saturating_sub(text::width(&branch) + 21));
 
         let mut spans = vec![Span::styled(" ", style)];
 
@@ -171,7 +375,8 @@ pub fn render_worktree_popup(
             spans.push(Span::styled("  ", style));
         }
 
-        spans.push(Span::styled(format!("{:<20} ", branch), styles.worktree_branch));
+        spans.push(Span::styled(format!("{} ", branch), styles.worktree_branch));
+        spans.extend(worktree_badges(statuses.get(index).and_then(Option::as_ref), styles));
         spans.push(Span::styled(path_display, styles.worktree_path));
 
         let line = Line::from(spans);
@@ -183,78 +388,578 @@ pub fn render_worktree_popup(
             }
         }
     }
+
+    let keys = "Enter: switch  Ctrl-t: open in tab  Ctrl-n: new worktree";
+    buf.set_line(inner.x, list_bottom, &Line::styled(keys, styles.footer), inner.width);
+}
+
+/// Render the form for a new worktree's branch and path
+///
+/// `on_path` says which field is being typed; `error` is why the last
+/// attempt to create it failed.
+pub fn render_worktree_form(
+    buf: &mut Buffer,
+    area: Rect,
+    branch: &str,
+    path: &str,
+    on_path: bool,
+    error: Option<&str>,
+    styles: &Styles,
+) {
+    let width = 70.min(area.width.saturating_sub(4));
+    let inner = render_centered_popup(buf, area, width, 7, "New Worktree", styles);
+
+    let fields = [("Branch", branch, !on_path), ("Path", path, on_path)];
+    for (i, (label, value, active)) in fields.into_iter().enumerate() {
+        let cursor = if active { "_" } else { "" };
+        let line = Line::from(vec![
+            Span::styled(format!(" {} ", pad(label, 7)), if active { styles.popup_title } else { styles.footer }),
+            Span::styled(format!("{}{}", value, cursor), styles.sidebar_normal),
+        ]);
+        buf.set_line(inner.x, inner.y + i as u16, &line, inner.width);
+    }
+
+    let message = match error {
+        Some(error) => Line::styled(format!(" {}", error), styles.stats_removed),
+        None => Line::styled(" An existing branch is checked out, a new one starts at HEAD", styles.footer),
+    };
+    buf.set_line(inner.x, inner.y + 3, &message, inner.width);
+    buf.set_line(
+        inner.x,
+        inner.y + 4,
+        &Line::styled(" Tab: next field  Enter: create and switch  Esc: back", styles.footer),
+        inner.width,
+    );
+}
+
+/// Render the stash browser
+///
+/// `shown` is the full hash of the stash whose changes are on screen.
+pub fn render_stash_popup(buf: &mut Buffer, area: Rect, stashes: &[Stash], cursor: usize, shown: Option<&str>, styles: &Styles) {
+    let width = 80.min(area.width.saturating_sub(4));
+    let height = (stashes.len().max(1) as u16 + 4).min(area.height.saturating_sub(4));
+    let inner = render_centered_popup(buf, area, width, height, "Stashes", styles);
+    // Too short for the instructions and rule
+    if inner.height < 2 {
+        return;
+    }
+
+    let instructions = if shown.is_some() {
+        "Enter: show this stash (again: back to the branch)  Esc: close"
+    } else {
+        "Enter: show this stash  Esc: close"
+    };
+    buf.set_line(inner.x, inner.y, &Line::styled(instructions, styles.footer), inner.width);
+    buf.set_line(
+        inner.x,
+        inner.y + 1,
+        &Line::styled("─".repeat(inner.width as usize), styles.border),
+        inner.width,
+    );
+    if stashes.is_empty() {
+        if inner.height > 2 {
+            buf.set_line(inner.x, inner.y + 2, &Line::styled(" No stashes", styles.footer), inner.width);
+        }
+        return;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let visible = inner.height.saturating_sub(2) as usize;
+    let offset = (cursor + 1).saturating_sub(visible);
+    for (i, stash) in stashes.iter().enumerate().skip(offset).take(visible) {
+        let y = inner.y + 2 + (i - offset) as u16;
+        let is_cursor = i == cursor;
+        let style = if is_cursor { styles.sidebar_cursor } else { styles.sidebar_normal };
+        let marker = if shown == Some(stash.full_hash.as_str()) { "* " } else { "  " };
+        let name = stash.name();
+        let age = format_age(now - stash.time);
+        let message_width = (inner.width as usize).saturating_sub(text::width(&name) + text::width(&age) + 7);
+
+        let line = Line::from(vec![
+            Span::styled(format!(" {}", marker), styles.worktree_current),
+            Span::styled(format!("{}  ", name), styles.worktree_branch),
+            Span::styled(format!("{}  ", age), styles.footer),
+            Span::styled(truncate(&stash.message, message_width), style),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+        if is_cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+/// Render a yes/no question, e.g. before deleting something
+///
+/// The answer is the caller's to take from the next key: `y` for yes,
+/// anything else for no.
+pub fn render_confirm_popup(buf: &mut Buffer, area: Rect, title: &str, message: &str, styles: &Styles) {
+    let width = 60.min(area.width.saturating_sub(4));
+    let rows = text::wrap(message, width.saturating_sub(4) as usize);
+    let height = (rows.len() as u16 + 4).min(area.height.saturating_sub(2));
+    let inner = render_centered_popup(buf, area, width, height, title, styles);
+
+    for (i, row) in rows.iter().enumerate().take(inner.height.saturating_sub(2) as usize) {
+        buf.set_line(inner.x + 1, inner.y + i as u16, &Line::styled(*row, styles.sidebar_normal), inner.width.saturating_sub(1));
+    }
+    if inner.height > 0 {
+        let keys = Line::from(vec![
+            Span::styled(" y", styles.popup_title),
+            Span::styled(": yes  ", styles.footer),
+            Span::styled("n/Esc", styles.popup_title),
+            Span::styled(": no", styles.footer),
+        ]);
+        buf.set_line(inner.x, inner.y + inner.height - 1, &keys, inner.width);
+    }
+}
+
+/// Render merge conflict prediction popup
+pub fn render_conflict_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    base_branch: &str,
+    files: &[String],
+    cursor: usize,
+    styles: &Styles,
+) {
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (files.len().max(1) as u16 + 4).min(area.height.saturating_sub(4));
+
+    let inner = render_centered_popup(buf, area, width, height, "Predicted Conflicts", styles);
+
+    let summary = if files.is_empty() {
+        format!("Merging into {} would be clean", base_branch)
+    } else {
+        format!("{} file(s) would conflict with {}  Enter: jump  Esc: close", files.len(), base_branch)
+    };
+    buf.set_line(inner.x, inner.y, &Line::styled(summary, styles.footer), inner.width);
+
+    // Separator
+    buf.set_line(
+        inner.x,
+        inner.y + 1,
+        &Line::styled("─".repeat(inner.width as usize), styles.border),
+        inner.width,
+    );
+
+    // Keep the cursor in view
+    let visible = inner.height.saturating_sub(2) as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
+
+    for (i, path) in files.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + 2 + (i - scroll) as u16;
+
+        let is_cursor = i == cursor;
+        let style = if is_cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+
+        let path_display = truncate(path, (inner.width as usize).saturating_sub(4));
+        let line = Line::from(vec![
+            Span::styled(" ! ", styles.stats_removed),
+            Span::styled(path_display, style),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+
+        if is_cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+/// Render the issues panel listing annotations on changed lines
+pub fn render_issues_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    issues: &[&Annotation],
+    cursor: usize,
+    styles: &Styles,
+) {
+    let width = 90.min(area.width.saturating_sub(4));
+    let height = (issues.len().max(1) as u16 + 4).min(area.height.saturating_sub(4));
+
+    let inner = render_centered_popup(buf, area, width, height, "Issues", styles);
+
+    let summary = if issues.is_empty() {
+        "No annotations on changed lines".to_string()
+    } else {
+        format!("{} annotation(s) on changed lines  Enter: jump  Esc: close", issues.len())
+    };
+    buf.set_line(inner.x, inner.y, &Line::styled(summary, styles.footer), inner.width);
+
+    // Separator
+    buf.set_line(
+        inner.x,
+        inner.y + 1,
+        &Line::styled("─".repeat(inner.width as usize), styles.border),
+        inner.width,
+    );
+
+    // Keep the cursor in view
+    let visible = inner.height.saturating_sub(2) as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
+
+    for (i, issue) in issues.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + 2 + (i - scroll) as u16;
+
+        let is_cursor = i == cursor;
+        let style = if is_cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+
+        let location = format!("{}:{} ", issue.path, issue.line);
+        let rule = issue.rule.as_deref().map(|r| format!(" [{}]", r)).unwrap_or_default();
+        let message = truncate(
+            &format!("{}{}", issue.message.lines().next().unwrap_or(""), rule),
+            (inner.width as usize).saturating_sub(text::width(&location) + 4),
+        );
+
+        let line = Line::from(vec![
+            Span::styled(" ● ", styles.severity(issue.severity)),
+            Span::styled(location, styles.worktree_branch),
+            Span::styled(message, style),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+
+        if is_cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+/// Render the symbol picker: symbols matching `filter`, changed ones marked
+pub fn render_symbol_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    symbols: &[&FileSymbol],
+    cursor: usize,
+    filter: &str,
+    styles: &Styles,
+) {
+    let width = 90.min(area.width.saturating_sub(4));
+    let height = (symbols.len().max(1) as u16 + 4).min(area.height.saturating_sub(4));
+
+    let inner = render_centered_popup(buf, area, width, height, "Go to symbol", styles);
+    // Too short for the filter and rule
+    if inner.height < 2 {
+        return;
+    }
+
+    buf.set_line(inner.x, inner.y, &Line::styled(format!("> {}", filter), styles.popup_title), inner.width);
+    buf.set_line(
+        inner.x,
+        inner.y + 1,
+        &Line::styled("─".repeat(inner.width as usize), styles.border),
+        inner.width,
+    );
+
+    if symbols.is_empty() {
+        if inner.height > 2 {
+            buf.set_line(inner.x, inner.y + 2, &Line::styled(" No matching symbols", styles.footer), inner.width);
+        }
+        return;
+    }
+
+    // Keep the cursor in view
+    let visible = inner.height.saturating_sub(2) as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
+    let name_width = (inner.width as usize / 2).saturating_sub(8);
+
+    for (i, entry) in symbols.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + 2 + (i - scroll) as u16;
+        let style = if i == cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+
+        let marker = if entry.changed { Span::styled(" ● ", styles.line_added) } else { Span::styled("   ", style) };
+        let location = format!("{}:{}", entry.path, entry.symbol.line);
+        let location_width = (inner.width as usize).saturating_sub(name_width + 9);
+        let line = Line::from(vec![
+            marker,
+            Span::styled(pad(entry.symbol.kind.label(), 5), styles.line_number),
+            Span::styled(pad(&truncate(&entry.symbol.name, name_width), name_width + 1), style),
+            Span::styled(text::truncate_start(&location, location_width), styles.worktree_path),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+
+        if i == cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
 }
 
-/// Render help overlay
-pub fn render_help_popup(buf: &mut Buffer, area: Rect, styles: &Styles) {
-    let width = 50.min(area.width - 4);
-    let height = 24.min(area.height - 4);
+/// Render the recently viewed files popup, most recent first
+pub fn render_recent_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    recent: &[String],
+    cursor: usize,
+    styles: &Styles,
+) {
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = (recent.len().max(1) as u16 + 2).min(area.height.saturating_sub(4));
+
+    let inner = render_centered_popup(buf, area, width, height, "Recent files", styles);
+
+    if recent.is_empty() {
+        buf.set_line(inner.x, inner.y, &Line::styled(" No files viewed yet", styles.footer), inner.width);
+        return;
+    }
+
+    // Keep the cursor in view
+    let visible = inner.height as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
 
-    let inner = render_centered_popup(buf, area, width, height, "Help", styles);
+    for (i, path) in recent.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + (i - scroll) as u16;
+        let style = if i == cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+
+        let path = truncate(path, (inner.width as usize).saturating_sub(2));
+        buf.set_line(inner.x, y, &Line::styled(format!(" {}", path), style), inner.width);
+        if i == cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+/// Render the open pull requests picker, or a loading note while `pulls` is `None`
+pub fn render_pull_popup(
+    buf: &mut Buffer,
+    area: Rect,
+    pulls: Option<&[PullRequest]>,
+    cursor: usize,
+    styles: &Styles,
+) {
+    let count = pulls.map_or(1, |p| p.len().max(1));
+    let width = 80.min(area.width.saturating_sub(4));
+    let height = (count as u16 + 2).min(area.height.saturating_sub(4));
+
+    let inner = render_centered_popup(buf, area, width, height, "Pull requests", styles);
+
+    let pulls = match pulls {
+        None => {
+            buf.set_line(inner.x, inner.y, &Line::styled(" Loading…", styles.footer), inner.width);
+            return;
+        }
+        Some([]) => {
+            buf.set_line(inner.x, inner.y, &Line::styled(" No open pull requests", styles.footer), inner.width);
+            return;
+        }
+        Some(pulls) => pulls,
+    };
 
-    let help_items = [
-        ("Navigation", ""),
+    // Keep the cursor in view
+    let visible = inner.height as usize;
+    let scroll = cursor.saturating_sub(visible.saturating_sub(1));
+
+    for (i, pull) in pulls.iter().enumerate().skip(scroll).take(visible) {
+        let y = inner.y + (i - scroll) as u16;
+        let style = if i == cursor {
+            styles.sidebar_cursor
+        } else {
+            styles.sidebar_normal
+        };
+        let checks_style = match pull.checks {
+            CheckStatus::Passing => styles.stats_added,
+            CheckStatus::Failing => styles.stats_removed,
+            _ => styles.footer,
+        };
+
+        let number = format!("#{:<5} ", pull.number);
+        let author = format!("  {}", pull.author);
+        let title_width = (inner.width as usize).saturating_sub(text::width(&number) + text::width(&author) + 3);
+        let line = Line::from(vec![
+            Span::styled(format!(" {} ", pull.checks.badge()), checks_style),
+            Span::styled(number, styles.worktree_branch),
+            Span::styled(truncate(&pull.title, title_width), style),
+            Span::styled(author, styles.footer),
+        ]);
+        buf.set_line(inner.x, y, &line, inner.width);
+        if i == cursor {
+            for x in inner.x..inner.x + inner.width {
+                buf[(x, y)].set_style(style);
+            }
+        }
+    }
+}
+
+/// Help contents: sections of (key, description) pairs
+const HELP_SECTIONS: &[(&str, &[(&str, &str)])] = &[
+    ("Navigation", &[
         ("j/k", "Scroll down/up"),
-        ("Ctrl+d/u", "Page down/up"),
+        ("Ctrl+d/u", "Half page down/up"),
+        ("Ctrl+f/b", "Page down/up"),
+        ("Ctrl+e/y", "Scroll one line"),
         ("g/G", "Go to top/bottom"),
+        ("H/M/L", "Top/middle/bottom of view"),
         ("n/N", "Next/previous file"),
-        ("Enter", "Jump to file (sidebar)"),
+        ("Ctrl+o/i", "Jump back/forward"),
+        ("Ctrl+g", "Show full path"),
+        ("/", "Search files"),
+        ("'", "Recently viewed files"),
+        ("T", "Go to a function or type"),
+        ("O", "Changed functions of this file"),
         ("Tab", "Switch focus"),
-        ("", ""),
-        ("View", ""),
-        ("u", "Cycle view (split/unified/full)"),
-        ("x", "Cycle context lines"),
+    ]),
+    ("Sidebar", &[
+        ("Enter", "Jump to file/hunk"),
+        ("o / ←→", "Show/hide hunks"),
+        ("p", "Content follows sidebar cursor"),
+        ("v", "Select mode (Space marks)"),
+        ("V", "Clear marked files"),
+        ("/", "Filter tree (type to narrow)"),
         ("[/]", "Resize sidebar (or drag border)"),
-        ("/", "Search files"),
+        ("S", "Show sidebar (narrow terminals)"),
+    ]),
+    ("Review", &[
+        ("r", "Mark file reviewed"),
+        ("#", "Note on the cursor line"),
+        ("R", "Next unreviewed file"),
+        ("U", "Only changes since last review"),
+        ("- / +", "Base one commit back / forward (HEAD~N)"),
+        (".", "Diff from the merge base / the base's tip"),
+        ("B", "Group by commit"),
+        ("t ←/→", "Replay branch commit by commit"),
+        ("m J/K", "Commit messages panel / scroll"),
+        ("I", "Issues from --annotations"),
+        ("C", "Predict merge conflicts"),
+    ]),
+    ("View", &[
+        ("u", "Cycle view (split/unified/words/full)"),
+        ("b", "Blame gutter (unified/full views)"),
+        ("Ctrl+w", "Wrap long lines (unified views)"),
+        ("a", "Cycle the diff algorithm"),
+        ("< / >", "Fewer/more context lines"),
+        ("x", "Hide/restore context lines"),
         ("Space", "Collapse/expand file"),
-        ("z", "Collapse/expand all"),
+        ("Z", "Collapse/expand all"),
+        ("zz/zt/zb", "Center/top/bottom view"),
         ("h", "Toggle hidden files"),
-        ("", ""),
-        ("Filters", ""),
-        ("c", "Commit filter"),
-        ("w", "Worktree switcher"),
-        ("W", "Worktree list"),
-        ("", ""),
+        ("i", "Include generated files"),
+        ("X", "Hexdump small binary files"),
+        ("f", "Filter by change type"),
+        ("l", "Limit to paths (globs, folders)"),
+        ("Ctrl-l", "Filter by extension or language"),
+        ("s", "Uncommitted: all / staged / unstaged"),
+        ("D", "Open file in external diff tool"),
+        ("e", "Edit file at the cursor line in $EDITOR"),
+        ("y / Y", "Copy hunk / file as a patch"),
+        ("=", "Compare file with a path or revision"),
+        ("E", "Export the whole diff as ANSI text"),
+        ("Ctrl-r", "Save a Markdown review report"),
+        ("Ctrl+s", "Save the diff as shown as a .patch"),
+        ("Ctrl+t", "Cycle the color theme"),
+    ]),
+    ("Commits & worktrees", &[
+        ("c", "Commit filter (v: range, o: only this commit, /: search, s: sort)"),
+        ("w", "Worktree switcher (Ctrl+t: in a tab, Ctrl+n: new)"),
+        ("d", "Diffstat of files and folders (s: sort)"),
+        ("$", "Stash browser"),
+        ("W", "Worktree dashboard (t: in a tab, d: delete, p: prune)"),
+        ("[N]gt / gT", "Next/previous tab"),
+        ("gq", "Close tab"),
+        ("F", "Fetch base remote"),
+        ("P", "Open pull requests (gh)"),
+        ("A", "Post notes as a draft PR review"),
+    ]),
+    ("General", &[
+        ("Q{a-z} … Q", "Record a macro"),
+        ("[N]@{a-z}", "Replay a macro (@@: last)"),
         ("?", "Toggle this help"),
         ("q", "Quit"),
-    ];
+    ]),
+];
+
+/// A line of the help overlay
+enum HelpLine {
+    Section(&'static str),
+    Blank,
+    Key(&'static str, &'static str),
+}
 
-    for (i, (key, desc)) in help_items.iter().enumerate() {
-        let y = inner.y + i as u16;
-        if y >= inner.y + inner.height {
-            break;
+fn help_lines() -> Vec<HelpLine> {
+    let mut lines = Vec::new();
+    for (i, (section, items)) in HELP_SECTIONS.iter().enumerate() {
+        if i > 0 {
+            lines.push(HelpLine::Blank);
         }
+        lines.push(HelpLine::Section(section));
+        lines.extend(items.iter().map(|(key, desc)| HelpLine::Key(key, desc)));
+    }
+    lines
+}
 
-        if key.is_empty() && desc.is_empty() {
-            continue;
-        }
+/// First line of each help section
+pub fn help_section_starts() -> Vec<usize> {
+    help_lines()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| matches!(line, HelpLine::Section(_)).then_some(i))
+        .collect()
+}
 
-        if desc.is_empty() {
-            // Section header
-            buf.set_line(
-                inner.x,
-                y,
-                &Line::styled(format!(" {}", key), styles.popup_title),
-                inner.width,
-            );
-        } else {
-            // Key/desc pair
-            let line = Line::from(vec![
-                Span::styled(format!("  {:>12} ", key), styles.help_key),
-                Span::styled(*desc, styles.help_desc),
-            ]);
-            buf.set_line(inner.x, y, &line, inner.width);
+/// Render help overlay scrolled to `scroll`, returning the scroll actually used
+pub fn render_help_popup(buf: &mut Buffer, area: Rect, scroll: usize, styles: &Styles) -> usize {
+    let lines = help_lines();
+    let width = 50.min(area.width.saturating_sub(4));
+    let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let visible = height.saturating_sub(2) as usize;
+    let scroll = scroll.min(lines.len().saturating_sub(visible));
+
+    // Name the section at the top of the view when it doesn't all fit
+    let title = if visible < lines.len() {
+        let starts = help_section_starts();
+        let section = starts.iter().rposition(|&start| start <= scroll).unwrap_or(0);
+        format!("Help · {} ({}/{}) · j/k Tab", HELP_SECTIONS[section].0, section + 1, starts.len())
+    } else {
+        "Help".to_string()
+    };
+    let inner = render_centered_popup(buf, area, width, height, &title, styles);
+
+    for (row, line) in lines.iter().skip(scroll).take(visible).enumerate() {
+        let y = inner.y + row as u16;
+        match line {
+            HelpLine::Section(name) => {
+                buf.set_line(inner.x, y, &Line::styled(format!(" {}", name), styles.popup_title), inner.width);
+            }
+            HelpLine::Blank => {}
+            HelpLine::Key(key, desc) => {
+                let line = Line::from(vec![
+                    Span::styled(format!("  {:>12} ", key), styles.help_key),
+                    Span::styled(*desc, styles.help_desc),
+                ]);
+                buf.set_line(inner.x, y, &line, inner.width);
+            }
         }
     }
-}
 
-/// Truncate a string
-fn truncate(s: &str, max: usize) -> String {
-    if s.len() <= max {
-        s.to_string()
-    } else if max > 3 {
-        format!("{}...", &s[..max - 3])
-    } else {
-        s[..max].to_string()
+    // Mark more content below
+    if scroll + visible < lines.len() && inner.height > 0 {
+        let x = inner.x + inner.width.saturating_sub(2);
+        buf.set_string(x, inner.y + inner.height - 1, "↓", styles.popup_title);
     }
+
+    scroll
 }
+
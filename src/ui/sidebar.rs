@@ -9,7 +9,10 @@ use ratatui::{
     widgets::{Block, Borders, Widget},
 };
 
-use super::{Styles, TreeNode};
+use std::collections::{HashMap, HashSet};
+
+use super::{is_marked, FlatTree, Styles};
+use super::text::{truncate_middle, width};
 
 /// Default sidebar width
 pub const DEFAULT_SIDEBAR_WIDTH: u16 = 35;
@@ -19,13 +22,41 @@ pub const MIN_SIDEBAR_WIDTH: u16 = 20;
 pub const MAX_SIDEBAR_WIDTH: u16 = 80;
 /// Sidebar resize increment
 pub const SIDEBAR_RESIZE_STEP: u16 = 5;
+/// Where the file list sits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SidebarPosition {
+    /// Column left of the diff
+    #[default]
+    Left,
+    /// Full-width strip below the diff
+    Bottom,
+}
+
+impl SidebarPosition {
+    /// All positions, for listing valid names
+    pub const ALL: &[SidebarPosition] = &[SidebarPosition::Left, SidebarPosition::Bottom];
+
+    /// Look up a position by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Name used on the command line and in config
+    pub fn name(self) -> &'static str {
+        match self {
+            SidebarPosition::Left => "left",
+            SidebarPosition::Bottom => "bottom",
+        }
+    }
+}
+
 /// Maximum visual indentation depth (to prevent deep files from being invisible)
 const MAX_VISUAL_INDENT: usize = 6;
 
 /// Sidebar widget showing file tree
 pub struct Sidebar<'a> {
     /// Flattened tree nodes to display
-    pub nodes: &'a [&'a TreeNode],
+    pub nodes: FlatTree<'a>,
     /// Current cursor position
     pub cursor: usize,
     /// Scroll offset
@@ -34,6 +65,14 @@ pub struct Sidebar<'a> {
     pub hidden_count: usize,
     /// Whether the sidebar is focused
     pub focused: bool,
+    /// Marked file/folder paths
+    pub marked: &'a HashSet<String>,
+    /// Whether selection mode is active
+    pub selecting: bool,
+    /// Paths of files the user has looked at
+    pub seen: &'a HashSet<String>,
+    /// Files marked reviewed, by path
+    pub reviewed: &'a HashMap<String, String>,
     /// Styles
     pub styles: &'a Styles,
 }
@@ -47,7 +86,9 @@ impl Widget for Sidebar<'_> {
             self.styles.border
         };
 
-        let title = if self.hidden_count > 0 {
+        let title = if self.selecting {
+            format!(" Select files ({} marked) ", self.marked.len())
+        } else if self.hidden_count > 0 {
             format!(" Files ({} hidden) ", self.hidden_count)
         } else {
             " Files ".to_string()
@@ -94,10 +135,20 @@ impl Widget for Sidebar<'_> {
                 ));
             }
 
-            // Folder icon or file indicator
+            // Folder icon, expanded-file indicator, or hunk marker
             if node.is_folder {
                 let icon = if node.expanded { "▼ " } else { "▶ " };
                 spans.push(Span::styled(icon, self.styles.folder_icon));
+            } else if node.hunk_index.is_none() && is_marked(&node.path, self.marked) {
+                spans.push(Span::styled("✓ ", self.styles.sidebar_marked));
+            } else if node.expanded {
+                spans.push(Span::styled("▼ ", self.styles.folder_icon));
+            } else if node.hunk_index.is_some() {
+                spans.push(Span::styled("@ ", self.styles.folder_icon));
+            } else if self.reviewed.contains_key(&node.path) {
+                spans.push(Span::styled("✓ ", self.styles.stats_added));
+            } else if self.seen.contains(&node.path) {
+                spans.push(Span::styled("· ", self.styles.line_number));
             } else {
                 spans.push(Span::styled("  ", style));
             }
@@ -111,13 +162,22 @@ impl Widget for Sidebar<'_> {
             };
             let max_name_width = (inner.width as usize)
                 .saturating_sub(indent_width + depth_indicator_width + 12);
-            let name = smart_truncate(&node.name, max_name_width);
-            spans.push(Span::styled(name, style));
+            let name = truncate_middle(&node.name, max_name_width);
+            let name_style = if is_cursor {
+                style
+            } else if node.hunk_index.is_some() {
+                self.styles.hunk_header
+            } else if is_marked(&node.path, self.marked) {
+                self.styles.sidebar_marked
+            } else {
+                style
+            };
+            spans.push(Span::styled(name, name_style));
 
             // Stats
             let stats = format!(" +{} -{}", node.added, node.removed);
-            let name_len: usize = spans.iter().map(|s| s.content.len()).sum();
-            let available = (inner.width as usize).saturating_sub(name_len + stats.len());
+            let name_len: usize = spans.iter().map(Span::width).sum();
+            let available = (inner.width as usize).saturating_sub(name_len + width(&stats));
 
             if available > 0 {
                 spans.push(Span::styled(" ".repeat(available), style));
@@ -147,45 +207,20 @@ impl Widget for Sidebar<'_> {
     }
 }
 
-/// Smart truncate: shows beginning...end for better context
-///
-/// For "very_long_filename.tsx" with max 12:
-/// - Old: "very_long..." (loses extension info)
-/// - New: "very...e.tsx" (preserves extension)
-fn smart_truncate(s: &str, max_width: usize) -> String {
-    if s.len() <= max_width {
-        return s.to_string();
-    }
-
-    if max_width < 5 {
-        // Too small for smart truncation
-        return s.chars().take(max_width).collect();
-    }
-
-    // For filenames, try to preserve the extension
-    let ellipsis = "…"; // Single character ellipsis
-    let available = max_width - 1; // Space minus ellipsis
-
-    // Split into prefix and suffix
-    // Allocate more to the beginning (where the unique part usually is)
-    let prefix_len = (available * 2) / 3;
-    let suffix_len = available - prefix_len;
-
-    let prefix: String = s.chars().take(prefix_len).collect();
-    let suffix: String = s.chars().rev().take(suffix_len).collect::<String>().chars().rev().collect();
-
-    format!("{}{}{}", prefix, ellipsis, suffix)
-}
-
 /// Render the sidebar
+#[allow(clippy::too_many_arguments)]
 pub fn render_sidebar(
     buf: &mut Buffer,
     area: Rect,
-    nodes: &[&TreeNode],
+    nodes: FlatTree<'_>,
     cursor: usize,
     scroll: usize,
     hidden_count: usize,
     focused: bool,
+    marked: &HashSet<String>,
+    selecting: bool,
+    seen: &HashSet<String>,
+    reviewed: &HashMap<String, String>,
     styles: &Styles,
 ) {
     let sidebar = Sidebar {
@@ -194,6 +229,10 @@ pub fn render_sidebar(
         scroll,
         hidden_count,
         focused,
+        marked,
+        selecting,
+        seen,
+        reviewed,
         styles,
     };
     sidebar.render(area, buf);
@@ -1,64 +1,442 @@
 //! UI styles
 //!
 //! Defines consistent styling for the entire application.
-//! Uses a delta-like color palette for diffs.
+//! Uses a delta-like color palette for diffs by default, with light,
+//! solarized, gruvbox and high-contrast themes to pick from.
 
 use ratatui::style::{Color, Modifier, Style};
 
-/// Color palette inspired by delta diff viewer
-pub mod colors {
-    use ratatui::style::Color;
+use crate::annotations::Severity;
+
+/// Named color themes, selecting both the UI palette and the syntax theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Delta-like palette for dark terminals
+    #[default]
+    Dark,
+    /// Palette for light terminals
+    Light,
+    /// Solarized dark
+    Solarized,
+    /// Gruvbox dark, warm and low in blue
+    Gruvbox,
+    /// Saturated colors on black for low-contrast screens and low vision
+    HighContrast,
+}
+
+impl Theme {
+    /// All themes, for listing in errors and help
+    pub const ALL: &[Theme] = &[Theme::Dark, Theme::Light, Theme::Solarized, Theme::Gruvbox, Theme::HighContrast];
+
+    /// Look up a theme by name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Name used on the command line and in config
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Solarized => "solarized",
+            Theme::Gruvbox => "gruvbox",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    /// Guess the theme from the terminal's background, dark unless it says it's light
+    ///
+    /// Reads `COLORFGBG`, which rxvt, Konsole, iTerm2 and others set to
+    /// `fg;bg` color indexes.
+    pub fn detect() -> Self {
+        std::env::var("COLORFGBG").ok().and_then(|value| Self::from_colorfgbg(&value)).unwrap_or_default()
+    }
+
+    /// Theme for a `COLORFGBG` value: light on a white or bright background
+    fn from_colorfgbg(value: &str) -> Option<Self> {
+        // The background is the last field; some terminals put `default` in between
+        let background: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+        Some(match background {
+            7 | 9..=15 => Theme::Light,
+            _ => Theme::Dark,
+        })
+    }
+
+    /// The theme after this one in [`Theme::ALL`], wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Syntect theme used for syntax highlighting
+    pub fn syntax_theme(self) -> &'static str {
+        match self {
+            Theme::Dark => "base16-ocean.dark",
+            Theme::Light => "InspiredGitHub",
+            Theme::Solarized => "Solarized (dark)",
+            Theme::Gruvbox => "base16-mocha.dark",
+            Theme::HighContrast => "base16-eighties.dark",
+        }
+    }
 
+    /// UI colors
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Dark => &Palette::DARK,
+            Theme::Light => &Palette::LIGHT,
+            Theme::Solarized => &Palette::SOLARIZED,
+            Theme::Gruvbox => &Palette::GRUVBOX,
+            Theme::HighContrast => &Palette::HIGH_CONTRAST,
+        }
+    }
+}
+
+/// Colors the UI styles are built from
+#[derive(Debug, Clone)]
+pub struct Palette {
     // Base colors
-    pub const FG: Color = Color::White;
-    pub const DIM: Color = Color::DarkGray;
+    pub fg: Color,
+    pub dim: Color,
 
-    // Diff colors (delta-like palette)
-    pub const ADDED_BG: Color = Color::Rgb(20, 70, 40);
-    pub const ADDED_FG: Color = Color::Rgb(120, 200, 120);
-    pub const REMOVED_BG: Color = Color::Rgb(80, 32, 32);
-    pub const REMOVED_FG: Color = Color::Rgb(200, 120, 120);
+    // Diff colors
+    pub added_bg: Color,
+    pub added_fg: Color,
+    pub removed_bg: Color,
+    pub removed_fg: Color,
 
     // Gutter colors
-    pub const GUTTER_ADDED: Color = Color::Green;
-    pub const GUTTER_REMOVED: Color = Color::Red;
-    pub const GUTTER_CONTEXT: Color = Color::DarkGray;
+    pub gutter_added: Color,
+    pub gutter_removed: Color,
+    pub gutter_context: Color,
 
     // Line numbers
-    pub const LINE_NUMBER: Color = Color::DarkGray;
+    pub line_number: Color,
 
     // UI elements
-    pub const HEADER_BG: Color = Color::Rgb(40, 44, 52);
-    pub const HEADER_FG: Color = Color::White;
-    pub const FOOTER_BG: Color = Color::Rgb(40, 44, 52);
-    pub const FOOTER_FG: Color = Color::DarkGray;
+    pub header_bg: Color,
+    pub header_fg: Color,
+    pub footer_bg: Color,
+    pub footer_fg: Color,
 
     // Stats
-    pub const STATS_ADDED: Color = Color::Green;
-    pub const STATS_REMOVED: Color = Color::Red;
+    pub stats_added: Color,
+    pub stats_removed: Color,
 
     // Selection
-    pub const CURSOR_BG: Color = Color::Rgb(80, 80, 100);
+    pub cursor_bg: Color,
+    pub marked: Color,
 
     // File headers
-    pub const FILE_HEADER_BG: Color = Color::Rgb(50, 55, 65);
-    pub const FILE_HEADER_FG: Color = Color::Cyan;
+    pub file_header_bg: Color,
+    pub file_header_fg: Color,
 
     // Hunk headers
-    pub const HUNK_HEADER_FG: Color = Color::Magenta;
+    pub hunk_header_fg: Color,
 
     // Borders
-    pub const BORDER: Color = Color::DarkGray;
-    pub const BORDER_FOCUS: Color = Color::Cyan;
+    pub border: Color,
+    pub border_focus: Color,
 
     // Popup
-    pub const POPUP_BG: Color = Color::Rgb(30, 34, 42);
-    pub const POPUP_BORDER: Color = Color::Cyan;
+    pub popup_bg: Color,
+    pub popup_border: Color,
+    pub help_key: Color,
+
+    // Annotations
+    pub annotation_error: Color,
+    pub annotation_warning: Color,
+    pub annotation_note: Color,
+
+    // Conflict markers left in added lines
+    pub conflict_bg: Color,
+    pub conflict_fg: Color,
+
+    // Lines moved elsewhere rather than removed or added
+    pub moved_removed_bg: Color,
+    pub moved_added_bg: Color,
+    pub moved_fg: Color,
 
     // Worktree
-    pub const WORKTREE_CURRENT: Color = Color::Green;
-    pub const WORKTREE_PATH: Color = Color::DarkGray;
-    pub const WORKTREE_BRANCH: Color = Color::Cyan;
+    pub worktree_current: Color,
+    pub worktree_path: Color,
+    pub worktree_branch: Color,
+}
+
+impl Palette {
+    /// Dark palette inspired by delta diff viewer
+    pub const DARK: Palette = Palette {
+        fg: Color::White,
+        dim: Color::DarkGray,
+
+        added_bg: Color::Rgb(20, 70, 40),
+        added_fg: Color::Rgb(120, 200, 120),
+        removed_bg: Color::Rgb(80, 32, 32),
+        removed_fg: Color::Rgb(200, 120, 120),
+
+        gutter_added: Color::Green,
+        gutter_removed: Color::Red,
+        gutter_context: Color::DarkGray,
+
+        line_number: Color::DarkGray,
+
+        header_bg: Color::Rgb(40, 44, 52),
+        header_fg: Color::White,
+        footer_bg: Color::Rgb(40, 44, 52),
+        footer_fg: Color::DarkGray,
+
+        stats_added: Color::Green,
+        stats_removed: Color::Red,
+
+        cursor_bg: Color::Rgb(80, 80, 100),
+        marked: Color::Yellow,
+
+        file_header_bg: Color::Rgb(50, 55, 65),
+        file_header_fg: Color::Cyan,
+
+        hunk_header_fg: Color::Magenta,
+
+        border: Color::DarkGray,
+        border_focus: Color::Cyan,
+
+        popup_bg: Color::Rgb(30, 34, 42),
+        popup_border: Color::Cyan,
+        help_key: Color::Yellow,
+
+        annotation_error: Color::LightRed,
+        annotation_warning: Color::Yellow,
+        annotation_note: Color::LightBlue,
+
+        conflict_bg: Color::Rgb(95, 70, 0),
+        conflict_fg: Color::Yellow,
+
+        moved_removed_bg: Color::Rgb(64, 40, 80),
+        moved_added_bg: Color::Rgb(24, 58, 82),
+        moved_fg: Color::Rgb(170, 170, 230),
+
+        worktree_current: Color::Green,
+        worktree_path: Color::DarkGray,
+        worktree_branch: Color::Cyan,
+    };
+
+    /// Light palette with dark text and pale diff backgrounds
+    pub const LIGHT: Palette = Palette {
+        fg: Color::Black,
+        dim: Color::Rgb(120, 120, 120),
+
+        added_bg: Color::Rgb(220, 245, 220),
+        added_fg: Color::Rgb(20, 100, 40),
+        removed_bg: Color::Rgb(250, 225, 225),
+        removed_fg: Color::Rgb(150, 30, 30),
+
+        gutter_added: Color::Rgb(20, 130, 40),
+        gutter_removed: Color::Rgb(180, 30, 30),
+        gutter_context: Color::Rgb(170, 170, 170),
+
+        line_number: Color::Rgb(150, 150, 150),
+
+        header_bg: Color::Rgb(225, 228, 235),
+        header_fg: Color::Black,
+        footer_bg: Color::Rgb(225, 228, 235),
+        footer_fg: Color::Rgb(90, 90, 90),
+
+        stats_added: Color::Rgb(20, 130, 40),
+        stats_removed: Color::Rgb(180, 30, 30),
+
+        cursor_bg: Color::Rgb(200, 212, 235),
+        marked: Color::Rgb(170, 110, 0),
+
+        file_header_bg: Color::Rgb(210, 220, 235),
+        file_header_fg: Color::Rgb(0, 90, 140),
+
+        hunk_header_fg: Color::Rgb(140, 40, 140),
+
+        border: Color::Rgb(170, 170, 170),
+        border_focus: Color::Rgb(0, 120, 170),
+
+        popup_bg: Color::Rgb(245, 245, 250),
+        popup_border: Color::Rgb(0, 120, 170),
+        help_key: Color::Rgb(170, 110, 0),
+
+        annotation_error: Color::Rgb(190, 20, 20),
+        annotation_warning: Color::Rgb(170, 110, 0),
+        annotation_note: Color::Rgb(0, 90, 170),
+
+        conflict_bg: Color::Rgb(255, 230, 160),
+        conflict_fg: Color::Rgb(120, 70, 0),
+
+        moved_removed_bg: Color::Rgb(238, 225, 248),
+        moved_added_bg: Color::Rgb(220, 236, 250),
+        moved_fg: Color::Rgb(90, 60, 150),
+
+        worktree_current: Color::Rgb(20, 130, 40),
+        worktree_path: Color::Rgb(120, 120, 120),
+        worktree_branch: Color::Rgb(0, 120, 170),
+    };
+
+    /// Solarized dark, with diff backgrounds tinted from its base02
+    pub const SOLARIZED: Palette = Palette {
+        fg: Color::Rgb(147, 161, 161),
+        dim: Color::Rgb(88, 110, 117),
+
+        added_bg: Color::Rgb(16, 64, 40),
+        added_fg: Color::Rgb(133, 153, 0),
+        removed_bg: Color::Rgb(70, 34, 40),
+        removed_fg: Color::Rgb(220, 50, 47),
+
+        gutter_added: Color::Rgb(133, 153, 0),
+        gutter_removed: Color::Rgb(220, 50, 47),
+        gutter_context: Color::Rgb(88, 110, 117),
+
+        line_number: Color::Rgb(88, 110, 117),
+
+        header_bg: Color::Rgb(7, 54, 66),
+        header_fg: Color::Rgb(238, 232, 213),
+        footer_bg: Color::Rgb(7, 54, 66),
+        footer_fg: Color::Rgb(131, 148, 150),
+
+        stats_added: Color::Rgb(133, 153, 0),
+        stats_removed: Color::Rgb(220, 50, 47),
+
+        cursor_bg: Color::Rgb(30, 80, 100),
+        marked: Color::Rgb(181, 137, 0),
+
+        file_header_bg: Color::Rgb(7, 54, 66),
+        file_header_fg: Color::Rgb(42, 161, 152),
+
+        hunk_header_fg: Color::Rgb(108, 113, 196),
+
+        border: Color::Rgb(88, 110, 117),
+        border_focus: Color::Rgb(38, 139, 210),
+
+        popup_bg: Color::Rgb(0, 43, 54),
+        popup_border: Color::Rgb(38, 139, 210),
+        help_key: Color::Rgb(181, 137, 0),
+
+        annotation_error: Color::Rgb(220, 50, 47),
+        annotation_warning: Color::Rgb(203, 75, 22),
+        annotation_note: Color::Rgb(38, 139, 210),
+
+        conflict_bg: Color::Rgb(80, 64, 0),
+        conflict_fg: Color::Rgb(181, 137, 0),
+
+        moved_removed_bg: Color::Rgb(40, 40, 80),
+        moved_added_bg: Color::Rgb(10, 60, 80),
+        moved_fg: Color::Rgb(108, 113, 196),
+
+        worktree_current: Color::Rgb(133, 153, 0),
+        worktree_path: Color::Rgb(88, 110, 117),
+        worktree_branch: Color::Rgb(42, 161, 152),
+    };
+
+    /// Gruvbox dark, with its muted greens and reds on brown-gray
+    pub const GRUVBOX: Palette = Palette {
+        fg: Color::Rgb(235, 219, 178),
+        dim: Color::Rgb(146, 131, 116),
+
+        added_bg: Color::Rgb(50, 54, 26),
+        added_fg: Color::Rgb(184, 187, 38),
+        removed_bg: Color::Rgb(60, 31, 30),
+        removed_fg: Color::Rgb(251, 73, 52),
+
+        gutter_added: Color::Rgb(152, 151, 26),
+        gutter_removed: Color::Rgb(204, 36, 29),
+        gutter_context: Color::Rgb(102, 92, 84),
+
+        line_number: Color::Rgb(124, 111, 100),
+
+        header_bg: Color::Rgb(60, 56, 54),
+        header_fg: Color::Rgb(235, 219, 178),
+        footer_bg: Color::Rgb(60, 56, 54),
+        footer_fg: Color::Rgb(168, 153, 132),
+
+        stats_added: Color::Rgb(184, 187, 38),
+        stats_removed: Color::Rgb(251, 73, 52),
+
+        cursor_bg: Color::Rgb(80, 73, 69),
+        marked: Color::Rgb(250, 189, 47),
+
+        file_header_bg: Color::Rgb(50, 48, 47),
+        file_header_fg: Color::Rgb(142, 192, 124),
+
+        hunk_header_fg: Color::Rgb(211, 134, 155),
+
+        border: Color::Rgb(102, 92, 84),
+        border_focus: Color::Rgb(254, 128, 25),
+
+        popup_bg: Color::Rgb(40, 40, 40),
+        popup_border: Color::Rgb(254, 128, 25),
+        help_key: Color::Rgb(250, 189, 47),
+
+        annotation_error: Color::Rgb(251, 73, 52),
+        annotation_warning: Color::Rgb(250, 189, 47),
+        annotation_note: Color::Rgb(131, 165, 152),
+
+        conflict_bg: Color::Rgb(90, 70, 20),
+        conflict_fg: Color::Rgb(250, 189, 47),
+
+        moved_removed_bg: Color::Rgb(72, 52, 70),
+        moved_added_bg: Color::Rgb(40, 62, 66),
+        moved_fg: Color::Rgb(211, 134, 155),
+
+        worktree_current: Color::Rgb(184, 187, 38),
+        worktree_path: Color::Rgb(146, 131, 116),
+        worktree_branch: Color::Rgb(142, 192, 124),
+    };
+
+    /// Bright, saturated colors on black, for readability over looks
+    pub const HIGH_CONTRAST: Palette = Palette {
+        fg: Color::White,
+        dim: Color::Gray,
+
+        added_bg: Color::Rgb(0, 85, 0),
+        added_fg: Color::LightGreen,
+        removed_bg: Color::Rgb(110, 0, 0),
+        removed_fg: Color::LightRed,
+
+        gutter_added: Color::LightGreen,
+        gutter_removed: Color::LightRed,
+        gutter_context: Color::Gray,
+
+        line_number: Color::Gray,
+
+        header_bg: Color::Black,
+        header_fg: Color::White,
+        footer_bg: Color::Black,
+        footer_fg: Color::White,
+
+        stats_added: Color::LightGreen,
+        stats_removed: Color::LightRed,
+
+        cursor_bg: Color::Blue,
+        marked: Color::LightYellow,
+
+        file_header_bg: Color::Rgb(30, 30, 30),
+        file_header_fg: Color::LightCyan,
+
+        hunk_header_fg: Color::LightMagenta,
+
+        border: Color::White,
+        border_focus: Color::LightYellow,
+
+        popup_bg: Color::Black,
+        popup_border: Color::LightYellow,
+        help_key: Color::LightYellow,
+
+        annotation_error: Color::LightRed,
+        annotation_warning: Color::LightYellow,
+        annotation_note: Color::LightCyan,
+
+        conflict_bg: Color::Rgb(120, 90, 0),
+        conflict_fg: Color::LightYellow,
+
+        moved_removed_bg: Color::Rgb(60, 0, 90),
+        moved_added_bg: Color::Rgb(0, 50, 90),
+        moved_fg: Color::LightMagenta,
+
+        worktree_current: Color::LightGreen,
+        worktree_path: Color::Gray,
+        worktree_branch: Color::LightCyan,
+    };
 }
 
 /// Collection of styles used throughout the UI
@@ -74,13 +452,19 @@ pub struct Styles {
     pub line_added: Style,
     pub line_removed: Style,
     pub line_context: Style,
+    pub word_added: Style,
+    pub word_removed: Style,
     pub gutter_added: Style,
     pub gutter_removed: Style,
     pub gutter_context: Style,
+    pub line_moved_removed: Style,
+    pub line_moved_added: Style,
+    pub gutter_moved: Style,
 
     // File headers
     pub file_header: Style,
     pub hunk_header: Style,
+    pub section_header: Style,
 
     // Stats
     pub stats_added: Style,
@@ -92,6 +476,7 @@ pub struct Styles {
     pub sidebar_hidden: Style,
     pub sidebar_hidden_cursor: Style,
     pub folder_icon: Style,
+    pub sidebar_marked: Style,
 
     // Borders
     pub border: Style,
@@ -109,6 +494,17 @@ pub struct Styles {
     // Help
     pub help_key: Style,
     pub help_desc: Style,
+
+    // Annotations
+    pub annotation_error: Style,
+    pub annotation_warning: Style,
+    pub annotation_note: Style,
+
+    // Conflict markers
+    pub conflict_marker: Style,
+
+    // Content cursor line
+    pub content_cursor: Style,
 }
 
 impl Default for Styles {
@@ -120,82 +516,152 @@ impl Default for Styles {
 impl Styles {
     /// Create a new Styles instance with default values
     pub fn new() -> Self {
+        Self::for_theme(Theme::default())
+    }
+
+    /// Create the styles for a theme
+    pub fn for_theme(theme: Theme) -> Self {
+        let colors = theme.palette();
         Self {
             // Header/Footer
             header: Style::default()
-                .bg(colors::HEADER_BG)
-                .fg(colors::HEADER_FG),
+                .bg(colors.header_bg)
+                .fg(colors.header_fg),
             footer: Style::default()
-                .bg(colors::FOOTER_BG)
-                .fg(colors::FOOTER_FG),
+                .bg(colors.footer_bg)
+                .fg(colors.footer_fg),
             footer_key: Style::default()
-                .fg(colors::HEADER_FG)
+                .fg(colors.header_fg)
                 .add_modifier(Modifier::BOLD),
 
             // Diff content
-            line_number: Style::default().fg(colors::LINE_NUMBER),
+            line_number: Style::default().fg(colors.line_number),
             line_added: Style::default()
-                .bg(colors::ADDED_BG)
-                .fg(colors::ADDED_FG),
+                .bg(colors.added_bg)
+                .fg(colors.added_fg),
             line_removed: Style::default()
-                .bg(colors::REMOVED_BG)
-                .fg(colors::REMOVED_FG),
-            line_context: Style::default().fg(colors::FG),
-            gutter_added: Style::default().fg(colors::GUTTER_ADDED),
-            gutter_removed: Style::default().fg(colors::GUTTER_REMOVED),
-            gutter_context: Style::default().fg(colors::GUTTER_CONTEXT),
+                .bg(colors.removed_bg)
+                .fg(colors.removed_fg),
+            line_context: Style::default().fg(colors.fg),
+            word_added: Style::default()
+                .bg(colors.added_bg)
+                .fg(colors.stats_added)
+                .add_modifier(Modifier::BOLD),
+            word_removed: Style::default()
+                .bg(colors.removed_bg)
+                .fg(colors.stats_removed)
+                .add_modifier(Modifier::BOLD),
+            gutter_added: Style::default().fg(colors.gutter_added),
+            gutter_removed: Style::default().fg(colors.gutter_removed),
+            gutter_context: Style::default().fg(colors.gutter_context),
+            line_moved_removed: Style::default()
+                .bg(colors.moved_removed_bg)
+                .fg(colors.moved_fg),
+            line_moved_added: Style::default()
+                .bg(colors.moved_added_bg)
+                .fg(colors.moved_fg),
+            gutter_moved: Style::default()
+                .fg(colors.moved_fg)
+                .add_modifier(Modifier::BOLD),
 
             // File headers
             file_header: Style::default()
-                .bg(colors::FILE_HEADER_BG)
-                .fg(colors::FILE_HEADER_FG)
+                .bg(colors.file_header_bg)
+                .fg(colors.file_header_fg)
                 .add_modifier(Modifier::BOLD),
             hunk_header: Style::default()
-                .fg(colors::HUNK_HEADER_FG)
+                .fg(colors.hunk_header_fg)
                 .add_modifier(Modifier::ITALIC),
+            section_header: Style::default()
+                .fg(colors.marked)
+                .add_modifier(Modifier::BOLD),
 
             // Stats
             stats_added: Style::default()
-                .fg(colors::STATS_ADDED)
+                .fg(colors.stats_added)
                 .add_modifier(Modifier::BOLD),
             stats_removed: Style::default()
-                .fg(colors::STATS_REMOVED)
+                .fg(colors.stats_removed)
                 .add_modifier(Modifier::BOLD),
 
             // Sidebar
-            sidebar_normal: Style::default().fg(colors::FG),
+            sidebar_normal: Style::default().fg(colors.fg),
             sidebar_cursor: Style::default()
-                .bg(colors::CURSOR_BG)
-                .fg(colors::FG)
+                .bg(colors.cursor_bg)
+                .fg(colors.fg)
                 .add_modifier(Modifier::BOLD),
-            sidebar_hidden: Style::default().fg(colors::DIM),
+            sidebar_hidden: Style::default().fg(colors.dim),
             sidebar_hidden_cursor: Style::default()
-                .bg(colors::CURSOR_BG)
-                .fg(colors::DIM),
-            folder_icon: Style::default().fg(colors::DIM),
+                .bg(colors.cursor_bg)
+                .fg(colors.dim),
+            folder_icon: Style::default().fg(colors.dim),
+            sidebar_marked: Style::default()
+                .fg(colors.marked)
+                .add_modifier(Modifier::BOLD),
 
             // Borders
-            border: Style::default().fg(colors::BORDER),
-            border_focus: Style::default().fg(colors::BORDER_FOCUS),
+            border: Style::default().fg(colors.border),
+            border_focus: Style::default().fg(colors.border_focus),
 
             // Popup
-            popup: Style::default().bg(colors::POPUP_BG).fg(colors::FG),
+            popup: Style::default().bg(colors.popup_bg).fg(colors.fg),
             popup_title: Style::default()
-                .fg(colors::POPUP_BORDER)
+                .fg(colors.popup_border)
                 .add_modifier(Modifier::BOLD),
 
             // Worktree
             worktree_current: Style::default()
-                .fg(colors::WORKTREE_CURRENT)
+                .fg(colors.worktree_current)
                 .add_modifier(Modifier::BOLD),
-            worktree_path: Style::default().fg(colors::WORKTREE_PATH),
-            worktree_branch: Style::default().fg(colors::WORKTREE_BRANCH),
+            worktree_path: Style::default().fg(colors.worktree_path),
+            worktree_branch: Style::default().fg(colors.worktree_branch),
 
             // Help
             help_key: Style::default()
-                .fg(Color::Yellow)
+                .fg(colors.help_key)
+                .add_modifier(Modifier::BOLD),
+            help_desc: Style::default().fg(colors.dim),
+
+            // Annotations
+            annotation_error: Style::default()
+                .fg(colors.annotation_error)
+                .add_modifier(Modifier::BOLD),
+            annotation_warning: Style::default()
+                .fg(colors.annotation_warning)
+                .add_modifier(Modifier::BOLD),
+            annotation_note: Style::default().fg(colors.annotation_note),
+
+            // Conflict markers
+            conflict_marker: Style::default()
+                .bg(colors.conflict_bg)
+                .fg(colors.conflict_fg)
                 .add_modifier(Modifier::BOLD),
-            help_desc: Style::default().fg(colors::DIM),
+
+            // Content cursor line
+            content_cursor: Style::default().add_modifier(Modifier::UNDERLINED),
+        }
+    }
+
+    /// Style for an annotation of the given severity
+    pub fn severity(&self, severity: Severity) -> Style {
+        match severity {
+            Severity::Error => self.annotation_error,
+            Severity::Warning => self.annotation_warning,
+            Severity::Note => self.annotation_note,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_colorfgbg() {
+        assert_eq!(Theme::from_colorfgbg("15;0"), Some(Theme::Dark));
+        assert_eq!(Theme::from_colorfgbg("0;15"), Some(Theme::Light));
+        assert_eq!(Theme::from_colorfgbg("0;default;7"), Some(Theme::Light));
+        assert_eq!(Theme::from_colorfgbg("7;8"), Some(Theme::Dark));
+        assert_eq!(Theme::from_colorfgbg("default"), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Text measurement
+//!
+//! How much of a string fits is a matter of terminal columns, not bytes or
+//! chars: CJK characters and most emoji take two columns, combining marks
+//! none. These helpers measure, cut and pad by display width, and only cut
+//! between grapheme clusters so an accented letter or emoji sequence is
+//! never split in half.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Marks where text was cut
+const ELLIPSIS: &str = "…";
+
+/// Columns `s` takes up in a terminal
+pub fn width(s: &str) -> usize {
+    s.width()
+}
+
+/// Longest start of `s` that fits in `max` columns
+pub fn take_width(s: &str, max: usize) -> &str {
+    let mut used = 0;
+    for (i, grapheme) in s.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > max {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+/// Longest end of `s` that fits in `max` columns
+pub fn take_width_end(s: &str, max: usize) -> &str {
+    let mut used = 0;
+    for (i, grapheme) in s.grapheme_indices(true).rev() {
+        used += grapheme.width();
+        if used > max {
+            return &s[i + grapheme.len()..];
+        }
+    }
+    s
+}
+
+/// Cut `s` to at most `max` columns, ending with an ellipsis when cut
+pub fn truncate(s: &str, max: usize) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    format!("{}{}", take_width(s, max - 1), ELLIPSIS)
+}
+
+/// Cut `s` to at most `max` columns from the start, keeping its end
+///
+/// Suits paths, whose last components say the most.
+pub fn truncate_start(s: &str, max: usize) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    format!("{}{}", ELLIPSIS, take_width_end(s, max - 1))
+}
+
+/// Cut `s` to at most `max` columns out of its middle
+///
+/// Keeps more of the start, where names usually differ, and the end, where
+/// the extension is: `very_long_filename.tsx` at 12 becomes `very_lo….tsx`.
+pub fn truncate_middle(s: &str, max: usize) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+    // Too narrow to be worth splitting
+    if max < 5 {
+        return take_width(s, max).to_string();
+    }
+    let available = max - 1;
+    let prefix = take_width(s, available * 2 / 3);
+    let suffix = take_width_end(s, available - width(prefix));
+    format!("{}{}{}", prefix, ELLIPSIS, suffix)
+}
+
+/// Shorten `path` to `max` columns as a breadcrumb, keeping its file name
+///
+/// Directories give way from the middle, so `src/ui/widgets/diff_view.rs`
+/// becomes `src/…/widgets/diff_view.rs` and then `src/…/diff_view.rs`; when
+/// even `…/diff_view.rs` doesn't fit, the file name is cut in its middle.
+pub fn breadcrumb(path: &str, max: usize) -> String {
+    if width(path) <= max {
+        return path.to_string();
+    }
+    let Some((dirs, name)) = path.rsplit_once('/') else {
+        return truncate_middle(path, max);
+    };
+    let dirs: Vec<&str> = dirs.split('/').collect();
+    for keep in (0..dirs.len().saturating_sub(1)).rev() {
+        let tail: String = dirs[dirs.len() - keep..].iter().map(|dir| format!("{}/", dir)).collect();
+        let crumb = format!("{}/{}/{}{}", dirs[0], ELLIPSIS, tail, name);
+        if width(&crumb) <= max {
+            return crumb;
+        }
+    }
+    let crumb = format!("{}/{}", ELLIPSIS, name);
+    if width(&crumb) <= max {
+        return crumb;
+    }
+    truncate_middle(name, max)
+}
+
+/// Cut `s` into rows of at most `columns` columns, breaking between graphemes
+///
+/// Always at least one row; a grapheme wider than `columns` gets a row of its own.
+pub fn wrap(s: &str, columns: usize) -> Vec<&str> {
+    let mut rows = Vec::new();
+    let (mut start, mut used) = (0, 0);
+    for (i, grapheme) in s.grapheme_indices(true) {
+        let w = grapheme.width();
+        if used + w > columns && i > start {
+            rows.push(&s[start..i]);
+            (start, used) = (i, 0);
+        }
+        used += w;
+    }
+    rows.push(&s[start..]);
+    rows
+}
+
+/// Pad `s` with spaces on the right to `columns`, or cut it to fit
+pub fn pad(s: &str, columns: usize) -> String {
+    let cut = take_width(s, columns);
+    format!("{}{}", cut, " ".repeat(columns - width(cut)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_aware_cuts() {
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(take_width("日本語", 5), "日本");
+        assert_eq!(take_width_end("日本語", 3), "語");
+        // A flag is one grapheme made of two chars, and is never split
+        assert_eq!(take_width("🇯🇵x", 1), "");
+        assert_eq!(take_width("e\u{301}té", 1), "e\u{301}");
+
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello world", 6), "hello…");
+        assert_eq!(truncate("日本語テキスト", 5), "日本…");
+        assert_eq!(truncate_start("src/ü/main.rs", 8), "…main.rs");
+        assert_eq!(truncate_middle("very_long_filename.tsx", 12), "very_lo….tsx");
+        assert_eq!(truncate_middle("日本語のファイル名.rs", 10), "日本語….rs");
+
+        assert_eq!(wrap("hello world", 4), vec!["hell", "o wo", "rld"]);
+        assert_eq!(wrap("日本語", 3), vec!["日", "本", "語"]);
+        assert_eq!(wrap("", 4), vec![""]);
+
+        assert_eq!(breadcrumb("src/ui/widgets/diff_view.rs", 40), "src/ui/widgets/diff_view.rs");
+        assert_eq!(breadcrumb("src/ui/widgets/diff_view.rs", 26), "src/…/widgets/diff_view.rs");
+        assert_eq!(breadcrumb("src/ui/widgets/diff_view.rs", 20), "src/…/diff_view.rs");
+        assert_eq!(breadcrumb("src/ui/widgets/diff_view.rs", 15), "…/diff_view.rs");
+        assert_eq!(breadcrumb("src/ui/widgets/diff_view.rs", 8), "diff….rs");
+
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(pad("日本語", 5), "日本 ");
+    }
+}
@@ -0,0 +1,115 @@
+//! Commit timeline scrubber
+//!
+//! A one-line bar of the branch's commits, oldest first, marking the commit
+//! whose cumulative state the diff currently shows.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::Widget,
+};
+
+use super::Styles;
+use super::text;
+
+/// One step of the timeline
+pub struct TimelineStep<'a> {
+    /// Abbreviated commit hash
+    pub hash: &'a str,
+    /// Commit subject
+    pub subject: &'a str,
+}
+
+/// Timeline widget shown in place of the footer while scrubbing
+pub struct Timeline<'a> {
+    /// Steps, oldest first
+    pub steps: &'a [TimelineStep<'a>],
+    /// Index of the current step
+    pub position: usize,
+    /// Styles
+    pub styles: &'a Styles,
+}
+
+impl Widget for Timeline<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+
+        for x in area.x..area.x + area.width {
+            buf[(x, area.y)].set_char(' ').set_style(self.styles.footer);
+        }
+
+        let Some(current) = self.steps.get(self.position) else {
+            return;
+        };
+        let label = format!(
+            " {}/{} {} {} ",
+            self.position + 1,
+            self.steps.len(),
+            current.hash,
+            current.subject
+        );
+
+        // Each step takes two cells ("●─"); show a window around the current one
+        let room = (area.width as usize).saturating_sub(text::width(&label) + 6) / 2;
+        let (start, end) = window(self.steps.len(), self.position, room.max(1));
+
+        let mut spans = vec![Span::styled(" ◀ ", self.styles.footer_key)];
+        if start > 0 {
+            spans.push(Span::styled("…", self.styles.footer));
+        }
+        for i in start..end {
+            let (dot, style) = match i.cmp(&self.position) {
+                std::cmp::Ordering::Less => ("●", self.styles.stats_added),
+                std::cmp::Ordering::Equal => ("◉", self.styles.footer_key),
+                std::cmp::Ordering::Greater => ("○", self.styles.footer),
+            };
+            spans.push(Span::styled(dot, style));
+            if i + 1 < end {
+                spans.push(Span::styled("─", self.styles.footer));
+            }
+        }
+        if end < self.steps.len() {
+            spans.push(Span::styled("…", self.styles.footer));
+        }
+        spans.push(Span::styled(" ▶", self.styles.footer_key));
+        spans.push(Span::styled(label, self.styles.header));
+
+        buf.set_line(area.x, area.y, &Line::from(spans), area.width);
+    }
+}
+
+/// Range of at most `room` steps containing `position`, as centered as possible
+fn window(len: usize, position: usize, room: usize) -> (usize, usize) {
+    if len <= room {
+        return (0, len);
+    }
+    let start = position.saturating_sub(room / 2).min(len - room);
+    (start, start + room)
+}
+
+/// Render the timeline scrubber
+pub fn render_timeline(
+    buf: &mut Buffer,
+    area: Rect,
+    steps: &[TimelineStep<'_>],
+    position: usize,
+    styles: &Styles,
+) {
+    Timeline { steps, position, styles }.render(area, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_follows_position() {
+        assert_eq!(window(5, 4, 10), (0, 5));
+        assert_eq!(window(20, 0, 5), (0, 5));
+        assert_eq!(window(20, 10, 5), (8, 13));
+        assert_eq!(window(20, 19, 5), (15, 20));
+    }
+}
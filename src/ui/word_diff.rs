@@ -0,0 +1,223 @@
+//! Word-level diffs
+//!
+//! In the word-diff view a removed line and the added line replacing it
+//! share one row, with the words that changed marked inline as
+//! `[-removed-]{+added+}`, like `git diff --word-diff`.
+
+use crate::git::{Hunk, LineType};
+
+/// Most words per line compared word by word; longer pairs show whole
+const MAX_WORDS: usize = 256;
+
+/// How a word differs between the old and new line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordChange {
+    /// In both lines
+    Same,
+    /// Only in the old line
+    Removed,
+    /// Only in the new line
+    Added,
+}
+
+/// A row of the word-diff view, as indices into the hunk's lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordRow {
+    /// A line shown as in the unified view
+    Line(usize),
+    /// A removed line and the added line replacing it, merged
+    Pair(usize, usize),
+}
+
+/// Rows of a hunk in the word-diff view
+///
+/// Within each run of removed lines followed by added lines, the first
+/// removed line pairs with the first added line and so on; lines left
+/// over on either side keep rows of their own.
+pub fn word_rows(hunk: &Hunk) -> Vec<WordRow> {
+    let mut rows = Vec::with_capacity(hunk.lines.len());
+    let lines = &hunk.lines;
+    let mut i = 0;
+    while i < lines.len() {
+        match lines[i].line_type {
+            LineType::Header => i += 1,
+            LineType::Removed => {
+                let removed_end = run_end(hunk, i, LineType::Removed);
+                let added_end = run_end(hunk, removed_end, LineType::Added);
+                let pairs = (removed_end - i).min(added_end - removed_end);
+                rows.extend((0..pairs).map(|k| WordRow::Pair(i + k, removed_end + k)));
+                rows.extend((i + pairs..removed_end).map(WordRow::Line));
+                rows.extend((removed_end + pairs..added_end).map(WordRow::Line));
+                i = added_end;
+            }
+            _ => {
+                rows.push(WordRow::Line(i));
+                i += 1;
+            }
+        }
+    }
+    rows
+}
+
+/// Number of rows [`word_rows`] gives a hunk, without building them
+pub fn word_row_count(hunk: &Hunk) -> usize {
+    let lines = &hunk.lines;
+    let mut count = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        match lines[i].line_type {
+            LineType::Header => i += 1,
+            LineType::Removed => {
+                let removed_end = run_end(hunk, i, LineType::Removed);
+                let added_end = run_end(hunk, removed_end, LineType::Added);
+                count += (removed_end - i).max(added_end - removed_end);
+                i = added_end;
+            }
+            _ => {
+                count += 1;
+                i += 1;
+            }
+        }
+    }
+    count
+}
+
+/// End of the run of `line_type` lines starting at `start`
+fn run_end(hunk: &Hunk, start: usize, line_type: LineType) -> usize {
+    start + hunk.lines[start..].iter().take_while(|l| l.line_type == line_type).count()
+}
+
+/// Split `old` and `new` into segments, in display order
+///
+/// Each removed stretch comes before the added one replacing it.
+/// Neighbouring words of the same kind are merged into one segment.
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<(WordChange, &'a str)> {
+    let old_words = words(old);
+    let new_words = words(new);
+    if old_words.len() > MAX_WORDS || new_words.len() > MAX_WORDS {
+        return [(WordChange::Removed, old), (WordChange::Added, new)]
+            .into_iter()
+            .filter(|(_, text)| !text.is_empty())
+            .collect();
+    }
+
+    // Longest common subsequence of the suffixes, old_words[i..] and new_words[j..]
+    let (n, m) = (old_words.len(), new_words.len());
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_words[i] == new_words[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut segments: Vec<(WordChange, &str)> = Vec::new();
+    let mut push = |change: WordChange, word: &'a str, source: &'a str| {
+        // Words are slices of their line, so neighbours from the same line can be joined
+        if let Some((last_change, text)) = segments.last_mut()
+            && *last_change == change
+        {
+            let start = text.as_ptr() as usize - source.as_ptr() as usize;
+            *text = &source[start..start + text.len() + word.len()];
+        } else {
+            segments.push((change, word));
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_words[i] == new_words[j] {
+            push(WordChange::Same, new_words[j], new);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push(WordChange::Removed, old_words[i], old);
+            i += 1;
+        } else {
+            push(WordChange::Added, new_words[j], new);
+            j += 1;
+        }
+    }
+    segments
+}
+
+/// Split a line into words, runs of whitespace, and single punctuation characters
+fn words(line: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Punct,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Punct
+        }
+    };
+
+    let mut words = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = class(c);
+        let mut end = start + c.len_utf8();
+        if kind != Class::Punct {
+            while let Some(&(next, d)) = chars.peek() {
+                if class(d) != kind {
+                    break;
+                }
+                end = next + d.len_utf8();
+                chars.next();
+            }
+        }
+        words.push(&line[start..end]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::DiffLine;
+
+    fn line(line_type: LineType, content: &str) -> DiffLine {
+        DiffLine { line_type, content: content.to_string(), old_lineno: None, new_lineno: None, moved: false }
+    }
+
+    #[test]
+    fn test_word_diff() {
+        use WordChange::*;
+        assert_eq!(
+            word_diff("timeout = 30 # seconds", "timeout = 60 # seconds"),
+            vec![(Same, "timeout = "), (Removed, "30"), (Added, "60"), (Same, " # seconds")]
+        );
+        assert_eq!(word_diff("", "new"), vec![(Added, "new")]);
+        assert_eq!(word_diff("same", "same"), vec![(Same, "same")]);
+    }
+
+    #[test]
+    fn test_word_rows() {
+        let hunk = Hunk {
+            old_start: 1,
+            old_count: 4,
+            new_start: 1,
+            new_count: 3,
+            header: String::new(),
+            lines: vec![
+                line(LineType::Context, "a"),
+                line(LineType::Removed, "b"),
+                line(LineType::Removed, "c"),
+                line(LineType::Added, "B"),
+                line(LineType::Context, "d"),
+            ],
+        };
+        let rows = word_rows(&hunk);
+        assert_eq!(rows, vec![WordRow::Line(0), WordRow::Pair(1, 3), WordRow::Line(2), WordRow::Line(4)]);
+        assert_eq!(word_row_count(&hunk), rows.len());
+    }
+}
@@ -0,0 +1,756 @@
+use std::path::Path;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use git2::Repository;
+use vibed::annotations::{Annotation, Severity};
+use vibed::app::{App, ViewOptions};
+use vibed::headless::Headless;
+
+/// Commit `files` on top of HEAD, writing them to the work tree too
+fn commit(repo: &Repository, message: &str, files: &[(&str, &str)]) -> git2::Oid {
+    let root = repo.workdir().unwrap();
+    let mut index = repo.index().unwrap();
+    for (path, content) in files {
+        std::fs::write(root.join(path), content).unwrap();
+        index.add_path(Path::new(path)).unwrap();
+    }
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+    let parents: Vec<git2::Commit> = repo.head().ok().and_then(|h| h.peel_to_commit().ok()).into_iter().collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+}
+
+#[test]
+fn test_headless_review() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("notes.txt", "one\ntwo\nthree\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("notes.txt", "one\n2\nthree\n"), ("added.txt", "new file\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("notes.txt"), "{}", screen);
+    assert!(screen.contains("added.txt"), "{}", screen);
+    assert!(screen.contains("new file"), "{}", screen);
+
+    gv.keys("?").unwrap();
+    assert!(gv.screen().unwrap().contains("Help"));
+    gv.keys("?").unwrap();
+    assert!(!gv.screen().unwrap().contains("Help"));
+    gv.keys("q").unwrap();
+    assert!(gv.quit());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_mark_reviewed() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-reviewed-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "one\n"), ("b.txt", "two\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "first edit\n"), ("b.txt", "second edit\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.keys("r").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("✓ 1/2 reviewed"), "{}", screen);
+    assert!(screen.contains("✓ a.txt"), "{}", screen);
+    // The reviewed file collapses, leaving the next one in view
+    assert!(!screen.contains("first edit"), "{}", screen);
+    assert!(screen.contains("second edit"), "{}", screen);
+
+    gv.keys("r").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(!screen.contains("✓ a.txt"), "{}", screen);
+    assert!(screen.contains("first edit"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_resume_session() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-session-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "one\n"), ("b.txt", "two\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "first edit\n"), ("b.txt", "second edit\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.keys("r").unwrap();
+    gv.app().save_session().unwrap();
+
+    let mut app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    app.resume_session();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("✓ 1/2 reviewed"), "{}", screen);
+    assert!(!screen.contains("first edit"), "{}", screen);
+
+    // Restored marks alone don't record the branch as reviewed; marking does
+    let snapshots = dir.join(".git/gv/reviewed.json");
+    gv.app().record_review().unwrap();
+    assert!(!snapshots.exists());
+    gv.keys("r").unwrap();
+    gv.app().record_review().unwrap();
+    assert!(snapshots.exists());
+    gv.keys("r").unwrap();
+
+    // A reviewed file that changed since opens again
+    commit(&repo, "more", &[("a.txt", "third edit\n")]);
+    let mut app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    app.resume_session();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(!screen.contains("reviewed"), "{}", screen);
+    assert!(screen.contains("third edit"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_collapse_across_reload() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-collapse-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let files = [("a.txt", "a\n"), ("b.txt", "b\n"), ("gen.txt", "g\n")];
+    let base = commit(&repo, "base", &files);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "alpha edit\n"), ("gen.txt", "// @generated\ngen edit\n")]);
+    std::fs::write(dir.join("b.txt"), "bravo dirty\n").unwrap();
+
+    let view = ViewOptions { generated: vec![regex::Regex::new("@generated").unwrap()], ..ViewOptions::default() };
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, view).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("alpha edit") && screen.contains("bravo dirty"), "{}", screen);
+    assert!(!screen.contains("gen edit"), "{}", screen);
+
+    // A file collapsed by hand stays collapsed; the others follow the rules again
+    gv.keys(" ").unwrap();
+    std::fs::write(dir.join("b.txt"), "// @generated\nbravo regenerated\n").unwrap();
+    gv.keys("a").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(!screen.contains("alpha edit"), "{}", screen);
+    assert!(!screen.contains("bravo regenerated"), "{}", screen);
+    assert!(!screen.contains("gen edit"), "{}", screen);
+
+    // Expanding it by hand sticks too
+    gv.keys(" a").unwrap();
+    assert!(gv.screen().unwrap().contains("alpha edit"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_line_notes() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-notes-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "one\ntwo\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "one\n2\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.keys("#looks off").unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("✎ looks off"), "{}", screen);
+
+    // The Markdown report lists files with their review status, then the notes
+    gv.keys("r").unwrap();
+    let report = gv.app().markdown();
+    assert!(report.contains("1 of 1 reviewed"), "{}", report);
+    assert!(report.contains("| `a.txt` | modified | 1 | 1 | ✓ |"), "{}", report);
+    assert!(report.ends_with("### Notes\n\n- `a.txt:1`: looks off\n"), "{}", report);
+
+    // Notes are saved as they're left, and show under their line in every diff layout
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.keys("u").unwrap();
+    assert!(gv.screen().unwrap().contains("✎ looks off"));
+
+    // Emptying a note deletes it
+    gv.keys("#").unwrap();
+    for _ in "looks off".chars() {
+        gv.press(KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    }
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    assert!(!gv.screen().unwrap().contains("looks off"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_merge_base() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-merge-base-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let fork = commit(&repo, "base", &[("a.txt", "one\n")]);
+    // The base moves on after the branch forks off it
+    let upstream = commit(&repo, "upstream", &[("up.txt", "upstream work\n")]);
+    repo.reference("refs/heads/base", upstream, true, "").unwrap();
+    repo.reset(&repo.find_object(fork, None).unwrap(), git2::ResetType::Hard, None).unwrap();
+    commit(&repo, "change", &[("a.txt", "branch work\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("branch work"), "{}", screen);
+    assert!(!screen.contains("up.txt"), "{}", screen);
+
+    // `.` diffs against the base's tip, which shows its newer file as removed
+    gv.keys(".").unwrap();
+    gv.app().wait_for_diffs();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("up.txt"), "{}", screen);
+    assert!(screen.contains("base..HEAD"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_path_filter() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-paths-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    std::fs::create_dir_all(dir.join("docs")).unwrap();
+    let base = commit(&repo, "base", &[("a.rs", "one\n"), ("docs/guide.md", "two\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.rs", "code edit\n"), ("docs/guide.md", "docs edit\n")]);
+
+    let view = ViewOptions { paths: vec!["docs".to_string()], ..ViewOptions::default() };
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, view).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("paths docs"), "{}", screen);
+    assert!(screen.contains("docs edit"), "{}", screen);
+    assert!(!screen.contains("code edit"), "{}", screen);
+
+    // Swap the filter for a glob, then clear it
+    gv.keys("l").unwrap();
+    for _ in 0..4 {
+        gv.press(KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    }
+    gv.keys("*.rs").unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("paths *.rs"), "{}", screen);
+    assert!(screen.contains("code edit"), "{}", screen);
+    assert!(!screen.contains("docs edit"), "{}", screen);
+
+    gv.keys("l").unwrap();
+    for _ in 0..4 {
+        gv.press(KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    }
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("code edit") && screen.contains("docs edit"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_language_filter() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-language-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("main.rs", "one\n"), ("app.py", "two\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("main.rs", "rust edit\n"), ("app.py", "python edit\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.press(KeyCode::Char('l'), KeyModifiers::CONTROL).unwrap();
+    gv.keys("pyth").unwrap();
+    // The files narrow while typing, before Enter
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("python edit"), "{}", screen);
+    assert!(!screen.contains("rust edit"), "{}", screen);
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    assert!(gv.screen().unwrap().contains("lang pyth"));
+
+    gv.press(KeyCode::Char('l'), KeyModifiers::CONTROL).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("rust edit") && screen.contains("python edit"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_tree_filter() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-tree-filter-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let files = [("server.rs", "one\n"), ("shared.rs", "two\n"), ("readme.md", "three\n")];
+    let base = commit(&repo, "base", &files);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("server.rs", "1\n"), ("shared.rs", "2\n"), ("readme.md", "3\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    gv.press(KeyCode::Tab, KeyModifiers::NONE).unwrap();
+
+    // Bound keys like `s` and `d` go into the query rather than running
+    gv.keys("/srv").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("filter: srv_ (1 files)"), "{}", screen);
+    gv.press(KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    gv.press(KeyCode::Backspace, KeyModifiers::NONE).unwrap();
+    gv.keys("hd").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("filter: shd_ (1 files)"), "{}", screen);
+
+    // Enter jumps to the match and brings the full tree back
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(!screen.contains("filter:"), "{}", screen);
+    assert!(screen.contains("server.rs") && screen.contains("readme.md"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_diffstat() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-diffstat-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "one\n"), ("src/big.rs", "x\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "edit\n"), ("src/big.rs", "1\n2\n3\n4\n5\n6\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 12).unwrap();
+    gv.keys("d").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("Diffstat (2 files, +7 -2) · sorted by path"), "{}", screen);
+    let row = |screen: &str, name: &str| screen.lines().position(|line| line.contains(name)).unwrap();
+    assert!(row(&screen, "a.txt") < row(&screen, "big.rs"), "{}", screen);
+    assert!(screen.contains("++++++-"), "{}", screen);
+
+    // By churn the folder holding the bigger change comes first; Enter on it opens its file
+    gv.keys("s").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("sorted by churn"), "{}", screen);
+    assert!(row(&screen, "src/") < row(&screen, "a.txt"), "{}", screen);
+    gv.keys("g").unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(!screen.contains("Diffstat"), "{}", screen);
+    // Scrolled as far down as the short diff lets it
+    assert!(screen.lines().take(3).any(|line| line.contains("│ src/big.rs")), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_commit_range() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-range-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "0\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    for i in 1..=4 {
+        commit(&repo, &format!("step {}", i), &[("a.txt", &format!("{}\n", i))]);
+    }
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let row = |screen: &str, name: &str| screen.lines().find(|line| line.contains(name)).unwrap().to_string();
+
+    // v, move, Space toggles the block: all four start selected, so the first three go
+    gv.keys("cvjj ").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(row(&screen, "step 4").contains("[ ]"), "{}", screen);
+    assert!(row(&screen, "step 2").contains("[ ]"), "{}", screen);
+    assert!(row(&screen, "step 1").contains("[x]"), "{}", screen);
+
+    // Shift-click selects from the cursor down to the clicked commit
+    let y = screen.lines().position(|line| line.contains("step 1")).unwrap() as u16;
+    let x = row(&screen, "step 1").find("step 1").unwrap() as u16;
+    gv.keys("k").unwrap();
+    gv.click(x, y, KeyModifiers::SHIFT).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(row(&screen, "step 4").contains("[ ]"), "{}", screen);
+    assert!(row(&screen, "step 3").contains("[x]"), "{}", screen);
+    assert!(row(&screen, "step 1").contains("[x]"), "{}", screen);
+
+    // A plain click toggles one
+    gv.click(x, y, KeyModifiers::NONE).unwrap();
+    assert!(row(&gv.screen().unwrap(), "step 1").contains("[ ]"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_commit_query() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-query-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "0\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    for (i, subject) in ["fix parser", "add docs", "fix lexer", "bump version"].iter().enumerate() {
+        commit(&repo, subject, &[("a.txt", &format!("{}\n", i))]);
+    }
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let row = |screen: &str, name: &str| screen.lines().find(|line| line.contains(name)).map(str::to_string);
+
+    // Only the matching commits are listed, and n deselects just those
+    gv.keys("c/FIX").unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    gv.keys("n").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("Select Commits /FIX"), "{}", screen);
+    assert!(row(&screen, "add docs").is_none(), "{}", screen);
+    assert!(row(&screen, "fix parser").unwrap().contains("[ ]"), "{}", screen);
+
+    // Esc clears the query, leaving the others selected
+    gv.press(KeyCode::Esc, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(row(&screen, "fix lexer").unwrap().contains("[ ]"), "{}", screen);
+    assert!(row(&screen, "add docs").unwrap().contains("[x]"), "{}", screen);
+    assert!(row(&screen, "bump version").unwrap().contains("[x]"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_commit_columns() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-columns-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "0\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+
+    // The child is authored before its parent, as after a rebase
+    let mut parent = repo.find_commit(base).unwrap();
+    let now = parent.time().seconds();
+    for (subject, author, days) in [("later work", "Ada", 2), ("earlier work", "Grace", 5)] {
+        let time = git2::Time::new(now - days * 86400, 0);
+        let sig = git2::Signature::new(author, &format!("{}@example.com", author.to_lowercase()), &time).unwrap();
+        let tree = parent.tree().unwrap();
+        let oid = repo.commit(Some("HEAD"), &sig, &sig, subject, &tree, &[&parent]).unwrap();
+        parent = repo.find_commit(oid).unwrap();
+    }
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let position = |screen: &str, name: &str| screen.lines().position(|line| line.contains(name)).unwrap();
+
+    gv.keys("c").unwrap();
+    let screen = gv.screen().unwrap();
+    let row = &screen.lines().nth(position(&screen, "earlier work")).unwrap();
+    assert!(row.contains("5d ago") && row.contains("Grace"), "{}", screen);
+    assert!(position(&screen, "earlier work") < position(&screen, "later work"), "{}", screen);
+
+    // s sorts by author date
+    gv.keys("s").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("newest first"), "{}", screen);
+    assert!(position(&screen, "later work") < position(&screen, "earlier work"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_create_worktree() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-create-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let main = dir.join("repo");
+    let repo = Repository::init(&main).unwrap();
+    commit(&repo, "base", &[("a.txt", "0\n")]);
+    let current = repo.head().unwrap().shorthand().unwrap().to_string();
+
+    let app = App::new(main.clone().into(), None, None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+
+    // A branch that's checked out already can't get a second worktree
+    gv.keys("w").unwrap();
+    gv.press(KeyCode::Char('n'), KeyModifiers::CONTROL).unwrap();
+    gv.keys(&current).unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("already checked out"), "{}", screen);
+
+    // The path follows the branch, and creating switches to the new worktree
+    gv.press(KeyCode::BackTab, KeyModifiers::SHIFT).unwrap();
+    gv.press(KeyCode::Char('u'), KeyModifiers::CONTROL).unwrap();
+    gv.keys("fix/tests").unwrap();
+    assert!(gv.screen().unwrap().contains("../repo-fix-tests"));
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(dir.join("repo-fix-tests").join("a.txt").exists(), "{}", screen);
+    assert!(screen.contains("fix/tests"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_macro() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-macro-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "1\n"), ("b.txt", "2\n"), ("c.txt", "3\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "one\n"), ("b.txt", "two\n"), ("c.txt", "three\n")]);
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+
+    // `Q{a-z}` records until the next `Q`, and `@` replays
+    gv.keys("Qa").unwrap();
+    assert!(gv.screen().unwrap().contains("recording @a"));
+    gv.keys("rQ").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("✓ 1/3 reviewed") && screen.contains("Recorded 1 keys into @a"), "{}", screen);
+    gv.keys("@a").unwrap();
+    assert!(!gv.screen().unwrap().contains("✓ a.txt"));
+
+    // A single `q` still quits
+    gv.keys("q").unwrap();
+    assert!(gv.quit());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_worktree_tab() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-tab-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let main = dir.join("repo");
+    let repo = Repository::init(&main).unwrap();
+    let base = commit(&repo, "base", &[("a.txt", "0\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.txt", "1\n")]);
+    repo.worktree("side", &dir.join("side"), None).unwrap();
+    commit(&Repository::open(dir.join("side")).unwrap(), "side work", &[("side.txt", "on the side\n")]);
+
+    let app = App::new(main.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    assert!(!gv.screen().unwrap().contains("side.txt"));
+
+    // The new tab lays out its own diffs rather than the ones it replaced
+    gv.keys("wj").unwrap();
+    gv.press(KeyCode::Char('t'), KeyModifiers::CONTROL).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("side.txt") && screen.contains("on the side"), "{}", screen);
+    gv.keys("gt").unwrap();
+    assert!(!gv.screen().unwrap().contains("on the side"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_delete_and_prune_worktrees() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-prune-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let main = dir.join("repo");
+    let repo = Repository::init(&main).unwrap();
+    commit(&repo, "base", &[("a.txt", "0\n")]);
+    repo.worktree("kept", &dir.join("kept"), None).unwrap();
+
+    let app = App::new(main.clone().into(), None, None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+
+    // The worktree being viewed can't go
+    gv.keys("Wd").unwrap();
+    assert!(gv.screen().unwrap().contains("Can't delete the worktree being viewed"));
+
+    // Deleting asks first
+    gv.keys("jd").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("Delete Worktree") && screen.contains("kept"), "{}", screen);
+    gv.keys("n").unwrap();
+    assert!(dir.join("kept").exists());
+    gv.keys("dy").unwrap();
+    assert!(!dir.join("kept").exists());
+
+    // Pruning forgets a worktree whose directory went away, and drops it from the list
+    repo.worktree("lost", &dir.join("lost"), None).unwrap();
+    gv.press(KeyCode::Esc, KeyModifiers::NONE).unwrap();
+    gv.keys("W").unwrap();
+    assert!(gv.screen().unwrap().contains("lost"));
+    std::fs::remove_dir_all(dir.join("lost")).unwrap();
+    gv.keys("p").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("Prune Worktrees") && screen.contains("lost"), "{}", screen);
+    gv.keys("y").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("Pruned 1 stale worktree(s)"), "{}", screen);
+    assert!(!screen.contains("lost"), "{}", screen);
+    assert!(repo.worktrees().unwrap().is_empty());
+    gv.keys("p").unwrap();
+    assert!(gv.screen().unwrap().contains("No stale worktrees to prune"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_worktree_badges() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-badges-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let main = dir.join("repo");
+    let repo = Repository::init(&main).unwrap();
+    commit(&repo, "base", &[("a.txt", "0\n")]);
+    let trunk = repo.head().unwrap().shorthand().unwrap().to_string();
+
+    // A linked worktree one commit ahead of its upstream, and a dirty main worktree
+    let head = repo.head().unwrap().peel_to_commit().unwrap();
+    let mut branch = repo.branch("feat", &head, false).unwrap();
+    branch.set_upstream(Some(&trunk)).unwrap();
+    let mut opts = git2::WorktreeAddOptions::new();
+    opts.reference(Some(branch.get()));
+    repo.worktree("feat", &dir.join("feat"), Some(&opts)).unwrap();
+    commit(&Repository::open(dir.join("feat")).unwrap(), "ahead", &[("b.txt", "1\n")]);
+    std::fs::write(main.join("new.txt"), "x\n").unwrap();
+
+    let app = App::new(main.clone().into(), None, None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let row = |screen: &str, name: &str| screen.lines().find(|line| line.contains(name)).unwrap().to_string();
+
+    gv.keys("w").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(row(&screen, "feat").contains("✓") && row(&screen, "feat").contains("↑1 ↓0"), "{}", screen);
+    assert!(row(&screen, &format!("* {}", trunk)).contains("● 1"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_stashes() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-stash-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut repo = Repository::init(&dir).unwrap();
+    commit(&repo, "base", &[("a.txt", "0\n")]);
+    std::fs::write(dir.join("stashed.txt"), "kept for later\n").unwrap();
+    let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+    repo.stash_save(&sig, "half done", Some(git2::StashFlags::INCLUDE_UNTRACKED)).unwrap();
+
+    let app = App::new(dir.clone().into(), None, None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+
+    gv.keys("$").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("stash@{0}") && screen.contains("half done"), "{}", screen);
+
+    // Enter shows the stash's changes, and again goes back to the branch
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("kept for later"), "{}", screen);
+    assert!(screen.contains("stash@{0}"), "{}", screen);
+    gv.keys("$").unwrap();
+    gv.press(KeyCode::Enter, KeyModifiers::NONE).unwrap();
+    assert!(!gv.screen().unwrap().contains("kept for later"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_image_preview() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-image-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let png = |width: u32, height: u32, color: [u8; 3]| {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb(color));
+        let mut bytes = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+        bytes
+    };
+    let root = repo.workdir().unwrap().to_path_buf();
+    std::fs::write(root.join("logo.png"), png(20, 10, [255, 0, 0])).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("logo.png")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+    let base = repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[]).unwrap();
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    std::fs::write(root.join("logo.png"), png(30, 12, [0, 0, 255])).unwrap();
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("image/png"), "{}", screen);
+    assert!(screen.contains("old 20×10") && screen.contains("new 30×12"), "{}", screen);
+    assert!(screen.contains('▀'), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_tiny_popups() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-tiny-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("lib.rs", "fn one() {}\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("lib.rs", "fn one() {}\n\nfn two() {}\n")]);
+    std::fs::write(dir.join("lib.rs"), "fn three() {}\n").unwrap();
+    let sig = git2::Signature::now("gv", "gv@example.com").unwrap();
+    repo.stash_save(&sig, "wip", None).unwrap();
+
+    // Popups fit themselves into a terminal smaller than they are
+    for (width, height) in [(8, 4), (8, 2), (4, 4), (20, 8)] {
+        let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+        let mut gv = Headless::new(app, width, height).unwrap();
+        gv.keys("$").unwrap();
+        gv.screen().unwrap();
+        gv.press(KeyCode::Esc, KeyModifiers::NONE).unwrap();
+        gv.keys("T").unwrap();
+        gv.screen().unwrap();
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_issues_on_changed_lines() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-issues-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let base = commit(&repo, "base", &[("a.rs", "one\ntwo\nthree\n")]);
+    repo.reference("refs/heads/base", base, true, "").unwrap();
+    commit(&repo, "change", &[("a.rs", "one\ntwo\n3\n")]);
+
+    let finding = |line: u32, message: &str| Annotation {
+        path: "a.rs".to_string(),
+        line,
+        message: message.to_string(),
+        severity: Severity::Warning,
+        rule: None,
+    };
+    let mut app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    app.set_annotations(vec![finding(2, "on context"), finding(3, "on the change")]);
+    let mut gv = Headless::new(app, 120, 30).unwrap();
+
+    // Only the finding on the changed line is listed
+    gv.keys("I").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("1 annotation(s) on changed lines"), "{}", screen);
+    assert!(screen.contains("on the change") && !screen.contains("on context"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_headless_conflicts_scroll() {
+    let dir = std::env::temp_dir().join(format!("gv-headless-conflicts-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let repo = Repository::init(&dir).unwrap();
+    let names: Vec<String> = (0..15).map(|i| format!("f{:02}.txt", i)).collect();
+    let files = |content: &'static str| names.iter().map(|name| (name.as_str(), content)).collect::<Vec<_>>();
+    let start = commit(&repo, "start", &files("0\n"));
+    let trunk = repo.head().unwrap().name().unwrap().to_string();
+    repo.branch("base", &repo.find_commit(start).unwrap(), false).unwrap();
+    repo.set_head("refs/heads/base").unwrap();
+    commit(&repo, "theirs", &files("base\n"));
+    repo.set_head(&trunk).unwrap();
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force())).unwrap();
+    commit(&repo, "ours", &files("head\n"));
+
+    let app = App::new(dir.clone().into(), Some("base".to_string()), None, ViewOptions::default()).unwrap();
+    let mut gv = Headless::new(app, 120, 14).unwrap();
+
+    // More conflicts than rows: j scrolls the list to the last one
+    gv.keys("C").unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("15 file(s) would conflict"), "{}", screen);
+    assert!(screen.contains("! f00.txt") && !screen.contains("! f14.txt"), "{}", screen);
+    gv.keys(&"j".repeat(14)).unwrap();
+    let screen = gv.screen().unwrap();
+    assert!(screen.contains("! f14.txt") && !screen.contains("! f00.txt"), "{}", screen);
+    let _ = std::fs::remove_dir_all(&dir);
+}